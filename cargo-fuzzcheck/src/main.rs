@@ -4,6 +4,7 @@ use std::cmp::Ordering;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::string::String;
+use std::time::{Duration, Instant};
 
 use std::fs;
 use std::io::Read;
@@ -83,6 +84,8 @@ SUBCOMMANDS:
     {fuzz}    Run the fuzz test
     {tmin}    Minify a crashing test input, requires --{input_file}
     {cmin}    Minify a corpus of test inputs, requires --{in_corpus}
+    {cdiff}   Compare the coverage of two corpora, requires --{in_corpus} and --{other_corpus}
+    {selftest} Check that the mutator and serializer of the target are consistent
 "#,
         init = COMMAND_INIT,
         clean = COMMAND_CLEAN,
@@ -92,6 +95,9 @@ SUBCOMMANDS:
         input_file = INPUT_FILE_FLAG,
         cmin = COMMAND_MINIFY_CORPUS,
         in_corpus = IN_CORPUS_FLAG,
+        cdiff = COMMAND_DIFF_CORPUS,
+        other_corpus = OTHER_CORPUS_FLAG,
+        selftest = COMMAND_SELF_TEST,
     );
     help += parser.usage("").as_str();
     help += format!(
@@ -104,6 +110,12 @@ cargo-fuzzcheck {init}
 cargo-fuzzcheck {run} target1 {fuzz}
     Launch the fuzzer on “target1” with default options.
 
+cargo-fuzzcheck {run} target1 {fuzz} --{features_in} "features.txt" --{features_out} "features.txt"
+
+    Launch the fuzzer on “target1”, prioritizing the test inputs that cover
+    features that no previous run recorded in "features.txt". On exit, the
+    features covered by this run are added to "features.txt".
+
 cargo-fuzzcheck {run} target1 {tmin} --{input_file} "artifacts/crash.json"
 
     Using “target1”, minify the test input defined in the file 
@@ -118,15 +130,50 @@ cargo-fuzzcheck {run} target1 {cmin} --{in_corpus} "fuzz-corpus" --{corpus_size}
     which should contain JSON-encoded test inputs.
     It will remove files from that folder until only the 25 most important
     test inputs remain.
+
+cargo-fuzzcheck {run} target1 {cmin} --{in_corpus} "fuzz-corpus" --{out_corpus} "seeds" --{curate} 20
+
+    Using “target1”, write the 20 highest-scoring test inputs of the corpus
+    "fuzz-corpus" to the folder "seeds", to distribute a small starter corpus.
+    Unlike a plain {cmin}, this does not try to preserve all the code coverage
+    of the corpus.
+
+cargo-fuzzcheck {run} target1 {cdiff} --{in_corpus} "fuzz-corpus" --{other_corpus} "new-seeds"
+
+    Using “target1”, compare the code coverage of the corpus "fuzz-corpus"
+    with that of the corpus "new-seeds". It will print the number of
+    features covered by only one of the two corpora, and how many of those
+    each input covers.
+
+cargo-fuzzcheck {run} target1 {read} --{input_file} "seeds/input.json" --{trace_only}
+
+    Using “target1”, run the test function on the input defined in the file
+    "seeds/input.json" and print the features it covers, one per line,
+    without mutating it.
+
+cargo-fuzzcheck {run} target1 {selftest}
+
+    Without running the test function of “target1”, check that its mutator
+    and serializer are consistent on 10,000 arbitrary values. It exits with
+    a non-zero status and prints the offending value on the first failure.
 "#,
         init = COMMAND_INIT,
         run = COMMAND_RUN,
         fuzz = COMMAND_FUZZ,
+        features_in = FEATURES_IN_FLAG,
+        features_out = FEATURES_OUT_FLAG,
         tmin = COMMAND_MINIFY_INPUT,
         input_file = INPUT_FILE_FLAG,
         cmin = COMMAND_MINIFY_CORPUS,
         in_corpus = IN_CORPUS_FLAG,
-        corpus_size = CORPUS_SIZE_FLAG
+        corpus_size = CORPUS_SIZE_FLAG,
+        out_corpus = OUT_CORPUS_FLAG,
+        curate = CURATE_FLAG,
+        cdiff = COMMAND_DIFF_CORPUS,
+        other_corpus = OTHER_CORPUS_FLAG,
+        read = COMMAND_READ,
+        trace_only = TRACE_ONLY_FLAG,
+        selftest = COMMAND_SELF_TEST
     )
     .as_str();
 
@@ -191,7 +238,9 @@ cargo-fuzzcheck {run} target1 {cmin} --{in_corpus} "fuzz-corpus" --{corpus_size}
                 FuzzerCommand::Read => {
                     panic!("unimplemented");
                 }
-                FuzzerCommand::MinifyCorpus => exec_normal_command(args, &target, target_triple),
+                FuzzerCommand::MinifyCorpus | FuzzerCommand::DiffCorpus | FuzzerCommand::SelfTest => {
+                    exec_normal_command(args, &target, target_triple)
+                }
             };
             if let Err(e) = r {
                 println!("{}", e);
@@ -451,6 +500,8 @@ fn command_line_arguments_string(args: &CommandLineArguments) -> Vec<String> {
         FuzzerCommand::MinifyInput => s.push(COMMAND_MINIFY_INPUT.to_owned()),
         FuzzerCommand::MinifyCorpus => s.push(COMMAND_MINIFY_CORPUS.to_owned()),
         FuzzerCommand::Fuzz => s.push(COMMAND_FUZZ.to_owned()),
+        FuzzerCommand::DiffCorpus => s.push(COMMAND_DIFF_CORPUS.to_owned()),
+        FuzzerCommand::SelfTest => s.push(COMMAND_SELF_TEST.to_owned()),
     };
 
     if let Some(input_file_args) = input_file_args {
@@ -462,6 +513,36 @@ fn command_line_arguments_string(args: &CommandLineArguments) -> Vec<String> {
     ]);
 
     s.append(&mut corpus_in_args.clone());
+    if let Some(metadata_out) = args.metadata_out.clone() {
+        s.append(&mut vec!["--".to_owned() + METADATA_OUT_FLAG, path_str(metadata_out)]);
+    }
+    if let Some(target) = args.target.clone() {
+        s.append(&mut vec!["--".to_owned() + TARGET_FLAG, target]);
+    }
+    if let Some(features_in) = args.features_in.clone() {
+        s.append(&mut vec!["--".to_owned() + FEATURES_IN_FLAG, path_str(features_in)]);
+    }
+    if let Some(features_out) = args.features_out.clone() {
+        s.append(&mut vec!["--".to_owned() + FEATURES_OUT_FLAG, path_str(features_out)]);
+    }
+    if let Some(coverage_out) = args.coverage_out.clone() {
+        s.append(&mut vec!["--".to_owned() + COVERAGE_OUT_FLAG, path_str(coverage_out)]);
+    }
+    if let Some(edge_weights) = args.edge_weights.clone() {
+        s.append(&mut vec!["--".to_owned() + EDGE_WEIGHTS_FLAG, path_str(edge_weights)]);
+    }
+    if let Some(save_all_inputs) = args.save_all_inputs.clone() {
+        s.append(&mut vec![
+            "--".to_owned() + SAVE_ALL_INPUTS_FLAG,
+            path_str(save_all_inputs),
+        ]);
+    }
+    if let Some(replay) = args.replay.clone() {
+        s.append(&mut vec!["--".to_owned() + REPLAY_FLAG, path_str(replay)]);
+    }
+    if let Some(other_corpus) = args.other_corpus.clone() {
+        s.append(&mut vec!["--".to_owned() + OTHER_CORPUS_FLAG, path_str(other_corpus)]);
+    }
     s.append(&mut corpus_out_args.clone());
     s.append(&mut artifacts_args.clone());
     s.append(&mut vec![
@@ -474,6 +555,117 @@ fn command_line_arguments_string(args: &CommandLineArguments) -> Vec<String> {
         args.max_nbr_of_runs.to_string(),
     ]);
 
+    s.append(&mut vec!["--".to_owned() + VERBOSITY_FLAG, args.verbosity.to_string()]);
+
+    if args.fork {
+        s.push("--".to_owned() + FORK_FLAG);
+    }
+    if args.print_pcs {
+        s.push("--".to_owned() + PRINT_PCS_FLAG);
+    }
+    if args.trace_only {
+        s.push("--".to_owned() + TRACE_ONLY_FLAG);
+    }
+    if args.no_cmp_trace {
+        s.push("--".to_owned() + NO_CMP_TRACE_FLAG);
+    }
+    if args.no_edge_trace {
+        s.push("--".to_owned() + NO_EDGE_TRACE_FLAG);
+    }
+    if args.explain_complexity {
+        s.push("--".to_owned() + EXPLAIN_COMPLEXITY_FLAG);
+    }
+    if args.adaptive_cplx {
+        s.push("--".to_owned() + ADAPTIVE_CPLX_FLAG);
+    }
+    if let Some(initial_cplx) = args.initial_cplx {
+        s.append(&mut vec!["--".to_owned() + INITIAL_CPLX_FLAG, initial_cplx.to_string()]);
+    }
+    if args.keep_seeds {
+        s.push("--".to_owned() + KEEP_SEEDS_FLAG);
+    }
+    if args.tui {
+        s.push("--".to_owned() + TUI_FLAG);
+    }
+    if args.save_all_crashes {
+        s.push("--".to_owned() + SAVE_ALL_CRASHES_FLAG);
+    }
+    if args.raw_corpus {
+        s.push("--".to_owned() + RAW_CORPUS_FLAG);
+    }
+    if args.arbitrary_from_corpus {
+        s.push("--".to_owned() + ARBITRARY_FROM_CORPUS_FLAG);
+    }
+    if args.crash_capture {
+        s.push("--".to_owned() + CRASH_CAPTURE_FLAG);
+    }
+    if args.deterministic {
+        s.push("--".to_owned() + DETERMINISTIC_FLAG);
+    }
+    if let Some(input_min_len) = args.input_min_len {
+        s.append(&mut vec![
+            "--".to_owned() + INPUT_MIN_LEN_FLAG,
+            input_min_len.to_string(),
+        ]);
+    }
+    if let Some(minimize_timeout) = args.minimize_timeout {
+        s.append(&mut vec![
+            "--".to_owned() + MINIMIZE_TIMEOUT_FLAG,
+            minimize_timeout.to_string(),
+        ]);
+    }
+    if let Some(minimize_to_cplx) = args.minimize_to_cplx {
+        s.append(&mut vec![
+            "--".to_owned() + MINIMIZE_TO_CPLX_FLAG,
+            minimize_to_cplx.to_string(),
+        ]);
+    }
+    if let Some(curate) = args.curate {
+        s.append(&mut vec!["--".to_owned() + CURATE_FLAG, curate.to_string()]);
+    }
+    if let Some(report_slowest_inputs) = args.report_slowest_inputs {
+        s.append(&mut vec![
+            "--".to_owned() + REPORT_SLOWEST_INPUTS_FLAG,
+            report_slowest_inputs.to_string(),
+        ]);
+    }
+    if let Some(reload_corpus_interval) = args.reload_corpus_interval {
+        s.append(&mut vec![
+            "--".to_owned() + RELOAD_CORPUS_INTERVAL_FLAG,
+            reload_corpus_interval.to_string(),
+        ]);
+    }
+    if let Some(energy_decay) = args.energy_decay {
+        s.append(&mut vec!["--".to_owned() + ENERGY_DECAY_FLAG, energy_decay.to_string()]);
+    }
+    if let Some(max_total_corpus_bytes) = args.max_total_corpus_bytes {
+        s.append(&mut vec![
+            "--".to_owned() + MAX_TOTAL_CORPUS_BYTES_FLAG,
+            max_total_corpus_bytes.to_string(),
+        ]);
+    }
+    if let Some(seed) = args.seed {
+        s.append(&mut vec!["--".to_owned() + SEED_FLAG, seed.to_string()]);
+    }
+    if let Some(crash_exitcode) = args.crash_exitcode {
+        s.append(&mut vec![
+            "--".to_owned() + CRASH_EXITCODE_FLAG,
+            crash_exitcode.to_string(),
+        ]);
+    }
+    if let Some(timeout_exitcode) = args.timeout_exitcode {
+        s.append(&mut vec![
+            "--".to_owned() + TIMEOUT_EXITCODE_FLAG,
+            timeout_exitcode.to_string(),
+        ]);
+    }
+    if let Some(input_max_len) = args.input_max_len {
+        s.append(&mut vec![
+            "--".to_owned() + INPUT_MAX_LEN_FLAG,
+            input_max_len.to_string(),
+        ]);
+    }
+
     s
 }
 
@@ -569,6 +761,12 @@ fn exec_input_minify_command(
 
     let file_to_minify = (&arguments.input_file).as_ref().unwrap().clone();
 
+    // the timeout applies to the whole minification, not to each run of the fuzzer
+    let deadline = arguments
+        .minimize_timeout
+        .take()
+        .map(|secs| Instant::now() + Duration::from_secs(secs as u64));
+
     let artifacts_folder = {
         let mut x = file_to_minify.parent().unwrap().to_path_buf();
         x.push(file_to_minify.file_stem().unwrap());
@@ -578,27 +776,29 @@ fn exec_input_minify_command(
     let _ = std::fs::create_dir(&artifacts_folder);
     arguments.artifacts_folder = Some(artifacts_folder.clone());
 
-    fn simplest_input_file(folder: &Path) -> Option<PathBuf> {
+    /// The simplest input of the folder, with its complexity
+    fn simplest_input_file(folder: &Path) -> Option<(PathBuf, f64)> {
         let files_with_complexity = std::fs::read_dir(folder)
             .ok()?
             .filter_map(|path| -> Option<(PathBuf, f64)> {
                 let path = path.ok()?.path();
                 let name_components: Vec<&str> = path.file_stem()?.to_str()?.splitn(2, "--").collect();
                 if name_components.len() == 2 {
-                    let cplx = name_components[0].parse::<f64>().ok()?;
+                    // the file name starts with 100 times the complexity of the input
+                    let cplx = name_components[0].parse::<f64>().ok()? / 100.0;
                     Some((path.to_path_buf(), cplx))
                 } else {
                     None
                 }
             });
 
-        files_with_complexity
-            .min_by(|x, y| std::cmp::PartialOrd::partial_cmp(&x.1, &y.1).unwrap_or(Ordering::Equal))
-            .map(|x| x.0)
+        files_with_complexity.min_by(|x, y| std::cmp::PartialOrd::partial_cmp(&x.1, &y.1).unwrap_or(Ordering::Equal))
     }
 
-    if let Some(simplest) = simplest_input_file(&artifacts_folder.as_path()) {
+    let mut simplest_cplx = None;
+    if let Some((simplest, cplx)) = simplest_input_file(&artifacts_folder.as_path()) {
         arguments.input_file = Some(simplest);
+        simplest_cplx = Some(cplx);
     }
     arguments.command = FuzzerCommand::Read;
 
@@ -619,11 +819,44 @@ fn exec_input_minify_command(
 
     arguments.command = FuzzerCommand::MinifyInput;
 
+    let target_cplx = arguments.minimize_to_cplx;
     loop {
-        arguments.input_file = simplest_input_file(&artifacts_folder).or(arguments.input_file);
+        if let Some((simplest, cplx)) = simplest_input_file(&artifacts_folder) {
+            arguments.input_file = Some(simplest);
+            simplest_cplx = Some(cplx);
+        }
+        if let (Some(target_cplx), Some(cplx)) = (target_cplx, simplest_cplx) {
+            if cplx < target_cplx as f64 {
+                break;
+            }
+        }
+
+        if let Some(deadline) = deadline {
+            let remaining_secs = deadline.saturating_duration_since(Instant::now()).as_secs();
+            if remaining_secs == 0 {
+                break;
+            }
+            arguments.minimize_timeout = Some(remaining_secs as usize);
+        }
 
         launch_exec(&arguments, target_folder, &non_instrumented_folder)?;
     }
+    if let Some(simplest) = arguments.input_file {
+        match simplest_cplx {
+            Some(cplx) => println!(
+                "The simplest crashing input found is {}, of complexity {:.2}",
+                simplest.display(),
+                cplx
+            ),
+            None => println!("The simplest crashing input found is {}", simplest.display()),
+        }
+    }
+    match (target_cplx, simplest_cplx) {
+        (Some(target_cplx), Some(cplx)) if cplx < target_cplx as f64 => {}
+        (Some(target_cplx), _) => println!("The target complexity {} was not reached", target_cplx),
+        _ => {}
+    }
+    Ok(())
 }
 
 fn path_str(p: PathBuf) -> String {
@@ -2,21 +2,56 @@ use std::env;
 use std::path::Path;
 use std::process::Command;
 
+/// The architectures on which `__builtin_return_address(1)` reliably returns
+/// the address of the caller's caller when the frame pointers are kept
+const SUPPORTED_ARCHS: &[&str] = &["x86_64", "aarch64"];
+
+const C_BUILTINS: &str = "src/code_coverage_sensor/c_builtins.c";
+
+fn run(cmd: &mut Command) -> Result<(), String> {
+    match cmd.status() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!("{:?} failed with {}", cmd, status)),
+        Err(e) => Err(format!("could not run {:?}: {}", cmd, e)),
+    }
+}
+
+/// Compiles `c_builtins.c` into a static library in `out_dir`
+fn build_c_builtins(out_dir: &str) -> Result<(), String> {
+    let cc = env::var("CC").unwrap_or_else(|_| "gcc".to_owned());
+    run(Command::new(cc)
+        .args([C_BUILTINS, "-c", "-fPIC", "-o"])
+        .arg(format!("{}/c_builtins.o", out_dir)))?;
+    run(Command::new("ar")
+        .args(["crus", "libc_builtins.a", "c_builtins.o"])
+        .current_dir(Path::new(out_dir)))
+}
+
 fn main() {
-    let out_dir = env::var("OUT_DIR").unwrap();
-    // note that there are a number of downsides to this approach, the comments
-    // below detail how to improve the portability of these commands.
-    Command::new("gcc")
-        .args(&["src/code_coverage_sensor/c_builtins.c", "-c", "-fPIC", "-o"])
-        .arg(&format!("{}/c_builtins.o", out_dir))
-        .status()
-        .unwrap();
-    Command::new("ar")
-        .args(&["crus", "libc_builtins.a", "c_builtins.o"])
-        .current_dir(&Path::new(&out_dir))
-        .status()
-        .unwrap();
+    println!("cargo:rerun-if-changed={}", C_BUILTINS);
+    println!("cargo:rerun-if-env-changed=CC");
+    println!("cargo:rustc-check-cfg=cfg(fuzzcheck_return_address)");
 
-    println!("cargo:rustc-link-search=native={}", out_dir);
-    println!("cargo:rustc-link-lib=static=c_builtins");
+    let out_dir = env::var("OUT_DIR").unwrap();
+    let arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default();
+    if !SUPPORTED_ARCHS.contains(&arch.as_str()) {
+        println!(
+            "cargo:warning=fuzzcheck cannot read return addresses on {}, comparisons will not be distinguished by their location",
+            arch
+        );
+        return;
+    }
+    match build_c_builtins(&out_dir) {
+        Ok(()) => {
+            println!("cargo:rustc-cfg=fuzzcheck_return_address");
+            println!("cargo:rustc-link-search=native={}", out_dir);
+            println!("cargo:rustc-link-lib=static=c_builtins");
+        }
+        Err(e) => {
+            println!(
+                "cargo:warning=fuzzcheck could not compile {}, comparisons will not be distinguished by their location: {}",
+                C_BUILTINS, e
+            );
+        }
+    }
 }
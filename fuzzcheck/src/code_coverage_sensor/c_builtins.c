@@ -1,4 +1,4 @@
-#import <stdint.h>
+#include <stdint.h>
 
 /// Returns the return address of the current function
 void* return_address() {
@@ -55,11 +55,37 @@ use std::sync::Once;
 
 use ahash::AHashSet;
 
+#[cfg(fuzzcheck_return_address)]
 extern "C" {
-    /// Returns the address of the calling function
+    /// Defined in `c_builtins.c`: the return address of the function that
+    /// called it, read with `__builtin_return_address(1)`
     fn return_address() -> usize;
 }
 
+/// The address that the current hook returns to, which identifies the
+/// instrumented instruction that called the hook.
+///
+/// It is only available on the platforms where `build.rs` compiles
+/// `c_builtins.c`, which are x86_64 and aarch64. It also requires the frame
+/// pointers to be kept, which `cargo fuzzcheck` ensures with
+/// `-Cforce-frame-pointers=yes`. On the other platforms, it is always 0, so
+/// the fuzzer still works, but all the comparisons and indirect calls of the
+/// program share the same address and are much less precise features.
+///
+/// It must be inlined into the hook, since an additional stack frame would
+/// change the return address that is read.
+#[cfg(fuzzcheck_return_address)]
+#[inline(always)]
+fn hook_return_address() -> PC {
+    unsafe { return_address() }
+}
+
+#[cfg(not(fuzzcheck_return_address))]
+#[inline(always)]
+fn hook_return_address() -> PC {
+    0
+}
+
 static START: Once = Once::new();
 
 #[export_name = "__sanitizer_cov_8bit_counters_init"]
@@ -74,8 +100,12 @@ fn counters_init(start: *mut u8, stop: *mut u8) {
         START.call_once(|| {
             SHARED_SENSOR.as_mut_ptr().write(CodeCoverageSensor {
                 is_recording: false,
+                cmp_recording: true,
+                edge_recording: true,
                 eight_bit_counters: slice::from_raw_parts_mut(start, dist),
                 features: AHashSet::new(),
+                recent_pcs: [0; NBR_RECENT_PCS],
+                nbr_recent_pcs: 0,
             });
         });
     }
@@ -99,7 +129,7 @@ fn trace_pc_indir(callee: usize) {
     if !sensor.is_recording {
         return;
     }
-    let caller = unsafe { return_address() };
+    let caller = hook_return_address();
     sensor.handle_trace_indir(caller, callee);
 }
 
@@ -109,10 +139,10 @@ fn trace_pc_indir(callee: usize) {
 #[export_name = "__sanitizer_cov_trace_cmp1"]
 fn trace_cmp1(arg1: u8, arg2: u8) {
     let sensor = shared_sensor();
-    if !sensor.is_recording {
+    if !sensor.is_recording || !sensor.cmp_recording {
         return;
     }
-    let pc = unsafe { return_address() };
+    let pc = hook_return_address();
     sensor.handle_trace_cmp(pc, u64::from(arg1), u64::from(arg2));
 }
 
@@ -122,10 +152,10 @@ fn trace_cmp1(arg1: u8, arg2: u8) {
 #[export_name = "__sanitizer_cov_trace_cmp2"]
 fn trace_cmp2(arg1: u16, arg2: u16) {
     let sensor = shared_sensor();
-    if !sensor.is_recording {
+    if !sensor.is_recording || !sensor.cmp_recording {
         return;
     }
-    let pc = unsafe { return_address() };
+    let pc = hook_return_address();
     sensor.handle_trace_cmp(pc, u64::from(arg1), u64::from(arg2));
 }
 
@@ -135,10 +165,10 @@ fn trace_cmp2(arg1: u16, arg2: u16) {
 #[export_name = "__sanitizer_cov_trace_cmp4"]
 fn trace_cmp4(arg1: u32, arg2: u32) {
     let sensor = shared_sensor();
-    if !sensor.is_recording {
+    if !sensor.is_recording || !sensor.cmp_recording {
         return;
     }
-    let pc = unsafe { return_address() };
+    let pc = hook_return_address();
     sensor.handle_trace_cmp(pc, u64::from(arg1), u64::from(arg2));
 }
 
@@ -148,10 +178,10 @@ fn trace_cmp4(arg1: u32, arg2: u32) {
 #[export_name = "__sanitizer_cov_trace_cmp8"]
 fn trace_cmp8(arg1: u64, arg2: u64) {
     let sensor = shared_sensor();
-    if !sensor.is_recording {
+    if !sensor.is_recording || !sensor.cmp_recording {
         return;
     }
-    let pc = unsafe { return_address() };
+    let pc = hook_return_address();
     sensor.handle_trace_cmp(pc, arg1, arg2);
 }
 
@@ -161,10 +191,10 @@ fn trace_cmp8(arg1: u64, arg2: u64) {
 #[export_name = "__sanitizer_cov_trace_const_cmp1"]
 fn trace_const_cmp1(arg1: u8, arg2: u8) {
     let sensor = shared_sensor();
-    if !sensor.is_recording {
+    if !sensor.is_recording || !sensor.cmp_recording {
         return;
     }
-    let pc = unsafe { return_address() };
+    let pc = hook_return_address();
 
     sensor.handle_trace_cmp(pc, u64::from(arg1), u64::from(arg2));
 }
@@ -175,10 +205,10 @@ fn trace_const_cmp1(arg1: u8, arg2: u8) {
 #[export_name = "__sanitizer_cov_trace_const_cmp2"]
 fn trace_const_cmp2(arg1: u16, arg2: u16) {
     let sensor = shared_sensor();
-    if !sensor.is_recording {
+    if !sensor.is_recording || !sensor.cmp_recording {
         return;
     }
-    let pc = unsafe { return_address() };
+    let pc = hook_return_address();
     sensor.handle_trace_cmp(pc, u64::from(arg1), u64::from(arg2));
 }
 
@@ -188,10 +218,10 @@ fn trace_const_cmp2(arg1: u16, arg2: u16) {
 #[export_name = "__sanitizer_cov_trace_const_cmp4"]
 fn trace_const_cmp4(arg1: u32, arg2: u32) {
     let sensor = shared_sensor();
-    if !sensor.is_recording {
+    if !sensor.is_recording || !sensor.cmp_recording {
         return;
     }
-    let pc = unsafe { return_address() };
+    let pc = hook_return_address();
     sensor.handle_trace_cmp(pc, u64::from(arg1), u64::from(arg2));
 }
 
@@ -201,10 +231,10 @@ fn trace_const_cmp4(arg1: u32, arg2: u32) {
 #[export_name = "__sanitizer_cov_trace_const_cmp8"]
 fn trace_const_cmp8(arg1: u64, arg2: u64) {
     let sensor = shared_sensor();
-    if !sensor.is_recording {
+    if !sensor.is_recording || !sensor.cmp_recording {
         return;
     }
-    let pc = unsafe { return_address() };
+    let pc = hook_return_address();
     sensor.handle_trace_cmp(pc, arg1, arg2);
 }
 
@@ -223,10 +253,10 @@ fn trace_const_cmp8(arg1: u64, arg2: u64) {
 #[export_name = "__sanitizer_cov_trace_switch"]
 fn trace_switch(val: u64, arg2: *mut u64) {
     let sensor = shared_sensor();
-    if !sensor.is_recording {
+    if !sensor.is_recording || !sensor.cmp_recording {
         return;
     }
-    let pc = unsafe { return_address() };
+    let pc = hook_return_address();
 
     let n = unsafe { *arg2 as usize };
     let mut cases = unsafe { slice::from_raw_parts_mut(arg2, n + 2).iter().take(1) };
@@ -250,10 +280,10 @@ fn trace_switch(val: u64, arg2: *mut u64) {
 #[export_name = "__sanitizer_cov_trace_div4"]
 fn trace_div4(val: u32) {
     let sensor = shared_sensor();
-    if !sensor.is_recording {
+    if !sensor.is_recording || !sensor.cmp_recording {
         return;
     }
-    let pc = unsafe { return_address() };
+    let pc = hook_return_address();
     sensor.handle_trace_cmp(pc, u64::from(val), 0);
 }
 
@@ -263,10 +293,10 @@ fn trace_div4(val: u32) {
 #[export_name = "__sanitizer_cov_trace_div8"]
 fn trace_div8(val: u64) {
     let sensor = shared_sensor();
-    if !sensor.is_recording {
+    if !sensor.is_recording || !sensor.cmp_recording {
         return;
     }
-    let pc = unsafe { return_address() };
+    let pc = hook_return_address();
     sensor.handle_trace_cmp(pc, val, 0);
 }
 
@@ -276,9 +306,9 @@ fn trace_div8(val: u64) {
 #[export_name = "__sanitizer_cov_trace_gep"]
 fn trace_gep(idx: libc::uintptr_t) {
     let sensor = shared_sensor();
-    if !sensor.is_recording {
+    if !sensor.is_recording || !sensor.cmp_recording {
         return;
     }
-    let pc = unsafe { return_address() };
+    let pc = hook_return_address();
     sensor.handle_trace_cmp(pc, idx as u64, 0);
 }
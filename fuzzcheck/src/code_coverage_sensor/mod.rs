@@ -5,11 +5,17 @@ mod hooks;
 use crate::Feature;
 
 use ahash::AHashSet;
+use std::collections::hash_map::DefaultHasher;
 use std::convert::TryFrom;
+use std::hash::Hasher;
 use std::mem::MaybeUninit;
 
 type PC = usize;
 
+/// The number of instrumented instructions reached last by a run that are
+/// kept to compute the signature of a crash
+const NBR_RECENT_PCS: usize = 16;
+
 static mut SHARED_SENSOR: MaybeUninit<CodeCoverageSensor> = MaybeUninit::<CodeCoverageSensor>::uninit();
 
 /// Returns a reference to the only `CodeCoverageSensor`
@@ -17,32 +23,100 @@ pub fn shared_sensor() -> &'static mut CodeCoverageSensor {
     unsafe { &mut *SHARED_SENSOR.as_mut_ptr() }
 }
 
+/// Initializes the shared sensor, without counters, for the tests of this
+/// crate, whose binary is not instrumented by SanitizerCoverage
+#[cfg(test)]
+pub(crate) fn init_shared_sensor_for_tests() {
+    static INIT: std::sync::Once = std::sync::Once::new();
+    INIT.call_once(|| unsafe {
+        (*std::ptr::addr_of_mut!(SHARED_SENSOR))
+            .as_mut_ptr()
+            .write(CodeCoverageSensor {
+                is_recording: false,
+                cmp_recording: true,
+                edge_recording: true,
+                eight_bit_counters: Box::leak(Vec::new().into_boxed_slice()),
+                features: AHashSet::new(),
+                recent_pcs: [0; NBR_RECENT_PCS],
+                nbr_recent_pcs: 0,
+            });
+    });
+}
+
 /// Records the code coverage of the program and converts it into `Feature`s
 /// that the `pool` can understand.
 pub struct CodeCoverageSensor {
     pub is_recording: bool,
+    /// Whether the operands of comparisons, switches, divisions, and array
+    /// indexing are recorded, in addition to the edges, while `is_recording`
+    /// is true. It is turned off by `--no-cmp-trace`.
+    pub cmp_recording: bool,
+    /// Whether the edges of the code coverage are recorded. It is turned off
+    /// by `--no-edge-trace`. The counters of the edges are still incremented
+    /// by the instrumented code, but they are then ignored.
+    pub edge_recording: bool,
     eight_bit_counters: &'static mut [u8],
     features: AHashSet<Feature>, //  could it be a BTreeSet?
+    /// The addresses of the last comparisons and indirect calls reached by
+    /// the current run, as a ring buffer. It is allocated with the sensor so
+    /// that it can be read from a signal handler.
+    recent_pcs: [PC; NBR_RECENT_PCS],
+    /// The number of addresses that were written to `recent_pcs` during the
+    /// current run, including those that were overwritten since
+    nbr_recent_pcs: usize,
 }
 
 impl CodeCoverageSensor {
     /// Handles a `trace_cmp` hook from SanitizerCoverage, by recording it
     /// as a `Feature` of kind `instruction`.
     fn handle_trace_cmp(&mut self, pc: PC, arg1: u64, arg2: u64) {
+        self.record_recent_pc(pc);
         let f = Feature::instruction(pc, arg1, arg2);
         self.features.insert(f);
     }
+    /// Handles a call to [compare_floats](crate::compare_floats), by recording
+    /// it as a `Feature` of kind `instruction`.
+    pub(crate) fn handle_float_comparison(&mut self, id: usize, arg1: f64, arg2: f64) {
+        let f = Feature::float_instruction(id, arg1, arg2);
+        self.features.insert(f);
+    }
     /// Handles a `trace_indir` hook from SanitizerCoverage, by recording it
     /// as a `Feature` of kind `indirect`.
     fn handle_trace_indir(&mut self, caller: PC, callee: PC) {
+        self.record_recent_pc(caller);
         let f = Feature::indir(caller ^ callee);
         self.features.insert(f);
     }
+    /// Handles a call to [penalize](crate::penalize), by recording it as a
+    /// `Feature` of kind `penalty`.
+    pub(crate) fn handle_penalty(&mut self, id: usize) {
+        let f = Feature::penalty(id);
+        self.features.insert(f);
+    }
 
     /// Runs the closure on all recorded features.
     pub(crate) fn iterate_over_collected_features<F>(&mut self, mut handle: F)
     where
-        F: FnMut(Feature) -> (),
+        F: FnMut(Feature),
+    {
+        if self.edge_recording {
+            self.iterate_over_edges(&mut handle);
+        }
+
+        // TODO: could covert features into a Vec and then sort that, will do it
+        // for now, but in the future I may need a proper alternative
+        let mut op_features: Vec<_> = self.features.iter().copied().collect();
+        op_features.sort();
+
+        for f in op_features.iter() {
+            handle(*f);
+        }
+    }
+
+    /// Runs the closure on the edges recorded by the counters.
+    fn iterate_over_edges<F>(&self, handle: &mut F)
+    where
+        F: FnMut(Feature),
     {
         const CHUNK_SIZE: usize = 32;
         let length_chunks = self.eight_bit_counters.len() / CHUNK_SIZE;
@@ -78,15 +152,12 @@ impl CodeCoverageSensor {
                 handle(f);
             }
         }
+    }
 
-        // TODO: could covert features into a Vec and then sort that, will do it
-        // for now, but in the future I may need a proper alternative
-        let mut op_features: Vec<_> = self.features.iter().copied().collect();
-        op_features.sort();
-
-        for f in op_features.iter() {
-            handle(*f);
-        }
+    /// The number of edge guards of the instrumented code, which is also the
+    /// number of counters
+    pub fn nbr_guards(&self) -> usize {
+        self.eight_bit_counters.len()
     }
 
     pub fn clear(&mut self) {
@@ -94,5 +165,106 @@ impl CodeCoverageSensor {
             *x = 0;
         }
         self.features.clear();
+        self.nbr_recent_pcs = 0;
+    }
+
+    /// Adds the address of an instrumented instruction to the ring buffer of
+    /// the last ones reached by the run
+    #[inline(always)]
+    fn record_recent_pc(&mut self, pc: PC) {
+        // all the instructions share the address 0 on the platforms where it
+        // cannot be read, see `hook_return_address`
+        if pc == 0 {
+            return;
+        }
+        self.recent_pcs[self.nbr_recent_pcs % NBR_RECENT_PCS] = pc;
+        self.nbr_recent_pcs += 1;
+    }
+
+    /// A hash of the place where the current run failed, which tells apart
+    /// the inputs that trigger different bugs.
+    ///
+    /// The counters of the edges do not record the order in which they were
+    /// reached, so the signature is computed from the distinct addresses of
+    /// the last comparisons and indirect calls of the run, which are closest
+    /// to the failure. When none was recorded, for example with
+    /// `--no-cmp-trace`, or because the address of the instructions cannot be
+    /// read on this platform, it is computed from all the edges reached by
+    /// the run instead.
+    ///
+    /// It does not allocate, so that it can be called from a signal handler.
+    pub(crate) fn crash_signature(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        let nbr_pcs = self.nbr_recent_pcs.min(NBR_RECENT_PCS);
+        if nbr_pcs > 0 {
+            let mut pcs = self.recent_pcs;
+            let pcs = &mut pcs[..nbr_pcs];
+            pcs.sort_unstable();
+            for (i, &pc) in pcs.iter().enumerate() {
+                if i == 0 || pcs[i - 1] != pc {
+                    hasher.write_usize(pc);
+                }
+            }
+        } else {
+            for (i, &counter) in self.eight_bit_counters.iter().enumerate() {
+                if counter != 0 {
+                    hasher.write_usize(i);
+                }
+            }
+        }
+        hasher.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sensor(nbr_guards: usize) -> CodeCoverageSensor {
+        CodeCoverageSensor {
+            is_recording: true,
+            cmp_recording: true,
+            edge_recording: true,
+            eight_bit_counters: Box::leak(vec![0; nbr_guards].into_boxed_slice()),
+            features: AHashSet::new(),
+            recent_pcs: [0; NBR_RECENT_PCS],
+            nbr_recent_pcs: 0,
+        }
+    }
+
+    #[test]
+    fn test_crash_signature() {
+        let mut sensor = sensor(4);
+        sensor.eight_bit_counters[1] = 1;
+        for pc in 1..100 {
+            sensor.handle_trace_cmp(pc, 0, 0);
+        }
+        let signature = sensor.crash_signature();
+
+        // only the last comparisons matter, not their order or the edges
+        sensor.clear();
+        sensor.eight_bit_counters[2] = 3;
+        for pc in (50..100).rev() {
+            sensor.handle_trace_cmp(pc, 1, 2);
+        }
+        for pc in 84..100 {
+            sensor.handle_trace_cmp(pc, 1, 2);
+        }
+        assert_eq!(signature, sensor.crash_signature());
+
+        sensor.clear();
+        for pc in 1..99 {
+            sensor.handle_trace_cmp(pc, 0, 0);
+        }
+        assert_ne!(signature, sensor.crash_signature());
+
+        // without comparisons, the edges tell the crashes apart
+        sensor.clear();
+        sensor.eight_bit_counters[1] = 1;
+        let signature = sensor.crash_signature();
+        sensor.eight_bit_counters[1] = 4;
+        assert_eq!(signature, sensor.crash_signature());
+        sensor.eight_bit_counters[3] = 1;
+        assert_ne!(signature, sensor.crash_signature());
     }
 }
@@ -1,6 +1,8 @@
 //! Collection of data structures and algorithms used by the rest of fuzzcheck
 
-use std::cmp::Ordering;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap};
+use std::hash::{Hash, Hasher};
 
 use core::cmp::PartialOrd;
 use rand::distributions::uniform::{SampleUniform, UniformSampler};
@@ -116,6 +118,37 @@ mod tests {
         let y = iter.find(|x| x.cmp(&86));
         assert_eq!(y, Some(89));
     }
+
+    #[test]
+    fn test_largest_keys() {
+        let mut largest = LargestKeys::new(3);
+        for (key, value) in [5, 1, 9, 7, 3, 9, 2].iter().zip("abcdefg".chars()) {
+            largest.insert(*key, value);
+        }
+        let keys: Vec<i32> = largest.sorted().into_iter().map(|(k, _)| *k).collect();
+        assert_eq!(keys, vec![9, 9, 7]);
+        assert_eq!(*largest.sorted()[2].1, 'd');
+        assert!(!largest.would_keep(&7));
+        assert!(largest.would_keep(&8));
+        assert!(LargestKeys::<usize, ()>::new(0).sorted().is_empty());
+    }
+
+    #[test]
+    fn test_slab_compact() {
+        let mut slab = Slab::new();
+        let keys: Vec<_> = (0..10).map(|x| slab.insert(x)).collect();
+        for &key in keys.iter().filter(|k| k.key % 3 != 0) {
+            slab.remove(key);
+        }
+        assert_eq!(slab.nbr_available_slots(), 6);
+
+        let new_keys = slab.compact();
+        assert_eq!(slab.nbr_available_slots(), 0);
+        assert_eq!(new_keys.len(), 4);
+        for &old_key in keys.iter().filter(|k| k.key % 3 == 0) {
+            assert_eq!(slab[new_keys[&old_key]], old_key.key);
+        }
+    }
 }
 
 // ========= Slab ============
@@ -174,6 +207,12 @@ impl<T> fmt::Debug for SlabKey<T> {
     }
 }
 
+impl<T> Hash for SlabKey<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key.hash(state)
+    }
+}
+
 impl<T> PartialOrd for SlabKey<T> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.key.cmp(&other.key))
@@ -225,6 +264,35 @@ impl<T> Slab<T> {
         }
     }
 
+    /// The number of slots that were freed by [remove](Slab::remove) and not reused yet
+    pub fn nbr_available_slots(&self) -> usize {
+        self.available_slots.len()
+    }
+
+    /// Rebuilds the storage of the slab without its removed elements.
+    ///
+    /// Returns a map from the old key of each remaining element to its new key.
+    pub fn compact(&mut self) -> HashMap<SlabKey<T>, SlabKey<T>> {
+        let mut available_slots = std::mem::take(&mut self.available_slots);
+        available_slots.sort();
+        available_slots.dedup();
+        let mut available_slots = available_slots.into_iter().peekable();
+
+        let old_storage = std::mem::take(&mut self.storage);
+        let mut new_keys = HashMap::with_capacity(old_storage.len());
+        for (old_key, x) in old_storage.into_iter().enumerate() {
+            if available_slots.peek() == Some(&old_key) {
+                available_slots.next();
+                continue;
+            }
+            new_keys.insert(SlabKey::new(old_key), SlabKey::new(self.storage.len()));
+            self.storage.push(x);
+        }
+        self.storage.shrink_to_fit();
+
+        new_keys
+    }
+
     pub fn get_mut(&mut self, key: SlabKey<T>) -> Option<&mut T> {
         // O(n) but in practice very fast because there will be almost no available slots
         if self.available_slots.contains(&key.key) {
@@ -279,3 +347,72 @@ where
             .unwrap_err()
     }
 }
+
+// ========= LargestKeys ============
+
+/**
+ * The values with the largest keys among all the values that were inserted,
+ * up to a fixed number of them, such as the inputs that took the longest to
+ * test.
+ *
+ * It is a min-heap, whose smallest key is evicted when a larger one is
+ * inserted into it while it is full.
+ */
+pub struct LargestKeys<K: Ord, V> {
+    capacity: usize,
+    heap: BinaryHeap<Reverse<KeyedValue<K, V>>>,
+}
+
+/// A value ordered by its key only
+struct KeyedValue<K: Ord, V> {
+    key: K,
+    value: V,
+}
+impl<K: Ord, V> PartialEq for KeyedValue<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+impl<K: Ord, V> Eq for KeyedValue<K, V> {}
+impl<K: Ord, V> PartialOrd for KeyedValue<K, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl<K: Ord, V> Ord for KeyedValue<K, V> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key)
+    }
+}
+
+impl<K: Ord, V> LargestKeys<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            heap: BinaryHeap::with_capacity(capacity + 1),
+        }
+    }
+
+    /// Whether a value with the given key would be kept, so that the value
+    /// is only built when it is needed
+    pub fn would_keep(&self, key: &K) -> bool {
+        self.heap.len() < self.capacity || self.heap.peek().map_or(false, |smallest| *key > smallest.0.key)
+    }
+
+    pub fn insert(&mut self, key: K, value: V) {
+        if !self.would_keep(&key) {
+            return;
+        }
+        self.heap.push(Reverse(KeyedValue { key, value }));
+        if self.heap.len() > self.capacity {
+            let _ = self.heap.pop();
+        }
+    }
+
+    /// The kept keys and values, the largest key first
+    pub fn sorted(&self) -> Vec<(&K, &V)> {
+        let mut elements: Vec<_> = self.heap.iter().map(|e| (&e.0.key, &e.0.value)).collect();
+        elements.sort_by(|a, b| b.0.cmp(a.0));
+        elements
+    }
+}
@@ -3,20 +3,107 @@
 //! test inputs.
 
 use crate::code_coverage_sensor::*;
-use crate::data_structures::{LargeStepFindIter, SlabKey};
+use crate::data_structures::{LargeStepFindIter, LargestKeys, SlabKey};
 use crate::pool::*;
+use crate::replay::ReplayMutator;
 use crate::signals_handler::*;
 use crate::world::*;
 use crate::{Feature, FuzzedInput, Mutator, Serializer};
 
 use fuzzcheck_arg_parser::*;
 
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::os::unix::io::FromRawFd;
 use std::panic::{catch_unwind, RefUnwindSafe, UnwindSafe};
+use std::path::PathBuf;
 use std::process::exit;
+use std::rc::Rc;
 use std::result::Result;
+use std::sync::atomic::{AtomicI32, Ordering as AtomicOrdering};
+use std::time::{Duration, Instant};
 
 use std::borrow::Borrow;
 
+/// The number of inputs tested in each round of [Fuzzer::bootstrap]
+const BOOTSTRAP_BATCH_SIZE: usize = 100;
+/// The number of rounds of [Fuzzer::bootstrap]
+const BOOTSTRAP_ROUNDS: usize = 10;
+/// The largest number of iterations between two [FuzzerEvent::Pulse]
+const MAX_PULSE_INTERVAL: usize = 1 << 20;
+/// The smallest initial maximum complexity of the inputs with `--adaptive-cplx`
+const ADAPTIVE_CPLX_START: f64 = 16.0;
+/// The number of iterations without a new feature after which `--adaptive-cplx`
+/// doubles the maximum complexity of the inputs
+const ADAPTIVE_CPLX_PLATEAU: usize = 100_000;
+/// The largest number of iterations over which `--initial-cplx` raises the
+/// maximum complexity of the inputs up to `--max-cplx`
+const INITIAL_CPLX_RAMP: usize = 1_000_000;
+/// The largest number of iterations for which `--save-all-inputs` can be
+/// used without a warning
+const SAVE_ALL_INPUTS_MAX_ITER: usize = 1_000_000;
+/// The number of mutations of each arbitrary value checked by `selftest`
+const SELF_TEST_MUTATIONS: usize = 10;
+/// The initial probability that the fuzzing loop tests a new arbitrary input
+/// instead of mutating an input of the pool
+const ARBITRARY_RATIO_START: f64 = 0.1;
+/// The bounds of the probability of testing an arbitrary input, so that
+/// neither kind of input is ever abandoned
+const ARBITRARY_RATIO_MIN: f64 = 0.01;
+const ARBITRARY_RATIO_MAX: f64 = 0.5;
+/// The weight of the latest run in the moving averages of [InputYield]
+const INPUT_YIELD_DECAY: f64 = 0.001;
+/// With --arbitrary-from-corpus, the probability that a new arbitrary input
+/// is built from two inputs of the pool instead of from scratch
+const CORPUS_CROSSOVER_RATIO: f64 = 0.5;
+/// The number of inputs of the corpus tested between two
+/// [FuzzerEvent::LoadingCorpus], unless [CORPUS_LOADING_PROGRESS_PERIOD]
+/// elapses first
+const CORPUS_LOADING_PROGRESS_INTERVAL: usize = 1000;
+const CORPUS_LOADING_PROGRESS_PERIOD: Duration = Duration::from_secs(1);
+
+/// The recent productivity of arbitrary and mutated inputs, measured as a
+/// moving average of the number of new features found by each run.
+///
+/// The fuzzing loop tests arbitrary inputs with a probability proportional to
+/// their share of the total productivity, within [ARBITRARY_RATIO_MIN] and
+/// [ARBITRARY_RATIO_MAX].
+struct InputYield {
+    arbitrary: f64,
+    mutate: f64,
+}
+impl InputYield {
+    fn new() -> Self {
+        // a small prior, which the first runs quickly outweigh
+        Self {
+            arbitrary: ARBITRARY_RATIO_START * INPUT_YIELD_DECAY,
+            mutate: (1.0 - ARBITRARY_RATIO_START) * INPUT_YIELD_DECAY,
+        }
+    }
+    fn record_arbitrary(&mut self, nbr_new_features: usize) {
+        self.arbitrary += (nbr_new_features as f64 - self.arbitrary) * INPUT_YIELD_DECAY;
+    }
+    fn record_mutate(&mut self, nbr_new_features: usize) {
+        self.mutate += (nbr_new_features as f64 - self.mutate) * INPUT_YIELD_DECAY;
+    }
+    /// The probability of testing an arbitrary input instead of a mutated one
+    fn arbitrary_ratio(&self) -> f64 {
+        let total = self.arbitrary + self.mutate;
+        if total > 0.0 {
+            (self.arbitrary / total).clamp(ARBITRARY_RATIO_MIN, ARBITRARY_RATIO_MAX)
+        } else {
+            ARBITRARY_RATIO_START
+        }
+    }
+}
+
 enum FuzzerInputIndex<M: Mutator> {
     Temporary(FuzzedInput<M>),
     Pool(PoolIndex<M>),
@@ -25,27 +112,47 @@ enum FuzzerInputIndex<M: Mutator> {
 struct AnalysisCache<M: Mutator> {
     existing_features: Vec<SlabKey<FeatureInPool<M>>>,
     new_features: Vec<Feature>,
+    /// The features collected by the forked process that ran the last test input
+    forked_features: Vec<Feature>,
+    /// The number of features hit by the last test input
+    nbr_features: usize,
+    /// The number of features hit by the last test input that were not in the pool
+    nbr_new_features: usize,
 }
 impl<M: Mutator> Default for AnalysisCache<M> {
     fn default() -> Self {
         Self {
             existing_features: Vec::new(),
             new_features: Vec::new(),
+            forked_features: Vec::new(),
+            nbr_features: 0,
+            nbr_new_features: 0,
         }
     }
 }
 
-struct FuzzerState<M: Mutator, S: Serializer<Value = M::Value>> {
+struct FuzzerState<M: Mutator> {
     mutator: M,
     pool: Pool<M>,
     input_idx: FuzzerInputIndex<M>,
     stats: FuzzerStats,
     settings: CommandLineArguments,
-    world: World<S>,
+    world: Box<dyn World<Value = M::Value>>,
     analysis_cache: AnalysisCache<M>,
+    input_yield: InputYield,
+    rng: SmallRng,
+    /// The seed from which the mutations of the inputs of the pool are
+    /// derived, so that they do not depend on the order in which the inputs
+    /// are picked
+    mutation_seed: u64,
+    /// The inputs that took the longest to test, with --report-slowest-inputs
+    slowest_inputs: Option<LargestKeys<Duration, M::Value>>,
+    /// When the test function started to run on the current input, while it
+    /// runs, so that the time of an input that timed out is known
+    run_start: Cell<Option<Instant>>,
 }
 
-impl<M: Mutator, S: Serializer<Value = M::Value>> FuzzerState<M, S> {
+impl<M: Mutator> FuzzerState<M> {
     fn get_input(&self) -> &FuzzedInput<M> {
         match &self.input_idx {
             FuzzerInputIndex::Temporary(input) => &input,
@@ -54,7 +161,7 @@ impl<M: Mutator, S: Serializer<Value = M::Value>> FuzzerState<M, S> {
     }
 }
 
-impl<M: Mutator, S: Serializer<Value = M::Value>> FuzzerState<M, S> {
+impl<M: Mutator> FuzzerState<M> {
     fn update_stats(&mut self) {
         let microseconds = self.world.elapsed_time();
         self.stats.exec_per_s = ((((self.stats.total_number_of_runs - self.stats.number_of_runs_since_last_reset_time)
@@ -64,67 +171,194 @@ impl<M: Mutator, S: Serializer<Value = M::Value>> FuzzerState<M, S> {
         self.stats.pool_size = self.pool.len();
         self.stats.score = self.pool.score();
         self.stats.avg_cplx = self.pool.average_complexity;
+        self.stats.budget_utilization = self.stats.avg_cplx / self.settings.max_input_cplx;
+        self.stats.arbitrary_ratio = self.input_yield.arbitrary_ratio();
         if microseconds > 1_000_000 {
+            // sorting the complexities of the pool is only worth it when reporting them
+            self.stats.median_cplx = self.pool.complexity_percentile(50.0);
             self.world.set_start_time();
+            self.world.record_throughput(self.stats.exec_per_s);
             self.stats.number_of_runs_since_last_reset_time = self.stats.total_number_of_runs;
+            self.world.report_event(FuzzerEvent::Stats, Some(self.stats));
         }
     }
 
+    /// Prints the `pc_guard` of each edge and the `pc` of each comparison
+    /// that produced a feature in the pool, along with the number of features
+    /// it produced, so that they can be symbolized with `addr2line`.
+    fn print_pcs(&self) {
+        let mut pcs: Vec<(&'static str, u64, usize)> = Vec::new();
+        // the features are sorted by tag and then by id, so features with the same pc are adjacent
+        for f in self.pool.features.iter().map(|f| f.feature) {
+            let kind = if f.tag() == Feature::instr_tag() {
+                "cmp"
+            } else if f.tag() == Feature::indir_tag() || f.is_penalty() {
+                // the id of an indirect call or penalty feature is not a pc
+                continue;
+            } else {
+                "edge"
+            };
+            match pcs.last_mut() {
+                Some((last_kind, pc, nbr_features)) if *last_kind == kind && *pc == f.id() => *nbr_features += 1,
+                _ => pcs.push((kind, f.id(), 1)),
+            }
+        }
+        self.world.report_event(FuzzerEvent::Pcs(pcs), None);
+    }
+
     fn receive_signal(&self, signal: i32) -> ! {
         self.world
             .report_event(FuzzerEvent::CaughtSignal(signal), Some(self.stats));
 
-        match signal {
-            4 | 6 | 10 | 11 | 8 => {
+        let status = termination_status(signal);
+        match status {
+            FuzzerTerminationStatus::Crash | FuzzerTerminationStatus::Timeout => {
                 let input = self.get_input();
                 let cplx = input.complexity(&self.mutator);
-                let _ = self.world.save_artifact(&input.value, cplx);
+                // with --fork, the crash happened in another process, whose features are lost
+                let signature = if self.settings.fork {
+                    None
+                } else {
+                    Some(shared_sensor().crash_signature())
+                };
+                let description = self.mutator.describe_value(&input.value);
+                let _ = self
+                    .world
+                    .save_artifact(&input.value, cplx, signature, description, input.replay_log.as_ref());
+            }
+            FuzzerTerminationStatus::Success => {
+                if self.settings.print_pcs {
+                    self.print_pcs();
+                }
+                let _ = self.save_global_features();
+            }
+            _ => (),
+        }
+        // the slowest inputs are most useful when the run ends with a timeout
+        let _ = self.save_slowest_inputs(matches!(status, FuzzerTerminationStatus::Timeout));
+        let _ = self.save_coverage_bitmap();
+        status.exit()
+    }
+
+    /// Writes the features covered by this run and by the previous ones to
+    /// the file given by `--features-out` and reports how many of them are new
+    fn save_global_features(&self) -> Result<(), std::io::Error> {
+        if self.settings.features_in.is_none() && self.settings.features_out.is_none() {
+            return Ok(());
+        }
+        let features = self.pool.all_global_features();
+        self.world.report_event(
+            FuzzerEvent::GlobalFeatures {
+                globally_new: self.pool.nbr_globally_new_features(),
+                total: features.len(),
+            },
+            None,
+        );
+        self.world.write_global_features(&features)
+    }
 
-                exit(FuzzerTerminationStatus::Crash as i32);
+    /// Writes the edges covered by this run to the file given by
+    /// `--coverage-out`
+    fn save_coverage_bitmap(&self) -> Result<(), std::io::Error> {
+        if self.settings.coverage_out.is_none() {
+            return Ok(());
+        }
+        let nbr_guards = shared_sensor().nbr_guards();
+        self.world
+            .write_coverage_bitmap(nbr_guards, &self.pool.coverage_bitmap(nbr_guards))
+    }
+
+    /// Saves the inputs that took the longest to test, with
+    /// `--report-slowest-inputs`.
+    ///
+    /// The time of a run is only recorded once the test function returns.
+    /// So when the run of the current input `timed_out`, it is added with
+    /// the time elapsed since it started.
+    fn save_slowest_inputs(&self, timed_out: bool) -> Result<(), std::io::Error> {
+        let slowest_inputs = if let Some(slowest_inputs) = &self.slowest_inputs {
+            slowest_inputs
+        } else {
+            return Ok(());
+        };
+        let mut inputs = slowest_inputs.sorted();
+        let elapsed = self.run_start.get().filter(|_| timed_out).map(|start| start.elapsed());
+        if let Some(elapsed) = &elapsed {
+            if slowest_inputs.would_keep(elapsed) {
+                let idx = inputs.iter().position(|(d, _)| *d < elapsed).unwrap_or(inputs.len());
+                inputs.insert(idx, (elapsed, &self.get_input().value));
+                inputs.truncate(self.settings.report_slowest_inputs.unwrap_or(0));
             }
-            2 | 15 => exit(FuzzerTerminationStatus::Success as i32),
-            _ => exit(FuzzerTerminationStatus::Unknown as i32),
         }
+        self.world.save_slowest_inputs(&inputs)
     }
 
     unsafe fn set_up_signal_handler(&self) {
         let ptr = NotThreadSafe(self as *const Self);
-        handle_signals(vec![4, 6, 10, 11, 8, 2, 15], move |sig| (&*ptr.0).receive_signal(sig));
+        handle_signals(HANDLED_SIGNALS.to_vec(), move |sig| (&*ptr.0).receive_signal(sig));
+    }
+}
+
+const HANDLED_SIGNALS: [i32; 9] = [4, 6, 10, 11, 8, 14, 24, 2, 15];
+
+/// How the run ends when the fuzzer, or the forked process running the test
+/// function, is stopped by the given signal
+fn termination_status(signal: i32) -> FuzzerTerminationStatus {
+    match signal {
+        4 | 6 | 10 | 11 | 8 => FuzzerTerminationStatus::Crash,
+        // sent by alarm(2) and by the CPU time limit of setrlimit(2) or `ulimit -t`
+        14 | 24 => FuzzerTerminationStatus::Timeout,
+        // a forked process killed by SIGKILL was most likely killed by the OOM killer
+        9 => FuzzerTerminationStatus::OutOfMemory,
+        2 | 15 => FuzzerTerminationStatus::Success,
+        _ => FuzzerTerminationStatus::Unknown,
     }
 }
 
-pub struct Fuzzer<T, F, M, S>
+pub struct Fuzzer<T, F, M>
 where
     T: ?Sized,
     M::Value: Borrow<T>,
     F: Fn(&T) -> bool,
     M: Mutator,
-    S: Serializer<Value = M::Value>,
 {
-    state: FuzzerState<M, S>,
+    state: FuzzerState<M>,
     test: F,
     phantom: std::marker::PhantomData<T>,
 }
 
-impl<T, F, M, S> Fuzzer<T, F, M, S>
+impl<T, F, M> Fuzzer<T, F, M>
 where
     T: ?Sized,
     M::Value: Borrow<T>,
     F: Fn(&T) -> bool,
     M: Mutator,
-    S: Serializer<Value = M::Value>,
 {
-    pub fn new(test: F, mutator: M, settings: CommandLineArguments, world: World<S>) -> Self {
+    fn new(test: F, mutator: M, mut settings: CommandLineArguments, world: Box<dyn World<Value = M::Value>>) -> Self {
         let default_el = FuzzedInput::default(&mutator);
+        // without --seed, a seed is drawn and recorded in the settings, so
+        // that it is written by --metadata-out and the run can be replayed
+        let seed = *settings.seed.get_or_insert_with(|| SmallRng::from_entropy().gen()) as u64;
+        // the pool and the fuzzer must not draw the same numbers
+        let mut pool = Pool::new(seed.wrapping_add(1));
+        pool.keep_seeds = settings.keep_seeds;
+        pool.energy_decay = settings.energy_decay.unwrap_or(DEFAULT_ENERGY_DECAY);
+        let rng = SmallRng::seed_from_u64(seed);
+        let mutation_seed = seed.wrapping_add(2);
+        let settings_slowest_inputs = settings.report_slowest_inputs.map(LargestKeys::new);
         Fuzzer {
             state: FuzzerState {
                 mutator,
-                pool: Pool::default(),
+                pool,
                 input_idx: FuzzerInputIndex::Temporary(default_el),
                 stats: FuzzerStats::new(),
                 settings,
                 world,
                 analysis_cache: AnalysisCache::default(),
+                input_yield: InputYield::new(),
+                rng,
+                mutation_seed,
+                slowest_inputs: settings_slowest_inputs,
+                run_start: Cell::new(None),
             },
             test,
             phantom: std::marker::PhantomData,
@@ -139,13 +373,36 @@ where
         }
     }
 
+    /// The time at which minification must stop, given by `--minimize-timeout`
+    fn minimize_deadline(&self) -> Option<Instant> {
+        self.state
+            .settings
+            .minimize_timeout
+            .map(|secs| Instant::now() + Duration::from_secs(secs as u64))
+    }
+
+    /// Returns true and reports it if the deadline has passed
+    fn deadline_reached(&self, deadline: Option<Instant>) -> bool {
+        if deadline.map_or(false, |deadline| Instant::now() >= deadline) {
+            self.state
+                .world
+                .report_event(FuzzerEvent::DeadlineReached, Some(self.state.stats));
+            true
+        } else {
+            false
+        }
+    }
+
     fn test_input(
         test: &F,
         mutator: &M,
         input: &FuzzedInput<M>,
-        world: &World<S>,
+        world: &dyn World<Value = M::Value>,
         stats: FuzzerStats,
     ) -> Result<(), std::io::Error> {
+        // the signal handler cannot serialize the input if the test crashes
+        world.capture_tested_input(&input.value)?;
+
         let sensor = shared_sensor();
         sensor.clear();
 
@@ -161,15 +418,134 @@ where
 
         if result.is_err() || !result.unwrap() {
             world.report_event(FuzzerEvent::TestFailure, Some(stats));
-            let mut features: Vec<Feature> = Vec::new();
-            sensor.iterate_over_collected_features(|f| features.push(f));
-            world.save_artifact(&input.value, input.complexity(mutator))?;
-            exit(FuzzerTerminationStatus::TestFailure as i32);
+            let signature = shared_sensor().crash_signature();
+            world.save_artifact(
+                &input.value,
+                input.complexity(mutator),
+                Some(signature),
+                mutator.describe_value(&input.value),
+                input.replay_log.as_ref(),
+            )?;
+            FuzzerTerminationStatus::Crash.exit();
         }
 
         Ok(())
     }
 
+    /// Runs the test function on the input in a forked process, so that a
+    /// crash or memory corruption in the tested code cannot affect the pool.
+    ///
+    /// The features collected by the forked process are sent back through a
+    /// pipe and returned. If the forked process crashed or the test failed,
+    /// the input is saved as an artifact and the fuzzer exits, as it would
+    /// without `--fork`.
+    fn test_input_in_fork(
+        test: &F,
+        mutator: &M,
+        input: &FuzzedInput<M>,
+        world: &dyn World<Value = M::Value>,
+        stats: FuzzerStats,
+    ) -> Result<Vec<Feature>, std::io::Error> {
+        let mut fds: [libc::c_int; 2] = [0; 2];
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let (read_fd, write_fd) = (fds[0], fds[1]);
+
+        match unsafe { libc::fork() } {
+            -1 => Err(std::io::Error::last_os_error()),
+            0 => {
+                // forked process: run the test and send the collected features to the parent
+                unsafe { libc::close(read_fd) };
+                reset_signal_handlers(&HANDLED_SIGNALS);
+
+                let sensor = shared_sensor();
+                sensor.clear();
+
+                sensor.is_recording = true;
+
+                let cell = NotUnwindSafe { value: &test };
+                let input_cell = NotUnwindSafe {
+                    value: input.value.borrow(),
+                };
+                let result = catch_unwind(|| (cell.value)(input_cell.value));
+
+                sensor.is_recording = false;
+
+                if result.is_err() || !result.unwrap() {
+                    unsafe { libc::_exit(FuzzerTerminationStatus::Crash as i32) };
+                }
+
+                let mut data: Vec<u8> = Vec::new();
+                sensor.iterate_over_collected_features(|f| data.extend_from_slice(&f.0.to_ne_bytes()));
+
+                let mut pipe = unsafe { File::from_raw_fd(write_fd) };
+                let status = if pipe.write_all(&data).is_ok() {
+                    FuzzerTerminationStatus::Success
+                } else {
+                    FuzzerTerminationStatus::Unknown
+                };
+                unsafe { libc::_exit(status as i32) };
+            }
+            child => {
+                unsafe { libc::close(write_fd) };
+                let mut pipe = unsafe { File::from_raw_fd(read_fd) };
+                let mut data: Vec<u8> = Vec::new();
+                pipe.read_to_end(&mut data)?;
+
+                let mut status: libc::c_int = 0;
+                if unsafe { libc::waitpid(child, &mut status, 0) } == -1 {
+                    return Err(std::io::Error::last_os_error());
+                }
+
+                if unsafe { libc::WIFSIGNALED(status) } {
+                    let signal = unsafe { libc::WTERMSIG(status) };
+                    world.report_event(FuzzerEvent::CaughtSignal(signal), Some(stats));
+                    let status = termination_status(signal);
+                    if let FuzzerTerminationStatus::Crash
+                    | FuzzerTerminationStatus::Timeout
+                    | FuzzerTerminationStatus::OutOfMemory = status
+                    {
+                        world.save_artifact(
+                            &input.value,
+                            input.complexity(mutator),
+                            None,
+                            mutator.describe_value(&input.value),
+                            input.replay_log.as_ref(),
+                        )?;
+                    }
+                    status.exit();
+                }
+                match unsafe { libc::WEXITSTATUS(status) } {
+                    x if x == FuzzerTerminationStatus::Success as i32 => (),
+                    x if x == FuzzerTerminationStatus::Crash as i32 => {
+                        world.report_event(FuzzerEvent::TestFailure, Some(stats));
+                        world.save_artifact(
+                            &input.value,
+                            input.complexity(mutator),
+                            None,
+                            mutator.describe_value(&input.value),
+                            input.replay_log.as_ref(),
+                        )?;
+                        FuzzerTerminationStatus::Crash.exit();
+                    }
+                    _ => {
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::Other,
+                            "The forked process could not send its code coverage.",
+                        ))
+                    }
+                }
+
+                let features = data
+                    .chunks_exact(8)
+                    .map(|bytes| Feature(u64::from_ne_bytes(<[u8; 8]>::try_from(bytes).unwrap())))
+                    .collect();
+                Ok(features)
+            }
+        }
+    }
+
     fn analyze(&mut self, cur_input_cplx: f64) -> Option<(Vec<SlabKey<FeatureInPool<M>>>, Vec<Feature>)> {
         let mut best_input_for_a_feature = false;
 
@@ -181,52 +557,106 @@ where
 
         let existing_features = &mut self.state.analysis_cache.existing_features;
         let new_features = &mut self.state.analysis_cache.new_features;
+        let forked_features = &self.state.analysis_cache.forked_features;
+        let mut nbr_features = 0;
 
-        sensor.iterate_over_collected_features(|feature| {
+        let handle_feature = |feature: Feature| {
+            nbr_features += 1;
+            // a penalty feature only lowers the score of the input, it never makes it interesting
+            let is_penalty = feature.is_penalty();
             if let Some(f_for_iter) = step_iter.find(|feature_for_iter| feature_for_iter.feature.cmp(&feature)) {
                 if f_for_iter.feature == feature {
                     existing_features.push(f_for_iter.key);
                     let f = &slab_features[f_for_iter.key];
-                    if cur_input_cplx < f.least_complexity {
+                    if cur_input_cplx < f.least_complexity && !is_penalty {
                         best_input_for_a_feature = true;
                     }
                 } else {
-                    best_input_for_a_feature = true;
+                    best_input_for_a_feature |= !is_penalty;
                     new_features.push(feature);
                 }
             } else {
-                best_input_for_a_feature = true; // the feature goes at the end of the pool, and it is new
+                best_input_for_a_feature |= !is_penalty; // the feature goes at the end of the pool, and it is new
                 new_features.push(feature);
             }
-        });
+        };
+        if self.state.settings.fork {
+            forked_features.iter().copied().for_each(handle_feature);
+        } else {
+            sensor.iterate_over_collected_features(handle_feature);
+        }
 
         let result = if best_input_for_a_feature {
             Some((existing_features.clone(), new_features.clone()))
         } else {
             None
         };
+        self.state.analysis_cache.nbr_new_features = new_features.iter().filter(|f| !f.is_penalty()).count();
         existing_features.clear();
         new_features.clear();
+        self.state.analysis_cache.nbr_features = nbr_features;
 
         result
     }
 
-    fn test_input_and_analyze(&mut self) -> Result<(), std::io::Error> {
+    /// Tests the current input and adds it to the pool if it is interesting.
+    /// The origin tells whether it is a seed read from the input corpus.
+    fn test_input_and_analyze(&mut self, origin: InputOrigin) -> Result<(), std::io::Error> {
         let input = self.state.get_input();
         let cplx = input.complexity(&self.state.mutator);
-        Self::test_input(
-            &self.test,
-            &self.state.mutator,
-            &input,
-            &self.state.world,
-            self.state.stats,
-        )?;
+        self.state
+            .world
+            .save_tested_input(&input.value, self.state.stats.total_number_of_runs)?;
+        let start = Instant::now();
+        self.state.run_start.set(Some(start));
+        if self.state.settings.fork {
+            let forked_features = Self::test_input_in_fork(
+                &self.test,
+                &self.state.mutator,
+                &input,
+                &*self.state.world,
+                self.state.stats,
+            )?;
+            self.state.analysis_cache.forked_features = forked_features;
+        } else {
+            Self::test_input(
+                &self.test,
+                &self.state.mutator,
+                &input,
+                &*self.state.world,
+                self.state.stats,
+            )?;
+        }
+        let duration = start.elapsed();
+        self.state.run_start.set(None);
+        if let Some(slowest_inputs) = &self.state.slowest_inputs {
+            if slowest_inputs.would_keep(&duration) {
+                let value = self.state.get_input().value.clone();
+                self.state.slowest_inputs.as_mut().unwrap().insert(duration, value);
+            }
+        }
         self.state.stats.total_number_of_runs += 1;
+        self.state.world.report_event(FuzzerEvent::Run, Some(self.state.stats));
 
         if let Some((existing_features, new_features)) = self.analyze(cplx) {
-            let input_cloned = self.state.get_input().new_source(&self.state.mutator);
-            let actions = self.state.pool.add(input_cloned, cplx, existing_features, new_features);
+            if let FuzzerCommand::Fuzz = self.state.settings.command {
+                self.make_room_in_pool()?;
+            }
+            let input_cloned = self
+                .state
+                .get_input()
+                .new_source(&self.state.mutator, self.state.mutation_seed);
+            let actions = match origin {
+                InputOrigin::Seed => self
+                    .state
+                    .pool
+                    .import_seed(input_cloned, cplx, existing_features, new_features),
+                InputOrigin::Discovered => self.state.pool.add(input_cloned, cplx, existing_features, new_features),
+            };
             self.state.update_stats();
+            if let FuzzerCommand::Fuzz = self.state.settings.command {
+                self.make_room_on_disk(&actions)?;
+            }
             self.state.world.do_actions(actions, &self.state.stats)?;
 
             Ok(())
@@ -235,73 +665,440 @@ where
         }
     }
 
+    /// While fuzzing, `--corpus-size` is a soft limit on the size of the pool.
+    /// When it is reached, the lowest-scoring input whose features are all
+    /// covered by other inputs is removed before a new input is added.
+    ///
+    /// The input that is currently being tested is never removed.
+    fn make_room_in_pool(&mut self) -> Result<(), std::io::Error> {
+        if self.state.pool.len() < self.state.settings.corpus_size {
+            return Ok(());
+        }
+        let keep = match self.state.input_idx {
+            FuzzerInputIndex::Pool(PoolIndex::Normal(key)) => Some(key),
+            _ => None,
+        };
+        let actions = self.state.pool.remove_lowest_scoring_redundant_input(keep);
+        self.state.world.do_actions(actions, &self.state.stats)
+    }
+
+    /// With `--max-total-corpus-bytes`, removes the files of the
+    /// lowest-scoring inputs of the pool from the output corpus until the
+    /// inputs about to be added to it fit under the limit.
+    ///
+    /// The evicted inputs stay in the pool, so no code coverage is lost.
+    fn make_room_on_disk(&mut self, actions: &[WorldAction<M::Value>]) -> Result<(), std::io::Error> {
+        if self.state.settings.max_total_corpus_bytes.is_none() {
+            return Ok(());
+        }
+        let mut remaining: Vec<(&M::Value, f64)> = self
+            .state
+            .pool
+            .iter_corpus()
+            .map(|(value, score, _)| (value, score))
+            .collect();
+        // usually only a few inputs are evicted, so the lowest-scoring one is
+        // looked for only when the world needs it, instead of sorting the pool
+        let mut evictable = std::iter::from_fn(|| {
+            let (idx, _) = remaining
+                .iter()
+                .enumerate()
+                .min_by(|(_, x), (_, y)| x.1.partial_cmp(&y.1).unwrap_or(Ordering::Equal))?;
+            Some(remaining.swap_remove(idx).0)
+        });
+
+        let mut nbr_evicted = 0;
+        for action in actions {
+            if let WorldAction::Add(value) = action {
+                nbr_evicted += self.state.world.make_room_in_output_corpus(value, &mut evictable)?;
+            }
+        }
+        if nbr_evicted > 0 {
+            self.state
+                .world
+                .report_event(FuzzerEvent::EvictedCorpusFiles(nbr_evicted), None);
+        }
+        Ok(())
+    }
+
+    /// Tests either a new arbitrary input or a mutation of an input of the
+    /// pool.
+    ///
+    /// While fuzzing, the choice depends on which of the two recently found
+    /// the most new features, see [InputYield]. Otherwise, an input of the
+    /// pool is always mutated.
+    ///
+    /// A mutated input is unmutated as soon as it is tested, so mutations are
+    /// never stacked on top of each other: at most one
+    /// [UnmutateToken](Mutator::UnmutateToken) is alive at any time, however
+    /// long a single input of the pool is explored.
     fn process_next_inputs(&mut self) -> Result<(), std::io::Error> {
+        if let FuzzerCommand::Fuzz = self.state.settings.command {
+            let arbitrary_ratio = self.state.input_yield.arbitrary_ratio();
+            if self.state.rng.gen_bool(arbitrary_ratio) {
+                return self.process_arbitrary_input();
+            }
+        }
         let idx = self.state.pool.random_index();
         self.state.input_idx = FuzzerInputIndex::Pool(idx);
         let input = self.state.pool.get(idx);
 
-        let unmutate_token = input.mutate(&self.state.mutator, self.state.settings.max_input_cplx);
+        let unmutate_token = if let Some(token) = input.mutate(&self.state.mutator, self.state.settings.max_input_cplx)
+        {
+            token
+        } else {
+            self.state.pool.mark_input_as_exhausted(idx, &self.state.mutator);
+            return Ok(());
+        };
         let cplx = input.complexity(&self.state.mutator);
 
-        if cplx < self.state.settings.max_input_cplx {
-            self.test_input_and_analyze()?;
-        }
+        let nbr_new_features = if cplx < self.state.settings.max_input_cplx {
+            self.test_input_and_analyze(InputOrigin::Discovered)?;
+            let nbr_new_features = self.state.analysis_cache.nbr_new_features;
+            self.state.input_yield.record_mutate(nbr_new_features);
+            Some(nbr_new_features)
+        } else {
+            None
+        };
 
         // Retrieving the input may fail because the input may have been deleted
         if let Some(input) = self.state.pool.retrieve_source_input_for_unmutate(idx) {
+            if let Some(nbr_new_features) = nbr_new_features {
+                self.state
+                    .mutator
+                    .record_mutation_outcome(&input.value, &unmutate_token, nbr_new_features);
+            }
             input.unmutate(&self.state.mutator, unmutate_token);
         }
 
         Ok(())
     }
 
-    fn process_initial_inputs(&mut self) -> Result<(), std::io::Error> {
-        let mut inputs: Vec<FuzzedInput<M>> = self
-            .state
+    /// Tests a new arbitrary input, instead of a mutation of an input of the pool
+    ///
+    /// With --arbitrary-from-corpus, the input is sometimes the
+    /// [crossover](Mutator::crossover) of two inputs of the pool. It is
+    /// generated from scratch if the mutator cannot combine them.
+    fn process_arbitrary_input(&mut self) -> Result<(), std::io::Error> {
+        let max_cplx = self.state.settings.max_input_cplx;
+        let seed = self.state.rng.gen();
+        let crossover = if self.state.settings.arbitrary_from_corpus && self.state.rng.gen_bool(CORPUS_CROSSOVER_RATIO)
+        {
+            self.crossover_of_pool_inputs(seed, max_cplx)
+        } else {
+            None
+        };
+        let input = if let Some((value, cache)) = crossover {
+            let mutation_step = self.state.mutator.mutation_step_from_value(&value);
+            FuzzedInput::new(value, cache, mutation_step)
+        } else {
+            FuzzedInput::arbitrary(&self.state.mutator, seed, max_cplx)
+        };
+        if input.complexity(&self.state.mutator) >= max_cplx {
+            return Ok(());
+        }
+        self.state.input_idx = FuzzerInputIndex::Temporary(input);
+        self.test_input_and_analyze(InputOrigin::Discovered)?;
+        let nbr_new_features = self.state.analysis_cache.nbr_new_features;
+        self.state.input_yield.record_arbitrary(nbr_new_features);
+        Ok(())
+    }
+
+    /// The crossover of two distinct inputs of the pool, if there are enough
+    /// of them and the mutator can combine them
+    fn crossover_of_pool_inputs(&mut self, seed: usize, max_cplx: f64) -> Option<(M::Value, M::Cache)> {
+        let parents = self.state.pool.sample_without_replacement(2);
+        if parents.len() < 2 {
+            return None;
+        }
+        let (a, b) = (self.state.pool.get_ref(parents[0]), self.state.pool.get_ref(parents[1]));
+        self.state
+            .mutator
+            .crossover(&a.value, &a.cache, &b.value, &b.cache, seed, max_cplx)
+    }
+
+    /// Returns the inputs read from a corpus, for a command that cannot do
+    /// anything useful without them.
+    ///
+    /// If the corpus could not be read or contains no valid input, the
+    /// problem is reported and the fuzzer exits with
+    /// [FuzzerTerminationStatus::EmptyCorpus], so that the run is not
+    /// mistaken for a successful one.
+    fn required_corpus(&self, corpus: Result<Vec<M::Value>, std::io::Error>, path: Option<PathBuf>) -> Vec<M::Value> {
+        let reason = match corpus {
+            Ok(inputs) if !inputs.is_empty() => return inputs,
+            Ok(_) => "it contains no valid input".to_owned(),
+            Err(e) => e.to_string(),
+        };
+        self.state
             .world
-            .read_input_corpus()
-            .unwrap_or_default()
+            .report_event(FuzzerEvent::CorpusError(path, reason), Some(self.state.stats));
+        FuzzerTerminationStatus::EmptyCorpus.exit();
+    }
+
+    fn process_initial_inputs(&mut self, corpus: Vec<M::Value>) -> Result<(), std::io::Error> {
+        let mut inputs: Vec<(FuzzedInput<M>, InputOrigin)> = corpus
             .into_iter()
             .map(|value| {
-                let cache = self.state.mutator.cache_from_value(&value);
-                let mutation_step = self.state.mutator.mutation_step_from_value(&value);
-                FuzzedInput::new(value, cache, mutation_step)
+                (
+                    FuzzedInput::new_from_value(&self.state.mutator, value),
+                    InputOrigin::Seed,
+                )
             })
             .collect();
 
-        if inputs.is_empty() {
-            for i in 0..100 {
-                let (v, cache) = self.state.mutator.arbitrary(i, self.state.settings.max_input_cplx);
-                let mutation_step = self.state.mutator.mutation_step_from_value(&v);
-                inputs.push(FuzzedInput::new(v, cache, mutation_step));
-            }
+        let corpus_is_empty = inputs.is_empty();
+        inputs.push((FuzzedInput::default(&self.state.mutator), InputOrigin::Discovered));
+        if self.state.settings.adaptive_cplx {
+            // start small, but never below the complexity of the inputs of the corpus
+            let most_complex_input = inputs
+                .iter()
+                .map(|(i, _)| i.complexity(&self.state.mutator))
+                .fold(ADAPTIVE_CPLX_START, f64::max);
+            self.state.settings.max_input_cplx = most_complex_input.min(self.state.settings.max_input_cplx);
         }
-        inputs.push(FuzzedInput::default(&self.state.mutator));
-        inputs.drain_filter(|i| i.complexity(&self.state.mutator) > self.state.settings.max_input_cplx);
+        inputs.retain(|(i, _)| i.complexity(&self.state.mutator) <= self.state.settings.max_input_cplx);
         assert!(!inputs.is_empty());
+        if let Some(initial_cplx) = self.state.settings.initial_cplx {
+            // the inputs of the corpus are kept even if they are more complex
+            self.state.settings.max_input_cplx = (initial_cplx as f64).min(self.state.settings.max_input_cplx);
+        }
 
         self.state.world.set_start_time();
-        for input in inputs {
+        let total = inputs.len();
+        // a large corpus can take minutes to load, so its progress is reported
+        let mut last_progress = (0, Instant::now());
+        for (i, (input, origin)) in inputs.into_iter().enumerate() {
             self.state.input_idx = FuzzerInputIndex::Temporary(input);
-            self.test_input_and_analyze()?;
+            self.test_input_and_analyze(origin)?;
+            let tested = i + 1;
+            if tested % CORPUS_LOADING_PROGRESS_INTERVAL == 0
+                || last_progress.1.elapsed() >= CORPUS_LOADING_PROGRESS_PERIOD
+            {
+                self.report_corpus_loading_progress(tested, total);
+                last_progress = (tested, Instant::now());
+            }
+        }
+        // the final count is only worth reporting after the intermediate ones
+        if last_progress.0 != 0 && last_progress.0 != total {
+            self.report_corpus_loading_progress(total, total);
+        }
+        if corpus_is_empty {
+            self.bootstrap(BOOTSTRAP_BATCH_SIZE, BOOTSTRAP_ROUNDS)?;
         }
 
         Ok(())
     }
 
-    fn main_loop(&mut self) -> Result<(), std::io::Error> {
+    /// Reports a [FuzzerEvent::LoadingCorpus] after the first `tested` of the
+    /// `total` initial inputs were tested
+    fn report_corpus_loading_progress(&mut self, tested: usize, total: usize) {
+        let event = FuzzerEvent::LoadingCorpus {
+            tested,
+            total,
+            pool_size: self.state.pool.len(),
+        };
+        self.state.world.report_event(event, None);
+    }
+
+    /// Seeks coverage greedily, to find a useful corpus faster than with
+    /// purely random inputs.
+    ///
+    /// A batch of `count` arbitrary inputs is tested. Then, for each of the
+    /// following `rounds - 1` rounds, the input of the previous batch that
+    /// hit the most features is mutated `count` times to form the next batch.
+    /// Every tested input goes through the usual pool acceptance logic.
+    fn bootstrap(&mut self, count: usize, rounds: usize) -> Result<(), std::io::Error> {
+        let max_cplx = self.state.settings.max_input_cplx;
+        let mutator = &self.state.mutator;
+        let mut batch: Vec<FuzzedInput<M>> = (0..count)
+            .map(|i| FuzzedInput::arbitrary(mutator, i, max_cplx))
+            .collect();
+
+        for round in 0..rounds {
+            let mut best: Option<(usize, FuzzedInput<M>)> = None;
+            for input in batch.drain(..) {
+                if input.complexity(&self.state.mutator) > max_cplx {
+                    continue;
+                }
+                self.state.input_idx = FuzzerInputIndex::Temporary(input);
+                self.test_input_and_analyze(InputOrigin::Discovered)?;
+
+                let nbr_features = self.state.analysis_cache.nbr_features;
+                if best
+                    .as_ref()
+                    .map_or(true, |(best_nbr_features, _)| nbr_features > *best_nbr_features)
+                {
+                    let input = self.state.get_input();
+                    best = Some((
+                        nbr_features,
+                        input.new_source(&self.state.mutator, self.state.mutation_seed),
+                    ));
+                }
+            }
+            let mut best = match best {
+                Some((_, best)) if round + 1 < rounds => best,
+                _ => break,
+            };
+            for _ in 0..count {
+                if let Some(unmutate_token) = best.mutate(&self.state.mutator, max_cplx) {
+                    batch.push(best.new_source(&self.state.mutator, self.state.mutation_seed));
+                    best.unmutate(&self.state.mutator, unmutate_token);
+                } else {
+                    break;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Tests the inputs that other fuzzers added to the input corpus since it
+    /// was last read, so that the pool benefits from their discoveries
+    fn reload_corpus(&mut self) -> Result<(), std::io::Error> {
+        let values = self.state.world.read_new_inputs_of_input_corpus()?;
+        if values.is_empty() {
+            return Ok(());
+        }
         self.state
             .world
-            .report_event(FuzzerEvent::Start, Some(self.state.stats));
-        self.process_initial_inputs()?;
+            .report_event(FuzzerEvent::ReloadCorpus(values.len()), Some(self.state.stats));
+        for value in values {
+            let input = FuzzedInput::new_from_value(&self.state.mutator, value);
+            if input.complexity(&self.state.mutator) > self.state.settings.max_input_cplx {
+                continue;
+            }
+            self.state.input_idx = FuzzerInputIndex::Temporary(input);
+            self.test_input_and_analyze(InputOrigin::Discovered)?;
+        }
+        Ok(())
+    }
+
+    /// Reports a [FuzzerEvent::Pulse] with the current state of the fuzzer
+    fn report_pulse(&mut self) {
+        self.state.update_stats();
+        self.state.world.report_event(
+            FuzzerEvent::Pulse {
+                iterations: self.state.stats.total_number_of_runs,
+                execs_per_sec: self.state.stats.exec_per_s,
+                corpus_size: self.state.pool.len(),
+                coverage: self.state.pool.feature_count(),
+                edges: self.state.pool.edge_count(),
+                comparisons: self.state.pool.comparison_count(),
+            },
+            None,
+        );
+    }
+
+    /// Runs the fuzzer until the maximum number of iterations is reached.
+    ///
+    /// A [FuzzerEvent::Pulse] is reported when the number of iterations
+    /// reaches a power of two, and then every [MAX_PULSE_INTERVAL]
+    /// iterations, so that a run that finds nothing new is still visible.
+    ///
+    /// With `--adaptive-cplx`, the maximum complexity of the inputs starts
+    /// low and is doubled, up to `--max-cplx`, whenever no new feature was
+    /// found in the last [ADAPTIVE_CPLX_PLATEAU] iterations.
+    ///
+    /// With `--initial-cplx`, the maximum complexity of the inputs starts at
+    /// the given value and rises linearly to `--max-cplx` over the first
+    /// [INITIAL_CPLX_RAMP] iterations, or half of `--max-iter` if it is lower.
+    ///
+    /// With `--reload-corpus-interval`, the new files of the input corpus are
+    /// tested periodically.
+    ///
+    /// With `--save-all-inputs`, every tested input is also written to a
+    /// folder, which is only reasonable for short runs.
+    fn main_loop(&mut self) -> Result<(), std::io::Error> {
+        let max_cplx_ceiling = self.state.settings.max_input_cplx;
+        self.state.world.report_event(
+            FuzzerEvent::Start(self.state.mutator.type_name()),
+            Some(self.state.stats),
+        );
+        if self.state.settings.save_all_inputs.is_some() && self.max_iter() > SAVE_ALL_INPUTS_MAX_ITER {
+            self.state.world.report_event(
+                FuzzerEvent::SaveAllInputsWarning(self.max_iter()),
+                Some(self.state.stats),
+            );
+        }
+        let global_features = self.state.world.read_global_features()?;
+        self.state.pool.set_global_features(global_features);
+        if self.state.settings.edge_weights.is_some() {
+            let edge_weights = self.state.world.read_edge_weights()?;
+            self.state
+                .world
+                .report_event(FuzzerEvent::EdgeWeights(edge_weights.len()), None);
+            self.state.pool.feature_weights.edge_multipliers = Rc::new(edge_weights);
+        }
+        self.state.world.create_output_folders()?;
+        let corpus = self.state.world.read_input_corpus()?;
+        self.process_initial_inputs(corpus)?;
         self.state
             .world
             .report_event(FuzzerEvent::DidReadCorpus, Some(self.state.stats));
 
+        let reload_corpus_interval = self
+            .state
+            .settings
+            .reload_corpus_interval
+            .map(|secs| Duration::from_secs(secs as u64));
+        let mut last_corpus_reload = Instant::now();
+
+        let initial_cplx_ramp = std::cmp::min(INITIAL_CPLX_RAMP, self.max_iter() / 2).max(1);
+
+        let mut next_pulse = 1;
+        let mut coverage = self.state.pool.feature_count();
+        let mut last_coverage_increase = self.state.stats.total_number_of_runs;
         while self.state.stats.total_number_of_runs < self.max_iter() {
+            if self.state.pool.should_compact() {
+                // safe because process_next_inputs will pick a new pool index
+                self.state.pool.compact();
+            }
             self.process_next_inputs()?;
+            // refresh the stats regularly so that they are reported even when the pool does not change
+            if self.state.stats.total_number_of_runs % 1024 == 0 {
+                self.state.update_stats();
+                if let Some(initial_cplx) = self.state.settings.initial_cplx {
+                    let progress = (self.state.stats.total_number_of_runs as f64 / initial_cplx_ramp as f64).min(1.0);
+                    let initial_cplx = (initial_cplx as f64).min(max_cplx_ceiling);
+                    self.state.settings.max_input_cplx = initial_cplx + (max_cplx_ceiling - initial_cplx) * progress;
+                }
+                if let Some(interval) = reload_corpus_interval {
+                    if last_corpus_reload.elapsed() >= interval {
+                        self.reload_corpus()?;
+                        last_corpus_reload = Instant::now();
+                    }
+                }
+            }
+            if self.state.stats.total_number_of_runs >= next_pulse {
+                self.report_pulse();
+                // the initial inputs may have already passed several milestones
+                while next_pulse <= self.state.stats.total_number_of_runs {
+                    next_pulse += std::cmp::min(next_pulse, MAX_PULSE_INTERVAL);
+                }
+            }
+            if self.state.pool.feature_count() > coverage {
+                coverage = self.state.pool.feature_count();
+                last_coverage_increase = self.state.stats.total_number_of_runs;
+            } else if self.state.settings.adaptive_cplx
+                && self.state.settings.max_input_cplx < max_cplx_ceiling
+                && self.state.stats.total_number_of_runs - last_coverage_increase >= ADAPTIVE_CPLX_PLATEAU
+            {
+                let max_cplx = (self.state.settings.max_input_cplx * 2.0).min(max_cplx_ceiling);
+                self.state.settings.max_input_cplx = max_cplx;
+                last_coverage_increase = self.state.stats.total_number_of_runs;
+                self.state
+                    .world
+                    .report_event(FuzzerEvent::MaxCplxIncreased(max_cplx), Some(self.state.stats));
+            }
         }
         self.state.world.report_event(FuzzerEvent::Done, Some(self.state.stats));
+        if self.state.settings.print_pcs {
+            self.state.print_pcs();
+        }
+        self.state.save_global_features()?;
+        self.state.save_slowest_inputs(false)?;
+        self.state.save_coverage_bitmap()?;
 
         Ok(())
     }
@@ -311,19 +1108,56 @@ where
     ///
     /// The number of inputs to keep is taken from
     /// [self.settings.corpus_size](FuzzerSettings::corpus_size)
+    ///
+    /// With `--curate N`, the inputs are not removed one by one. Instead, the
+    /// N inputs with the highest score in the full corpus are kept.
     fn corpus_minifying_loop(&mut self) -> Result<(), std::io::Error> {
-        self.state
-            .world
-            .report_event(FuzzerEvent::Start, Some(self.state.stats));
+        self.state.world.report_event(
+            FuzzerEvent::Start(self.state.mutator.type_name()),
+            Some(self.state.stats),
+        );
 
-        self.process_initial_inputs()?;
+        let corpus = self.required_corpus(
+            self.state.world.read_input_corpus(),
+            self.state.settings.corpus_in.clone(),
+        );
+        self.process_initial_inputs(corpus)?;
 
         self.state
             .world
             .report_event(FuzzerEvent::DidReadCorpus, Some(self.state.stats));
 
-        while self.state.pool.len() > self.state.settings.corpus_size {
+        // no input covered anything, so there is nothing to minify
+        if self.state.pool.len() == 0 {
+            self.state.world.report_event(FuzzerEvent::Done, Some(self.state.stats));
+            return Ok(());
+        }
+
+        if let Some(curate) = self.state.settings.curate {
+            // the input corpus was written to the output corpus while it was read, keep only the best inputs
+            let inputs = self.state.pool.most_valuable_inputs(self.state.pool.len());
+            let mut actions = Vec::new();
+            for (value, _) in inputs.into_iter().skip(curate) {
+                actions.push(WorldAction::ReportEvent(FuzzerEvent::Remove));
+                actions.push(WorldAction::Remove(value));
+            }
+            self.state.world.do_actions(actions, &self.state.stats)?;
+            self.state.world.report_event(FuzzerEvent::Done, Some(self.state.stats));
+            return Ok(());
+        }
+
+        let deadline = self.minimize_deadline();
+
+        let actions = self.state.pool.prune_redundant();
+        self.state.world.do_actions(actions, &self.state.stats)?;
+        self.state.update_stats();
+
+        // each removal is saved immediately, so the corpus is as small as possible when the deadline is reached
+        while self.state.pool.len() > self.state.settings.corpus_size && !self.deadline_reached(deadline) {
             let actions = self.state.pool.remove_lowest_scoring_input();
+            if actions.is_empty() {
+                break;
+            }
             self.state.world.do_actions(actions, &self.state.stats)?;
             self.state.update_stats();
         }
@@ -332,23 +1166,295 @@ where
         Ok(())
     }
 
+    /// Tests each input produced by replaying the mutator decisions recorded
+    /// in the file given by `--replay`, in order, until one of them fails.
+    ///
+    /// The decisions are replayed from the value of `--input-file`, if given,
+    /// which is saved next to the replay log of an artifact.
+    fn replay_loop(&mut self) -> Result<(), std::io::Error> {
+        let decisions = self.state.world.read_replay_log()?;
+        let mut replay = ReplayMutator::new(&self.state.mutator, decisions);
+        if self.state.settings.input_file.is_some() {
+            replay = replay.starting_from(self.state.world.read_input_file()?);
+        }
+        let values = replay
+            .values()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        for value in values {
+            self.features_of_value(value)?;
+            self.state.stats.total_number_of_runs += 1;
+        }
+        self.state.world.report_event(FuzzerEvent::Done, Some(self.state.stats));
+        Ok(())
+    }
+
+    /// Runs the test function on the value and returns the features it covers
+    fn features_of_value(&mut self, value: M::Value) -> Result<Vec<Feature>, std::io::Error> {
+        self.state.input_idx = FuzzerInputIndex::Temporary(FuzzedInput::new_from_value(&self.state.mutator, value));
+        let input = self.state.get_input();
+        if self.state.settings.fork {
+            Self::test_input_in_fork(
+                &self.test,
+                &self.state.mutator,
+                &input,
+                &*self.state.world,
+                self.state.stats,
+            )
+        } else {
+            Self::test_input(
+                &self.test,
+                &self.state.mutator,
+                &input,
+                &*self.state.world,
+                self.state.stats,
+            )?;
+            let mut features = Vec::new();
+            shared_sensor().iterate_over_collected_features(|f| features.push(f));
+            Ok(features)
+        }
+    }
+
+    /// Compares the code coverage of the input corpus with that of the
+    /// corpus given by `--other-corpus`.
+    ///
+    /// It reports the features covered by only one of the two corpora, and,
+    /// for each input, how many of those features it covers.
+    fn corpus_diff_loop(&mut self) -> Result<(), std::io::Error> {
+        let inputs = self.required_corpus(
+            self.state.world.read_input_corpus(),
+            self.state.settings.corpus_in.clone(),
+        );
+        let other_inputs = self.required_corpus(
+            self.state.world.read_other_corpus(),
+            self.state.settings.other_corpus.clone(),
+        );
+
+        let mut features_of_inputs = Vec::with_capacity(inputs.len());
+        for input in inputs.iter() {
+            features_of_inputs.push(self.features_of_value(input.clone())?);
+        }
+        let mut features_of_other_inputs = Vec::with_capacity(other_inputs.len());
+        for input in other_inputs.iter() {
+            features_of_other_inputs.push(self.features_of_value(input.clone())?);
+        }
+
+        let features: HashSet<Feature> = features_of_inputs.iter().flatten().copied().collect();
+        let other_features: HashSet<Feature> = features_of_other_inputs.iter().flatten().copied().collect();
+
+        let contribution_of_inputs: Vec<_> = inputs
+            .iter()
+            .zip(features_of_inputs.iter())
+            .map(|(input, fs)| (input, fs.iter().filter(|&f| !other_features.contains(f)).count()))
+            .collect();
+        let contribution_of_other_inputs: Vec<_> = other_inputs
+            .iter()
+            .zip(features_of_other_inputs.iter())
+            .map(|(input, fs)| (input, fs.iter().filter(|&f| !features.contains(f)).count()))
+            .collect();
+
+        self.state.world.report_corpus_diff(
+            features.difference(&other_features).count(),
+            other_features.difference(&features).count(),
+            &contribution_of_inputs,
+            &contribution_of_other_inputs,
+        );
+        Ok(())
+    }
+
     fn input_minifying_loop(&mut self) -> Result<(), std::io::Error> {
-        self.state
-            .world
-            .report_event(FuzzerEvent::Start, Some(self.state.stats));
+        self.state.world.report_event(
+            FuzzerEvent::Start(self.state.mutator.type_name()),
+            Some(self.state.stats),
+        );
         let value = self.state.world.read_input_file()?;
-        let cache = self.state.mutator.cache_from_value(&value);
-        let mutation_step = self.state.mutator.mutation_step_from_value(&value);
-        let input = FuzzedInput::<M>::new(value, cache, mutation_step);
+        let input = FuzzedInput::new_from_value(&self.state.mutator, value);
         let input_cplx = input.complexity(&self.state.mutator);
+        if let Some(target_cplx) = self.state.settings.minimize_to_cplx {
+            if input_cplx < target_cplx as f64 {
+                self.state
+                    .world
+                    .report_event(FuzzerEvent::TargetCplxReached(input_cplx), Some(self.state.stats));
+                return Ok(());
+            }
+        }
         self.state.settings.max_input_cplx = input_cplx - 0.01;
 
+        let mut shrink_source = Some(input.new_source(&self.state.mutator, self.state.mutation_seed));
+        let mut shrink_step = 0;
         self.state.pool.add_favored_input(input);
 
+        let deadline = self.minimize_deadline();
         self.state.world.set_start_time();
-        loop {
-            self.process_next_inputs()?;
+        // simpler crashing inputs are saved as soon as they are found, so the best one is kept on disk
+        while self.state.stats.total_number_of_runs < self.max_iter() && !self.deadline_reached(deadline) {
+            if let Some(source) = &shrink_source {
+                if !self.process_shrunk_input(source, &mut shrink_step)? {
+                    shrink_source = None;
+                }
+            } else {
+                self.process_next_inputs()?;
+            }
         }
+        self.state.world.report_event(FuzzerEvent::Done, Some(self.state.stats));
+
+        Ok(())
+    }
+
+    /// Tests the next simplification of the input to minify given by
+    /// [Mutator::shrink_below], or returns false if there is none left.
+    ///
+    /// A simplification that still fails the test is saved as an artifact
+    /// and ends the run, so the next run starts shrinking it in turn.
+    fn process_shrunk_input(&mut self, source: &FuzzedInput<M>, step: &mut usize) -> Result<bool, std::io::Error> {
+        let max_cplx = self.state.settings.max_input_cplx;
+        let (value, cache) = match self
+            .state
+            .mutator
+            .shrink_below(&source.value, &source.cache, step, max_cplx)
+        {
+            Some(shrunk) => shrunk,
+            None => return Ok(false),
+        };
+        let mutation_step = self.state.mutator.mutation_step_from_value(&value);
+        self.state.input_idx = FuzzerInputIndex::Temporary(FuzzedInput::new(value, cache, mutation_step));
+        self.test_input_and_analyze(InputOrigin::Discovered)?;
+        Ok(true)
+    }
+
+    /// Checks that the mutator and the serializer are consistent with each
+    /// other, without running the test function.
+    ///
+    /// For each of `--max-iter` arbitrary values, it checks that:
+    /// * the complexity computed from the cache of the value is the same as
+    ///   the one computed from a new cache
+    /// * the value can be deserialized from its serialized form, and the
+    ///   deserialized value is serialized to the same data
+    /// * unmutating each of its first [SELF_TEST_MUTATIONS] mutations
+    ///   restores the value and its complexity
+    ///
+    /// On the first failure, the offending value is printed and saved as an
+    /// artifact, and the fuzzer exits with a non-zero status.
+    fn self_test_loop(&mut self) -> Result<(), std::io::Error> {
+        self.state.world.report_event(
+            FuzzerEvent::Start(self.state.mutator.type_name()),
+            Some(self.state.stats),
+        );
+        self.state.world.set_start_time();
+        while self.state.stats.total_number_of_runs < self.max_iter() {
+            Self::self_test_value(
+                &self.state.mutator,
+                &*self.state.world,
+                self.state.stats.total_number_of_runs,
+                self.state.settings.max_input_cplx,
+            )?;
+            self.state.stats.total_number_of_runs += 1;
+        }
+        self.state.world.report_event(FuzzerEvent::Done, Some(self.state.stats));
+
+        Ok(())
+    }
+
+    fn self_test_value(
+        mutator: &M,
+        world: &dyn World<Value = M::Value>,
+        seed: usize,
+        max_cplx: f64,
+    ) -> Result<(), std::io::Error> {
+        // the caches may be updated incrementally, which accumulates rounding errors
+        fn same_cplx(a: f64, b: f64) -> bool {
+            (a - b).abs() <= 1e-6 * a.abs().max(1.0)
+        }
+        let fresh_cplx = |value: &M::Value| mutator.complexity(value, &mutator.cache_from_value(value));
+
+        let (mut value, mut cache) = mutator.arbitrary(seed, max_cplx);
+        let cplx = mutator.complexity(&value, &cache);
+        if !same_cplx(cplx, fresh_cplx(&value)) {
+            return Self::self_test_failure(
+                world,
+                &format!(
+                    "The complexity of the arbitrary value for seed {} is {:.2} according to its cache, but {:.2} according to a new cache",
+                    seed,
+                    cplx,
+                    fresh_cplx(&value)
+                ),
+                &value,
+                cplx,
+            );
+        }
+
+        let (data, deserialized) = world.serialization_round_trip(&value);
+        match deserialized {
+            None => {
+                return Self::self_test_failure(
+                    world,
+                    &format!("The arbitrary value for seed {} could not be deserialized", seed),
+                    &value,
+                    cplx,
+                )
+            }
+            Some(deserialized) => {
+                if world.serialization_round_trip(&deserialized).0 != data {
+                    return Self::self_test_failure(
+                        world,
+                        &format!(
+                            "The arbitrary value for seed {} is serialized differently after being deserialized",
+                            seed
+                        ),
+                        &value,
+                        cplx,
+                    );
+                }
+            }
+        }
+
+        let mut step = mutator.mutation_step_from_value(&value);
+        for mutation in 0..SELF_TEST_MUTATIONS {
+            let token = if let Some(token) = mutator.mutate(&mut value, &mut cache, &mut step, max_cplx) {
+                token
+            } else {
+                break;
+            };
+            let mutated_cplx = mutator.complexity(&value, &cache);
+            if !same_cplx(mutated_cplx, fresh_cplx(&value)) {
+                return Self::self_test_failure(
+                    world,
+                    &format!(
+                        "After mutation {} of the arbitrary value for seed {}, its complexity is {:.2} according to its cache, but {:.2} according to a new cache",
+                        mutation,
+                        seed,
+                        mutated_cplx,
+                        fresh_cplx(&value)
+                    ),
+                    &value,
+                    mutated_cplx,
+                );
+            }
+            mutator.unmutate(&mut value, &mut cache, token);
+            let unmutated_cplx = mutator.complexity(&value, &cache);
+            if world.serialization_round_trip(&value).0 != data || !same_cplx(unmutated_cplx, cplx) {
+                return Self::self_test_failure(
+                    world,
+                    &format!(
+                        "Unmutating mutation {} of the arbitrary value for seed {} did not restore the value",
+                        mutation, seed
+                    ),
+                    &value,
+                    unmutated_cplx,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn self_test_failure(
+        world: &dyn World<Value = M::Value>,
+        reason: &str,
+        value: &M::Value,
+        cplx: f64,
+    ) -> Result<(), std::io::Error> {
+        world.report_self_test_failure(reason, value);
+        world.save_artifact(value, cplx, None, None, None)?;
+        FuzzerTerminationStatus::Crash.exit();
     }
 }
 
@@ -361,44 +1467,283 @@ unsafe impl<T> Send for NotThreadSafe<T> {}
 impl<T> UnwindSafe for NotUnwindSafe<T> {}
 impl<T> RefUnwindSafe for NotUnwindSafe<T> {}
 
+/// How a run ended, which determines the exit code of the fuzzer.
+///
+/// The value of each variant is its default exit code. The codes of
+/// [Crash](FuzzerTerminationStatus::Crash) and
+/// [Timeout](FuzzerTerminationStatus::Timeout) can be changed with
+/// `--crash-exitcode` and `--timeout-exitcode`.
+#[derive(Clone, Copy)]
 pub enum FuzzerTerminationStatus {
     Success = 0,
-    Crash = 1,
-    TestFailure = 2,
     Unknown = 3,
+    /// A command that needs the inputs of a corpus could not read any
+    EmptyCorpus = 4,
+    /// The test function panicked, returned false, or crashed the process
+    Crash = 70,
+    /// The test function was stopped by SIGALRM or SIGXCPU
+    Timeout = 71,
+    /// The forked process running the test function was killed by SIGKILL,
+    /// which is how the OOM killer stops a process
+    OutOfMemory = 72,
 }
 
-pub fn launch<T, F, M, S>(test: F, mutator: M, serializer: S, args: CommandLineArguments) -> Result<(), std::io::Error>
+/// The exit codes given by `--crash-exitcode` and `--timeout-exitcode`, which
+/// are set once by [launch]. They are global so that the fuzzer can exit
+/// from the thread handling the signals, and from associated functions that
+/// do not have the settings.
+static CRASH_EXIT_CODE: AtomicI32 = AtomicI32::new(FuzzerTerminationStatus::Crash as i32);
+static TIMEOUT_EXIT_CODE: AtomicI32 = AtomicI32::new(FuzzerTerminationStatus::Timeout as i32);
+
+impl FuzzerTerminationStatus {
+    fn code(self) -> i32 {
+        match self {
+            FuzzerTerminationStatus::Crash => CRASH_EXIT_CODE.load(AtomicOrdering::SeqCst),
+            FuzzerTerminationStatus::Timeout => TIMEOUT_EXIT_CODE.load(AtomicOrdering::SeqCst),
+            status => status as i32,
+        }
+    }
+
+    /// Exits the process with the code of the status.
+    ///
+    /// A forked process must exit with `libc::_exit(status as i32)` instead,
+    /// so that its parent can recognize the status whatever the codes given
+    /// on the command line.
+    fn exit(self) -> ! {
+        exit(self.code())
+    }
+}
+
+pub fn launch<T, F, M, S>(
+    test: F,
+    mut mutator: M,
+    serializer: S,
+    args: CommandLineArguments,
+) -> Result<(), std::io::Error>
 where
     T: ?Sized,
     M::Value: Borrow<T>,
     F: Fn(&T) -> bool,
     M: Mutator,
-    S: Serializer<Value = M::Value>,
+    S: Serializer<Value = M::Value> + 'static,
 {
     let command = args.command;
 
-    let mut fuzzer = Fuzzer::new(test, mutator, args.clone(), World::new(serializer, args));
+    if args.input_min_len.is_some() || args.input_max_len.is_some() {
+        let len_range = args.input_min_len.unwrap_or(0)..=args.input_max_len.unwrap_or(std::usize::MAX);
+        if !mutator.set_length_range(len_range) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "The mutator {} does not support --{} and --{}",
+                    std::any::type_name::<M>(),
+                    INPUT_MIN_LEN_FLAG,
+                    INPUT_MAX_LEN_FLAG
+                ),
+            ));
+        }
+    }
+
+    if args.raw_corpus && !serializer.is_raw() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!(
+                "The serializer {} does not save inputs as raw bytes, which --{} requires",
+                std::any::type_name::<S>(),
+                RAW_CORPUS_FLAG
+            ),
+        ));
+    }
+
+    // the comparison hooks are called very often, so skipping them speeds up compute-heavy tests
+    shared_sensor().cmp_recording = !args.no_cmp_trace;
+    shared_sensor().edge_recording = !args.no_edge_trace;
+
+    if let Some(code) = args.crash_exitcode {
+        CRASH_EXIT_CODE.store(code, AtomicOrdering::SeqCst);
+    }
+    if let Some(code) = args.timeout_exitcode {
+        TIMEOUT_EXIT_CODE.store(code, AtomicOrdering::SeqCst);
+    }
+
+    let world = FileSystemWorld::new(serializer, args.clone());
+    let mut fuzzer = Fuzzer::new(test, mutator, args, Box::new(world));
+    fuzzer.state.world.write_metadata(
+        &fuzzer.state.settings,
+        std::any::type_name::<M>(),
+        fuzzer.state.mutator.type_name(),
+    )?;
     unsafe { fuzzer.state.set_up_signal_handler() };
     match command {
         FuzzerCommand::Fuzz => fuzzer.main_loop()?,
         FuzzerCommand::MinifyInput => fuzzer.input_minifying_loop()?,
+        FuzzerCommand::Read if fuzzer.state.settings.explain_complexity => {
+            let mutator = &fuzzer.state.mutator;
+            let (value, cache) = if fuzzer.state.settings.input_file.is_some() {
+                let value = fuzzer.state.world.read_input_file()?;
+                let cache = mutator.cache_from_value(&value);
+                (value, cache)
+            } else {
+                // the seed given by --seed, or the one recorded by Fuzzer::new
+                let seed = fuzzer.state.settings.seed.unwrap();
+                mutator.arbitrary(seed, fuzzer.state.settings.max_input_cplx)
+            };
+            fuzzer.state.world.report_event(
+                FuzzerEvent::ComplexityBreakdown(
+                    mutator.complexity(&value, &cache),
+                    mutator.complexity_breakdown(&value, &cache),
+                ),
+                None,
+            );
+        }
+        FuzzerCommand::Read if fuzzer.state.settings.trace_only => {
+            let value = fuzzer.state.world.read_input_file()?;
+            let mut features = fuzzer.features_of_value(value)?;
+            features.sort();
+            features.dedup();
+            fuzzer.state.world.report_event(FuzzerEvent::Features(features), None);
+        }
+        FuzzerCommand::Read if fuzzer.state.settings.replay.is_some() => fuzzer.replay_loop()?,
         FuzzerCommand::Read => {
             let value = fuzzer.state.world.read_input_file()?;
-            let cache = fuzzer.state.mutator.cache_from_value(&value);
-            let mutation_step = fuzzer.state.mutator.mutation_step_from_value(&value);
-
-            fuzzer.state.input_idx = FuzzerInputIndex::Temporary(FuzzedInput::new(value, cache, mutation_step));
+            fuzzer.state.input_idx =
+                FuzzerInputIndex::Temporary(FuzzedInput::new_from_value(&fuzzer.state.mutator, value));
             let input = fuzzer.state.get_input();
-            Fuzzer::<T, F, M, S>::test_input(
+            Fuzzer::<T, F, M>::test_input(
                 &fuzzer.test,
                 &fuzzer.state.mutator,
                 &input,
-                &fuzzer.state.world,
+                &*fuzzer.state.world,
                 fuzzer.state.stats,
             )?;
         }
         FuzzerCommand::MinifyCorpus => fuzzer.corpus_minifying_loop()?,
+        FuzzerCommand::DiffCorpus => fuzzer.corpus_diff_loop()?,
+        FuzzerCommand::SelfTest => fuzzer.self_test_loop()?,
     };
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::sync::Mutex;
+
+    /// Adds a larger number to the value at each step, until it wraps around
+    struct AddingMutator;
+    impl Mutator for AddingMutator {
+        type Value = u8;
+        type Cache = ();
+        type MutationStep = u8;
+        type UnmutateToken = u8;
+
+        fn cache_from_value(&self, _value: &u8) {}
+        fn mutation_step_from_value(&self, _value: &u8) -> u8 {
+            1
+        }
+        fn max_complexity(&self) -> f64 {
+            8.0
+        }
+        fn min_complexity(&self) -> f64 {
+            8.0
+        }
+        fn complexity(&self, _value: &u8, _cache: &()) -> f64 {
+            8.0
+        }
+        fn arbitrary(&self, seed: usize, _max_cplx: f64) -> (u8, ()) {
+            (seed as u8, ())
+        }
+        fn mutate(&self, value: &mut u8, _cache: &mut (), step: &mut u8, _max_cplx: f64) -> Option<u8> {
+            if *step == 0 {
+                return None;
+            }
+            let token = *value;
+            *value = value.wrapping_add(*step);
+            *step = step.wrapping_add(1);
+            Some(token)
+        }
+        fn unmutate(&self, value: &mut u8, _cache: &mut (), t: u8) {
+            *value = t;
+        }
+    }
+
+    /// The shared sensor records the features of one test function at a time
+    static SENSOR: Mutex<()> = Mutex::new(());
+
+    fn fuzz_settings(max_nbr_of_runs: usize) -> CommandLineArguments {
+        let args: Vec<String> = vec![COMMAND_FUZZ.to_owned()];
+        let mut settings = CommandLineArguments::from_parser(&options_parser(), &args, DEFAULT_ARGUMENTS).unwrap();
+        settings.max_nbr_of_runs = max_nbr_of_runs;
+        settings
+    }
+
+    #[test]
+    fn test_fuzz_test_world() {
+        let _sensor = SENSOR.lock().unwrap();
+        init_shared_sensor_for_tests();
+        let mut settings = fuzz_settings(2000);
+        settings.seed = Some(0);
+        settings.print_pcs = true;
+
+        let world = TestWorld::new(vec![7u8]);
+        // the distance to 200 is the only feedback, and the test never fails
+        let test = |x: &u8| {
+            crate::compare_floats(0, *x as f64, 200.0);
+            true
+        };
+        let mut fuzzer = Fuzzer::new(test, AddingMutator, settings, Box::new(world.clone()));
+        fuzzer.main_loop().unwrap();
+
+        let events = world.events.take();
+        assert!(matches!(events.first(), Some(FuzzerEvent::Start(_))));
+        assert!(events.iter().any(|e| matches!(e, FuzzerEvent::DidReadCorpus)));
+        assert!(events.iter().any(|e| matches!(e, FuzzerEvent::New)));
+        // the program counters asked by --print-pcs are reported to the world after the run
+        assert!(matches!(
+            events[events.len() - 2..],
+            [FuzzerEvent::Done, FuzzerEvent::Pcs(_)]
+        ));
+        assert!(fuzzer.state.stats.total_number_of_runs >= 2000);
+
+        // the pool was saved to the output corpus, which holds the closest value to 200
+        let output_corpus = world.output_corpus.take();
+        assert!(output_corpus.len() > 1);
+        assert!(output_corpus.contains(&200));
+        assert!(world.artifacts.take().is_empty());
+    }
+
+    /// The values tested by a run of the fuzzer, in order, along with the
+    /// seed recorded in its settings
+    fn tested_values(settings: CommandLineArguments) -> (Vec<u8>, Option<usize>) {
+        let tested = RefCell::new(vec![]);
+        let test = |x: &u8| {
+            tested.borrow_mut().push(*x);
+            crate::compare_floats(0, *x as f64, 200.0);
+            true
+        };
+        let seed = {
+            let mut fuzzer = Fuzzer::new(test, AddingMutator, settings, Box::new(TestWorld::new(vec![7u8])));
+            fuzzer.main_loop().unwrap();
+            fuzzer.state.settings.seed
+        };
+        (tested.into_inner(), seed)
+    }
+
+    #[test]
+    fn test_recorded_seed_replays_the_run() {
+        let _sensor = SENSOR.lock().unwrap();
+        init_shared_sensor_for_tests();
+        let (tested, seed) = tested_values(fuzz_settings(500));
+        assert!(seed.is_some());
+
+        // the pool picks the same inputs, which are mutated in the same way
+        let mut settings = fuzz_settings(500);
+        settings.seed = seed;
+        assert_eq!(tested_values(settings).0, tested);
+
+        let mut settings = fuzz_settings(500);
+        settings.seed = seed.map(|seed| seed.wrapping_add(1));
+        assert_ne!(tested_values(settings).0, tested);
+    }
+}
@@ -3,12 +3,12 @@
 //! functions.
 //!
 
-#![feature(drain_filter)]
 #![feature(never_type)]
 #![feature(thread_spawn_unchecked)]
 #![feature(ptr_offset_from)]
 #![feature(vec_remove_item)]
 #![feature(is_sorted)]
+#![cfg_attr(test, feature(test))]
 
 #[macro_use]
 extern crate lazy_static;
@@ -20,11 +20,15 @@ mod fuzzer;
 mod world;
 
 mod pool;
+pub mod replay;
 mod signals_handler;
 
 use fuzzcheck_arg_parser::*;
 
+use replay::{DecisionLog, ReplayLog};
+
 use std::borrow::Borrow;
+use std::ops::RangeInclusive;
 
 /// Fuzz-test the given test function.
 pub fn launch<T, F, M, S>(test: F, mutator: M, serializer: S) -> Result<(), std::io::Error>
@@ -33,10 +37,158 @@ where
     M::Value: Borrow<T>,
     F: Fn(&T) -> bool,
     M: Mutator,
-    S: Serializer<Value = M::Value>,
+    S: Serializer<Value = M::Value> + 'static,
 {
+    let args = parse_arguments();
+    fuzzer::launch(test, mutator, serializer, args)
+}
+
+/// Records that the test function took a path that the fuzzer should steer
+/// away from, such as a slow path or a known-uninteresting branch.
+///
+/// Each `id` is a distinct penalty feature, which lowers the score of the
+/// inputs that reach it, so that they are mutated less often and removed
+/// first from a full pool. It is ignored outside of the test function.
+///
+/// ```ignore
+/// if request.len() > CACHE_SIZE {
+///     fuzzcheck::penalize(SLOW_PATH);
+///     return slow_lookup(request);
+/// }
+/// ```
+pub fn penalize(id: usize) {
+    let sensor = code_coverage_sensor::shared_sensor();
+    if sensor.is_recording {
+        sensor.handle_penalty(id);
+    }
+}
+
+/// Records a comparison between two floating-point numbers made by the test
+/// function, so that the fuzzer is guided towards inputs that make them
+/// closer to each other.
+///
+/// SanitizerCoverage only instruments integer comparisons, so a predicate
+/// such as `x < 0.5` gives no feedback to the fuzzer until it is satisfied.
+/// Each `id` is a distinct comparison, whose features are derived from the
+/// distance between `x` and `y`. It is ignored outside of the test function.
+///
+/// ```ignore
+/// fuzzcheck::compare_floats(THRESHOLD_CMP, x, 0.5);
+/// if x < 0.5 {
+///     return rare_path(x);
+/// }
+/// ```
+pub fn compare_floats(id: usize, x: f64, y: f64) {
+    let sensor = code_coverage_sensor::shared_sensor();
+    if sensor.is_recording {
+        sensor.handle_float_comparison(id, x, y);
+    }
+}
+
+/// Parses the command line arguments, or prints the help message and exits
+fn parse_arguments() -> CommandLineArguments {
     let env_args: Vec<_> = std::env::args().collect();
     let parser = options_parser();
+    match CommandLineArguments::from_parser(&parser, &env_args[1..], DEFAULT_ARGUMENTS) {
+        Ok(r) => r,
+        Err(e) => {
+            println!("{}\n\n{}", e, help(&parser));
+            std::process::exit(1);
+        }
+    }
+}
+
+/// A fuzz target registered in [FuzzTargets]: a test function along with
+/// its mutator and serializer, ready to be launched
+type FuzzTarget = Box<dyn FnOnce(CommandLineArguments) -> Result<(), std::io::Error>>;
+
+/// A collection of named fuzz targets compiled into the same binary.
+///
+/// The target to fuzz-test is chosen with `--target NAME` when the binary is
+/// launched with [launch_targets].
+///
+/// ```ignore
+/// let targets = FuzzTargets::new()
+///     .add("parse", test_parse, parse_mutator, SerdeSerializer::default())
+///     .add("encode", test_encode, encode_mutator, SerdeSerializer::default());
+/// let _ = fuzzcheck::launch_targets(targets);
+/// ```
+#[derive(Default)]
+pub struct FuzzTargets {
+    /// The name of each target, the name of the type it tests, and the target
+    targets: Vec<(String, &'static str, FuzzTarget)>,
+}
+
+impl FuzzTargets {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the test function under the given name.
+    ///
+    /// Panics if a target with the same name was already registered.
+    pub fn add<T, F, M, S>(mut self, name: &str, test: F, mutator: M, serializer: S) -> Self
+    where
+        T: ?Sized + 'static,
+        M::Value: Borrow<T>,
+        F: Fn(&T) -> bool + 'static,
+        M: Mutator + 'static,
+        S: Serializer<Value = M::Value> + 'static,
+    {
+        assert!(
+            self.targets.iter().all(|(other, _, _)| other != name),
+            "The fuzz target {} was registered twice",
+            name
+        );
+        let type_name = mutator.type_name();
+        let target: FuzzTarget = Box::new(move |args| fuzzer::launch(test, mutator, serializer, args));
+        self.targets.push((name.to_owned(), type_name, target));
+        self
+    }
+
+    /// The names of the registered targets, in the order they were added
+    pub fn names(&self) -> Vec<&str> {
+        self.targets.iter().map(|(name, _, _)| name.as_str()).collect()
+    }
+}
+
+/// Fuzz-test the target whose name is given by `--target NAME`.
+///
+/// If there is a single target, `--target` can be omitted.
+pub fn launch_targets(mut targets: FuzzTargets) -> Result<(), std::io::Error> {
+    let args = parse_arguments();
+
+    let idx = match &args.target {
+        Some(name) => targets.targets.iter().position(|(other, _, _)| other == name),
+        None if targets.targets.len() == 1 => Some(0),
+        None => None,
+    };
+    if let Some(idx) = idx {
+        let (_, _, target) = targets.targets.swap_remove(idx);
+        target(args)
+    } else {
+        let available = targets
+            .targets
+            .iter()
+            .map(|(name, type_name, _)| format!("{} ({})", name, type_name))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let message = match args.target {
+            Some(name) => format!(
+                "Unknown fuzz target ‘{}’, the available targets are: {}",
+                name, available
+            ),
+            None => format!(
+                "A fuzz target must be chosen with --{}, the available targets are: {}",
+                TARGET_FLAG, available
+            ),
+        };
+        Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, message))
+    }
+}
+
+/// The help message printed when the command line arguments are invalid
+fn help(parser: &getopts::Options) -> String {
     let mut help = format!(
         r#""
 fuzzcheck <SUBCOMMAND> [OPTIONS]
@@ -45,12 +197,17 @@ SUBCOMMANDS:
     {fuzz}    Run the fuzz test
     {tmin}    Minify a crashing test input, requires --{input_file}
     {cmin}    Minify a corpus of test inputs, requires --{in_corpus}
+    {cdiff}   Compare the coverage of two corpora, requires --{in_corpus} and --{other_corpus}
+    {selftest} Check that the mutator and serializer are consistent
 "#,
         fuzz = COMMAND_FUZZ,
         tmin = COMMAND_MINIFY_INPUT,
         input_file = INPUT_FILE_FLAG,
         cmin = COMMAND_MINIFY_CORPUS,
         in_corpus = IN_CORPUS_FLAG,
+        cdiff = COMMAND_DIFF_CORPUS,
+        other_corpus = OTHER_CORPUS_FLAG,
+        selftest = COMMAND_SELF_TEST,
     );
     help += parser.usage("").as_str();
     help += format!(
@@ -74,25 +231,40 @@ fuzzcheck {cmin} --{in_corpus} "fuzz-corpus" --{corpus_size} 25
     contain JSON-encoded test inputs.
     It will remove files from that folder until only the 25 most important
     test inputs remain.
+
+fuzzcheck {cdiff} --{in_corpus} "fuzz-corpus" --{other_corpus} "new-seeds"
+
+    Compare the code coverage of the corpus "fuzz-corpus" with that of the
+    corpus "new-seeds". It will print the number of features covered by
+    only one of the two corpora, and how many of those each input covers.
+
+fuzzcheck {read} --{input_file} "seeds/input.json" --{trace_only}
+
+    Run the test function on the input defined in the file "seeds/input.json"
+    and print the features it covers, one per line, without mutating it.
+
+fuzzcheck {selftest} --{max_iter} 1000
+
+    Without running the test function, check that the mutator and serializer
+    are consistent on 1000 arbitrary values. It exits with a non-zero status
+    and prints the offending value on the first failure.
 "#,
         fuzz = COMMAND_FUZZ,
         tmin = COMMAND_MINIFY_INPUT,
         input_file = INPUT_FILE_FLAG,
         cmin = COMMAND_MINIFY_CORPUS,
         in_corpus = IN_CORPUS_FLAG,
-        corpus_size = CORPUS_SIZE_FLAG
+        corpus_size = CORPUS_SIZE_FLAG,
+        cdiff = COMMAND_DIFF_CORPUS,
+        other_corpus = OTHER_CORPUS_FLAG,
+        read = COMMAND_READ,
+        trace_only = TRACE_ONLY_FLAG,
+        selftest = COMMAND_SELF_TEST,
+        max_iter = MAX_NBR_RUNS_FLAG
     )
     .as_str();
 
-    let args = match CommandLineArguments::from_parser(&parser, &env_args[1..], DEFAULT_ARGUMENTS) {
-        Ok(r) => r,
-        Err(e) => {
-            println!("{}\n\n{}", e, help);
-            std::process::exit(1);
-        }
-    };
-
-    fuzzer::launch(test, mutator, serializer, args)
+    help
 }
 
 /**
@@ -141,6 +313,11 @@ fuzzcheck {cmin} --{in_corpus} "fuzz-corpus" --{corpus_size} 25
  * apply mutations to a value such that better mutations are tried first, and
  * duplicate mutations are avoided.
  *
+ * When the mutation step shows that there is no further distinct mutation to
+ * apply to the value, [mutate](crate::Mutator::mutate) returns `None` and
+ * leaves the value unchanged. For example, a `bool` can only be mutated once.
+ * The fuzzer then spends fewer iterations on that value.
+ *
  * ## Unmutate
  *
  * Finally, it is important to note that values and caches are mutated
@@ -162,7 +339,7 @@ fuzzcheck {cmin} --{in_corpus} "fuzz-corpus" --{corpus_size} 25
  * cache: c1 (ommitted from example)
  * step: s1 (ommitted from example)
  *
- * let unmutate_token = self.mutate(&mut value, &mut cache, &mut step, max_cplx);
+ * let unmutate_token = self.mutate(&mut value, &mut cache, &mut step, max_cplx).unwrap();
  *
  * // value = [[1, 3], [5], [9, 1, 8]]
  * // token = Element(2, Remove(1))
@@ -190,6 +367,19 @@ pub trait Mutator: Sized {
     /// Compute the initial mutation step for the given value
     fn mutation_step_from_value(&self, value: &Self::Value) -> Self::MutationStep;
 
+    /// Compute the initial mutation step for the given value, for a fuzzer
+    /// whose mutations are derived from `seed`.
+    ///
+    /// The mutations of an input then only depend on its value and on the
+    /// seed, not on when the fuzzer picks it, so that mutating a single input
+    /// can be reproduced in isolation. Mutators whose mutation step holds a
+    /// random number generator should seed it with a hash of the value and
+    /// of `seed`, and mutators of composite values should forward it to the
+    /// mutators of their parts. By default, the seed is ignored.
+    fn seeded_mutation_step_from_value(&self, value: &Self::Value, _seed: u64) -> Self::MutationStep {
+        self.mutation_step_from_value(value)
+    }
+
     /// The maximum complexity of an input of this type
     fn max_complexity(&self) -> f64;
     /// The minimum complexity of an input of this type
@@ -200,15 +390,98 @@ pub trait Mutator: Sized {
     /// Create an arbitrary value
     fn arbitrary(&self, seed: usize, max_cplx: f64) -> (Self::Value, Self::Cache);
 
+    /// Mutate the value in place and return a token to reverse the mutation,
+    /// or return `None` if the mutation step is exhausted
     fn mutate(
         &self,
         value: &mut Self::Value,
         cache: &mut Self::Cache,
         step: &mut Self::MutationStep,
         max_cplx: f64,
-    ) -> Self::UnmutateToken;
+    ) -> Option<Self::UnmutateToken>;
 
     fn unmutate(&self, value: &mut Self::Value, cache: &mut Self::Cache, t: Self::UnmutateToken);
+
+    /// Restrict the length of the values produced by a mutator of collections
+    ///
+    /// Return `false` if the mutator does not support length bounds, which
+    /// is the default.
+    fn set_length_range(&mut self, _len_range: RangeInclusive<usize>) -> bool {
+        false
+    }
+
+    /// The complexity of each field of the value, for mutators of structs
+    /// and tuples, to find out which field contributes the most to the
+    /// complexity of the value.
+    ///
+    /// It is empty for mutators of values without fields, which is the default.
+    fn complexity_breakdown(&self, _value: &Self::Value, _cache: &Self::Cache) -> Vec<(&'static str, f64)> {
+        vec![]
+    }
+
+    /// Returns the next of a deterministic sequence of simpler versions of
+    /// the value, whose complexity is lower than `max_cplx`, and advances
+    /// `step` past it. The step starts at 0.
+    ///
+    /// Input minification tests all of them before it falls back on random
+    /// mutations. Returns `None` once the sequence is exhausted, which is
+    /// immediately by default.
+    fn shrink_below(
+        &self,
+        _value: &Self::Value,
+        _cache: &Self::Cache,
+        _step: &mut usize,
+        _max_cplx: f64,
+    ) -> Option<(Self::Value, Self::Cache)> {
+        None
+    }
+
+    /// Builds a new value by combining pieces of two existing values, such as
+    /// the beginning of one vector followed by the end of another, using
+    /// `seed` to choose the pieces. Its complexity should be lower than
+    /// `max_cplx`.
+    ///
+    /// With `--arbitrary-from-corpus`, the fuzzer sometimes uses it instead
+    /// of [arbitrary](Mutator::arbitrary) to create a new input from two inputs
+    /// of the pool. Returns `None` if the values cannot be combined, which is
+    /// always the case by default.
+    fn crossover(
+        &self,
+        _a: &Self::Value,
+        _a_cache: &Self::Cache,
+        _b: &Self::Value,
+        _b_cache: &Self::Cache,
+        _seed: usize,
+        _max_cplx: f64,
+    ) -> Option<(Self::Value, Self::Cache)> {
+        None
+    }
+
+    /// Called by the fuzzer after testing a mutated value, before it is
+    /// unmutated with `token`, with the number of new features that the
+    /// value covered.
+    ///
+    /// Adaptive mutators can use it to favor the kinds of mutations that
+    /// were the most productive so far. Mutators of composite values should
+    /// forward it to the mutators of their parts. It does nothing by default.
+    fn record_mutation_outcome(&self, _value: &Self::Value, _token: &Self::UnmutateToken, _nbr_new_features: usize) {}
+
+    /// A human-readable description of the value, such as a pretty-printed
+    /// tree, which is saved next to the artifacts to make them easier to read
+    /// than their serialized form.
+    ///
+    /// Returns `None` by default, in which case only the serialized artifact
+    /// is saved. The values are not required to implement `Debug`, but a
+    /// mutator whose values do can return `Some(format!("{:#?}", value))`.
+    fn describe_value(&self, _value: &Self::Value) -> Option<String> {
+        None
+    }
+
+    /// The name of the type of the values produced by the mutator, used to
+    /// describe the fuzz target in the output of the fuzzer
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<Self::Value>()
+    }
 }
 
 /**
@@ -224,6 +497,12 @@ pub trait Serializer {
     fn extension(&self) -> &str;
     fn from_data(&self, data: &[u8]) -> Option<Self::Value>;
     fn to_data(&self, value: &Self::Value) -> Vec<u8>;
+    /// Whether the serialized form of a value is the raw bytes of the input,
+    /// as expected by other fuzzers such as libFuzzer and AFL. Only a raw
+    /// serializer can be used with `--raw-corpus`.
+    fn is_raw(&self) -> bool {
+        false
+    }
 }
 
 /**
@@ -237,40 +516,88 @@ pub trait Serializer {
  * actually contains a lot of information.
  *
  * - The first two bits designate the kind of the `Feature`, which can be either
- * `edge`, `indirect`, or `instruction`.
+ *   `edge`, `indirect`, `instruction`, or `penalty`.
  * - Then, the next 54 bits are the `id` of the feature. They are supposed to
- * uniquely identify a point in the source code.
- * - Finally, the last 8 bits are for the `payload` of the feature. They are
- * the information associated with the feature, such as the number of times
- * the control flow edge was reached or a hash of the operands to the
- * instruction.
- * - Note that for `indirect` features, `id` and `payload` are merged.
+ *   uniquely identify a point in the source code.
+ * - Finally, the last [PAYLOAD_BITS](Feature::PAYLOAD_BITS) (8) bits are for
+ *   the `payload` of the feature. They are the information associated with the
+ *   feature, such as the number of times the control flow edge was reached or
+ *   a hash of the operands to the instruction. Features that differ only by
+ *   their payload belong to the same group in the pool.
+ * - Note that for `indirect` and `penalty` features, `id` and `payload` are
+ *   merged.
  *
- * Each feature has a certain [score](Feature::score) that is currently only
- * determined by its `tag`.
+ * Each feature has a base score that is determined by its `tag`: comparison
+ * features are worth less than edges by default (see `FeatureWeights` in
+ * the pool). The score of `penalty` features, recorded by [penalize], is
+ * negative: they lower the score of the inputs that reach them, and never
+ * make an input interesting on their own.
+ *
+ * Features are ordered by their whole `u64`, payload included, so two
+ * features are equal only if they are the same feature. The pool keeps its
+ * features sorted in this order and looks them up with binary searches.
  */
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 struct Feature(u64);
 
 impl Feature {
+    /// The number of bits at the end of edge and instruction features that
+    /// hold their payload
+    const PAYLOAD_BITS: u64 = 8;
+
     /// The bit offset for the id of the feature
     fn id_offset() -> u64 {
-        8
+        Feature::PAYLOAD_BITS
+    }
+    /// The mask of the payload bits of edge and instruction features
+    fn payload_mask() -> u64 {
+        (1 << Feature::PAYLOAD_BITS) - 1
+    }
+    /// One more than the largest id of edge and instruction features
+    fn id_modulus() -> u64 {
+        1 << (Feature::tag_offset() - Feature::id_offset())
     }
     /// The bit offset for the tag of the feature
     fn tag_offset() -> u64 {
         62
     }
 
-    // fn edge_tag() -> u64 {
-    //     0b00
-    // }
+    fn edge_tag() -> u64 {
+        0b00
+    }
     fn indir_tag() -> u64 {
         0b01
     }
     fn instr_tag() -> u64 {
         0b10
     }
+    fn penalty_tag() -> u64 {
+        0b11
+    }
+
+    /// The tag of the feature, which identifies its kind
+    fn tag(&self) -> u64 {
+        self.0 >> Feature::tag_offset()
+    }
+    /// The id of an edge or instruction feature, which is its `pc_guard`
+    /// or `pc` respectively
+    fn id(&self) -> u64 {
+        (self.0 & !(0b11 << Feature::tag_offset())) >> Feature::id_offset()
+    }
+    /// The payload of an edge or instruction feature
+    #[cfg(test)]
+    fn payload(&self) -> u64 {
+        self.0 & Feature::payload_mask()
+    }
+    /// Create a feature of the same kind and id as `base`, whose payload is
+    /// the last [PAYLOAD_BITS](Feature::PAYLOAD_BITS) bits of `payload`.
+    ///
+    /// `base` must be an edge or instruction feature, since the payload of
+    /// indirect and penalty features is merged with their id.
+    fn with_payload(base: Feature, payload: u64) -> Feature {
+        debug_assert!(base.tag() != Feature::indir_tag() && base.tag() != Feature::penalty_tag());
+        Feature(base.erasing_payload().0 | (payload & Feature::payload_mask()))
+    }
     /// Create a “control flow edge” feature identified by the given `pc_guard`
     /// whose payload is the intensity of the given `counter`.
     fn edge(pc_guard: usize, counter: u16) -> Feature {
@@ -279,38 +606,100 @@ impl Feature {
         // feature |= 0b00 << Feature::tag_offset();
         // take 32 last bits, I don't want to worry about programs with more than 4 billion instrumented edges anyway
         feature |= ((pc_guard & 0xFFFF_FFFF) as u64) << Feature::id_offset();
-        feature |= Feature::score_from_counter(counter) as u64; // will only ever be 8 bits long
 
-        Feature(feature)
+        Feature::with_payload(Feature(feature), Feature::score_from_counter(counter) as u64)
     }
     /// Create an “indirect call” feature identified by the given `caller_xor_callee`
     fn indir(caller_xor_callee: usize) -> Feature {
         let mut feature: u64 = 0;
         // keep 62 bits with modulo
         feature |= Feature::indir_tag() << Feature::tag_offset();
-        feature |= (caller_xor_callee as u64) % (0x4000_0000_0000_0000 as u64);
+        feature |= (caller_xor_callee as u64) % 0x4000_0000_0000_0000;
+
+        Feature(feature)
+    }
+    /// Create a “penalty” feature identified by the given `id`, chosen by the
+    /// test function
+    fn penalty(id: usize) -> Feature {
+        let mut feature: u64 = 0;
+        // keep 62 bits with modulo
+        feature |= Feature::penalty_tag() << Feature::tag_offset();
+        feature |= (id as u64) % 0x4000_0000_0000_0000;
 
         Feature(feature)
     }
+    /// Whether the feature lowers the score of the inputs that reach it
+    fn is_penalty(&self) -> bool {
+        self.tag() == Feature::penalty_tag()
+    }
     /// Create an “instructon” feature identified by the given `pc` whose payload
     /// is a ~hash of the two arguments.
     fn instruction(pc: usize, arg1: u64, arg2: u64) -> Feature {
         let mut feature: u64 = 0;
-        feature |= Feature::instr_tag() << Feature::tag_offset(); // won't do anything
-                                                                  // keep 54 bits with modulo
-        feature |= ((pc as u64) % 0x40_0000_0000_0000) << Feature::id_offset(); // id
-        feature |= Feature::score_from_counter((arg1 ^ arg2).count_ones() as u16) as u64;
+        feature |= Feature::instr_tag() << Feature::tag_offset();
+        // keep the 54 bits that fit between the tag and the payload
+        feature |= ((pc as u64) % Feature::id_modulus()) << Feature::id_offset(); // id
 
-        Feature(feature)
+        Feature::with_payload(
+            Feature(feature),
+            Feature::score_from_counter((arg1 ^ arg2).count_ones() as u16) as u64,
+        )
+    }
+
+    /// Create an “instruction” feature identified by the given `id` for a
+    /// comparison between two floating-point numbers. Its payload is the
+    /// bucketed [distance](Feature::float_distance) between the two arguments.
+    fn float_instruction(id: usize, arg1: f64, arg2: f64) -> Feature {
+        let mut feature: u64 = 0;
+        feature |= Feature::instr_tag() << Feature::tag_offset();
+        feature |= ((id as u64) % Feature::id_modulus()) << Feature::id_offset(); // id
+
+        Feature::with_payload(
+            Feature(feature),
+            Feature::score_from_distance(Feature::float_distance(arg1, arg2)) as u64,
+        )
+    }
+
+    /// The number of representable floating-point numbers between `x` and `y`.
+    ///
+    /// The IEEE bit patterns of the two floats are mapped to integers that
+    /// are ordered like the floats themselves, and then subtracted. So the
+    /// distance between two consecutive floats is 1, regardless of their
+    /// magnitude, and the distance between `-0.0` and `0.0` is 0.
+    fn float_distance(x: f64, y: f64) -> u64 {
+        fn ordered_bits(x: f64) -> i64 {
+            let bits = x.to_bits() as i64;
+            if bits < 0 {
+                // negative floats are ordered in the opposite direction of their bit patterns
+                std::i64::MIN - bits
+            } else {
+                bits
+            }
+        }
+        let (x, y) = (ordered_bits(x), ordered_bits(y));
+        if x > y {
+            (x as i128 - y as i128) as u64
+        } else {
+            (y as i128 - x as i128) as u64
+        }
+    }
+
+    /// “Hash” a distance into a number between 0 and 64.
+    ///
+    /// It is the number of significant bits of the distance, so that
+    /// each bucket is twice as large as the previous one.
+    fn score_from_distance(distance: u64) -> u8 {
+        (64 - distance.leading_zeros()) as u8
     }
 
     fn erasing_payload(&self) -> Self {
-        if (self.0 >> Self::tag_offset()) == Self::indir_tag() {
-            // if it is indirect, there is no payload to erase
+        let tag = self.0 >> Self::tag_offset();
+        if tag == Self::indir_tag() || tag == Self::penalty_tag() {
+            // if it is indirect or a penalty, there is no payload to erase
             *self
         } else {
             // else, zero out the payload bits
-            Feature(self.0 & 0xFFFF_FFFF_FFFF_FF00)
+            Feature(self.0 & !Feature::payload_mask())
         }
     }
 
@@ -338,6 +727,12 @@ struct FuzzedInput<Mut: Mutator> {
     pub value: Mut::Value,
     pub cache: Mut::Cache,
     pub mutation_step: Mut::MutationStep,
+    /// The seed from which the mutation step was derived, see
+    /// [seeded_mutation_step_from_value](Mutator::seeded_mutation_step_from_value)
+    pub mutation_seed: u64,
+    /// The decisions of the mutator that produced the current value, if it
+    /// can be replayed
+    pub replay_log: Option<ReplayLog<Mut::Value>>,
 }
 
 impl<Mut: Mutator> FuzzedInput<Mut> {
@@ -346,6 +741,24 @@ impl<Mut: Mutator> FuzzedInput<Mut> {
             value,
             cache,
             mutation_step,
+            mutation_seed: 0,
+            replay_log: None,
+        }
+    }
+    pub fn new_from_value(m: &Mut, value: Mut::Value) -> Self {
+        let cache = m.cache_from_value(&value);
+        let mutation_step = m.mutation_step_from_value(&value);
+        Self::new(value, cache, mutation_step)
+    }
+    /// An arbitrary input, whose replay log starts with the `arbitrary` call
+    pub fn arbitrary(m: &Mut, seed: usize, max_cplx: f64) -> Self {
+        let (value, cache) = m.arbitrary(seed, max_cplx);
+        let mutation_step = m.mutation_step_from_value(&value);
+        let mut decisions = DecisionLog::new();
+        decisions.arbitrary(seed, max_cplx);
+        Self {
+            replay_log: Some(ReplayLog { start: None, decisions }),
+            ..Self::new(value, cache, mutation_step)
         }
     }
     pub fn default(m: &Mut) -> Self {
@@ -354,23 +767,103 @@ impl<Mut: Mutator> FuzzedInput<Mut> {
         Self::new(value, cache, mutation_step)
     }
 
-    pub fn new_source(&self, m: &Mut) -> Self {
-        Self::new(
-            self.value.clone(),
-            self.cache.clone(),
-            m.mutation_step_from_value(&self.value),
-        )
+    /// A copy of the input, with a fresh mutation step derived from `mutation_seed`.
+    ///
+    /// Its replay log starts from its current value, which is kept so that
+    /// the mutations of the copy can be replayed.
+    pub fn new_source(&self, m: &Mut, mutation_seed: u64) -> Self {
+        let mut decisions = DecisionLog::new();
+        decisions.reset_step(Some(mutation_seed));
+        Self {
+            value: self.value.clone(),
+            cache: self.cache.clone(),
+            mutation_step: m.seeded_mutation_step_from_value(&self.value, mutation_seed),
+            mutation_seed,
+            replay_log: Some(ReplayLog {
+                start: Some(self.value.clone()),
+                decisions,
+            }),
+        }
+    }
+
+    /// Restarts the mutations of the input from the beginning
+    pub fn reset_mutation_step(&mut self, m: &Mut) {
+        self.mutation_step = m.seeded_mutation_step_from_value(&self.value, self.mutation_seed);
+        if let Some(replay_log) = &mut self.replay_log {
+            replay_log.decisions.reset_step(Some(self.mutation_seed));
+        }
     }
 
     pub fn complexity(&self, m: &Mut) -> f64 {
         m.complexity(&self.value, &self.cache)
     }
 
-    pub fn mutate(&mut self, m: &Mut, max_cplx: f64) -> Mut::UnmutateToken {
-        m.mutate(&mut self.value, &mut self.cache, &mut self.mutation_step, max_cplx)
+    pub fn mutate(&mut self, m: &Mut, max_cplx: f64) -> Option<Mut::UnmutateToken> {
+        let token = m.mutate(&mut self.value, &mut self.cache, &mut self.mutation_step, max_cplx);
+        if let Some(replay_log) = &mut self.replay_log {
+            replay_log.decisions.mutate(max_cplx, token.is_some());
+        }
+        token
     }
 
     pub fn unmutate(&mut self, m: &Mut, t: Mut::UnmutateToken) {
         m.unmutate(&mut self.value, &mut self.cache, t);
+        if let Some(replay_log) = &mut self.replay_log {
+            replay_log.decisions.unmutate();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_float_distance() {
+        assert_eq!(Feature::float_distance(1.5, 1.5), 0);
+        assert_eq!(Feature::float_distance(-0.0, 0.0), 0);
+        // consecutive floats are at distance 1, whatever their magnitude
+        assert_eq!(Feature::float_distance(1.0, f64::from_bits(1.0f64.to_bits() + 1)), 1);
+        assert_eq!(
+            Feature::float_distance(1e300, f64::from_bits(1e300f64.to_bits() + 1)),
+            1
+        );
+        assert_eq!(
+            Feature::float_distance(-1.0, f64::from_bits((-1.0f64).to_bits() + 1)),
+            1
+        );
+        // the smallest positive and negative floats are on each side of zero
+        assert_eq!(Feature::float_distance(-f64::from_bits(1), f64::from_bits(1)), 2);
+        assert_eq!(Feature::float_distance(-2.0, 3.0), Feature::float_distance(3.0, -2.0));
+        assert!(Feature::float_distance(0.0, 1.0) < Feature::float_distance(0.0, 2.0));
+        assert!(Feature::float_distance(-1.0, 0.0) < Feature::float_distance(-2.0, 0.0));
+        assert_eq!(
+            Feature::float_distance(std::f64::NEG_INFINITY, std::f64::INFINITY),
+            2 * std::f64::INFINITY.to_bits()
+        );
+    }
+
+    #[test]
+    fn test_score_from_distance() {
+        assert_eq!(Feature::score_from_distance(0), 0);
+        assert_eq!(Feature::score_from_distance(1), 1);
+        assert_eq!(Feature::score_from_distance(2), 2);
+        assert_eq!(Feature::score_from_distance(3), 2);
+        assert_eq!(Feature::score_from_distance(4), 3);
+        assert_eq!(Feature::score_from_distance(1 << 40), 41);
+        assert_eq!(Feature::score_from_distance(std::u64::MAX), 64);
+    }
+
+    #[test]
+    fn test_float_instruction() {
+        let close = Feature::float_instruction(7, 0.5, 0.5000001);
+        let far = Feature::float_instruction(7, 0.5, 1e10);
+        // the same comparison, with a different payload
+        assert_eq!(close.erasing_payload(), far.erasing_payload());
+        assert_ne!(close, far);
+        assert_ne!(
+            Feature::float_instruction(7, 0.5, 1.0).erasing_payload(),
+            Feature::float_instruction(8, 0.5, 1.0).erasing_payload()
+        );
     }
 }
@@ -56,9 +56,12 @@
 //!
 
 use std::cmp::Ordering;
-use std::collections::BTreeSet;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::ops::Range;
+use std::rc::Rc;
 
 use rand::rngs::SmallRng;
 use rand::{Rng, SeedableRng};
@@ -66,10 +69,68 @@ use rand::{Rng, SeedableRng};
 use rand::distributions::uniform::{UniformFloat, UniformSampler};
 use rand::distributions::Distribution;
 
+use fuzzcheck_arg_parser::DEFAULT_ENERGY_DECAY;
+
 use crate::data_structures::{Slab, SlabKey, WeightedIndex};
 use crate::world::{FuzzerEvent, WorldAction};
 use crate::{Feature, FuzzedInput, Mutator};
 
+/// The factor applied to the weight of an exhausted input when picking an
+/// input to mutate
+const EXHAUSTED_INPUT_WEIGHT: f64 = 0.1;
+/// The factor applied to the weight of an input covering a feature that no
+/// previous run covered when picking an input to mutate
+const GLOBALLY_NEW_INPUT_WEIGHT: f64 = 4.0;
+/// The number of times an input is picked for mutation between two
+/// applications of the energy decay to its weight
+const MUTATIONS_PER_ENERGY_DECAY: usize = 1000;
+/// The lowest factor that the energy decay can apply to the weight of an input
+const MIN_ENERGY_WEIGHT: f64 = 0.01;
+/// The lowest weight with which an input is picked for mutation, used instead
+/// of its score when penalty features bring the score to zero or below
+const MIN_INPUT_WEIGHT: f64 = 0.001;
+
+/// The base score of each kind of feature, before it is divided between the
+/// inputs and the other features of its group
+#[derive(Clone)]
+pub struct FeatureWeights {
+    /// The weight of control flow edges and indirect calls
+    pub edge: f64,
+    /// The weight of comparison instructions
+    pub instruction: f64,
+    /// The factor applied to the weight of some edges, by id, given by
+    /// --edge-weights. The other edges keep the weight of all edges.
+    pub edge_multipliers: Rc<HashMap<u64, f64>>,
+    /// The weight of the features recorded by [penalize](crate::penalize),
+    /// which is subtracted from the score of the inputs
+    pub penalty: f64,
+}
+
+impl Default for FeatureWeights {
+    fn default() -> Self {
+        FeatureWeights {
+            edge: 1.0,
+            instruction: 0.5,
+            edge_multipliers: Rc::default(),
+            penalty: 1.0,
+        }
+    }
+}
+
+impl FeatureWeights {
+    fn of(&self, feature: Feature) -> f64 {
+        if feature.tag() == Feature::instr_tag() {
+            self.instruction
+        } else if feature.tag() == Feature::edge_tag() {
+            self.edge * self.edge_multipliers.get(&feature.id()).copied().unwrap_or(1.0)
+        } else if feature.is_penalty() {
+            -self.penalty
+        } else {
+            self.edge
+        }
+    }
+}
+
 /// Index of an input in the Pool
 pub enum PoolIndex<M: Mutator> {
     Normal(SlabKey<Input<M>>),
@@ -86,6 +147,16 @@ impl<M: Mutator> Clone for PoolIndex<M> {
 }
 impl<M: Mutator> Copy for PoolIndex<M> {}
 
+/// Where an input of the pool comes from
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InputOrigin {
+    /// The input was read from the input corpus, which is usually curated by
+    /// hand
+    Seed,
+    /// The input was found by the fuzzer
+    Discovered,
+}
+
 /**
  * An element stored in the pool, containing its value, cache, mutation step,
  * as well as analysed code coverage and computed score.
@@ -96,6 +167,9 @@ pub struct Input<M: Mutator> {
     least_complex_for_features: BTreeSet<SlabKey<FeatureInPool<M>>>,
     /// Holds the key of each [FeatureInPool] associated with this input.
     all_features: Vec<SlabKey<FeatureInPool<M>>>,
+    /// A hash of the sorted features of the input, to quickly find the
+    /// inputs that have the same features
+    feature_set_hash: u64,
     /// The computed score of the input
     score: f64,
     /// Data associated with the input: value, cache, and mutation step
@@ -106,6 +180,18 @@ pub struct Input<M: Mutator> {
     complexity: f64,
     /// The corresponding index of the input in [pool.inputs](self::Pool::inputs)
     idx_in_pool: usize,
+    /// Whether the mutator ran out of mutations to apply to the input at
+    /// least once, in which case the input is picked less often
+    is_exhausted: bool,
+    /// Whether the input covers a feature that is not in
+    /// [pool.global_features](self::Pool::global_features), in which case it
+    /// is picked more often
+    is_globally_new: bool,
+    /// Whether the input is a seed or was discovered by the fuzzer
+    origin: InputOrigin,
+    /// The number of times the input was picked for mutation by
+    /// [random_index](Pool::random_index)
+    times_mutated: usize,
 }
 
 pub struct FeatureInPool<M: Mutator> {
@@ -193,7 +279,7 @@ struct FeatureGroupId {
 impl Feature {
     fn group_id(&self) -> FeatureGroupId {
         FeatureGroupId {
-            // erase last 8 bits, which stand for the payload
+            // erase the last Feature::PAYLOAD_BITS bits, which stand for the payload
             id: self.erasing_payload(),
         }
     }
@@ -229,12 +315,31 @@ pub struct Pool<M: Mutator> {
     favored_input: Option<FuzzedInput<M>>,
 
     pub average_complexity: f64,
+    pub feature_weights: FeatureWeights,
+    /// The features covered by previous fuzzing runs, read from --features-in
+    global_features: HashSet<Feature>,
+    /// One bit per edge guard, set when an edge feature of the guard is first
+    /// added to the pool, and never cleared
+    edge_bitmap: Vec<u8>,
+    /// Whether the inputs imported with [import_seed](Pool::import_seed) are
+    /// never removed from the pool, set by --keep-seeds
+    pub keep_seeds: bool,
+    /// The factor applied to the weight of an input every
+    /// [MUTATIONS_PER_ENERGY_DECAY] times it is picked for mutation, so that
+    /// the inputs that were rarely mutated are picked more often than the
+    /// ones that were mutated a lot. It is set by --energy-decay, and 1.0
+    /// disables the decay.
+    pub energy_decay: f64,
+    total_score: f64,
+    /// The cumulative sum of the weights with which inputs are picked for mutation
     cumulative_weights: Vec<f64>,
     rng: SmallRng,
 }
 
 impl<M: Mutator> Pool<M> {
-    pub fn default() -> Self {
+    /// An empty pool, whose random number generator, used to pick the
+    /// inputs to mutate, is seeded with the given seed
+    pub fn new(rng_seed: u64) -> Self {
         Pool {
             features: Vec::new(),
             slab_features: Slab::new(),
@@ -248,8 +353,14 @@ impl<M: Mutator> Pool<M> {
             favored_input: None,
 
             average_complexity: 0.0,
+            feature_weights: FeatureWeights::default(),
+            global_features: HashSet::new(),
+            edge_bitmap: Vec::new(),
+            keep_seeds: false,
+            energy_decay: DEFAULT_ENERGY_DECAY,
+            total_score: 0.0,
             cumulative_weights: Vec::default(),
-            rng: SmallRng::from_entropy(),
+            rng: SmallRng::seed_from_u64(rng_seed),
         }
     }
 
@@ -258,7 +369,49 @@ impl<M: Mutator> Pool<M> {
     }
 
     pub fn score(&self) -> f64 {
-        *self.cumulative_weights.last().unwrap_or(&0.0)
+        self.total_score
+    }
+
+    /// Sets the features covered by previous fuzzing runs. The inputs that
+    /// cover any other feature are considered globally new and are picked
+    /// more often for mutation.
+    pub(crate) fn set_global_features(&mut self, features: impl IntoIterator<Item = Feature>) {
+        self.global_features = features.into_iter().collect();
+        let slab_features = &self.slab_features;
+        let global_features = &self.global_features;
+        for &key in self.inputs.iter() {
+            let input = &mut self.slab_inputs[key];
+            input.is_globally_new = input
+                .all_features
+                .iter()
+                .any(|&f| !global_features.contains(&slab_features[f].feature));
+        }
+        self.update_stats();
+    }
+
+    /// The number of features covered by the pool that were not covered by
+    /// previous fuzzing runs
+    pub fn nbr_globally_new_features(&self) -> usize {
+        self.features
+            .iter()
+            .filter(|f| !self.global_features.contains(&f.feature))
+            .count()
+    }
+
+    /// The union of the features covered by previous fuzzing runs and of
+    /// those covered by the pool, sorted
+    pub(crate) fn all_global_features(&self) -> Vec<Feature> {
+        let mut features: Vec<Feature> = self.global_features.iter().copied().collect();
+        features.extend(self.features.iter().map(|f| f.feature));
+        features.sort();
+        features.dedup();
+        features
+    }
+
+    /// Whether the input is a seed that must stay in the pool because of
+    /// --keep-seeds
+    fn is_kept_seed(&self, key: SlabKey<Input<M>>) -> bool {
+        self.keep_seeds && self.slab_inputs[key].origin == InputOrigin::Seed
     }
 
     pub(crate) fn add(
@@ -268,14 +421,69 @@ impl<M: Mutator> Pool<M> {
         existing_features: Vec<SlabKey<FeatureInPool<M>>>,
         new_features: Vec<Feature>,
     ) -> Vec<WorldAction<M::Value>> {
+        self.add_with_origin(
+            data,
+            complexity,
+            existing_features,
+            new_features,
+            InputOrigin::Discovered,
+        )
+    }
+
+    /// Adds an input read from the input corpus to the pool.
+    ///
+    /// It behaves like [add](Pool::add), except that the input is marked as
+    /// a [seed](InputOrigin::Seed). If [keep_seeds](Pool::keep_seeds) is true,
+    /// it is then never removed from the pool, even when simpler inputs cover
+    /// all of its features.
+    pub(crate) fn import_seed(
+        &mut self,
+        data: FuzzedInput<M>,
+        complexity: f64,
+        existing_features: Vec<SlabKey<FeatureInPool<M>>>,
+        new_features: Vec<Feature>,
+    ) -> Vec<WorldAction<M::Value>> {
+        self.add_with_origin(data, complexity, existing_features, new_features, InputOrigin::Seed)
+    }
+
+    fn add_with_origin(
+        &mut self,
+        data: FuzzedInput<M>,
+        complexity: f64,
+        existing_features: Vec<SlabKey<FeatureInPool<M>>>,
+        new_features: Vec<Feature>,
+        origin: InputOrigin,
+    ) -> Vec<WorldAction<M::Value>> {
+        if existing_features.is_empty() && new_features.is_empty() {
+            // the input would have a score of 0 and could never be picked
+            return vec![];
+        }
+        let feature_set_hash = self.feature_set_hash(&existing_features, &new_features);
+        // two inputs with the same features are redundant, so only the simplest one is kept
+        let duplicate = self.input_with_same_features(feature_set_hash, &existing_features, &new_features);
+        if let Some(duplicate) = duplicate {
+            if self.slab_inputs[duplicate].complexity <= complexity {
+                return vec![];
+            }
+        }
+        let duplicate_to_delete = duplicate.filter(|&key| !self.is_kept_seed(key));
+
+        let weights = self.feature_weights.clone();
+        let keep_seeds = self.keep_seeds;
         let element_key: SlabKey<Input<M>> = {
             let element = Input {
                 least_complex_for_features: BTreeSet::default(),
                 all_features: vec![],
+                // set below, once all the features of the input are known
+                feature_set_hash: 0,
                 score: 0.0,
                 data,
                 complexity,
                 idx_in_pool: self.inputs.len(),
+                is_exhausted: false,
+                is_globally_new: false,
+                origin,
+                times_mutated: 0,
             };
             let i_key = self.slab_inputs.insert(element);
             self.inputs.push(i_key);
@@ -288,6 +496,13 @@ impl<M: Mutator> Pool<M> {
         for feature_key in existing_features.iter() {
             let feature = &mut self.slab_features[*feature_key];
 
+            if feature.feature.is_penalty() {
+                // a penalty feature never keeps an input in the pool, whatever its complexity
+                self.slab_inputs[element_key].all_features.push(*feature_key);
+                feature.inputs.push(element_key);
+                continue;
+            }
+
             for input_key in feature.inputs.iter() {
                 let affected_element = &mut self.slab_inputs[*input_key];
                 if affected_element.complexity >= complexity {
@@ -299,7 +514,8 @@ impl<M: Mutator> Pool<M> {
                     // and then process them all at once?
                     // and also for each element in this list a list of features to delete
                     affected_element.least_complex_for_features.remove(feature_key);
-                    if affected_element.least_complex_for_features.is_empty() {
+                    let is_kept_seed = keep_seeds && affected_element.origin == InputOrigin::Seed;
+                    if affected_element.least_complex_for_features.is_empty() && !is_kept_seed {
                         // then this will only be called once by element
                         to_delete.push(*input_key);
                     }
@@ -316,26 +532,44 @@ impl<M: Mutator> Pool<M> {
             element.all_features.push(*feature_key);
             feature.inputs.push(element_key);
         }
+        to_delete.extend(duplicate_to_delete);
 
         let element = &mut self.slab_inputs[element_key];
+        let mut nbr_new_groups = 0;
         for &f in new_features.iter() {
             let f_key = self.slab_features.next_key();
 
             let new_feature_for_iter = FeatureForIteration { key: f_key, feature: f };
-            let group_key = Self::insert_feature(
+            let (group_key, is_new_group) = Self::insert_feature(
                 &mut self.features,
                 &mut self.feature_groups,
                 &mut self.slab_feature_groups,
                 new_feature_for_iter,
             );
 
+            if is_new_group {
+                nbr_new_groups += 1;
+            }
+
             let f_in_pool = FeatureInPool::new(f_key, f, group_key, vec![element_key], element_key, complexity);
             self.slab_features.insert(f_in_pool);
 
             element.all_features.push(f_key);
-            element.least_complex_for_features.insert(f_key);
+            if !f.is_penalty() {
+                element.least_complex_for_features.insert(f_key);
+            }
+
+            if f.tag() == Feature::edge_tag() {
+                let guard = f.id() as usize;
+                if self.edge_bitmap.len() <= guard / 8 {
+                    self.edge_bitmap.resize(guard / 8 + 1, 0);
+                }
+                self.edge_bitmap[guard / 8] |= 1 << (guard % 8);
+            }
         }
 
+        self.update_feature_set_hash(element_key);
+
         to_delete.sort();
         to_delete.dedup();
 
@@ -364,8 +598,16 @@ impl<M: Mutator> Pool<M> {
                 let feature_key = f_for_iter.key;
                 let feature_in_pool = &mut self.slab_features[feature_key];
 
-                let old_feature_score = Self::score_of_feature(group.old_size, feature_in_pool.old_multiplicity);
-                let new_feature_score = Self::score_of_feature(group.size(), feature_in_pool.inputs.len());
+                let old_feature_score = Self::score_of_feature(
+                    weights.of(feature_in_pool.feature),
+                    group.old_size,
+                    feature_in_pool.old_multiplicity,
+                );
+                let new_feature_score = Self::score_of_feature(
+                    weights.of(feature_in_pool.feature),
+                    group.size(),
+                    feature_in_pool.inputs.len(),
+                );
                 let change_in_score = new_feature_score - old_feature_score;
 
                 for &input_key in feature_in_pool.inputs.iter() {
@@ -405,8 +647,16 @@ impl<M: Mutator> Pool<M> {
 
             let group = &self.slab_feature_groups[feature_in_pool.group_key];
 
-            let old_feature_score = Self::score_of_feature(group.old_size, feature_in_pool.old_multiplicity);
-            let new_feature_score = Self::score_of_feature(group.size(), feature_in_pool.inputs.len());
+            let old_feature_score = Self::score_of_feature(
+                weights.of(feature_in_pool.feature),
+                group.old_size,
+                feature_in_pool.old_multiplicity,
+            );
+            let new_feature_score = Self::score_of_feature(
+                weights.of(feature_in_pool.feature),
+                group.size(),
+                feature_in_pool.inputs.len(),
+            );
 
             let change_in_score = new_feature_score - old_feature_score;
 
@@ -424,19 +674,29 @@ impl<M: Mutator> Pool<M> {
         for f_key in element.all_features.iter() {
             let feature_in_pool = &mut self.slab_features[*f_key];
             let group = &self.slab_feature_groups[feature_in_pool.group_key];
-            let feature_score = Self::score_of_feature(group.size(), feature_in_pool.inputs.len());
+            let feature_score = Self::score_of_feature(
+                weights.of(feature_in_pool.feature),
+                group.size(),
+                feature_in_pool.inputs.len(),
+            );
             element.score += feature_score;
+            if !feature_in_pool.feature.is_penalty() && !self.global_features.contains(&feature_in_pool.feature) {
+                element.is_globally_new = true;
+            }
         }
 
         let value = element.data.value.clone();
 
         let mut actions: Vec<WorldAction<M::Value>> = Vec::new();
 
+        if nbr_new_groups > 0 {
+            actions.push(WorldAction::ReportEvent(FuzzerEvent::NewGroup(nbr_new_groups)));
+        }
         if !deleted_values.is_empty() {
             actions.push(WorldAction::ReportEvent(FuzzerEvent::Replace(deleted_values.len())));
         } else {
             actions.push(WorldAction::ReportEvent(FuzzerEvent::New));
-            actions.push(WorldAction::Add(value, vec![]));
+            actions.push(WorldAction::Add(value));
         }
 
         for i in deleted_values.into_iter() {
@@ -450,7 +710,64 @@ impl<M: Mutator> Pool<M> {
         actions
     }
 
+    /// A hash of the sorted features of an input
+    fn feature_set_hash(&self, existing_features: &[SlabKey<FeatureInPool<M>>], new_features: &[Feature]) -> u64 {
+        let features: Vec<Feature> = existing_features
+            .iter()
+            .map(|&f_key| self.slab_features[f_key].feature)
+            .chain(new_features.iter().copied())
+            .collect();
+        Self::hash_features(features)
+    }
+
+    fn hash_features(mut features: Vec<Feature>) -> u64 {
+        features.sort();
+        let mut hasher = DefaultHasher::new();
+        features.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Recomputes the `feature_set_hash` of the input from its features.
+    /// It must be called every time its `all_features` changes.
+    fn update_feature_set_hash(&mut self, key: SlabKey<Input<M>>) {
+        let features: Vec<Feature> = self.slab_inputs[key]
+            .all_features
+            .iter()
+            .map(|&f_key| self.slab_features[f_key].feature)
+            .collect();
+        self.slab_inputs[key].feature_set_hash = Self::hash_features(features);
+    }
+
+    /// The input of the pool that has exactly the given features, if any
+    fn input_with_same_features(
+        &self,
+        feature_set_hash: u64,
+        existing_features: &[SlabKey<FeatureInPool<M>>],
+        new_features: &[Feature],
+    ) -> Option<SlabKey<Input<M>>> {
+        if !new_features.is_empty() {
+            // no input of the pool has the new features
+            return None;
+        }
+        let mut features = existing_features.to_vec();
+        features.sort();
+        // an input with the same features is one of the inputs of any of them
+        let candidates = &self.slab_features[*features.first()?].inputs;
+        candidates.iter().copied().find(|&key| {
+            let input = &self.slab_inputs[key];
+            if input.feature_set_hash != feature_set_hash || input.all_features.len() != features.len() {
+                return false;
+            }
+            let mut input_features = input.all_features.clone();
+            input_features.sort();
+            input_features == features
+        })
+    }
+
     pub fn delete_elements(&mut self, to_delete: Vec<SlabKey<Input<M>>>, should_not_update_key: SlabKey<Input<M>>) {
+        let weights = self.feature_weights.clone();
+        // the features whose only inputs were deleted
+        let mut features_without_inputs = vec![];
         for &to_delete_key in to_delete.iter() {
             let to_swap_idx = self.inputs.len() - 1;
             let to_swap_key = *self.inputs.last().unwrap();
@@ -475,8 +792,13 @@ impl<M: Mutator> Pool<M> {
 
                 let group = &self.slab_feature_groups[f_in_pool.group_key];
 
-                let new_feature_score = Self::score_of_feature(group.old_size, f_in_pool.inputs.len());
-                let old_feature_score = Self::score_of_feature(group.old_size, f_in_pool.old_multiplicity);
+                let new_feature_score =
+                    Self::score_of_feature(weights.of(f_in_pool.feature), group.old_size, f_in_pool.inputs.len());
+                let old_feature_score = Self::score_of_feature(
+                    weights.of(f_in_pool.feature),
+                    group.old_size,
+                    f_in_pool.old_multiplicity,
+                );
                 let change_in_score = new_feature_score - old_feature_score;
 
                 for input_key in f_in_pool.inputs.iter() {
@@ -486,19 +808,86 @@ impl<M: Mutator> Pool<M> {
                     }
                 }
                 f_in_pool.old_multiplicity = f_in_pool.inputs.len();
+                if f_in_pool.inputs.is_empty() {
+                    features_without_inputs.push(f_key);
+                }
             }
             self.slab_inputs.remove(to_delete_key);
         }
+        for f_key in features_without_inputs {
+            self.remove_feature(f_key, should_not_update_key);
+        }
     }
 
+    /// Removes a feature that is not reached by any input anymore, along with
+    /// its group if it was the last feature of the group.
+    ///
+    /// The group of the feature becomes smaller, which raises the score of
+    /// the other features of the group, and thus of the inputs that reach
+    /// them, except for the input with the key `should_not_update_key`.
+    fn remove_feature(&mut self, f_key: SlabKey<FeatureInPool<M>>, should_not_update_key: SlabKey<Input<M>>) {
+        let weights = self.feature_weights.clone();
+        let f_in_pool = &self.slab_features[f_key];
+        // otherwise, the features of these inputs, and thus their
+        // feature_set_hash, would change
+        debug_assert!(f_in_pool.inputs.is_empty());
+        let feature = f_in_pool.feature;
+        let group_key = f_in_pool.group_key;
+
+        let feature_idx = self.features.binary_search_by_key(&feature, |f| f.feature).unwrap();
+        self.features.remove(feature_idx);
+        self.slab_features.remove(f_key);
+
+        let group_idx = self
+            .feature_groups
+            .binary_search_by_key(&feature.group_id(), |&g| self.slab_feature_groups[g].id)
+            .unwrap();
+        for group_key in self.feature_groups[group_idx + 1..].iter() {
+            let group = &mut self.slab_feature_groups[*group_key];
+            group.idcs.start -= 1;
+            group.idcs.end -= 1;
+        }
+
+        let group = &mut self.slab_feature_groups[group_key];
+        group.idcs.end -= 1;
+        if group.size() == 0 {
+            self.feature_groups.remove(group_idx);
+            self.slab_feature_groups.remove(group_key);
+            return;
+        }
+        // the scores of the inputs are up to date with the old size of the group
+        let old_size = group.old_size;
+        group.old_size -= 1;
+        for f_for_iter in self.features[group.idcs.clone()].iter() {
+            let f_in_pool = &self.slab_features[f_for_iter.key];
+            let old_feature_score =
+                Self::score_of_feature(weights.of(f_in_pool.feature), old_size, f_in_pool.old_multiplicity);
+            let new_feature_score =
+                Self::score_of_feature(weights.of(f_in_pool.feature), old_size - 1, f_in_pool.old_multiplicity);
+            let change_in_score = new_feature_score - old_feature_score;
+            for input_key in f_in_pool.inputs.iter() {
+                if *input_key != should_not_update_key {
+                    self.slab_inputs[*input_key].score += change_in_score;
+                }
+            }
+        }
+    }
+
+    /// Removes the lowest-scoring input of the pool, ignoring the seeds kept
+    /// because of --keep-seeds. Nothing is removed if there is no other input.
     pub(crate) fn remove_lowest_scoring_input(&mut self) -> Vec<WorldAction<M::Value>> {
         let slab = &self.slab_inputs;
         let pick_key = self
             .inputs
             .iter()
-            .min_by(|&&k1, &&k2| slab[k1].score.partial_cmp(&slab[k2].score).unwrap_or(Ordering::Less))
             .copied()
-            .unwrap();
+            .filter(|&key| !self.is_kept_seed(key))
+            .min_by(|&k1, &k2| slab[k1].score.partial_cmp(&slab[k2].score).unwrap_or(Ordering::Less));
+        let pick_key = if let Some(pick_key) = pick_key {
+            pick_key
+        } else {
+            return vec![];
+        };
 
         let deleted_value = self.slab_inputs[pick_key].data.value.clone();
 
@@ -514,21 +903,220 @@ impl<M: Mutator> Pool<M> {
         actions
     }
 
-    /// Returns the index of the group of the feature
+    /// Removes the lowest-scoring input of the pool whose features are all
+    /// covered by other inputs, so that no code coverage is lost.
+    ///
+    /// The input with the key `keep`, if any, is never removed, and neither
+    /// are the seeds kept because of --keep-seeds. No input is removed if
+    /// every other input is the only one covering some feature.
+    pub(crate) fn remove_lowest_scoring_redundant_input(
+        &mut self,
+        keep: Option<SlabKey<Input<M>>>,
+    ) -> Vec<WorldAction<M::Value>> {
+        let slab_inputs = &self.slab_inputs;
+        let slab_features = &self.slab_features;
+        let pick_key = self
+            .inputs
+            .iter()
+            .copied()
+            .filter(|&key| Some(key) != keep && !self.is_kept_seed(key))
+            .filter(|&key| {
+                slab_inputs[key].all_features.iter().all(|&f_key| {
+                    let f_in_pool = &slab_features[f_key];
+                    f_in_pool.inputs.len() > 1 || f_in_pool.feature.is_penalty()
+                })
+            })
+            .min_by(|&k1, &k2| {
+                slab_inputs[k1]
+                    .score
+                    .partial_cmp(&slab_inputs[k2].score)
+                    .unwrap_or(Ordering::Less)
+            });
+        let pick_key = if let Some(pick_key) = pick_key {
+            pick_key
+        } else {
+            return vec![];
+        };
+
+        let deleted_value = self.slab_inputs[pick_key].data.value.clone();
+        let features = self.slab_inputs[pick_key].all_features.clone();
+
+        self.delete_elements(vec![pick_key], SlabKey::invalid());
+
+        // the features for which the deleted input was the least complex one now
+        // have another least complex input
+        for f_key in features {
+            let f_in_pool = &self.slab_features[f_key];
+            if f_in_pool.least_complex_input != pick_key || f_in_pool.feature.is_penalty() {
+                continue;
+            }
+            let slab_inputs = &self.slab_inputs;
+            let new_least_complex_input = *self.slab_features[f_key]
+                .inputs
+                .iter()
+                .min_by(|&&k1, &&k2| {
+                    slab_inputs[k1]
+                        .complexity
+                        .partial_cmp(&slab_inputs[k2].complexity)
+                        .unwrap_or(Ordering::Less)
+                })
+                .unwrap();
+            let input = &mut self.slab_inputs[new_least_complex_input];
+            input.least_complex_for_features.insert(f_key);
+            let f_in_pool = &mut self.slab_features[f_key];
+            f_in_pool.least_complex_input = new_least_complex_input;
+            f_in_pool.least_complexity = input.complexity;
+        }
+
+        self.update_stats();
+
+        vec![
+            WorldAction::ReportEvent(FuzzerEvent::Remove),
+            WorldAction::Remove(deleted_value),
+        ]
+    }
+
+    /// Removes every input whose features are all reached by simpler inputs.
+    ///
+    /// Such an input is not the least complex input for any feature, and
+    /// removing it does not change the set of features covered by the pool.
+    /// It is useful after loading (or merging) a large corpus, whose inputs
+    /// were not added in order of increasing complexity.
+    pub(crate) fn prune_redundant(&mut self) -> Vec<WorldAction<M::Value>> {
+        let to_delete: Vec<SlabKey<Input<M>>> = self
+            .inputs
+            .iter()
+            .copied()
+            .filter(|&key| !self.is_kept_seed(key))
+            .filter(|&key| self.slab_inputs[key].least_complex_for_features.is_empty())
+            .collect();
+
+        let deleted_values: Vec<_> = to_delete
+            .iter()
+            .map(|&key| self.slab_inputs[key].data.value.clone())
+            .collect();
+
+        self.delete_elements(to_delete, SlabKey::invalid());
+
+        let mut actions: Vec<WorldAction<M::Value>> = Vec::new();
+        for value in deleted_values.into_iter() {
+            actions.push(WorldAction::ReportEvent(FuzzerEvent::Remove));
+            actions.push(WorldAction::Remove(value));
+        }
+
+        self.update_stats();
+
+        actions
+    }
+
+    /// Returns a clone of the `n` highest-scoring inputs of the pool, along
+    /// with their score, from the highest to the lowest score.
+    ///
+    /// Inputs with the same score are ordered from the least to the most
+    /// complex.
+    pub fn most_valuable_inputs(&self, n: usize) -> Vec<(M::Value, f64)> {
+        let slab = &self.slab_inputs;
+        let mut keys = self.inputs.clone();
+        keys.sort_by(|&k1, &k2| {
+            let (i1, i2) = (&slab[k1], &slab[k2]);
+            i2.score
+                .partial_cmp(&i1.score)
+                .unwrap_or(Ordering::Equal)
+                .then(i1.complexity.partial_cmp(&i2.complexity).unwrap_or(Ordering::Equal))
+        });
+        keys.into_iter()
+            .take(n)
+            .map(|key| (slab[key].data.value.clone(), slab[key].score))
+            .collect()
+    }
+
+    /// The complexity below which `p` percent of the inputs of the pool are,
+    /// using the nearest-rank method. For example, `complexity_percentile(50.0)`
+    /// is the median complexity of the pool.
+    ///
+    /// Unlike [average_complexity](Pool::average_complexity), it is computed
+    /// from a sorted copy of the complexities of the inputs on every call.
+    pub fn complexity_percentile(&self, p: f64) -> f64 {
+        let slab = &self.slab_inputs;
+        let mut complexities: Vec<f64> = self.inputs.iter().map(|&key| slab[key].complexity).collect();
+        if complexities.is_empty() {
+            return 0.0;
+        }
+        complexities.sort_by(|c1, c2| c1.partial_cmp(c2).unwrap_or(Ordering::Equal));
+        let rank = (p.max(0.0).min(100.0) / 100.0 * complexities.len() as f64).ceil() as usize;
+        complexities[rank.max(1) - 1]
+    }
+
+    /// Whether so many inputs were deleted from the pool that it is worth
+    /// calling [compact](Pool::compact)
+    pub(crate) fn should_compact(&self) -> bool {
+        let nbr_available_slots = self.slab_inputs.nbr_available_slots();
+        nbr_available_slots > 1000 && nbr_available_slots > self.inputs.len()
+    }
+
+    /// Rebuilds the slabs of the pool without their removed elements, and
+    /// updates every key stored in the pool accordingly.
+    ///
+    /// Any [PoolIndex] obtained before calling this method is invalidated.
+    pub(crate) fn compact(&mut self) {
+        let input_keys = self.slab_inputs.compact();
+        let feature_keys = self.slab_features.compact();
+        let group_keys = self.slab_feature_groups.compact();
+
+        for input_key in self.inputs.iter_mut() {
+            *input_key = input_keys[&*input_key];
+            let input = &mut self.slab_inputs[*input_key];
+            input.least_complex_for_features = input
+                .least_complex_for_features
+                .iter()
+                .map(|f_key| feature_keys[f_key])
+                .collect();
+            for f_key in input.all_features.iter_mut() {
+                *f_key = feature_keys[&*f_key];
+            }
+        }
+
+        for f_iter in self.features.iter_mut() {
+            f_iter.key = feature_keys[&f_iter.key];
+            let f_in_pool = &mut self.slab_features[f_iter.key];
+            f_in_pool.key = f_iter.key;
+            f_in_pool.group_key = group_keys[&f_in_pool.group_key];
+            for input_key in f_in_pool.inputs.iter_mut() {
+                *input_key = input_keys[&*input_key];
+            }
+            // the least complex input may have been deleted from the pool
+            f_in_pool.least_complex_input = input_keys
+                .get(&f_in_pool.least_complex_input)
+                .copied()
+                .unwrap_or_else(SlabKey::invalid);
+        }
+
+        for group_key in self.feature_groups.iter_mut() {
+            *group_key = group_keys[&*group_key];
+        }
+    }
+
+    /// Returns the index of the group of the feature, and whether the group
+    /// was created for it
     fn insert_feature(
         features: &mut Vec<FeatureForIteration<M>>,
         feature_groups: &mut Vec<SlabKey<FeatureGroup>>,
         slab_feature_groups: &mut Slab<FeatureGroup>,
         new_feature_for_iter: FeatureForIteration<M>,
-    ) -> SlabKey<FeatureGroup> {
-        // TODO: CHANGE THIS, too slow
-        let insertion_idx = sorted_insert(features, new_feature_for_iter, |other_f| {
-            new_feature_for_iter.feature < other_f.feature
-        });
+    ) -> (SlabKey<FeatureGroup>, bool) {
+        // the features are looked up with binary searches, which requires them to be distinct
+        debug_assert!(
+            features
+                .binary_search_by_key(&new_feature_for_iter.feature, |f| f.feature)
+                .is_err(),
+            "the feature {:?} is already in the pool",
+            new_feature_for_iter.feature
+        );
+        let insertion_idx = sorted_insert(features, new_feature_for_iter, |f| f.feature);
 
         let group_of_new_feature = new_feature_for_iter.feature.group_id();
 
-        let (group_index, group_key) =
+        let (group_index, group_key, is_new_group) =
             match feature_groups.binary_search_by_key(&group_of_new_feature, |g| slab_feature_groups[*g].id) {
                 Ok(group_idx) => {
                     let group_key = feature_groups[group_idx];
@@ -540,13 +1128,13 @@ impl<M: Mutator> Pool<M> {
                     } else {
                         unreachable!();
                     }
-                    (group_idx, group_key)
+                    (group_idx, group_key, false)
                 }
                 Err(group_insertion_index) => {
                     let group = FeatureGroup::new(group_of_new_feature, insertion_idx..(insertion_idx + 1));
                     let group_key = slab_feature_groups.insert(group);
                     feature_groups.insert(group_insertion_index, group_key);
-                    (group_insertion_index, group_key)
+                    (group_insertion_index, group_key, true)
                 }
             };
 
@@ -557,11 +1145,11 @@ impl<M: Mutator> Pool<M> {
             group.idcs.start += 1;
         }
 
-        group_key
+        (group_key, is_new_group)
     }
 
-    pub fn score_of_feature(group_size: usize, exact_feature_multiplicity: usize) -> f64 {
-        1.0 / (group_size as f64 * exact_feature_multiplicity as f64)
+    pub fn score_of_feature(weight: f64, group_size: usize, exact_feature_multiplicity: usize) -> f64 {
+        weight / (group_size as f64 * exact_feature_multiplicity as f64)
     }
 
     /// Returns the index of an interesting input in the pool
@@ -576,26 +1164,146 @@ impl<M: Mutator> Pool<M> {
             };
             let x = dist.sample(&mut self.rng);
             let key = self.inputs[x];
+            let input = &mut self.slab_inputs[key];
+            input.times_mutated += 1;
+            if input.times_mutated % MUTATIONS_PER_ENERGY_DECAY == 0 && self.energy_decay < 1.0 {
+                self.update_stats();
+            }
             PoolIndex::Normal(key)
         }
     }
 
+    /// Iterates over the inputs of the pool, along with their score and the
+    /// number of times they were picked for mutation
+    pub fn iter_corpus(&self) -> impl Iterator<Item = (&M::Value, f64, usize)> {
+        self.inputs.iter().map(move |&key| {
+            let input = &self.slab_inputs[key];
+            (&input.data.value, input.score, input.times_mutated)
+        })
+    }
+
+    /// The factor applied to the weight of an input that was picked for
+    /// mutation the given number of times
+    fn energy_weight(energy_decay: f64, times_mutated: usize) -> f64 {
+        let nbr_decays = (times_mutated / MUTATIONS_PER_ENERGY_DECAY) as f64;
+        energy_decay.powf(nbr_decays).max(MIN_ENERGY_WEIGHT)
+    }
+
+    /// Returns the indices of `k` distinct inputs of the pool, or of all of
+    /// them if there are fewer, so that each worker of a batch starts from a
+    /// different input.
+    ///
+    /// The inputs are picked with the same weights as in
+    /// [random_index](Pool::random_index), but without replacement. The
+    /// favored input is never picked.
+    pub fn sample_without_replacement(&mut self, k: usize) -> Vec<PoolIndex<M>> {
+        let rng = &mut self.rng;
+        let mut previous_cumulative_weight = 0.0;
+        // Efraimidis and Spirakis: keep the k inputs with the largest u^(1/weight),
+        // where u is uniform in [0, 1), compared through their logarithms
+        let mut sort_keys: Vec<(f64, SlabKey<Input<M>>)> = self
+            .inputs
+            .iter()
+            .zip(self.cumulative_weights.iter())
+            .filter_map(|(&key, &cumulative_weight)| {
+                let weight = cumulative_weight - previous_cumulative_weight;
+                previous_cumulative_weight = cumulative_weight;
+                if weight > 0.0 {
+                    Some((rng.gen::<f64>().ln() / weight, key))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        sort_keys.sort_by(|x, y| y.0.partial_cmp(&x.0).unwrap_or(Ordering::Equal));
+        sort_keys
+            .into_iter()
+            .take(k)
+            .map(|(_, key)| PoolIndex::Normal(key))
+            .collect()
+    }
+
     pub fn len(&self) -> usize {
         self.inputs.len()
     }
 
+    /// The number of features covered by the inputs of the pool
+    pub fn feature_count(&self) -> usize {
+        self.features.len()
+    }
+
+    /// The number of edge features covered by the inputs of the pool. An
+    /// edge covered with different counter intensities counts once per
+    /// intensity.
+    pub fn edge_count(&self) -> usize {
+        self.count_features_with_tag(Feature::edge_tag())
+    }
+
+    /// The number of comparison features covered by the inputs of the pool
+    pub fn comparison_count(&self) -> usize {
+        self.count_features_with_tag(Feature::instr_tag())
+    }
+
+    /// The edges covered during the run, as a bitmap of `nbr_guards` bits
+    /// packed in bytes, where the bit of the guard `i` is the bit `i % 8` of
+    /// the byte `i / 8`, counting from the least significant.
+    ///
+    /// An edge stays in the bitmap after the inputs that covered it are
+    /// removed from the pool.
+    pub fn coverage_bitmap(&self, nbr_guards: usize) -> Vec<u8> {
+        let mut bitmap = self.edge_bitmap.clone();
+        bitmap.resize((nbr_guards + 7) / 8, 0);
+        bitmap
+    }
+
+    fn count_features_with_tag(&self, tag: u64) -> usize {
+        self.features.iter().filter(|f| f.feature.tag() == tag).count()
+    }
+
+    /// Deprioritizes the input at the given index, whose mutations are
+    /// exhausted, and resets its mutation step so that it can still be
+    /// mutated when it is picked.
+    pub(crate) fn mark_input_as_exhausted(&mut self, idx: PoolIndex<M>, mutator: &M) {
+        match idx {
+            PoolIndex::Normal(key) => {
+                let input = &mut self.slab_inputs[key];
+                input.data.reset_mutation_step(mutator);
+                if !input.is_exhausted {
+                    input.is_exhausted = true;
+                    self.update_stats();
+                }
+            }
+            PoolIndex::Favored => {
+                let input = self.favored_input.as_mut().unwrap();
+                input.reset_mutation_step(mutator);
+            }
+        }
+    }
+
     /// Update global statistics of the pool following a change in its content
     fn update_stats(&mut self) {
         let slab = &self.slab_inputs;
+        let energy_decay = self.energy_decay;
         self.cumulative_weights = self
             .inputs
             .iter()
             .map(|&key| &slab[key])
             .scan(0.0, |state, x| {
-                *state += x.score;
+                // penalty features can bring the score to zero or below, but every input
+                // must keep a positive weight for the cumulative weights to be increasing
+                let mut weight = x.score.max(MIN_INPUT_WEIGHT);
+                if x.is_exhausted {
+                    weight *= EXHAUSTED_INPUT_WEIGHT;
+                }
+                if x.is_globally_new {
+                    weight *= GLOBALLY_NEW_INPUT_WEIGHT;
+                }
+                weight *= Self::energy_weight(energy_decay, x.times_mutated);
+                *state += weight;
                 Some(*state)
             })
             .collect();
+        self.total_score = self.inputs.iter().map(|&key| slab[key].score).sum();
 
         self.average_complexity = self
             .inputs
@@ -687,11 +1395,15 @@ impl<M: Mutator> Pool<M> {
             .feature_groups
             .windows(2)
             .all(|gs| slab_groups[gs[0]].idcs.end == slab_groups[gs[1]].idcs.start));
-        assert!(slab_groups[*self.feature_groups.last().unwrap()].idcs.end == self.features.len());
+        match self.feature_groups.last() {
+            Some(&last_group) => assert!(slab_groups[last_group].idcs.end == self.features.len()),
+            None => assert!(self.features.is_empty()),
+        }
 
         for f_iter in self.features.iter() {
             let f_key = f_iter.key;
             let f_in_pool = &self.slab_features[f_key];
+            assert!(!f_in_pool.inputs.is_empty());
             for input_key in f_in_pool.inputs.iter() {
                 let input = &self.slab_inputs[*input_key];
                 assert!(input.all_features.contains(&f_key));
@@ -700,7 +1412,8 @@ impl<M: Mutator> Pool<M> {
 
         for input_key in self.inputs.iter() {
             let input = &self.slab_inputs[*input_key];
-            assert!(input.score > 0.0);
+            // only penalty features can bring the score of an input to zero or below
+            assert!(input.score > 0.0 || input.all_features.iter().any(|&fk| slab[fk].feature.is_penalty()));
             let expected_input_score = input.all_features.iter().fold(0.0, |c, &fk| {
                 let f = &slab[fk];
                 let slab_groups = &self.slab_feature_groups;
@@ -710,13 +1423,15 @@ impl<M: Mutator> Pool<M> {
                     .map(|&g| &slab_groups[g])
                     .find(|g| g.id == f.feature.group_id())
                     .unwrap();
-                c + Self::score_of_feature(group.size(), f.inputs.len())
+                c + Self::score_of_feature(self.feature_weights.of(f.feature), group.size(), f.inputs.len())
             });
             assert!(
                 (input.score - expected_input_score).abs() < 0.01,
                 format!("{:.2} != {:.2}", input.score, expected_input_score)
             );
-            assert!(!input.least_complex_for_features.is_empty());
+            assert!(!input.least_complex_for_features.is_empty() || self.is_kept_seed(*input_key));
+            let features = input.all_features.iter().map(|&fk| slab[fk].feature).collect();
+            assert_eq!(input.feature_set_hash, Self::hash_features(features));
 
             for f_key in input.least_complex_for_features.iter() {
                 let f_in_pool = &self.slab_features[*f_key];
@@ -753,18 +1468,25 @@ impl<M: Mutator> Pool<M> {
     }
 }
 
-/// Add the element in the correct place in the sorted vector
-fn sorted_insert<T, F>(vec: &mut Vec<T>, element: T, is_before: F) -> usize
+/// Adds the element to the vector, which is sorted by the given key, after
+/// the elements whose key is lower or equal, and returns its index
+fn sorted_insert<T, K, F>(vec: &mut Vec<T>, element: T, key: F) -> usize
 where
-    F: Fn(&T) -> bool,
+    K: Ord,
+    F: Fn(&T) -> K,
 {
-    let mut insertion = 0;
-    for e in vec.iter() {
-        if is_before(e) {
-            break;
+    let element_key = key(&element);
+    // the comparison never returns Equal, so the search ends on the insertion point
+    let search = vec.binary_search_by(|e| {
+        if key(e) <= element_key {
+            Ordering::Less
+        } else {
+            Ordering::Greater
         }
-        insertion += 1;
-    }
+    });
+    let insertion = match search {
+        Ok(insertion) | Err(insertion) => insertion,
+    };
     vec.insert(insertion, element);
     insertion
 }
@@ -774,6 +1496,7 @@ where
 // TODO: write tests for adding inputs that are not simplest for any feature but are predicted to have a greater score
 #[cfg(test)]
 mod tests {
+    extern crate test;
     use super::*;
 
     fn mock(cplx: f64) -> FuzzedInput<VoidMutator> {
@@ -784,8 +1507,41 @@ mod tests {
         Feature::edge(pc_guard, intensity)
     }
 
+    #[test]
+    fn test_sorted_insert() {
+        let mut vec = vec![];
+        for &(key, name) in [(3, "a"), (1, "b"), (3, "c"), (2, "d"), (0, "e"), (3, "f")].iter() {
+            sorted_insert(&mut vec, (key, name), |&(key, _)| key);
+        }
+        // equal elements are kept in the order in which they were inserted
+        assert_eq!(vec, vec![(0, "e"), (1, "b"), (2, "d"), (3, "a"), (3, "c"), (3, "f")]);
+        assert_eq!(sorted_insert(&mut vec, (2, "g"), |&(key, _)| key), 3);
+    }
+
+    /// Adds an input covering many features, in an order that is unrelated
+    /// to their sorted order
+    #[bench]
+    fn bench_add_many_features(b: &mut test::Bencher) {
+        let features: Vec<Feature> = (0..10_000).map(|i| edge_f((i * 7919) % 10_000, 1)).collect();
+        b.iter(|| {
+            let mut pool = Pool::<VoidMutator>::new(0);
+            let _ = pool.add(mock(1.0), 1.0, vec![], features.clone());
+            pool
+        });
+    }
+
     type FK = SlabKey<FeatureInPool<VoidMutator>>;
 
+    /// The features reached by at least one input of the pool
+    fn covered_features(pool: &Pool<VoidMutator>) -> Vec<Feature> {
+        pool.features
+            .iter()
+            .map(|f_iter| &pool.slab_features[f_iter.key])
+            .filter(|f_in_pool| !f_in_pool.inputs.is_empty())
+            .map(|f_in_pool| f_in_pool.feature)
+            .collect()
+    }
+
     #[test]
     fn property_test() {
         use rand::seq::IteratorRandom;
@@ -805,7 +1561,7 @@ mod tests {
 
             let mut rng = SmallRng::from_entropy();
 
-            let mut pool = Pool::<VoidMutator>::default();
+            let mut pool = Pool::<VoidMutator>::new(0);
 
             for i in 0..rng.gen_range(0, 100) {
                 let nbr_new_features = if new_features.len() > 0 {
@@ -872,6 +1628,19 @@ mod tests {
                     format!("{:.3} > {:.3}", prev_score, pool.score())
                 );
             }
+            let features_before_pruning = covered_features(&pool);
+            let _ = pool.prune_redundant();
+            if pool.len() > 0 {
+                pool.sanity_check();
+            }
+            assert_eq!(features_before_pruning, covered_features(&pool));
+
+            pool.compact();
+            if pool.len() > 0 {
+                pool.sanity_check();
+            }
+            assert_eq!(features_before_pruning, covered_features(&pool));
+
             for _ in 0..pool.len() {
                 let prev_score = pool.score();
                 let _ = pool.remove_lowest_scoring_input();
@@ -884,6 +1653,468 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_comparison_features_are_worth_less_than_edges() {
+        let mut pool = Pool::<VoidMutator>::new(0);
+        let _ = pool.add(mock(1.0), 1.0, vec![], vec![edge_f(0, 1)]);
+        let edge_score = pool.score();
+        let _ = pool.add(mock(1.0), 1.0, vec![], vec![Feature::instruction(1, 0, 0)]);
+        pool.sanity_check();
+        assert!((pool.score() - edge_score * 1.5).abs() < 0.01);
+
+        let mut pool = Pool::<VoidMutator>::new(0);
+        pool.feature_weights.instruction = 2.0;
+        let _ = pool.add(mock(1.0), 1.0, vec![], vec![Feature::instruction(1, 0, 0)]);
+        assert!((pool.score() - 2.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_edge_multipliers() {
+        let mut pool = Pool::<VoidMutator>::new(0);
+        pool.feature_weights.edge_multipliers = Rc::new(vec![(1, 3.0)].into_iter().collect());
+        assert_eq!(pool.feature_weights.of(edge_f(0, 1)), 1.0);
+        assert_eq!(pool.feature_weights.of(edge_f(1, 1)), 3.0);
+        assert_eq!(pool.feature_weights.of(edge_f(1, 200)), 3.0);
+        // the ids of comparisons and indirect calls are not edges
+        assert_eq!(pool.feature_weights.of(Feature::instruction(1, 0, 0)), 0.5);
+
+        let _ = pool.add(mock(1.0), 1.0, vec![], vec![edge_f(0, 1)]);
+        let _ = pool.add(mock(2.0), 2.0, vec![], vec![edge_f(1, 1)]);
+        pool.sanity_check();
+        assert!((pool.score() - 4.0).abs() < 0.01);
+        // the input reaching the weighted edge is worth more, so it is kept
+        let _ = pool.remove_lowest_scoring_input();
+        assert_eq!(covered_features(&pool), vec![edge_f(1, 1)]);
+    }
+
+    #[test]
+    fn test_inputs_with_same_features() {
+        let mut pool = Pool::<VoidMutator>::new(0);
+        let _ = pool.add(mock(2.0), 2.0, vec![], vec![edge_f(0, 1), edge_f(1, 1)]);
+        let keys: Vec<_> = pool.features.iter().map(|f| f.key).collect();
+        // as complex as the input with the same features
+        let actions = pool.add(mock(2.0), 2.0, keys.clone(), vec![]);
+        assert!(actions.is_empty());
+        assert_eq!(pool.len(), 1);
+        // a subset of the features is not a duplicate
+        let _ = pool.add(mock(3.0), 3.0, vec![keys[0]], vec![]);
+        assert_eq!(pool.len(), 2);
+        // simpler, so it replaces the input with the same features
+        let _ = pool.add(mock(1.0), 1.0, keys, vec![]);
+        pool.sanity_check();
+        assert_eq!(pool.len(), 1);
+        assert_eq!(pool.slab_inputs[pool.inputs[0]].complexity, 1.0);
+    }
+
+    #[test]
+    fn test_feature_counts() {
+        let mut pool = Pool::<VoidMutator>::new(0);
+        assert_eq!(pool.feature_count(), 0);
+        let _ = pool.add(mock(1.0), 1.0, vec![], vec![edge_f(0, 1), edge_f(0, 2), edge_f(1, 1)]);
+        let _ = pool.add(
+            mock(1.0),
+            1.0,
+            vec![],
+            vec![Feature::instruction(1, 0, 0), Feature::indir(1)],
+        );
+        assert_eq!(pool.feature_count(), 5);
+        assert_eq!(pool.edge_count(), 3);
+        assert_eq!(pool.comparison_count(), 1);
+    }
+
+    #[test]
+    fn test_pool_with_comparison_features_only() {
+        let mut pool = Pool::<VoidMutator>::new(0);
+        let cmp_f = |pc, arg1| Feature::instruction(pc, arg1, 0);
+        let _ = pool.add(mock(1.0), 1.0, vec![], vec![cmp_f(0, 0), cmp_f(1, 0)]);
+        let _ = pool.add(mock(2.0), 2.0, vec![], vec![cmp_f(1, u64::MAX), cmp_f(2, 0)]);
+        pool.sanity_check();
+        assert_eq!(pool.edge_count(), 0);
+        assert!(pool.score() > 0.0);
+        assert_eq!(pool.comparison_count(), 4);
+        assert_eq!(covered_features(&pool).len(), 4);
+        for _ in 0..100 {
+            let idx = pool.random_index();
+            let _ = pool.get(idx);
+        }
+    }
+
+    #[test]
+    fn test_new_group_event() {
+        fn nbr_new_groups(actions: &[WorldAction<f64>]) -> Option<usize> {
+            actions.iter().find_map(|a| match a {
+                WorldAction::ReportEvent(FuzzerEvent::NewGroup(count)) => Some(*count),
+                _ => None,
+            })
+        }
+        let mut pool = Pool::<VoidMutator>::new(0);
+        let actions = pool.add(mock(1.0), 1.0, vec![], vec![edge_f(0, 1), edge_f(0, 2), edge_f(1, 1)]);
+        assert_eq!(nbr_new_groups(&actions), Some(2));
+        // a new counter of a known edge only refines its group
+        let actions = pool.add(mock(2.0), 2.0, vec![], vec![edge_f(1, 2)]);
+        assert_eq!(nbr_new_groups(&actions), None);
+        let actions = pool.add(
+            mock(2.0),
+            2.0,
+            vec![],
+            vec![edge_f(1, 3), Feature::instruction(1, 0, 0)],
+        );
+        assert_eq!(nbr_new_groups(&actions), Some(1));
+    }
+
+    #[test]
+    fn test_seeded_pools_pick_the_same_inputs() {
+        fn picked_inputs(seed: u64) -> Vec<Option<SlabKey<Input<VoidMutator>>>> {
+            let mut pool = Pool::<VoidMutator>::new(seed);
+            for i in 0..10 {
+                let cplx = 1.0 + i as f64;
+                let _ = pool.add(mock(cplx), cplx, vec![], vec![edge_f(i, 1)]);
+            }
+            (0..100)
+                .map(|_| match pool.random_index() {
+                    PoolIndex::Normal(key) => Some(key),
+                    PoolIndex::Favored => None,
+                })
+                .collect()
+        }
+        assert_eq!(picked_inputs(1), picked_inputs(1));
+        assert_ne!(picked_inputs(1), picked_inputs(2));
+    }
+
+    #[test]
+    fn test_add_input_without_features() {
+        let mut pool = Pool::<VoidMutator>::new(0);
+        let actions = pool.add(mock(1.0), 1.0, vec![], vec![]);
+        assert!(actions.is_empty());
+        assert_eq!(pool.len(), 0);
+
+        let _ = pool.add(mock(2.0), 2.0, vec![], vec![edge_f(0, 1)]);
+        let score = pool.score();
+        let actions = pool.add(mock(1.0), 1.0, vec![], vec![]);
+        assert!(actions.is_empty());
+        assert_eq!(pool.len(), 1);
+        assert_eq!(pool.score(), score);
+        pool.sanity_check();
+    }
+
+    #[test]
+    fn test_most_valuable_inputs() {
+        let mut pool = Pool::<VoidMutator>::new(0);
+        let _ = pool.add(mock(1.0), 1.0, vec![], vec![edge_f(0, 1)]);
+        let _ = pool.add(mock(2.0), 2.0, vec![], vec![Feature::instruction(1, 0, 0)]);
+        let _ = pool.add(mock(3.0), 3.0, vec![], vec![edge_f(2, 1), edge_f(3, 1)]);
+        pool.sanity_check();
+
+        let best = pool.most_valuable_inputs(2);
+        assert_eq!(best.len(), 2);
+        assert_eq!(best[0].0, 3.0);
+        assert!((best[0].1 - 2.0).abs() < 0.01);
+        assert_eq!(best[1].0, 1.0);
+        assert!((best[1].1 - 1.0).abs() < 0.01);
+
+        assert_eq!(pool.most_valuable_inputs(10).len(), 3);
+        assert!(pool.most_valuable_inputs(0).is_empty());
+    }
+
+    #[test]
+    fn test_penalty_features() {
+        let penalty = Feature::penalty(7);
+        let mut pool = Pool::<VoidMutator>::new(0);
+        let _ = pool.add(mock(1.0), 1.0, vec![], vec![edge_f(0, 1), penalty]);
+        pool.sanity_check();
+        assert!(pool.score().abs() < 0.01);
+        // an input whose score is zero can still be picked
+        assert!(matches!(pool.random_index(), PoolIndex::Normal(_)));
+
+        assert_eq!(pool.slab_features[pool.features[1].key].feature, penalty);
+        let _ = pool.add(mock(2.0), 2.0, vec![], vec![edge_f(1, 1)]);
+        pool.sanity_check();
+        // the penalized input has the lowest score
+        let _ = pool.remove_lowest_scoring_input();
+        assert_eq!(covered_features(&pool), vec![edge_f(1, 1)]);
+
+        let mut pool = Pool::<VoidMutator>::new(0);
+        let _ = pool.add(mock(1.0), 1.0, vec![], vec![edge_f(0, 1), penalty]);
+        let (f0, f_penalty) = (pool.features[0].key, pool.features[1].key);
+        // a simpler input reaching the same edge replaces the first one, which
+        // is not kept by the penalty feature
+        let _ = pool.add(mock(0.5), 0.5, vec![f0, f_penalty], vec![]);
+        pool.sanity_check();
+        assert_eq!(pool.len(), 1);
+        assert!(pool.remove_lowest_scoring_redundant_input(None).is_empty());
+    }
+
+    #[test]
+    fn test_remove_lowest_scoring_redundant_input() {
+        fn pool_with_redundant_inputs() -> Pool<VoidMutator> {
+            let mut pool = Pool::<VoidMutator>::new(0);
+            let _ = pool.add(mock(1.0), 1.0, vec![], vec![edge_f(0, 1)]);
+            let f0 = pool.features[0].key;
+            let _ = pool.add(mock(2.0), 2.0, vec![f0], vec![edge_f(1, 1)]);
+            let f1 = pool.features[1].key;
+            let _ = pool.add(mock(3.0), 3.0, vec![f1], vec![edge_f(2, 1)]);
+            pool.sanity_check();
+            pool
+        }
+        fn values(pool: &Pool<VoidMutator>) -> Vec<f64> {
+            let mut values: Vec<f64> = pool.most_valuable_inputs(pool.len()).into_iter().map(|x| x.0).collect();
+            values.sort_by(|x, y| x.partial_cmp(y).unwrap());
+            values
+        }
+
+        // the inputs 1.0 and 2.0 are redundant, but 1.0 has the lowest score
+        let mut pool = pool_with_redundant_inputs();
+        let actions = pool.remove_lowest_scoring_redundant_input(None);
+        assert_eq!(actions.len(), 2);
+        assert_eq!(values(&pool), vec![2.0, 3.0]);
+        assert_eq!(covered_features(&pool).len(), 3);
+        pool.sanity_check();
+        // every remaining input is now the only one to cover a feature
+        assert!(pool.remove_lowest_scoring_redundant_input(None).is_empty());
+
+        let mut pool = pool_with_redundant_inputs();
+        let keep = pool
+            .inputs
+            .iter()
+            .copied()
+            .find(|&k| pool.slab_inputs[k].data.value == 1.0);
+        let _ = pool.remove_lowest_scoring_redundant_input(keep);
+        assert_eq!(values(&pool), vec![1.0, 3.0]);
+        assert_eq!(covered_features(&pool).len(), 3);
+        pool.sanity_check();
+    }
+
+    #[test]
+    fn test_sample_without_replacement() {
+        let mut pool = Pool::<VoidMutator>::new(0);
+        // the scores of the three inputs are 1, 2, and 4 times the weight of an edge
+        let _ = pool.add(mock(1.0), 1.0, vec![], vec![edge_f(0, 1)]);
+        let _ = pool.add(mock(2.0), 2.0, vec![], vec![edge_f(1, 1), edge_f(2, 1)]);
+        let _ = pool.add(mock(3.0), 3.0, vec![], (3..7).map(|pc| edge_f(pc, 1)).collect());
+        let value = |pool: &Pool<VoidMutator>, idx: PoolIndex<VoidMutator>| pool.get_ref(idx).value;
+
+        assert_eq!(pool.sample_without_replacement(5).len(), 3);
+        assert!(pool.sample_without_replacement(0).is_empty());
+
+        let mut counts = [0; 3];
+        for _ in 0..7000 {
+            let sample = pool.sample_without_replacement(2);
+            assert_eq!(sample.len(), 2);
+            assert_ne!(value(&pool, sample[0]), value(&pool, sample[1]));
+            let first = pool.sample_without_replacement(1)[0];
+            counts[value(&pool, first) as usize - 1] += 1;
+        }
+        // the first pick is proportional to the score: about 1000, 2000, and 4000
+        for (count, expected) in counts.iter().zip([1000, 2000, 4000].iter()) {
+            assert!((*count as f64 - *expected as f64).abs() < 0.1 * *expected as f64);
+        }
+    }
+
+    #[test]
+    fn test_complexity_percentile() {
+        let mut pool = Pool::<VoidMutator>::new(0);
+        assert_eq!(pool.complexity_percentile(50.0), 0.0);
+        // one very complex input drags the average up, but not the median
+        for (i, cplx) in [4.0, 1.0, 3.0, 2.0, 100.0].iter().enumerate() {
+            let _ = pool.add(mock(*cplx), *cplx, vec![], vec![edge_f(i, 1)]);
+        }
+        assert_eq!(pool.average_complexity, 22.0);
+        assert_eq!(pool.complexity_percentile(50.0), 3.0);
+        assert_eq!(pool.complexity_percentile(0.0), 1.0);
+        assert_eq!(pool.complexity_percentile(80.0), 4.0);
+        assert_eq!(pool.complexity_percentile(90.0), 100.0);
+        assert_eq!(pool.complexity_percentile(100.0), 100.0);
+    }
+
+    #[test]
+    fn test_features_without_inputs_are_removed() {
+        let mut pool = Pool::<VoidMutator>::new(0);
+        let _ = pool.add(mock(1.0), 1.0, vec![], vec![edge_f(0, 1)]);
+        let _ = pool.add(mock(2.0), 2.0, vec![], vec![edge_f(0, 2), edge_f(1, 1)]);
+        assert_eq!(pool.feature_groups.len(), 2);
+
+        // the first input has the lowest score, and is the only one to reach edge_f(0, 1)
+        let _ = pool.remove_lowest_scoring_input();
+        pool.sanity_check();
+        assert_eq!(pool.len(), 1);
+        assert_eq!(pool.feature_count(), 2);
+        assert_eq!(covered_features(&pool), vec![edge_f(0, 2), edge_f(1, 1)]);
+        // edge_f(0, 2) is now alone in its group, so it is worth as much as edge_f(1, 1)
+        assert_eq!(pool.feature_groups.len(), 2);
+        assert!((pool.score() - 2.0 * pool.feature_weights.of(edge_f(1, 1))).abs() < 1e-9);
+
+        let _ = pool.remove_lowest_scoring_input();
+        pool.sanity_check();
+        assert_eq!(pool.feature_count(), 0);
+        assert!(pool.feature_groups.is_empty());
+    }
+
+    #[test]
+    fn test_prune_redundant() {
+        let mut pool = Pool::<VoidMutator>::new(0);
+        pool.keep_seeds = true;
+        let _ = pool.import_seed(mock(3.0), 3.0, vec![], vec![edge_f(0, 1)]);
+        let f0 = pool.features[0].key;
+        // the seed is kept, although a simpler input covers its only feature
+        let _ = pool.add(mock(1.0), 1.0, vec![f0], vec![edge_f(1, 1)]);
+        let _ = pool.add(mock(2.0), 2.0, vec![], vec![edge_f(2, 1)]);
+        pool.sanity_check();
+        assert!(pool.prune_redundant().is_empty());
+        assert_eq!(pool.len(), 3);
+
+        // once the seeds are not kept anymore, the redundant seed is removed
+        pool.keep_seeds = false;
+        let actions = pool.prune_redundant();
+        assert_eq!(actions.len(), 2);
+        pool.sanity_check();
+        assert_eq!(pool.len(), 2);
+        assert_eq!(pool.feature_count(), 3);
+        assert!(pool.iter_corpus().all(|(value, _, _)| *value < 3.0));
+        assert!(pool.prune_redundant().is_empty());
+    }
+
+    #[test]
+    fn test_keep_seeds() {
+        fn pool_with_seed(keep_seeds: bool) -> Pool<VoidMutator> {
+            let mut pool = Pool::<VoidMutator>::new(0);
+            pool.keep_seeds = keep_seeds;
+            let _ = pool.import_seed(mock(3.0), 3.0, vec![], vec![edge_f(0, 1)]);
+            let f0 = pool.features[0].key;
+            // a simpler input covering the same feature replaces the seed
+            let _ = pool.add(mock(1.0), 1.0, vec![f0], vec![edge_f(1, 1)]);
+            pool.sanity_check();
+            pool
+        }
+
+        let pool = pool_with_seed(false);
+        assert_eq!(pool.len(), 1);
+
+        let mut pool = pool_with_seed(true);
+        assert_eq!(pool.len(), 2);
+        let _ = pool.remove_lowest_scoring_input();
+        let _ = pool.remove_lowest_scoring_input();
+        assert!(pool.remove_lowest_scoring_input().is_empty());
+        assert!(pool.remove_lowest_scoring_redundant_input(None).is_empty());
+        assert!(pool.prune_redundant().is_empty());
+        assert_eq!(pool.len(), 1);
+        assert_eq!(pool.most_valuable_inputs(1)[0].0, 3.0);
+        pool.sanity_check();
+    }
+
+    #[test]
+    fn test_global_features() {
+        let mut pool = Pool::<VoidMutator>::new(0);
+        pool.set_global_features(vec![edge_f(0, 1), edge_f(1, 1)]);
+        let _ = pool.add(mock(1.0), 1.0, vec![], vec![edge_f(0, 1)]);
+        let _ = pool.add(mock(2.0), 2.0, vec![], vec![edge_f(2, 1)]);
+        pool.sanity_check();
+
+        assert_eq!(pool.nbr_globally_new_features(), 1);
+        assert_eq!(
+            pool.all_global_features(),
+            vec![edge_f(0, 1), edge_f(1, 1), edge_f(2, 1)]
+        );
+        // both inputs have the same score, but the globally new one is picked more often
+        let weights = &pool.cumulative_weights;
+        assert!((weights[1] - weights[0] - weights[0] * GLOBALLY_NEW_INPUT_WEIGHT).abs() < 0.01);
+
+        // once the features of the pool are known, no input is globally new anymore
+        pool.set_global_features(pool.all_global_features());
+        assert_eq!(pool.nbr_globally_new_features(), 0);
+        let weights = &pool.cumulative_weights;
+        assert!((weights[1] - 2.0 * weights[0]).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_coverage_bitmap() {
+        let mut pool = Pool::<VoidMutator>::new(0);
+        let _ = pool.add(mock(1.0), 1.0, vec![], vec![edge_f(0, 1)]);
+        let _ = pool.add(
+            mock(2.0),
+            2.0,
+            vec![],
+            vec![edge_f(9, 1), Feature::instruction(3, 0, 0)],
+        );
+        pool.sanity_check();
+
+        // only the edges are in the bitmap, which has one bit per guard
+        assert_eq!(pool.coverage_bitmap(20), vec![0b0000_0001, 0b0000_0010, 0]);
+        assert_eq!(pool.coverage_bitmap(16).len(), 2);
+    }
+
+    #[test]
+    fn test_energy_decay() {
+        let mut pool = Pool::<VoidMutator>::new(0);
+        let _ = pool.add(mock(1.0), 1.0, vec![], vec![edge_f(0, 1)]);
+        let _ = pool.add(mock(2.0), 2.0, vec![], vec![edge_f(1, 1)]);
+        for _ in 0..10_000 {
+            let _ = pool.random_index();
+        }
+        let times_mutated: Vec<usize> = pool.iter_corpus().map(|(_, _, times_mutated)| times_mutated).collect();
+        assert_eq!(times_mutated.iter().sum::<usize>(), 10_000);
+        // both inputs have the same score, so they are picked about as often
+        assert!(times_mutated.iter().all(|&n| n > 4000));
+
+        // an input that was mutated a lot is picked less often than a new one
+        let key = pool.inputs[0];
+        pool.slab_inputs[key].times_mutated = 10 * MUTATIONS_PER_ENERGY_DECAY;
+        pool.slab_inputs[pool.inputs[1]].times_mutated = 0;
+        pool.update_stats();
+        let weights = &pool.cumulative_weights;
+        let expected = DEFAULT_ENERGY_DECAY.powi(10);
+        assert!((weights[0] / (weights[1] - weights[0]) - expected).abs() < 0.01);
+
+        // but its weight does not decay forever
+        pool.slab_inputs[key].times_mutated = usize::MAX;
+        pool.update_stats();
+        let weights = &pool.cumulative_weights;
+        assert!((weights[0] / (weights[1] - weights[0]) - MIN_ENERGY_WEIGHT).abs() < 0.001);
+
+        // the decay can be disabled
+        pool.energy_decay = 1.0;
+        pool.update_stats();
+        let weights = &pool.cumulative_weights;
+        assert!((weights[1] - 2.0 * weights[0]).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_group_id_ignores_exactly_the_payload_bits() {
+        let bases = [edge_f(37, 0), Feature::instruction(56, 89, 88)];
+        for &base in bases.iter() {
+            for payload in 0..(1 << Feature::PAYLOAD_BITS) {
+                let feature = Feature::with_payload(base, payload);
+                assert_eq!(feature.payload(), payload);
+                assert_eq!(feature.tag(), base.tag());
+                assert_eq!(feature.id(), base.id());
+                assert_eq!(feature.group_id(), base.group_id());
+            }
+            // the bits above the payload are truncated
+            assert_eq!(
+                Feature::with_payload(base, 1 << Feature::PAYLOAD_BITS),
+                Feature::with_payload(base, 0)
+            );
+            // any other bit is part of the group
+            for bit in Feature::PAYLOAD_BITS..64 {
+                let other = Feature(base.0 ^ (1 << bit));
+                if other.tag() != Feature::indir_tag() {
+                    assert_ne!(other.group_id(), base.group_id());
+                }
+            }
+        }
+        // the payload of an indirect feature is merged with its id
+        let indir = Feature::indir(0xFF);
+        assert_ne!(indir.group_id(), Feature::indir(0x00).group_id());
+    }
+
+    #[test]
+    fn test_fuzzed_input_from_value() {
+        let m = VoidMutator {};
+        for &cplx in [0.0, 1.5, 10.0].iter() {
+            let input = FuzzedInput::new_from_value(&m, cplx);
+            assert_eq!(input.complexity(&m), m.complexity(&cplx, &m.cache_from_value(&cplx)));
+        }
+    }
+
     // #[test]
     // fn test_features() {
     //     let x1 = Feature::edge(37, 3);
@@ -944,7 +2175,8 @@ mod tests {
             _cache: &mut Self::Cache,
             _step: &mut Self::MutationStep,
             _max_cplx: f64,
-        ) -> Self::UnmutateToken {
+        ) -> Option<Self::UnmutateToken> {
+            None
         }
 
         fn unmutate(&self, _value: &mut Self::Value, _cache: &mut Self::Cache, _t: Self::UnmutateToken) {}
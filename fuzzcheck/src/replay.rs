@@ -0,0 +1,577 @@
+//! Recording and replaying the decisions of a mutator.
+//!
+//! A [RecordingMutator] logs the arguments of every call to `arbitrary`,
+//! `mutate`, and `unmutate` of the mutator that it wraps. Since mutators are
+//! deterministic, a [ReplayMutator] can then reproduce the exact sequence of
+//! values that was produced, from the log alone. This pins a failing input
+//! even when the mutator or the code generating the calls is later changed.
+//!
+//! The log describes the life of a single value at a time: `arbitrary`
+//! replaces the current value, `mutate` mutates it, and `unmutate` reverts
+//! its last mutation that was not yet reverted. A log that does not start
+//! with `arbitrary` is replayed from a given [start](ReplayMutator::starting_from)
+//! value, such as an input read from a corpus.
+//!
+//! The fuzzer keeps a [DecisionLog] for each input of its pool, starting from
+//! the value of the input when it was added to the pool. When a mutation of
+//! the input fails the test, the log and that value are saved next to the
+//! artifact.
+//!
+//! The log is saved as text, with one decision per line, and can be replayed
+//! against a fuzz target with `read --replay FILE`.
+
+use crate::world::write_atomically;
+use crate::Mutator;
+
+use std::cell::RefCell;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+/// A call to a mutator, with the arguments that determine its result
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MutatorDecision {
+    Arbitrary {
+        seed: usize,
+        max_cplx: f64,
+    },
+    Mutate {
+        max_cplx: f64,
+    },
+    Unmutate,
+    /// A number of successful mutations, each of them immediately reverted
+    MutateReverted {
+        max_cplx: f64,
+        count: usize,
+    },
+    /// The mutation step was computed again from the current value
+    ResetStep,
+    /// The mutation step was computed again from the current value and a seed
+    ResetSeededStep {
+        seed: u64,
+    },
+}
+
+impl fmt::Display for MutatorDecision {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MutatorDecision::Arbitrary { seed, max_cplx } => write!(f, "arbitrary {} {}", seed, max_cplx),
+            MutatorDecision::Mutate { max_cplx } => write!(f, "mutate {}", max_cplx),
+            MutatorDecision::Unmutate => write!(f, "unmutate"),
+            MutatorDecision::MutateReverted { max_cplx, count } => {
+                write!(f, "mutate-reverted {} {}", max_cplx, count)
+            }
+            MutatorDecision::ResetStep => write!(f, "reset-step"),
+            MutatorDecision::ResetSeededStep { seed } => write!(f, "reset-step {}", seed),
+        }
+    }
+}
+
+impl FromStr for MutatorDecision {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split_whitespace().collect();
+        let decision = match parts.as_slice() {
+            ["arbitrary", seed, max_cplx] => MutatorDecision::Arbitrary {
+                seed: seed.parse().map_err(|_| format!("invalid seed in {:?}", s))?,
+                max_cplx: max_cplx.parse().map_err(|_| format!("invalid complexity in {:?}", s))?,
+            },
+            ["mutate", max_cplx] => MutatorDecision::Mutate {
+                max_cplx: max_cplx.parse().map_err(|_| format!("invalid complexity in {:?}", s))?,
+            },
+            ["unmutate"] => MutatorDecision::Unmutate,
+            ["mutate-reverted", max_cplx, count] => MutatorDecision::MutateReverted {
+                max_cplx: max_cplx.parse().map_err(|_| format!("invalid complexity in {:?}", s))?,
+                count: count.parse().map_err(|_| format!("invalid count in {:?}", s))?,
+            },
+            ["reset-step"] => MutatorDecision::ResetStep,
+            ["reset-step", seed] => MutatorDecision::ResetSeededStep {
+                seed: seed.parse().map_err(|_| format!("invalid seed in {:?}", s))?,
+            },
+            _ => return Err(format!("unknown mutator decision {:?}", s)),
+        };
+        Ok(decision)
+    }
+}
+
+/// Writes the decisions to a file, one per line, such that an interrupted
+/// run does not leave a truncated file
+pub fn write_decisions(path: &Path, decisions: &[MutatorDecision]) -> io::Result<()> {
+    let content: String = decisions.iter().map(|d| format!("{}\n", d)).collect();
+    write_atomically(path, content)
+}
+
+/// Reads the decisions written by [write_decisions]
+pub fn read_decisions(path: &Path) -> io::Result<Vec<MutatorDecision>> {
+    fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| line.parse().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)))
+        .collect()
+}
+
+/// The decisions made by a mutator on a single value, in order.
+///
+/// A successful mutation that is immediately reverted is merged with the
+/// previous one into a [MutateReverted](MutatorDecision::MutateReverted)
+/// decision, so that the log of a value that is mutated and unmutated many
+/// times stays small.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DecisionLog {
+    decisions: Vec<MutatorDecision>,
+    /// Whether the last decision is a successful mutation that was not reverted yet
+    pending_mutation: bool,
+}
+
+impl DecisionLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The decisions recorded so far
+    pub fn decisions(&self) -> &[MutatorDecision] {
+        &self.decisions
+    }
+
+    pub fn arbitrary(&mut self, seed: usize, max_cplx: f64) {
+        self.push(MutatorDecision::Arbitrary { seed, max_cplx });
+    }
+
+    /// Records a call to `mutate`, which `succeeded` if it returned an unmutate token
+    pub fn mutate(&mut self, max_cplx: f64, succeeded: bool) {
+        self.push(MutatorDecision::Mutate { max_cplx });
+        self.pending_mutation = succeeded;
+    }
+
+    pub fn unmutate(&mut self) {
+        if !self.pending_mutation {
+            return self.push(MutatorDecision::Unmutate);
+        }
+        self.pending_mutation = false;
+        let max_cplx = match self.decisions.pop() {
+            Some(MutatorDecision::Mutate { max_cplx }) => max_cplx,
+            _ => unreachable!(),
+        };
+        match self.decisions.last_mut() {
+            Some(MutatorDecision::MutateReverted { max_cplx: c, count }) if *c == max_cplx => *count += 1,
+            _ => self
+                .decisions
+                .push(MutatorDecision::MutateReverted { max_cplx, count: 1 }),
+        }
+    }
+
+    /// Records that the mutation step was computed again from the current
+    /// value, and from the given seed if any
+    pub fn reset_step(&mut self, seed: Option<u64>) {
+        self.push(match seed {
+            Some(seed) => MutatorDecision::ResetSeededStep { seed },
+            None => MutatorDecision::ResetStep,
+        });
+    }
+
+    fn push(&mut self, decision: MutatorDecision) {
+        self.decisions.push(decision);
+        self.pending_mutation = false;
+    }
+}
+
+/// The decisions that produced an input, along with the value that they
+/// start from, unless they start with `arbitrary`
+#[derive(Clone)]
+pub struct ReplayLog<V> {
+    pub start: Option<V>,
+    pub decisions: DecisionLog,
+}
+
+/// A mutator that logs every decision of the mutator it wraps, so that the
+/// values it produces can be reproduced by a [ReplayMutator].
+///
+/// The log grows with every call, so it is meant for short runs.
+pub struct RecordingMutator<M: Mutator> {
+    m: M,
+    decisions: RefCell<DecisionLog>,
+}
+
+impl<M: Mutator> RecordingMutator<M> {
+    pub fn new(m: M) -> Self {
+        Self {
+            m,
+            decisions: RefCell::new(DecisionLog::new()),
+        }
+    }
+
+    /// The decisions recorded so far
+    pub fn decisions(&self) -> Vec<MutatorDecision> {
+        self.decisions.borrow().decisions().to_vec()
+    }
+
+    /// Writes the decisions recorded so far to a file, which can be given
+    /// to `read --replay`
+    pub fn write_log(&self, path: &Path) -> io::Result<()> {
+        write_decisions(path, self.decisions.borrow().decisions())
+    }
+}
+
+impl<M: Mutator> Mutator for RecordingMutator<M> {
+    type Value = M::Value;
+    type Cache = M::Cache;
+    type MutationStep = M::MutationStep;
+    type UnmutateToken = M::UnmutateToken;
+
+    fn cache_from_value(&self, value: &Self::Value) -> Self::Cache {
+        self.m.cache_from_value(value)
+    }
+
+    fn mutation_step_from_value(&self, value: &Self::Value) -> Self::MutationStep {
+        self.decisions.borrow_mut().reset_step(None);
+        self.m.mutation_step_from_value(value)
+    }
+
+    fn seeded_mutation_step_from_value(&self, value: &Self::Value, seed: u64) -> Self::MutationStep {
+        self.decisions.borrow_mut().reset_step(Some(seed));
+        self.m.seeded_mutation_step_from_value(value, seed)
+    }
+
+    fn max_complexity(&self) -> f64 {
+        self.m.max_complexity()
+    }
+
+    fn min_complexity(&self) -> f64 {
+        self.m.min_complexity()
+    }
+
+    fn complexity(&self, value: &Self::Value, cache: &Self::Cache) -> f64 {
+        self.m.complexity(value, cache)
+    }
+
+    fn arbitrary(&self, seed: usize, max_cplx: f64) -> (Self::Value, Self::Cache) {
+        self.decisions.borrow_mut().arbitrary(seed, max_cplx);
+        self.m.arbitrary(seed, max_cplx)
+    }
+
+    fn mutate(
+        &self,
+        value: &mut Self::Value,
+        cache: &mut Self::Cache,
+        step: &mut Self::MutationStep,
+        max_cplx: f64,
+    ) -> Option<Self::UnmutateToken> {
+        let token = self.m.mutate(value, cache, step, max_cplx);
+        self.decisions.borrow_mut().mutate(max_cplx, token.is_some());
+        token
+    }
+
+    fn unmutate(&self, value: &mut Self::Value, cache: &mut Self::Cache, t: Self::UnmutateToken) {
+        self.decisions.borrow_mut().unmutate();
+        self.m.unmutate(value, cache, t)
+    }
+
+    fn set_length_range(&mut self, len_range: std::ops::RangeInclusive<usize>) -> bool {
+        self.m.set_length_range(len_range)
+    }
+
+    fn complexity_breakdown(&self, value: &Self::Value, cache: &Self::Cache) -> Vec<(&'static str, f64)> {
+        self.m.complexity_breakdown(value, cache)
+    }
+
+    fn record_mutation_outcome(&self, value: &Self::Value, token: &Self::UnmutateToken, nbr_new_features: usize) {
+        self.m.record_mutation_outcome(value, token, nbr_new_features)
+    }
+
+    fn shrink_below(
+        &self,
+        value: &Self::Value,
+        cache: &Self::Cache,
+        step: &mut usize,
+        max_cplx: f64,
+    ) -> Option<(Self::Value, Self::Cache)> {
+        self.m.shrink_below(value, cache, step, max_cplx)
+    }
+
+    fn crossover(
+        &self,
+        a: &Self::Value,
+        a_cache: &Self::Cache,
+        b: &Self::Value,
+        b_cache: &Self::Cache,
+        seed: usize,
+        max_cplx: f64,
+    ) -> Option<(Self::Value, Self::Cache)> {
+        self.m.crossover(a, a_cache, b, b_cache, seed, max_cplx)
+    }
+
+    fn describe_value(&self, value: &Self::Value) -> Option<String> {
+        self.m.describe_value(value)
+    }
+
+    fn type_name(&self) -> &'static str {
+        self.m.type_name()
+    }
+}
+
+/// Reproduces the values produced by a [RecordingMutator] from its log.
+pub struct ReplayMutator<'a, M: Mutator> {
+    m: &'a M,
+    decisions: Vec<MutatorDecision>,
+    start: Option<M::Value>,
+}
+
+impl<'a, M: Mutator> ReplayMutator<'a, M> {
+    pub fn new(m: &'a M, decisions: Vec<MutatorDecision>) -> Self {
+        Self {
+            m,
+            decisions,
+            start: None,
+        }
+    }
+
+    /// Replays the decisions that come before the first `arbitrary` on the
+    /// given value, with a mutation step computed from it
+    pub fn starting_from(mut self, value: M::Value) -> Self {
+        self.start = Some(value);
+        self
+    }
+
+    /// The values that were produced by each `arbitrary` call and each
+    /// successful `mutate` call of the log, in order.
+    ///
+    /// Returns an error if the log calls `mutate`, `unmutate`, or resets the
+    /// mutation step before the first `arbitrary` without a start value, or
+    /// if it calls `unmutate` more often than `mutate`.
+    pub fn values(&self) -> Result<Vec<M::Value>, String> {
+        let mut values = vec![];
+        let mut current: Option<(M::Value, M::Cache, M::MutationStep)> = self.start.clone().map(|value| {
+            let cache = self.m.cache_from_value(&value);
+            let step = self.m.mutation_step_from_value(&value);
+            (value, cache, step)
+        });
+        // the tokens of the successful mutations that were not reverted yet
+        let mut tokens: Vec<M::UnmutateToken> = vec![];
+        for (i, decision) in self.decisions.iter().enumerate() {
+            if let MutatorDecision::Arbitrary { seed, max_cplx } = *decision {
+                let (value, cache) = self.m.arbitrary(seed, max_cplx);
+                let step = self.m.mutation_step_from_value(&value);
+                values.push(value.clone());
+                current = Some((value, cache, step));
+                tokens.clear();
+                continue;
+            }
+            let (value, cache, step) = current.as_mut().ok_or_else(|| {
+                format!(
+                    "decision {} ({}) comes before the first arbitrary value, and no start value was given",
+                    i, decision
+                )
+            })?;
+            match *decision {
+                MutatorDecision::Arbitrary { .. } => unreachable!(),
+                MutatorDecision::Mutate { max_cplx } => {
+                    if let Some(token) = self.m.mutate(value, cache, step, max_cplx) {
+                        values.push(value.clone());
+                        tokens.push(token);
+                    }
+                }
+                MutatorDecision::Unmutate => {
+                    let token = tokens
+                        .pop()
+                        .ok_or_else(|| format!("decision {} (unmutate) has no mutation to revert", i))?;
+                    self.m.unmutate(value, cache, token);
+                }
+                MutatorDecision::MutateReverted { max_cplx, count } => {
+                    for _ in 0..count {
+                        if let Some(token) = self.m.mutate(value, cache, step, max_cplx) {
+                            values.push(value.clone());
+                            self.m.unmutate(value, cache, token);
+                        }
+                    }
+                }
+                MutatorDecision::ResetStep => {
+                    *step = self.m.mutation_step_from_value(value);
+                }
+                MutatorDecision::ResetSeededStep { seed } => {
+                    *step = self.m.seeded_mutation_step_from_value(value, seed);
+                }
+            }
+        }
+        Ok(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Adds a different number to the value at each step
+    struct CountingMutator;
+    impl Mutator for CountingMutator {
+        type Value = u64;
+        type Cache = ();
+        type MutationStep = u64;
+        type UnmutateToken = u64;
+
+        fn cache_from_value(&self, _value: &u64) {}
+        fn mutation_step_from_value(&self, _value: &u64) -> u64 {
+            0
+        }
+        fn max_complexity(&self) -> f64 {
+            64.0
+        }
+        fn min_complexity(&self) -> f64 {
+            64.0
+        }
+        fn complexity(&self, _value: &u64, _cache: &()) -> f64 {
+            64.0
+        }
+        fn arbitrary(&self, seed: usize, max_cplx: f64) -> (u64, ()) {
+            (seed as u64 * 1000 + max_cplx as u64, ())
+        }
+        fn mutate(&self, value: &mut u64, _cache: &mut (), step: &mut u64, _max_cplx: f64) -> Option<u64> {
+            if *step == 5 {
+                return None;
+            }
+            let token = *value;
+            *step += 1;
+            *value += *step * *step;
+            Some(token)
+        }
+        fn unmutate(&self, value: &mut u64, _cache: &mut (), t: u64) {
+            *value = t;
+        }
+    }
+
+    #[test]
+    fn test_record_and_replay() {
+        let m = RecordingMutator::new(CountingMutator);
+        let mut produced = vec![];
+        for seed in 0..3 {
+            let (mut value, mut cache) = m.arbitrary(seed, 10.0);
+            let mut step = m.mutation_step_from_value(&value);
+            produced.push(value);
+            for i in 0..8 {
+                if let Some(token) = m.mutate(&mut value, &mut cache, &mut step, 10.0 + i as f64) {
+                    produced.push(value);
+                    // keep every other mutation
+                    if i % 2 == 0 {
+                        m.unmutate(&mut value, &mut cache, token);
+                    }
+                }
+                if i == 6 {
+                    step = m.mutation_step_from_value(&value);
+                }
+                if i == 7 {
+                    step = m.seeded_mutation_step_from_value(&value, seed as u64);
+                }
+            }
+        }
+
+        let path = std::env::temp_dir().join(format!("fuzzcheck-test-replay-{}.txt", std::process::id()));
+        m.write_log(&path).unwrap();
+        let decisions = read_decisions(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(decisions, m.decisions());
+
+        let replayed = ReplayMutator::new(&CountingMutator, decisions).values().unwrap();
+        assert_eq!(replayed, produced);
+
+        let invalid = ReplayMutator::new(&CountingMutator, vec![MutatorDecision::Unmutate]);
+        assert!(invalid.values().is_err());
+        assert!("mutate ten".parse::<MutatorDecision>().is_err());
+    }
+
+    #[test]
+    fn test_reverted_mutations_are_merged() {
+        let mut log = DecisionLog::new();
+        log.reset_step(Some(3));
+        for _ in 0..1000 {
+            log.mutate(10.0, true);
+            log.unmutate();
+        }
+        // a failed mutation has nothing to revert
+        log.mutate(10.0, false);
+        log.mutate(10.0, true);
+        log.mutate(10.0, true);
+        log.unmutate();
+        log.unmutate();
+        log.mutate(10.0, true);
+        assert_eq!(
+            log.decisions(),
+            &[
+                MutatorDecision::ResetSeededStep { seed: 3 },
+                MutatorDecision::MutateReverted {
+                    max_cplx: 10.0,
+                    count: 1000
+                },
+                MutatorDecision::Mutate { max_cplx: 10.0 },
+                MutatorDecision::Mutate { max_cplx: 10.0 },
+                MutatorDecision::MutateReverted {
+                    max_cplx: 10.0,
+                    count: 1
+                },
+                MutatorDecision::Unmutate,
+                MutatorDecision::Mutate { max_cplx: 10.0 },
+            ]
+        );
+        let line = log.decisions()[1].to_string();
+        assert_eq!(line.parse::<MutatorDecision>().unwrap(), log.decisions()[1]);
+    }
+
+    #[test]
+    fn test_replay_from_start_value() {
+        let m = RecordingMutator::new(CountingMutator);
+        let start = 40;
+        let (mut value, mut cache) = (start, ());
+        let mut step = m.seeded_mutation_step_from_value(&value, 2);
+        let mut produced = vec![];
+        for _ in 0..3 {
+            let token = m.mutate(&mut value, &mut cache, &mut step, 10.0).unwrap();
+            produced.push(value);
+            m.unmutate(&mut value, &mut cache, token);
+        }
+        m.mutate(&mut value, &mut cache, &mut step, 10.0).unwrap();
+        produced.push(value);
+
+        let decisions = m.decisions();
+        assert!(ReplayMutator::new(&CountingMutator, decisions.clone())
+            .values()
+            .is_err());
+        let replayed = ReplayMutator::new(&CountingMutator, decisions)
+            .starting_from(start)
+            .values()
+            .unwrap();
+        assert_eq!(replayed, produced);
+        assert_eq!(replayed.last(), Some(&value));
+    }
+
+    #[test]
+    fn test_replay_mutations_of_pool_input() {
+        let m = CountingMutator;
+        let arbitrary = crate::FuzzedInput::arbitrary(&m, 3, 10.0);
+        let mut input = arbitrary.new_source(&m, 9);
+        // mutated as by the fuzzer, which resets the step of an exhausted input
+        for _ in 0..12 {
+            if let Some(token) = input.mutate(&m, 10.0) {
+                input.unmutate(&m, token);
+            } else {
+                input.reset_mutation_step(&m);
+            }
+        }
+        input.mutate(&m, 10.0).unwrap();
+
+        let log = input.replay_log.clone().unwrap();
+        assert_eq!(log.start, Some(arbitrary.value));
+        let replayed = ReplayMutator::new(&m, log.decisions.decisions().to_vec())
+            .starting_from(log.start.unwrap())
+            .values()
+            .unwrap();
+        assert_eq!(replayed.last(), Some(&input.value));
+
+        // an arbitrary input is replayed from its seed alone
+        let log = arbitrary.replay_log.unwrap();
+        assert_eq!(log.start, None);
+        let replayed = ReplayMutator::new(&m, log.decisions.decisions().to_vec()).values();
+        assert_eq!(replayed, Ok(vec![arbitrary.value]));
+    }
+}
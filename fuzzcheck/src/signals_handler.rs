@@ -2,7 +2,12 @@
 //! recover from crashes.
 
 use libc::*;
+use std::ffi::CString;
 use std::mem;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
 use std::sync::{Arc, Condvar, Mutex};
 use std::thread;
 
@@ -16,6 +21,9 @@ where
     F: Fn(i32) -> ! + Send,
 {
     fn signal_handler(sig: c_int, _info: *mut siginfo_t, _data: *mut c_void) {
+        if let SIGILL | SIGABRT | SIGBUS | SIGSEGV | SIGFPE = sig {
+            write_captured_input();
+        }
         let &(ref lock, ref cvar) = &**PAIR2;
         let mut signal = lock.lock().unwrap();
         *signal = sig as i32;
@@ -50,3 +58,107 @@ where
             .unwrap()
     };
 }
+
+/// Restore the default behaviour of the given signals.
+///
+/// It is used in forked processes, where the thread waiting for the
+/// signals caught by [handle_signals] does not exist.
+pub fn reset_signal_handlers(signals: &[i32]) {
+    for signal in signals.iter() {
+        unsafe {
+            libc::signal(*signal, SIG_DFL);
+        }
+    }
+}
+
+/*
+ * With --crash-capture, the input under test is serialized before each test
+ * and published here, so that the signal handler can write it to a file even
+ * if the crash left the process in a state where the usual way of saving an
+ * artifact, which allocates and takes locks, would deadlock or crash again.
+ *
+ * The signal handler may interrupt the test at any point, so it can only use
+ * async-signal-safe operations: it reads these atomics and calls `open`,
+ * `write`, and `close`, without allocating or locking. For the same reason,
+ * the input must be fully serialized before the test is called: nothing can
+ * be serialized from within the handler.
+ */
+static CAPTURE_PATH: AtomicPtr<c_char> = AtomicPtr::new(ptr::null_mut());
+static CAPTURE_DATA: AtomicPtr<u8> = AtomicPtr::new(ptr::null_mut());
+static CAPTURE_LEN: AtomicUsize = AtomicUsize::new(0);
+
+/// Sets the file to which the captured input is written if the process
+/// crashes. It is kept for the rest of the process.
+pub fn set_crash_capture_path(path: &Path) {
+    let path = CString::new(path.as_os_str().as_bytes()).expect("the path contains a nul byte");
+    let old = CAPTURE_PATH.swap(path.into_raw(), Ordering::SeqCst);
+    if !old.is_null() {
+        drop(unsafe { CString::from_raw(old) });
+    }
+}
+
+/// Publishes the serialized input that is about to be tested, replacing the
+/// previous one.
+pub fn capture_input(data: Vec<u8>) {
+    let data = data.into_boxed_slice();
+    let len = data.len();
+    let new = Box::into_raw(data) as *mut u8;
+    // the pointer is null while the length changes, so that the handler never
+    // reads a pointer with the length of another buffer
+    let old = CAPTURE_DATA.swap(ptr::null_mut(), Ordering::SeqCst);
+    let old_len = CAPTURE_LEN.swap(len, Ordering::SeqCst);
+    CAPTURE_DATA.store(new, Ordering::SeqCst);
+    if !old.is_null() {
+        drop(unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(old, old_len)) });
+    }
+}
+
+/// Writes the captured input to the capture file, if both are set. It only
+/// uses async-signal-safe functions.
+fn write_captured_input() {
+    let path = CAPTURE_PATH.load(Ordering::SeqCst);
+    let data = CAPTURE_DATA.load(Ordering::SeqCst);
+    let len = CAPTURE_LEN.load(Ordering::SeqCst);
+    if path.is_null() || data.is_null() {
+        return;
+    }
+    unsafe {
+        let fd = open(path, O_WRONLY | O_CREAT | O_TRUNC, 0o644);
+        if fd < 0 {
+            return;
+        }
+        let mut written = 0;
+        while written < len {
+            let n = write(fd, data.add(written) as *const c_void, len - written);
+            if n <= 0 {
+                break;
+            }
+            written += n as usize;
+        }
+        close(fd);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_write_captured_input() {
+        let path = std::env::temp_dir().join(format!("fuzzcheck-test-crash-input-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        set_crash_capture_path(&path);
+        capture_input(vec![1, 2, 3]);
+        capture_input(vec![4, 5]);
+        write_captured_input();
+        assert_eq!(fs::read(&path).unwrap(), vec![4, 5]);
+
+        // an empty input is captured too
+        capture_input(vec![]);
+        write_captured_input();
+        assert!(fs::read(&path).unwrap().is_empty());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
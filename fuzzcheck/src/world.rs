@@ -1,23 +1,29 @@
 //! This is the interface between the fuzzer and the rest of the world.
-//! It manages the fuzzing corpus in the file system as well as the terminal
-//! output.
 //!
-
-// In the future it would be nice to make it a trait so that it is easy to
-// create different “World” implementations.
+//! The [World] trait describes how the fuzzer reads and writes its corpora,
+//! artifacts, and other files, and how it reports its progress.
+//! [FileSystemWorld] manages them in the file system and on the terminal
+//! output, while the `TestWorld` of the unit tests keeps them in memory.
 
 use fuzzcheck_arg_parser::*;
-use std::collections::hash_map::DefaultHasher;
+use std::cell::{Cell, RefCell, RefMut};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs;
 
-use std::hash::{Hash, Hasher};
-use std::io::{self, Result};
+use std::io::{self, Result, Write};
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use std::path::Path;
-use std::time::Instant;
+use sha1::Sha1;
 
+use crate::replay::{read_decisions, write_decisions, MutatorDecision, ReplayLog};
+use crate::signals_handler::{capture_input, set_crash_capture_path};
 use crate::{Feature, Serializer};
 
+#[cfg(test)]
+use std::rc::Rc;
+
 #[derive(Clone, Copy, Default)]
 pub struct FuzzerStats {
     pub total_number_of_runs: usize,
@@ -26,6 +32,16 @@ pub struct FuzzerStats {
     pub pool_size: usize,
     pub exec_per_s: usize,
     pub avg_cplx: f64,
+    /// The median complexity of the inputs of the pool, which is less
+    /// sensitive than the average to a few very complex inputs
+    pub median_cplx: f64,
+    /// The average complexity of the inputs of the pool divided by the
+    /// maximum complexity of the inputs, which tells whether the inputs are
+    /// constrained by `--max-cplx`
+    pub budget_utilization: f64,
+    /// The probability that the fuzzing loop tests a new arbitrary input
+    /// instead of mutating an input of the pool
+    pub arbitrary_ratio: f64,
 }
 
 impl FuzzerStats {
@@ -37,55 +53,422 @@ impl FuzzerStats {
             pool_size: 0,
             exec_per_s: 0,
             avg_cplx: 0.0,
+            median_cplx: 0.0,
+            budget_utilization: 0.0,
+            arbitrary_ratio: 0.0,
+        }
+    }
+}
+
+/// The number of per-second throughput measurements kept by a [FileSystemWorld]
+const THROUGHPUT_HISTORY_LEN: usize = 64;
+
+/// A ring buffer of the number of iterations per second of the most recent
+/// seconds of the fuzzing run, used to spot slowdowns.
+struct ThroughputHistory {
+    exec_per_s: VecDeque<usize>,
+}
+
+impl ThroughputHistory {
+    fn new() -> Self {
+        Self {
+            exec_per_s: VecDeque::with_capacity(THROUGHPUT_HISTORY_LEN),
+        }
+    }
+
+    fn record(&mut self, exec_per_s: usize) {
+        if self.exec_per_s.len() == THROUGHPUT_HISTORY_LEN {
+            self.exec_per_s.pop_front();
+        }
+        self.exec_per_s.push_back(exec_per_s);
+    }
+
+    fn average(xs: impl ExactSizeIterator<Item = usize>) -> f64 {
+        let len = xs.len();
+        xs.sum::<usize>() as f64 / len as f64
+    }
+
+    /// The relative change, in percent, between the average throughput of
+    /// the older half of the history and that of the newer half
+    fn trend(&self) -> f64 {
+        let half = self.exec_per_s.len() / 2;
+        let older = Self::average(self.exec_per_s.iter().copied().take(half));
+        let newer = Self::average(self.exec_per_s.iter().copied().skip(half));
+        if older == 0.0 {
+            0.0
+        } else {
+            (newer - older) / older * 100.0
+        }
+    }
+}
+
+/// The shortest time between two refreshes of the status line of `--tui`
+const STATUS_LINE_REFRESH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The progress of the fuzzer, shown with `--tui` on a single line of the
+/// terminal that is rewritten in place, instead of one line per event.
+struct StatusLine {
+    start: Instant,
+    iterations: usize,
+    exec_per_s: usize,
+    pool_size: usize,
+    /// The number of features covered by the pool, as of the last pulse
+    coverage: usize,
+    last_new_feature: Option<Instant>,
+    last_draw: Option<Instant>,
+    /// Whether the line is on the terminal, and must be erased before
+    /// anything else is printed
+    is_displayed: bool,
+}
+
+impl StatusLine {
+    fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            iterations: 0,
+            exec_per_s: 0,
+            pool_size: 0,
+            coverage: 0,
+            last_new_feature: None,
+            last_draw: None,
+            is_displayed: false,
+        }
+    }
+
+    /// Updates the line with the event and its stats. Returns true if the
+    /// event is summarized by the line, and false if it must be printed
+    /// on its own.
+    fn update(&mut self, event: &FuzzerEvent, stats: Option<FuzzerStats>) -> bool {
+        if let Some(stats) = stats {
+            self.iterations = stats.total_number_of_runs;
+            self.exec_per_s = stats.exec_per_s;
+            self.pool_size = stats.pool_size;
+        }
+        match *event {
+            FuzzerEvent::New | FuzzerEvent::Replace(_) => {
+                self.last_new_feature = Some(Instant::now());
+                true
+            }
+            FuzzerEvent::Remove | FuzzerEvent::Stats | FuzzerEvent::Run => true,
+            FuzzerEvent::Pulse {
+                iterations,
+                execs_per_sec,
+                corpus_size,
+                coverage,
+                ..
+            } => {
+                self.iterations = iterations;
+                self.exec_per_s = execs_per_sec;
+                self.pool_size = corpus_size;
+                self.coverage = coverage;
+                true
+            }
+            _ => false,
         }
     }
+
+    fn text(&self, now: Instant) -> String {
+        let last_new_feature = self.last_new_feature.map_or("never".to_owned(), |t| {
+            format!("{}s ago", now.saturating_duration_since(t).as_secs())
+        });
+        format!(
+            "iter: {}  exec/s: {}  corpus: {}  cov: {}  last new: {}  time: {}s",
+            self.iterations,
+            self.exec_per_s,
+            self.pool_size,
+            self.coverage,
+            last_new_feature,
+            now.saturating_duration_since(self.start).as_secs()
+        )
+    }
+
+    /// Rewrites the line, unless it was already rewritten very recently
+    fn draw(&mut self, force: bool) {
+        let now = Instant::now();
+        if !force && self.last_draw.map_or(false, |t| now - t < STATUS_LINE_REFRESH_INTERVAL) {
+            return;
+        }
+        self.last_draw = Some(now);
+        print!("\r\x1b[K{}", self.text(now));
+        let _ = io::stdout().flush();
+        self.is_displayed = true;
+    }
+
+    /// Erases the line, so that an event can be printed in its place
+    fn clear(&mut self) {
+        if self.is_displayed {
+            print!("\r\x1b[K");
+            self.is_displayed = false;
+        }
+    }
+
+    /// Leaves the line on the terminal and moves to the next line, so that
+    /// the final state of the fuzzer stays visible
+    fn finish(&mut self) {
+        self.draw(true);
+        println!();
+        self.is_displayed = false;
+    }
 }
 
 #[derive(Clone)]
 pub enum FuzzerEvent {
-    Start,
+    /// The fuzzer started, with the name of the type of its inputs
+    Start(&'static str),
     Done,
     New,
+    /// The input that was just added covers the given number of feature
+    /// groups that no input covered before, such as new comparison
+    /// instructions or new code regions. It is reported before
+    /// [New](FuzzerEvent::New) or [Replace](FuzzerEvent::Replace).
+    NewGroup(usize),
     Replace(usize),
     Remove,
     DidReadCorpus,
     CaughtSignal(i32),
     TestFailure,
+    Stats,
+    Run,
+    DeadlineReached,
+    /// The input to minify has the given complexity, which is below the
+    /// target given by --minimize-to-cplx
+    TargetCplxReached(f64),
+    /// Inputs added to the input corpus by other fuzzers were read, with
+    /// `--reload-corpus-interval`
+    ReloadCorpus(usize),
+    /// The maximum complexity of the inputs was raised by --adaptive-cplx
+    /// because the coverage stopped growing
+    MaxCplxIncreased(f64),
+    /// The number of features covered by the pool that are absent from the
+    /// file given by --features-in, and the total number of features covered
+    /// by this run and the previous ones
+    GlobalFeatures {
+        globally_new: usize,
+        total: usize,
+    },
+    /// The number of edges whose weight was given by --edge-weights
+    EdgeWeights(usize),
+    /// The given number of files were removed from the output corpus to stay
+    /// under --max-total-corpus-bytes
+    EvictedCorpusFiles(usize),
+    /// --save-all-inputs was given for a run of the given maximum number of
+    /// iterations, which is long enough to write a very large number of files
+    SaveAllInputsWarning(usize),
+    /// A file of a corpus was skipped, for the given reason
+    SkippedCorpusFile(PathBuf, &'static str),
+    /// The input corpus given to the fuzz command does not exist, so the
+    /// fuzzer starts with an empty corpus
+    MissingInputCorpus(PathBuf),
+    /// A corpus was read, with the number of files that it contains and the
+    /// number of inputs that could be decoded from them
+    ReadCorpus {
+        path: PathBuf,
+        files: usize,
+        inputs: usize,
+    },
+    /// The given number of initial inputs, out of the total, were tested
+    /// while loading the corpus, and the pool now has `pool_size` inputs
+    LoadingCorpus {
+        tested: usize,
+        total: usize,
+        pool_size: usize,
+    },
+    /// A command that needs the inputs of a corpus could not read any, for
+    /// the given reason
+    CorpusError(Option<PathBuf>, String),
+    /// One of the slowest inputs, which took the given time to test, was
+    /// saved at the given path, with --report-slowest-inputs
+    SlowestInput(Duration, PathBuf),
+    /// A failing input was not saved because its crash signature is the
+    /// same as that of the given artifact
+    DuplicateCrash {
+        signature: u64,
+        artifact: String,
+    },
+    /// The program counters that produced the features of the pool, with
+    /// their kind and the number of features each one produced, as asked by
+    /// --print-pcs
+    Pcs(Vec<(&'static str, u64, usize)>),
+    /// The features covered by an input, in the same order for every run, as
+    /// asked by the --trace-only option of the read command
+    Features(Vec<Feature>),
+    /// The complexity of an input followed by the complexity of each of its
+    /// fields, as asked by --explain-complexity
+    ComplexityBreakdown(f64, Vec<(&'static str, f64)>),
+    /// A heartbeat reported at iteration milestones of the fuzzing loop
+    Pulse {
+        iterations: usize,
+        execs_per_sec: usize,
+        corpus_size: usize,
+        /// The number of features covered by the pool
+        coverage: usize,
+        /// The number of those features that are edges
+        edges: usize,
+        /// The number of those features that are comparisons
+        comparisons: usize,
+    },
+}
+
+impl FuzzerEvent {
+    /// The minimum `--verbosity` level at which the event is reported
+    fn verbosity(&self) -> usize {
+        match self {
+            FuzzerEvent::Done
+            | FuzzerEvent::CaughtSignal(_)
+            | FuzzerEvent::TestFailure
+            | FuzzerEvent::DeadlineReached
+            | FuzzerEvent::TargetCplxReached(_)
+            | FuzzerEvent::GlobalFeatures { .. }
+            | FuzzerEvent::SaveAllInputsWarning(_)
+            | FuzzerEvent::CorpusError(..)
+            | FuzzerEvent::DuplicateCrash { .. }
+            | FuzzerEvent::Pcs(_)
+            | FuzzerEvent::Features(_)
+            | FuzzerEvent::ComplexityBreakdown(..) => 0,
+            FuzzerEvent::Start(_)
+            | FuzzerEvent::DidReadCorpus
+            | FuzzerEvent::Stats
+            | FuzzerEvent::Pulse { .. }
+            | FuzzerEvent::MaxCplxIncreased(_)
+            | FuzzerEvent::NewGroup(_)
+            | FuzzerEvent::ReloadCorpus(_)
+            | FuzzerEvent::EdgeWeights(_)
+            | FuzzerEvent::EvictedCorpusFiles(_)
+            | FuzzerEvent::SkippedCorpusFile(..)
+            | FuzzerEvent::MissingInputCorpus(_)
+            | FuzzerEvent::ReadCorpus { .. }
+            | FuzzerEvent::LoadingCorpus { .. }
+            | FuzzerEvent::SlowestInput(..) => 1,
+            FuzzerEvent::New | FuzzerEvent::Replace(_) | FuzzerEvent::Remove => 2,
+            FuzzerEvent::Run => 3,
+        }
+    }
 }
 
 #[derive(Clone)]
 pub(crate) enum WorldAction<T> {
     Remove(T),
-    Add(T, Vec<Feature>),
+    Add(T),
     ReportEvent(FuzzerEvent),
 }
 
-pub struct World<S: Serializer> {
-    settings: CommandLineArguments,
-    instant: Instant,
-    serializer: S,
-}
+/// The input and output of the fuzzer: its corpora, artifacts, and other
+/// files, as well as the reports of its progress.
+///
+/// The fuzzer only calls these methods through a `dyn World`, so that it can
+/// be tested without touching the file system. It is an internal seam for
+/// the tests of the fuzzer, not an extension point: [FileSystemWorld] is the
+/// only backend outside of the tests.
+pub(crate) trait World {
+    type Value;
 
-impl<S: Serializer> World<S> {
-    pub fn new(serializer: S, settings: CommandLineArguments) -> Self {
-        Self {
-            settings,
-            instant: std::time::Instant::now(),
-            serializer,
-        }
-    }
+    /// Starts measuring the time elapsed since now
+    fn set_start_time(&mut self);
+    /// The number of microseconds elapsed since the start time
+    fn elapsed_time(&self) -> usize;
+    /// Records the number of iterations per second of the last second
+    fn record_throughput(&mut self, exec_per_s: usize);
 
-    pub(crate) fn do_actions(&self, actions: Vec<WorldAction<S::Value>>, stats: &FuzzerStats) -> Result<()> {
+    /// Reads the inputs of the input corpus
+    fn read_input_corpus(&self) -> Result<Vec<Self::Value>>;
+    /// Creates the output corpus and artifacts folders if they don't exist
+    fn create_output_folders(&self) -> Result<()>;
+    /// Reads the inputs added to the input corpus by other fuzzers since the
+    /// last time it was read, along with those that could not be read then
+    fn read_new_inputs_of_input_corpus(&self) -> Result<Vec<Self::Value>>;
+    /// Reads the corpus given by --other-corpus, to compare it with the input corpus
+    fn read_other_corpus(&self) -> Result<Vec<Self::Value>>;
+    /// Reads the input given by --input-file
+    fn read_input_file(&self) -> Result<Self::Value>;
+    /// Reads the mutator decisions to replay, given by --replay
+    fn read_replay_log(&self) -> Result<Vec<MutatorDecision>>;
+    /// Reads the features covered by previous runs, given by --features-in
+    fn read_global_features(&self) -> Result<Vec<Feature>>;
+    /// Writes the features covered by this run and by the previous ones,
+    /// to be read by a later run
+    fn write_global_features(&self, features: &[Feature]) -> Result<()>;
+    /// Writes the bitmap of the edges covered by this run, with one bit per
+    /// edge guard, to the file given by --coverage-out
+    fn write_coverage_bitmap(&self, nbr_guards: usize, bitmap: &[u8]) -> Result<()>;
+    /// Reads the factor applied to the weight of each listed edge, by id,
+    /// given by --edge-weights
+    fn read_edge_weights(&self) -> Result<HashMap<u64, f64>>;
+
+    fn add_to_output_corpus(&self, input: Self::Value) -> Result<()>;
+    fn remove_from_output_corpus(&self, input: Self::Value) -> Result<()>;
+    /// With --max-total-corpus-bytes, removes the files of the `evictable`
+    /// inputs from the output corpus, in order, until `input` can be added
+    /// to it without exceeding the limit. The number of removed files is
+    /// returned.
+    ///
+    /// Only the evictable inputs that are needed are taken from the iterator.
+    /// The files of the output corpus that belong to none of them, such as
+    /// those saved by a previous run, are never removed.
+    fn make_room_in_output_corpus<'a>(
+        &self,
+        input: &Self::Value,
+        evictable: &mut dyn Iterator<Item = &'a Self::Value>,
+    ) -> Result<usize>
+    where
+        Self::Value: 'a;
+    /// Saves an input that made the test fail or crash, along with the
+    /// [crash signature](crate::code_coverage_sensor::CodeCoverageSensor::crash_signature) of the failure, if it
+    /// is known.
+    ///
+    /// While fuzzing, the input is not saved if an artifact with the same
+    /// signature was already saved, unless `--save-all-crashes` is given.
+    /// The [description](crate::Mutator::describe_value) of the input, if
+    /// any, is saved in the `descriptions` subfolder of the artifacts folder,
+    /// and the [decisions](crate::replay) of the mutator that produced it,
+    /// if they are known, in the `replays` subfolder.
+    fn save_artifact(
+        &self,
+        input: &Self::Value,
+        cplx: f64,
+        signature: Option<u64>,
+        description: Option<String>,
+        replay_log: Option<&ReplayLog<Self::Value>>,
+    ) -> Result<()>;
+    /// Saves an input that is about to be tested, with --save-all-inputs
+    fn save_tested_input(&self, input: &Self::Value, run: usize) -> Result<()>;
+    /// Saves the inputs that took the longest to test, slowest first, along
+    /// with the time they took, with --report-slowest-inputs
+    fn save_slowest_inputs(&self, inputs: &[(&Duration, &Self::Value)]) -> Result<()>;
+    /// Serializes an input that is about to be tested, with --crash-capture,
+    /// so that the signal handler can save it if the test crashes the process
+    fn capture_tested_input(&self, input: &Self::Value) -> Result<()>;
+    /// Writes the configuration of the run, with --metadata-out, from the
+    /// settings of the fuzzer, which include the seed it drew if none was given
+    fn write_metadata(&self, settings: &CommandLineArguments, mutator: &str, value_type: &str) -> Result<()>;
+    /// Serializes the input and deserializes the result, returning both the
+    /// serialized input and the deserialized value, if any
+    fn serialization_round_trip(&self, input: &Self::Value) -> (Vec<u8>, Option<Self::Value>);
+
+    fn report_event(&self, event: FuzzerEvent, stats: Option<FuzzerStats>);
+    /// Reports the number of features that are covered by only one of two
+    /// corpora, followed by the number of those features that each input covers
+    fn report_corpus_diff(
+        &self,
+        only_in_corpus: usize,
+        only_in_other_corpus: usize,
+        contribution_of_inputs: &[(&Self::Value, usize)],
+        contribution_of_other_inputs: &[(&Self::Value, usize)],
+    );
+    /// Reports why the mutator failed the self-test, along with the
+    /// offending value
+    fn report_self_test_failure(&self, reason: &str, input: &Self::Value);
+
+    fn do_actions(&self, actions: Vec<WorldAction<Self::Value>>, stats: &FuzzerStats) -> Result<()> {
         for a in actions {
             match a {
-                WorldAction::Add(x, _) => {
+                WorldAction::Add(x) => {
                     self.add_to_output_corpus(x)?;
                 }
                 WorldAction::Remove(x) => {
                     self.remove_from_output_corpus(x)?;
                 }
                 WorldAction::ReportEvent(e) => match e {
-                    FuzzerEvent::New | FuzzerEvent::Remove | FuzzerEvent::Replace(_) => {
+                    FuzzerEvent::New | FuzzerEvent::NewGroup(_) | FuzzerEvent::Remove | FuzzerEvent::Replace(_) => {
                         self.report_event(e, Some(*stats))
                     }
                     _ => self.report_event(e, None),
@@ -96,20 +479,174 @@ impl<S: Serializer> World<S> {
     }
 }
 
-impl<S: Serializer> World<S> {
-    pub fn set_start_time(&mut self) {
-        self.instant = Instant::now();
-    }
-    pub fn elapsed_time(&self) -> usize {
-        self.instant.elapsed().as_micros() as usize
+/// The extension of the temporary files written by [write_atomically]
+const TEMPORARY_FILE_EXTENSION: &str = "tmp";
+
+/// Writes the content to the file at the given path, such that a reader of
+/// the file either sees its previous content or the complete new content,
+/// even if the fuzzer is killed while writing.
+///
+/// The content is first written to a temporary file in the same folder,
+/// which is synced to the disk and then renamed over the target file.
+pub(crate) fn write_atomically(path: &Path, content: impl AsRef<[u8]>) -> Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "The path does not name a file."))?;
+    let temp_name = format!(
+        ".{}.{}.{}",
+        file_name.to_string_lossy(),
+        std::process::id(),
+        TEMPORARY_FILE_EXTENSION
+    );
+    let temp_path = path.with_file_name(temp_name);
+
+    let result = fs::File::create(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(content.as_ref())?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&temp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
     }
+    result
+}
 
-    pub fn read_input_corpus(&self) -> Result<Vec<S::Value>> {
-        if self.settings.corpus_in.is_none() {
-            return Result::Ok(vec![]);
+/// Whether the file is a temporary file left by [write_atomically], which
+/// may be incomplete and must not be read
+fn is_temporary_file(path: &Path) -> bool {
+    let is_hidden = path
+        .file_name()
+        .map_or(false, |name| name.to_string_lossy().starts_with('.'));
+    is_hidden && path.extension().map_or(false, |ext| ext == TEMPORARY_FILE_EXTENSION)
+}
+
+/// The file of the artifacts folder that lists the crash signature of each
+/// artifact saved while fuzzing, one `signature<TAB>file name` per line
+const CRASH_SIGNATURES_FILE: &str = "crash-signatures.txt";
+
+/// The subfolder of the artifacts folder that contains the description of
+/// each artifact, as given by [Mutator::describe_value](crate::Mutator::describe_value)
+const DESCRIPTIONS_FOLDER: &str = "descriptions";
+
+/// The subfolder of the artifacts folder that contains the decisions of the
+/// mutator that produced each artifact, along with the value they start from
+const REPLAYS_FOLDER: &str = "replays";
+
+/// The subfolder of the artifacts folder that contains the inputs that took
+/// the longest to test, with --report-slowest-inputs
+const SLOWEST_INPUTS_FOLDER: &str = "slowest";
+
+/// The first bytes of the file written by --coverage-out, followed by the
+/// number of edge guards and by the bitmap
+const COVERAGE_BITMAP_MAGIC: &[u8; 8] = b"FZCKCOV1";
+
+/// The file of the artifacts folder to which the signal handler writes the
+/// input that crashed the process, with --crash-capture
+const CRASH_CAPTURE_FILE: &str = "crash-input";
+
+/// The name of the artifact whose crash signature is listed with the given
+/// signature in the file, if any
+fn find_crash_signature(path: &Path, signature: u64) -> Option<String> {
+    let content = fs::read_to_string(path).ok()?;
+    let signature = format!("{:016x}", signature);
+    content.lines().find_map(|line| {
+        let mut parts = line.splitn(2, '\t');
+        if parts.next()? == signature {
+            parts.next().map(str::to_owned)
+        } else {
+            None
+        }
+    })
+}
+
+/// The SHA-1 of the serialized form of an input, in hexadecimal, which names
+/// its files as in the corpora of libFuzzer and AFL
+fn content_hash(content: &[u8]) -> String {
+    Sha1::from(content).digest().to_string()
+}
+
+/// A [World] that keeps the corpora, artifacts, and other files of the fuzzer
+/// in the file system, and reports events on the standard output
+pub struct FileSystemWorld<S: Serializer> {
+    settings: CommandLineArguments,
+    instant: Instant,
+    serializer: S,
+    throughput: ThroughputHistory,
+    /// The files of the input corpus that were already read, along with the
+    /// files written to the output corpus, which are not read again when the
+    /// input corpus is reloaded
+    known_corpus_files: RefCell<HashSet<PathBuf>>,
+    /// The files of the input corpus that could not be read into an input,
+    /// maybe because another fuzzer was still writing them. They are read
+    /// again when the input corpus is reloaded.
+    corpus_files_to_retry: RefCell<HashSet<PathBuf>>,
+    /// The path and size of the files of the output corpus, by the hash of
+    /// their content, which is also their name unless a previous run saved
+    /// them under another name. It is read from the folder before the first
+    /// input is written to it, so that these inputs are not written again,
+    /// and then updated as files are added and removed.
+    corpus_out_files: RefCell<Option<HashMap<String, (PathBuf, usize)>>>,
+    /// The total size in bytes of the files of
+    /// [corpus_out_files](FileSystemWorld::corpus_out_files), kept up to date
+    /// as files are written and removed
+    corpus_out_bytes: Cell<usize>,
+    /// The status line of `--tui`, if the standard output is a terminal
+    status_line: Option<RefCell<StatusLine>>,
+    /// Whether the file to which the signal handler writes the crashing
+    /// input was set, with --crash-capture
+    crash_capture_path_is_set: Cell<bool>,
+}
+
+impl<S: Serializer> FileSystemWorld<S> {
+    pub fn new(serializer: S, settings: CommandLineArguments) -> Self {
+        // the status line would only garble the output of a pipe or a file
+        let is_terminal = unsafe { libc::isatty(libc::STDOUT_FILENO) } == 1;
+        let status_line = if settings.tui && is_terminal {
+            Some(RefCell::new(StatusLine::new()))
+        } else {
+            None
+        };
+        Self {
+            settings,
+            instant: std::time::Instant::now(),
+            serializer,
+            throughput: ThroughputHistory::new(),
+            known_corpus_files: RefCell::new(HashSet::new()),
+            corpus_files_to_retry: RefCell::new(HashSet::new()),
+            corpus_out_files: RefCell::new(None),
+            corpus_out_bytes: Cell::new(0),
+            status_line,
+            crash_capture_path_is_set: Cell::new(false),
         }
-        let corpus = self.settings.corpus_in.as_ref().unwrap().as_path();
+    }
+
+    /// The folder given by --artifacts, or `./artifacts/` by default
+    fn artifacts_folder(&self) -> PathBuf {
+        self.settings
+            .artifacts_folder
+            .clone()
+            .unwrap_or_else(|| Path::new("./artifacts/").to_path_buf())
+    }
 
+    /// Reads the inputs of the corpus saved in the files for which
+    /// `should_read` returns true.
+    ///
+    /// The files that cannot be read or decoded into an input are skipped,
+    /// and each of them is reported, unless it is already waiting to be read
+    /// again. The number of files that were considered and the skipped files
+    /// are returned along with the inputs.
+    fn read_corpus(
+        &self,
+        corpus: &Path,
+        mut should_read: impl FnMut(&Path) -> bool,
+    ) -> Result<(Vec<S::Value>, usize, Vec<PathBuf>)> {
+        if !corpus.exists() {
+            return Result::Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "The corpus path does not exist.",
+            ));
+        }
         if !corpus.is_dir() {
             return Result::Err(io::Error::new(
                 io::ErrorKind::Other,
@@ -117,21 +654,450 @@ impl<S: Serializer> World<S> {
             ));
         }
         let mut inputs: Vec<S::Value> = Vec::new();
-        for entry in fs::read_dir(corpus)? {
-            let entry = entry?;
-            if entry.path().is_dir() {
+        let mut nbr_files = 0;
+        let mut skipped = Vec::new();
+        // the order of the entries of a folder depends on the file system,
+        // so sort them to test the inputs in the same order in every run
+        let mut paths = fs::read_dir(corpus)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<Vec<_>>>()?;
+        paths.sort();
+        for path in paths {
+            if path.is_dir() || !should_read(&path) {
                 continue;
             }
-            let data = fs::read(entry.path())?;
-            if let Some(i) = self.serializer.from_data(&data) {
-                inputs.push(i);
+            nbr_files += 1;
+            let skipped_reason = if is_temporary_file(&path) {
+                "incomplete temporary file"
+            } else if let Ok(data) = fs::read(&path) {
+                if let Some(i) = self.serializer.from_data(&data) {
+                    inputs.push(i);
+                    continue;
+                }
+                "could not be decoded into a valid input"
             } else {
-                continue;
+                "could not be read"
+            };
+            if !self.corpus_files_to_retry.borrow().contains(&path) {
+                self.report_event(FuzzerEvent::SkippedCorpusFile(path.clone(), skipped_reason), None);
             }
+            skipped.push(path);
         }
+        Ok((inputs, nbr_files, skipped))
+    }
+
+    /// The files of the output corpus along with their size, read from the
+    /// folder the first time they are needed
+    fn corpus_out_files(&self, corpus: &Path) -> RefMut<'_, HashMap<String, (PathBuf, usize)>> {
+        RefMut::map(self.corpus_out_files.borrow_mut(), |files| {
+            files.get_or_insert_with(|| {
+                let files = Self::files_by_content_hash(corpus);
+                self.corpus_out_bytes.set(files.values().map(|(_, len)| len).sum());
+                files
+            })
+        })
+    }
+
+    /// The files of the folder along with their size, by the hash of their
+    /// content. The files that cannot be read are left out.
+    fn files_by_content_hash(folder: &Path) -> HashMap<String, (PathBuf, usize)> {
+        let entries = if let Ok(entries) = fs::read_dir(folder) {
+            entries
+        } else {
+            return HashMap::new();
+        };
+        entries
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                if !path.is_file() || is_temporary_file(&path) {
+                    return None;
+                }
+                let content = fs::read(&path).ok()?;
+                Some((content_hash(&content), (path, content.len())))
+            })
+            .collect()
+    }
+
+    /// The name of the file in which the input is saved in a corpus
+    fn input_name(&self, input: &S::Value) -> String {
+        self.corpus_file_name(&content_hash(&self.serializer.to_data(input)))
+    }
+
+    /// The name of the file in which an input with the given content hash is
+    /// saved in a corpus. With --raw-corpus, it has no extension, as in the
+    /// corpora of libFuzzer and AFL.
+    fn corpus_file_name(&self, hash: &str) -> String {
+        if self.settings.raw_corpus {
+            return hash.to_owned();
+        }
+        Path::new(hash)
+            .with_extension(self.serializer.extension())
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Prints the event on its own line, followed by the stats, if its
+    /// verbosity is not above `--verbosity`
+    fn print_event(&self, event: FuzzerEvent, stats: Option<FuzzerStats>) {
+        if event.verbosity() > self.settings.verbosity {
+            return;
+        }
+        match event {
+            FuzzerEvent::Start(type_name) => {
+                println!("START\t{}", type_name);
+                return;
+            }
+            FuzzerEvent::Done => print!("DONE\t"),
+            FuzzerEvent::New => print!("NEW\t"),
+            FuzzerEvent::NewGroup(count) => print!("NEW GROUP {}\t", count),
+            FuzzerEvent::Remove => print!("REMOVE\t"),
+            FuzzerEvent::DidReadCorpus => {
+                println!("FINISHED READING CORPUS");
+                return;
+            }
+            FuzzerEvent::CaughtSignal(signal) => match signal {
+                4 | 6 | 10 | 11 | 8 => println!("\n================ CRASH DETECTED ================"),
+                2 | 15 => println!("\n================ RUN INTERRUPTED ================"),
+                _ => println!("\n================ SIGNAL {:?} ================", signal),
+            },
+            FuzzerEvent::TestFailure => println!("\n================ TEST FAILED ================"),
+            FuzzerEvent::Replace(count) => {
+                print!("RPLC {}\t", count);
+            }
+            FuzzerEvent::Stats => print!("STATS\t"),
+            FuzzerEvent::Run => print!("RUN\t"),
+            FuzzerEvent::DeadlineReached => print!("DEADLINE REACHED\t"),
+            FuzzerEvent::TargetCplxReached(cplx) => print!("TARGET CPLX REACHED {:.2}\t", cplx),
+            FuzzerEvent::ReloadCorpus(count) => print!("RELOAD {}\t", count),
+            FuzzerEvent::MaxCplxIncreased(max_cplx) => print!("MAX CPLX {:.2}\t", max_cplx),
+            FuzzerEvent::GlobalFeatures { globally_new, total } => {
+                println!("GLOBAL FEATURES\tnew: {}\ttotal: {}", globally_new, total);
+                return;
+            }
+            FuzzerEvent::EdgeWeights(count) => {
+                println!("EDGE WEIGHTS\t{}", count);
+                return;
+            }
+            FuzzerEvent::EvictedCorpusFiles(count) => {
+                println!("EVICTED\t{} files of the output corpus", count);
+                return;
+            }
+            FuzzerEvent::SaveAllInputsWarning(max_iter) => {
+                let max_iter = if max_iter == usize::max_value() {
+                    "an unlimited number of".to_owned()
+                } else {
+                    max_iter.to_string()
+                };
+                println!(
+                    "WARNING\t--{} will write {} files, use --{} to limit the length of the run",
+                    SAVE_ALL_INPUTS_FLAG, max_iter, MAX_NBR_RUNS_FLAG
+                );
+                return;
+            }
+            FuzzerEvent::SkippedCorpusFile(path, reason) => {
+                println!("SKIPPED\t{}\t{}", path.display(), reason);
+                return;
+            }
+            FuzzerEvent::Pcs(pcs) => {
+                for (kind, pc, nbr_features) in pcs {
+                    println!("PC\t{}\t{:#x}\t{}", kind, pc, nbr_features);
+                }
+                return;
+            }
+            FuzzerEvent::Features(features) => {
+                for feature in features {
+                    println!("FEATURE\t{:016x}", feature.0);
+                }
+                return;
+            }
+            FuzzerEvent::ComplexityBreakdown(cplx, breakdown) => {
+                println!("COMPLEXITY\t{:.2}", cplx);
+                for (field, field_cplx) in breakdown {
+                    println!("FIELD\t{}\t{:.2}\t{:.1}%", field, field_cplx, field_cplx / cplx * 100.0);
+                }
+                return;
+            }
+            FuzzerEvent::DuplicateCrash { signature, artifact } => {
+                println!(
+                    "DUPLICATE CRASH\tthe crash signature {:016x} is the same as that of {}, use --{} to save it anyway",
+                    signature, artifact, SAVE_ALL_CRASHES_FLAG
+                );
+                return;
+            }
+            FuzzerEvent::SlowestInput(duration, path) => {
+                println!("SLOWEST\t{:?}\t{}", duration, path.display());
+                return;
+            }
+            FuzzerEvent::MissingInputCorpus(path) => {
+                println!(
+                    "NO INPUT CORPUS\t{} does not exist, starting with an empty corpus",
+                    path.display()
+                );
+                return;
+            }
+            FuzzerEvent::LoadingCorpus {
+                tested,
+                total,
+                pool_size,
+            } => {
+                println!(
+                    "LOADING CORPUS\tloaded {} of {} inputs, {} accepted into the pool",
+                    tested, total, pool_size
+                );
+                return;
+            }
+            FuzzerEvent::ReadCorpus { path, files, inputs } => {
+                println!(
+                    "READ CORPUS\t{}\tfiles: {}\tinputs: {}\tskipped: {}",
+                    path.display(),
+                    files,
+                    inputs,
+                    files - inputs
+                );
+                return;
+            }
+            FuzzerEvent::CorpusError(path, reason) => {
+                let path = path.map_or("(none)".to_owned(), |p| p.display().to_string());
+                println!("ERROR\tno input could be read from the corpus {}: {}", path, reason);
+                return;
+            }
+            FuzzerEvent::Pulse {
+                iterations,
+                execs_per_sec,
+                corpus_size,
+                coverage,
+                edges,
+                comparisons,
+            } => {
+                println!(
+                    "PULSE\t{}\tcov: {}\tedges: {}\tcmp: {}\tcorpus: {}\texec/s: {}",
+                    iterations, coverage, edges, comparisons, corpus_size, execs_per_sec
+                );
+                return;
+            }
+        };
+        if let Some(stats) = stats {
+            print!("{}\t", stats.total_number_of_runs);
+            print!("score: {:.2}\t", stats.score);
+            print!("pool: {}\t", stats.pool_size);
+            print!("exec/s: {}\t", stats.exec_per_s);
+            print!("cplx: {:.2}\t", stats.avg_cplx);
+            print!("median cplx: {:.2}\t", stats.median_cplx);
+            if let FuzzerCommand::Fuzz = self.settings.command {
+                print!("budget: {:.1}%\t", stats.budget_utilization * 100.0);
+                print!("arbitrary: {:.1}%\t", stats.arbitrary_ratio * 100.0);
+            }
+            println!();
+        }
+        if let FuzzerEvent::Done = event {
+            self.report_throughput();
+        }
+    }
+
+    fn report_throughput(&self) {
+        let history = &self.throughput.exec_per_s;
+        if history.len() < 2 {
+            return;
+        }
+        print!("THROUGHPUT\t");
+        print!("min: {}\t", history.iter().min().unwrap());
+        print!("avg: {:.0}\t", ThroughputHistory::average(history.iter().copied()));
+        print!("max: {}\t", history.iter().max().unwrap());
+        print!("trend: {:+.1}%\t", self.throughput.trend());
+        println!();
+    }
+}
+
+impl<S: Serializer> World for FileSystemWorld<S> {
+    type Value = S::Value;
+
+    fn set_start_time(&mut self) {
+        self.instant = Instant::now();
+    }
+    fn elapsed_time(&self) -> usize {
+        self.instant.elapsed().as_micros() as usize
+    }
+    /// Records the number of iterations per second of the last second
+    fn record_throughput(&mut self, exec_per_s: usize) {
+        self.throughput.record(exec_per_s);
+    }
+
+    fn read_input_corpus(&self) -> Result<Vec<Self::Value>> {
+        if self.settings.corpus_in.is_none() {
+            return Result::Ok(vec![]);
+        }
+        let corpus_in = self.settings.corpus_in.as_ref().unwrap();
+        // a fuzzer can start from scratch, unlike the commands that process a corpus
+        if let FuzzerCommand::Fuzz = self.settings.command {
+            if !corpus_in.exists() {
+                self.report_event(FuzzerEvent::MissingInputCorpus(corpus_in.clone()), None);
+                return Ok(vec![]);
+            }
+        }
+        let known_corpus_files = &self.known_corpus_files;
+        let (inputs, files, skipped) = self.read_corpus(corpus_in, |path| {
+            known_corpus_files.borrow_mut().insert(path.to_path_buf());
+            true
+        })?;
+        *self.corpus_files_to_retry.borrow_mut() = skipped.into_iter().collect();
+        self.report_event(
+            FuzzerEvent::ReadCorpus {
+                path: corpus_in.clone(),
+                files,
+                inputs: inputs.len(),
+            },
+            None,
+        );
         Ok(inputs)
     }
-    pub fn read_input_file(&self) -> Result<S::Value> {
+    fn create_output_folders(&self) -> Result<()> {
+        if let Some(corpus_out) = &self.settings.corpus_out {
+            fs::create_dir_all(corpus_out)?;
+        }
+        fs::create_dir_all(self.artifacts_folder())
+    }
+    /// Reads the files of the input corpus that were not read before, which
+    /// were added to it by other fuzzers since the last time it was read, and
+    /// the files that could not be read the last time
+    fn read_new_inputs_of_input_corpus(&self) -> Result<Vec<Self::Value>> {
+        let corpus_in = if let Some(corpus_in) = &self.settings.corpus_in {
+            corpus_in
+        } else {
+            return Ok(vec![]);
+        };
+        // the other fuzzers may not have created it yet
+        if !corpus_in.exists() {
+            return Ok(vec![]);
+        }
+        let known_corpus_files = &self.known_corpus_files;
+        let corpus_files_to_retry = &self.corpus_files_to_retry;
+        let (inputs, _, skipped) = self.read_corpus(corpus_in, |path| {
+            known_corpus_files.borrow_mut().insert(path.to_path_buf()) || corpus_files_to_retry.borrow().contains(path)
+        })?;
+        // the files that were read successfully are not retried anymore
+        *self.corpus_files_to_retry.borrow_mut() = skipped.into_iter().collect();
+        Ok(inputs)
+    }
+    /// Reads the corpus given by --other-corpus, to compare it with the input corpus
+    fn read_other_corpus(&self) -> Result<Vec<Self::Value>> {
+        if let Some(other_corpus) = &self.settings.other_corpus {
+            let (inputs, files, _) = self.read_corpus(other_corpus, |_| true)?;
+            self.report_event(
+                FuzzerEvent::ReadCorpus {
+                    path: other_corpus.clone(),
+                    files,
+                    inputs: inputs.len(),
+                },
+                None,
+            );
+            Ok(inputs)
+        } else {
+            Result::Err(io::Error::new(
+                io::ErrorKind::Other,
+                "No corpus to compare with was given as argument",
+            ))
+        }
+    }
+    /// Reads the features covered by previous runs from the file given by
+    /// --features-in, if any. A file that does not exist yet is treated as
+    /// empty, so that the same file can be given to --features-in and
+    /// --features-out from the very first run.
+    fn read_global_features(&self) -> Result<Vec<Feature>> {
+        let path = if let Some(path) = &self.settings.features_in {
+            path
+        } else {
+            return Ok(vec![]);
+        };
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+        let content = fs::read_to_string(path)?;
+        content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                u64::from_str_radix(line, 16).map(Feature).map_err(|_| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("{:?} is not a valid feature in {}", line, path.display()),
+                    )
+                })
+            })
+            .collect()
+    }
+    /// Writes the given features to the file given by --features-out, if
+    /// any, one per line, in the format read by
+    /// [read_global_features](World::read_global_features)
+    fn write_global_features(&self, features: &[Feature]) -> Result<()> {
+        let path = if let Some(path) = &self.settings.features_out {
+            path
+        } else {
+            return Ok(());
+        };
+        let mut content = String::with_capacity(features.len() * 17);
+        for feature in features {
+            content.push_str(&format!("{:016x}\n", feature.0));
+        }
+        write_atomically(path, content)
+    }
+    /// Writes the bitmap to the file given by --coverage-out, if any, after
+    /// the magic bytes `FZCKCOV1` and the number of edge guards as a
+    /// little-endian `u64`. The bit of the guard `i` is the bit `i % 8` of
+    /// the byte `i / 8` of the bitmap, counting from the least significant.
+    fn write_coverage_bitmap(&self, nbr_guards: usize, bitmap: &[u8]) -> Result<()> {
+        let path = if let Some(path) = &self.settings.coverage_out {
+            path
+        } else {
+            return Ok(());
+        };
+        let mut content = Vec::with_capacity(16 + bitmap.len());
+        content.extend_from_slice(COVERAGE_BITMAP_MAGIC);
+        content.extend_from_slice(&(nbr_guards as u64).to_le_bytes());
+        content.extend_from_slice(bitmap);
+        write_atomically(path, content)
+    }
+    /// Reads the file given by --edge-weights, if any. Each line contains
+    /// the id of an edge in hexadecimal, as printed by --print-pcs, followed
+    /// by a positive factor. Empty lines and lines starting with `#` are
+    /// ignored.
+    fn read_edge_weights(&self) -> Result<HashMap<u64, f64>> {
+        let path = if let Some(path) = &self.settings.edge_weights {
+            path
+        } else {
+            return Ok(HashMap::new());
+        };
+        let content = fs::read_to_string(path)?;
+        let mut weights = HashMap::new();
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |reason: &str| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {} of {}: {}: {:?}", i + 1, path.display(), reason, line),
+                )
+            };
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let (id, factor) = if let [id, factor] = parts.as_slice() {
+                (id, factor)
+            } else {
+                return Err(invalid("expected an edge and a factor"));
+            };
+            let id = id.trim_start_matches("0x");
+            let id = u64::from_str_radix(id, 16).map_err(|_| invalid("invalid edge"))?;
+            let factor = factor
+                .parse::<f64>()
+                .ok()
+                .filter(|f| f.is_finite() && *f > 0.0)
+                .ok_or_else(|| invalid("the factor must be a positive number"))?;
+            weights.insert(id, factor);
+        }
+        Ok(weights)
+    }
+    fn read_input_file(&self) -> Result<Self::Value> {
         if let Some(input_file) = &self.settings.input_file {
             let data = fs::read(input_file)?;
             if let Some(input) = self.serializer.from_data(&data) {
@@ -150,7 +1116,18 @@ impl<S: Serializer> World<S> {
         }
     }
 
-    pub fn add_to_output_corpus(&self, input: S::Value) -> Result<()> {
+    fn read_replay_log(&self) -> Result<Vec<MutatorDecision>> {
+        if let Some(path) = &self.settings.replay {
+            read_decisions(path)
+        } else {
+            Result::Err(io::Error::new(
+                io::ErrorKind::Other,
+                "No replay log was given as argument",
+            ))
+        }
+    }
+
+    fn add_to_output_corpus(&self, input: Self::Value) -> Result<()> {
         if self.settings.corpus_out.is_none() {
             return Ok(());
         }
@@ -160,93 +1137,943 @@ impl<S: Serializer> World<S> {
             std::fs::create_dir_all(corpus)?;
         }
 
-        let mut hasher = DefaultHasher::new();
         let content = self.serializer.to_data(&input);
-        content.hash(&mut hasher);
-        let hash = hasher.finish();
-        let name = format!("{:x}", hash);
-        let path = corpus.join(name).with_extension(self.serializer.extension());
-        fs::write(path, content)?;
+        let hash = content_hash(&content);
+        let mut files = self.corpus_out_files(corpus);
+        if let Some((path, _)) = files.get(&hash) {
+            if path.is_file() {
+                // an identical input is already saved, maybe by a previous run
+                self.known_corpus_files.borrow_mut().insert(path.clone());
+                return Ok(());
+            }
+        }
+        let path = corpus.join(self.corpus_file_name(&hash));
+        let len = content.len();
+        write_atomically(&path, content)?;
+        let removed_len = files.insert(hash, (path.clone(), len)).map_or(0, |(_, len)| len);
+        self.corpus_out_bytes
+            .set(self.corpus_out_bytes.get() + len - removed_len);
+        self.known_corpus_files.borrow_mut().insert(path);
+
+        Ok(())
+    }
+
+    /// Writes an input that is about to be tested to the folder given by
+    /// --save-all-inputs, if any.
+    ///
+    /// The file is named after the number of the run followed by the hash of
+    /// the input, so that sorting the files by name gives the order in which
+    /// the inputs were tested.
+    fn save_tested_input(&self, input: &Self::Value, run: usize) -> Result<()> {
+        let folder = if let Some(folder) = &self.settings.save_all_inputs {
+            folder.as_path()
+        } else {
+            return Ok(());
+        };
+        if !folder.is_dir() {
+            std::fs::create_dir_all(folder)?;
+        }
+
+        let content = self.serializer.to_data(input);
+        let name = format!("{:010}--{}", run, content_hash(&content));
+        let path = folder.join(name).with_extension(self.serializer.extension());
+        fs::write(path, content)
+    }
+
+    /// Writes the inputs to the `slowest` subfolder of the artifacts folder,
+    /// replacing the ones saved by a previous run.
+    ///
+    /// Each file is named after the rank of the input followed by the time it
+    /// took to test, in microseconds, so that sorting the files by name gives
+    /// the slowest inputs first.
+    fn save_slowest_inputs(&self, inputs: &[(&Duration, &Self::Value)]) -> Result<()> {
+        let folder = self.artifacts_folder().join(SLOWEST_INPUTS_FOLDER);
+        if folder.is_dir() {
+            fs::remove_dir_all(&folder)?;
+        }
+        fs::create_dir_all(&folder)?;
+
+        for (rank, (duration, input)) in inputs.iter().enumerate() {
+            let name = format!("{:04}--{}us", rank, duration.as_micros());
+            let path = folder.join(name).with_extension(self.serializer.extension());
+            write_atomically(&path, self.serializer.to_data(input))?;
+            self.report_event(FuzzerEvent::SlowestInput(**duration, path), None);
+        }
+        Ok(())
+    }
 
+    /// The input is written to `crash-input` in the artifacts folder, with the
+    /// extension of the serializer, only if the process crashes. It is then
+    /// still there after the run, even if the usual artifact was saved too.
+    fn capture_tested_input(&self, input: &Self::Value) -> Result<()> {
+        if !self.settings.crash_capture {
+            return Ok(());
+        }
+        if !self.crash_capture_path_is_set.get() {
+            let artifacts_folder = self.artifacts_folder();
+            if !artifacts_folder.is_dir() {
+                std::fs::create_dir_all(&artifacts_folder)?;
+            }
+            let path = artifacts_folder
+                .join(CRASH_CAPTURE_FILE)
+                .with_extension(self.serializer.extension());
+            set_crash_capture_path(&path);
+            self.crash_capture_path_is_set.set(true);
+        }
+        capture_input(self.serializer.to_data(input));
         Ok(())
     }
 
-    pub fn remove_from_output_corpus(&self, input: S::Value) -> Result<()> {
+    fn remove_from_output_corpus(&self, input: Self::Value) -> Result<()> {
         if self.settings.corpus_out.is_none() {
             return Ok(());
         }
         let corpus = self.settings.corpus_out.as_ref().unwrap().as_path();
 
-        let mut hasher = DefaultHasher::new();
-        let content = self.serializer.to_data(&input);
-        content.hash(&mut hasher);
-        let hash = hasher.finish();
-        let name = format!("{:x}", hash);
-
-        let path = corpus.join(name).with_extension(self.serializer.extension());
+        let hash = content_hash(&self.serializer.to_data(&input));
+        // the input may be saved under the name given to it by another run
+        let saved_file = self
+            .corpus_out_files
+            .borrow_mut()
+            .as_mut()
+            .and_then(|files| files.remove(&hash));
+        let path = if let Some((path, len)) = saved_file {
+            self.corpus_out_bytes.set(self.corpus_out_bytes.get() - len);
+            path
+        } else {
+            corpus.join(self.corpus_file_name(&hash))
+        };
         let _ = fs::remove_file(path);
 
         Ok(())
     }
 
-    pub fn save_artifact(&self, input: &S::Value, cplx: f64) -> Result<()> {
-        let default = Path::new("./artifacts/").to_path_buf();
-        let artifacts_folder = self.settings.artifacts_folder.as_ref().unwrap_or(&default).as_path();
+    /// The input is still added if it is larger than the limit on its own,
+    /// once every evictable file was removed.
+    fn make_room_in_output_corpus<'a>(
+        &self,
+        input: &Self::Value,
+        evictable: &mut dyn Iterator<Item = &'a Self::Value>,
+    ) -> Result<usize>
+    where
+        Self::Value: 'a,
+    {
+        let (corpus, max_bytes) = match (&self.settings.corpus_out, self.settings.max_total_corpus_bytes) {
+            (Some(corpus), Some(max_bytes)) => (corpus.as_path(), max_bytes),
+            _ => return Ok(0),
+        };
+        let content = self.serializer.to_data(input);
+        let hash = content_hash(&content);
+        let mut files = self.corpus_out_files(corpus);
+        if files.contains_key(&hash) {
+            // the input is already saved and takes no more room
+            return Ok(0);
+        }
+        let mut nbr_removed = 0;
+        while self.corpus_out_bytes.get() + content.len() > max_bytes {
+            let value = if let Some(value) = evictable.next() {
+                value
+            } else {
+                break;
+            };
+            let evicted_hash = content_hash(&self.serializer.to_data(value));
+            if evicted_hash == hash {
+                continue;
+            }
+            if let Some((path, len)) = files.remove(&evicted_hash) {
+                let _ = fs::remove_file(path);
+                self.corpus_out_bytes.set(self.corpus_out_bytes.get() - len);
+                nbr_removed += 1;
+            }
+        }
+        Ok(nbr_removed)
+    }
+
+    /// Writes the configuration of the run to the file given by
+    /// --metadata-out, if any, as a JSON object
+    fn write_metadata(&self, settings: &CommandLineArguments, mutator: &str, value_type: &str) -> Result<()> {
+        let path = if let Some(path) = &settings.metadata_out {
+            path
+        } else {
+            return Ok(());
+        };
+        fn json_string(s: &str) -> String {
+            let mut result = String::from("\"");
+            for c in s.chars() {
+                match c {
+                    '"' => result.push_str("\\\""),
+                    '\\' => result.push_str("\\\\"),
+                    c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+                    c => result.push(c),
+                }
+            }
+            result.push('"');
+            result
+        }
+        fn json_path(p: &Option<PathBuf>) -> String {
+            p.as_ref()
+                .map(|p| json_string(&p.to_string_lossy()))
+                .unwrap_or_else(|| "null".to_owned())
+        }
+        fn json_usize(x: &Option<usize>) -> String {
+            x.map(|x| x.to_string()).unwrap_or_else(|| "null".to_owned())
+        }
+        let command = match settings.command {
+            FuzzerCommand::Fuzz => COMMAND_FUZZ,
+            FuzzerCommand::Read => COMMAND_READ,
+            FuzzerCommand::MinifyInput => COMMAND_MINIFY_INPUT,
+            FuzzerCommand::MinifyCorpus => COMMAND_MINIFY_CORPUS,
+            FuzzerCommand::DiffCorpus => COMMAND_DIFF_CORPUS,
+            FuzzerCommand::SelfTest => COMMAND_SELF_TEST,
+        };
+        let start_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        let fields = vec![
+            ("fuzzcheck_version", json_string(env!("CARGO_PKG_VERSION"))),
+            ("start_time", start_time.to_string()),
+            ("mutator", json_string(mutator)),
+            ("value_type", json_string(value_type)),
+            ("command", json_string(command)),
+            ("max_nbr_of_runs", settings.max_nbr_of_runs.to_string()),
+            ("max_input_cplx", settings.max_input_cplx.to_string()),
+            ("corpus_size", settings.corpus_size.to_string()),
+            ("verbosity", settings.verbosity.to_string()),
+            ("fork", settings.fork.to_string()),
+            ("print_pcs", settings.print_pcs.to_string()),
+            ("trace_only", settings.trace_only.to_string()),
+            ("no_cmp_trace", settings.no_cmp_trace.to_string()),
+            ("no_edge_trace", settings.no_edge_trace.to_string()),
+            ("explain_complexity", settings.explain_complexity.to_string()),
+            ("adaptive_cplx", settings.adaptive_cplx.to_string()),
+            ("keep_seeds", settings.keep_seeds.to_string()),
+            ("tui", settings.tui.to_string()),
+            ("save_all_crashes", settings.save_all_crashes.to_string()),
+            ("raw_corpus", settings.raw_corpus.to_string()),
+            ("arbitrary_from_corpus", settings.arbitrary_from_corpus.to_string()),
+            ("crash_capture", settings.crash_capture.to_string()),
+            ("deterministic", settings.deterministic.to_string()),
+            ("input_min_len", json_usize(&settings.input_min_len)),
+            ("input_max_len", json_usize(&settings.input_max_len)),
+            ("minimize_timeout", json_usize(&settings.minimize_timeout)),
+            ("minimize_to_cplx", json_usize(&settings.minimize_to_cplx)),
+            ("initial_cplx", json_usize(&settings.initial_cplx)),
+            ("curate", json_usize(&settings.curate)),
+            ("report_slowest_inputs", json_usize(&settings.report_slowest_inputs)),
+            ("reload_corpus_interval", json_usize(&settings.reload_corpus_interval)),
+            (
+                "energy_decay",
+                settings
+                    .energy_decay
+                    .map(|x| x.to_string())
+                    .unwrap_or_else(|| "null".to_owned()),
+            ),
+            ("seed", json_usize(&settings.seed)),
+            ("input_file", json_path(&settings.input_file)),
+            ("corpus_in", json_path(&settings.corpus_in)),
+            ("other_corpus", json_path(&settings.other_corpus)),
+            ("corpus_out", json_path(&settings.corpus_out)),
+            ("artifacts_folder", json_path(&settings.artifacts_folder)),
+            ("features_in", json_path(&settings.features_in)),
+            ("features_out", json_path(&settings.features_out)),
+            ("coverage_out", json_path(&settings.coverage_out)),
+            ("edge_weights", json_path(&settings.edge_weights)),
+            ("max_total_corpus_bytes", json_usize(&settings.max_total_corpus_bytes)),
+            ("save_all_inputs", json_path(&settings.save_all_inputs)),
+            ("replay", json_path(&settings.replay)),
+            (
+                "crash_exitcode",
+                settings
+                    .crash_exitcode
+                    .map(|x| x.to_string())
+                    .unwrap_or_else(|| "null".to_owned()),
+            ),
+            (
+                "timeout_exitcode",
+                settings
+                    .timeout_exitcode
+                    .map(|x| x.to_string())
+                    .unwrap_or_else(|| "null".to_owned()),
+            ),
+            (
+                "target",
+                settings
+                    .target
+                    .as_ref()
+                    .map(|t| json_string(t))
+                    .unwrap_or_else(|| "null".to_owned()),
+            ),
+        ];
+        let fields: Vec<String> = fields
+            .into_iter()
+            .map(|(name, value)| format!("  {}: {}", json_string(name), value))
+            .collect();
+        write_atomically(path, format!("{{\n{}\n}}\n", fields.join(",\n")))
+    }
+
+    fn save_artifact(
+        &self,
+        input: &Self::Value,
+        cplx: f64,
+        signature: Option<u64>,
+        description: Option<String>,
+        replay_log: Option<&ReplayLog<Self::Value>>,
+    ) -> Result<()> {
+        let artifacts_folder = self.artifacts_folder();
+        let artifacts_folder = artifacts_folder.as_path();
 
         if !artifacts_folder.is_dir() {
             std::fs::create_dir_all(artifacts_folder)?;
         }
 
-        let mut hasher = DefaultHasher::new();
+        // the minified versions of an artifact all have the same signature on purpose
+        let signature = signature.filter(|_| matches!(self.settings.command, FuzzerCommand::Fuzz));
+        let signatures_path = artifacts_folder.join(CRASH_SIGNATURES_FILE);
+        if let (Some(signature), false) = (signature, self.settings.save_all_crashes) {
+            if let Some(artifact) = find_crash_signature(&signatures_path, signature) {
+                self.report_event(FuzzerEvent::DuplicateCrash { signature, artifact }, None);
+                return Ok(());
+            }
+        }
+
         let content = self.serializer.to_data(&input);
-        content.hash(&mut hasher);
-        let hash = hasher.finish();
+        let hash = content_hash(&content);
 
         let name = if let FuzzerCommand::MinifyInput | FuzzerCommand::Read = self.settings.command {
-            format!("{:.0}--{:x}", cplx * 100.0, hash)
+            format!("{:.0}--{}", cplx * 100.0, hash)
         } else {
-            format!("{:x}", hash)
+            hash
         };
 
         let path = artifacts_folder.join(name).with_extension(self.serializer.extension());
         println!("Saving at {:?}", path);
-        fs::write(path, content)?;
+        write_atomically(&path, content)?;
+        if let Some(description) = description {
+            // in a subfolder, so that it is never mistaken for an artifact
+            let descriptions_folder = artifacts_folder.join(DESCRIPTIONS_FOLDER);
+            fs::create_dir_all(&descriptions_folder)?;
+            let description_path = descriptions_folder
+                .join(path.file_name().unwrap())
+                .with_extension("txt");
+            println!("Saving the description at {:?}", description_path);
+            write_atomically(&description_path, description)?;
+        }
+        if let Some(replay_log) = replay_log {
+            let replays_folder = artifacts_folder.join(REPLAYS_FOLDER);
+            fs::create_dir_all(&replays_folder)?;
+            let log_path = replays_folder.join(path.file_name().unwrap()).with_extension("replay");
+            println!("Saving the mutations that produced it at {:?}", log_path);
+            write_decisions(&log_path, replay_log.decisions.decisions())?;
+            // named like the artifact, but in the replays folder
+            if let Some(start) = &replay_log.start {
+                let start_path = replays_folder.join(path.file_name().unwrap());
+                write_atomically(&start_path, self.serializer.to_data(start))?;
+                println!(
+                    "Replay them with: read --{} {:?} --{} {:?}",
+                    REPLAY_FLAG, log_path, INPUT_FILE_FLAG, start_path
+                );
+            } else {
+                println!("Replay them with: read --{} {:?}", REPLAY_FLAG, log_path);
+            }
+        }
+        if let Some(signature) = signature {
+            let mut signatures = fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&signatures_path)?;
+            writeln!(
+                signatures,
+                "{:016x}\t{}",
+                signature,
+                path.file_name().unwrap().to_string_lossy()
+            )?;
+        }
         Result::Ok(())
     }
 
-    pub fn report_event(&self, event: FuzzerEvent, stats: Option<FuzzerStats>) {
-        match event {
-            FuzzerEvent::Start => {
-                println!("START");
-                return;
-            }
-            FuzzerEvent::Done => {
-                println!("DONE");
-                return;
-            }
-            FuzzerEvent::New => print!("NEW\t"),
-            FuzzerEvent::Remove => print!("REMOVE\t"),
-            FuzzerEvent::DidReadCorpus => {
-                println!("FINISHED READING CORPUS");
-                return;
-            }
-            FuzzerEvent::CaughtSignal(signal) => match signal {
-                4 | 6 | 10 | 11 | 8 => println!("\n================ CRASH DETECTED ================"),
-                2 | 15 => println!("\n================ RUN INTERRUPTED ================"),
-                _ => println!("\n================ SIGNAL {:?} ================", signal),
-            },
-            FuzzerEvent::TestFailure => println!("\n================ TEST FAILED ================"),
-            FuzzerEvent::Replace(count) => {
-                print!("RPLC {}\t", count);
+    /// Prints the event, or with `--tui`, updates the status line with it.
+    ///
+    /// The events summarized by the status line are never printed on their
+    /// own with `--tui`, regardless of `--verbosity`.
+    fn report_event(&self, event: FuzzerEvent, stats: Option<FuzzerStats>) {
+        let status_line = if let Some(status_line) = &self.status_line {
+            status_line
+        } else {
+            return self.print_event(event, stats);
+        };
+        // a signal can interrupt the fuzzer while it draws the status line,
+        // and then report its event from the signal handler
+        let mut status_line = if let Ok(status_line) = status_line.try_borrow_mut() {
+            status_line
+        } else {
+            return self.print_event(event, stats);
+        };
+        if status_line.update(&event, stats) {
+            let is_new = matches!(event, FuzzerEvent::New | FuzzerEvent::Replace(_));
+            status_line.draw(is_new);
+            return;
+        }
+        let is_final = matches!(
+            event,
+            FuzzerEvent::Done
+                | FuzzerEvent::CaughtSignal(_)
+                | FuzzerEvent::TestFailure
+                | FuzzerEvent::DeadlineReached
+                | FuzzerEvent::TargetCplxReached(_)
+        );
+        // once the line is left on the terminal at the end of the run, the
+        // events that follow, such as the saved artifacts, are printed below it
+        let was_displayed = status_line.is_displayed;
+        if is_final {
+            status_line.finish();
+        } else {
+            status_line.clear();
+        }
+        self.print_event(event, stats);
+        if !is_final && was_displayed {
+            status_line.draw(true);
+        }
+    }
+
+    /// Prints the number of features that are covered by only one of two
+    /// corpora, followed by the number of those features that each input covers
+    fn report_corpus_diff(
+        &self,
+        only_in_corpus: usize,
+        only_in_other_corpus: usize,
+        contribution_of_inputs: &[(&Self::Value, usize)],
+        contribution_of_other_inputs: &[(&Self::Value, usize)],
+    ) {
+        println!("ONLY IN CORPUS\t{}", only_in_corpus);
+        println!("ONLY IN OTHER CORPUS\t{}", only_in_other_corpus);
+        for (input, contribution) in contribution_of_inputs {
+            println!("CORPUS INPUT {}\t{}", self.input_name(input), contribution);
+        }
+        for (input, contribution) in contribution_of_other_inputs {
+            println!("OTHER CORPUS INPUT {}\t{}", self.input_name(input), contribution);
+        }
+    }
+
+    /// Serializes the input and deserializes the result, returning both the
+    /// serialized input and the deserialized value, if any
+    fn serialization_round_trip(&self, input: &Self::Value) -> (Vec<u8>, Option<Self::Value>) {
+        let data = self.serializer.to_data(input);
+        let value = self.serializer.from_data(&data);
+        (data, value)
+    }
+
+    /// Prints why the mutator failed the self-test, followed by the
+    /// serialized offending value
+    fn report_self_test_failure(&self, reason: &str, input: &Self::Value) {
+        println!("\n================ SELF TEST FAILED ================");
+        println!("{}", reason);
+        println!("{}", String::from_utf8_lossy(&self.serializer.to_data(input)));
+    }
+}
+
+/// A [World] that keeps its corpora and artifacts in memory and records the
+/// reported events, to test the fuzzer without touching the file system.
+///
+/// Its clones share the same storage, so that a clone can be inspected after
+/// the original was given to the fuzzer.
+#[cfg(test)]
+#[derive(Clone)]
+pub(crate) struct TestWorld<V: Clone + PartialEq> {
+    pub input_corpus: Rc<RefCell<Vec<V>>>,
+    pub output_corpus: Rc<RefCell<Vec<V>>>,
+    pub artifacts: Rc<RefCell<Vec<V>>>,
+    pub events: Rc<RefCell<Vec<FuzzerEvent>>>,
+    instant: Instant,
+}
+
+#[cfg(test)]
+impl<V: Clone + PartialEq> TestWorld<V> {
+    pub fn new(input_corpus: Vec<V>) -> Self {
+        Self {
+            input_corpus: Rc::new(RefCell::new(input_corpus)),
+            output_corpus: Rc::new(RefCell::new(vec![])),
+            artifacts: Rc::new(RefCell::new(vec![])),
+            events: Rc::new(RefCell::new(vec![])),
+            instant: Instant::now(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl<V: Clone + PartialEq> World for TestWorld<V> {
+    type Value = V;
+
+    fn set_start_time(&mut self) {
+        self.instant = Instant::now();
+    }
+    fn elapsed_time(&self) -> usize {
+        self.instant.elapsed().as_micros() as usize
+    }
+    fn record_throughput(&mut self, _exec_per_s: usize) {}
+
+    fn read_input_corpus(&self) -> Result<Vec<V>> {
+        Ok(self.input_corpus.borrow().clone())
+    }
+    fn create_output_folders(&self) -> Result<()> {
+        Ok(())
+    }
+    fn read_new_inputs_of_input_corpus(&self) -> Result<Vec<V>> {
+        Ok(vec![])
+    }
+    fn read_other_corpus(&self) -> Result<Vec<V>> {
+        Ok(vec![])
+    }
+    fn read_input_file(&self) -> Result<V> {
+        Result::Err(io::Error::new(io::ErrorKind::Other, "A test world has no input file"))
+    }
+    fn read_replay_log(&self) -> Result<Vec<MutatorDecision>> {
+        Result::Err(io::Error::new(io::ErrorKind::Other, "A test world has no replay log"))
+    }
+    fn read_global_features(&self) -> Result<Vec<Feature>> {
+        Ok(vec![])
+    }
+    fn write_global_features(&self, _features: &[Feature]) -> Result<()> {
+        Ok(())
+    }
+    fn write_coverage_bitmap(&self, _nbr_guards: usize, _bitmap: &[u8]) -> Result<()> {
+        Ok(())
+    }
+    fn read_edge_weights(&self) -> Result<HashMap<u64, f64>> {
+        Ok(HashMap::new())
+    }
+
+    fn add_to_output_corpus(&self, input: V) -> Result<()> {
+        self.output_corpus.borrow_mut().push(input);
+        Ok(())
+    }
+    fn remove_from_output_corpus(&self, input: V) -> Result<()> {
+        let mut output_corpus = self.output_corpus.borrow_mut();
+        if let Some(idx) = output_corpus.iter().position(|x| *x == input) {
+            output_corpus.remove(idx);
+        }
+        Ok(())
+    }
+    fn make_room_in_output_corpus<'a>(&self, _input: &V, _evictable: &mut dyn Iterator<Item = &'a V>) -> Result<usize>
+    where
+        V: 'a,
+    {
+        Ok(0)
+    }
+    fn save_artifact(
+        &self,
+        input: &V,
+        _cplx: f64,
+        _signature: Option<u64>,
+        _description: Option<String>,
+        _replay_log: Option<&ReplayLog<V>>,
+    ) -> Result<()> {
+        self.artifacts.borrow_mut().push(input.clone());
+        Ok(())
+    }
+    fn save_tested_input(&self, _input: &V, _run: usize) -> Result<()> {
+        Ok(())
+    }
+    fn save_slowest_inputs(&self, _inputs: &[(&Duration, &V)]) -> Result<()> {
+        Ok(())
+    }
+    fn capture_tested_input(&self, _input: &V) -> Result<()> {
+        Ok(())
+    }
+    fn write_metadata(&self, _settings: &CommandLineArguments, _mutator: &str, _value_type: &str) -> Result<()> {
+        Ok(())
+    }
+    fn serialization_round_trip(&self, input: &V) -> (Vec<u8>, Option<V>) {
+        // there is no serializer, so the value is kept as is
+        (vec![], Some(input.clone()))
+    }
+
+    fn report_event(&self, event: FuzzerEvent, _stats: Option<FuzzerStats>) {
+        self.events.borrow_mut().push(event);
+    }
+    fn report_corpus_diff(
+        &self,
+        _only_in_corpus: usize,
+        _only_in_other_corpus: usize,
+        _contribution_of_inputs: &[(&V, usize)],
+        _contribution_of_other_inputs: &[(&V, usize)],
+    ) {
+    }
+    fn report_self_test_failure(&self, _reason: &str, _input: &V) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_atomically() {
+        let folder = std::env::temp_dir().join(format!("fuzzcheck-test-write-atomically-{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        let path = folder.join("input.json");
+
+        write_atomically(&path, "first").unwrap();
+        write_atomically(&path, "second").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        // no temporary file is left behind
+        assert_eq!(fs::read_dir(&folder).unwrap().count(), 1);
+
+        assert!(is_temporary_file(&folder.join(".input.json.123.tmp")));
+        assert!(!is_temporary_file(&path));
+        assert!(!is_temporary_file(&folder.join("input.tmp")));
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn test_status_line() {
+        let mut status_line = StatusLine::new();
+        let stats = FuzzerStats {
+            total_number_of_runs: 1000,
+            exec_per_s: 250,
+            pool_size: 12,
+            ..FuzzerStats::new()
+        };
+        assert!(status_line.update(&FuzzerEvent::Stats, Some(stats)));
+        let now = status_line.start + Duration::from_secs(4);
+        assert_eq!(
+            status_line.text(now),
+            "iter: 1000  exec/s: 250  corpus: 12  cov: 0  last new: never  time: 4s"
+        );
+
+        let pulse = FuzzerEvent::Pulse {
+            iterations: 1024,
+            execs_per_sec: 256,
+            corpus_size: 13,
+            coverage: 80,
+            edges: 60,
+            comparisons: 20,
+        };
+        assert!(status_line.update(&pulse, None));
+        assert!(status_line.update(&FuzzerEvent::New, Some(stats)));
+        let now = status_line.last_new_feature.unwrap() + Duration::from_secs(2);
+        assert!(status_line.text(now).contains("cov: 80  last new: 2s ago"));
+
+        // the other events are printed on their own
+        assert!(!status_line.update(&FuzzerEvent::Done, Some(stats)));
+        assert!(!status_line.update(&FuzzerEvent::ReloadCorpus(1), None));
+        let loading = FuzzerEvent::LoadingCorpus {
+            tested: 1000,
+            total: 5000,
+            pool_size: 10,
+        };
+        assert!(!status_line.update(&loading, None));
+    }
+
+    struct ByteSerializer;
+    impl Serializer for ByteSerializer {
+        type Value = u8;
+        fn extension(&self) -> &str {
+            "bin"
+        }
+        fn from_data(&self, data: &[u8]) -> Option<u8> {
+            if data.len() == 1 {
+                Some(data[0])
+            } else {
+                None
             }
+        }
+        fn to_data(&self, value: &u8) -> Vec<u8> {
+            vec![*value]
+        }
+        fn is_raw(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn test_read_corpus_skips_invalid_files() {
+        let folder = std::env::temp_dir().join(format!("fuzzcheck-test-read-corpus-{}", std::process::id()));
+        fs::create_dir_all(folder.join("subfolder")).unwrap();
+        fs::write(folder.join("a.bin"), [1]).unwrap();
+        fs::write(folder.join("b.bin"), [2, 3]).unwrap();
+        fs::write(folder.join(".c.bin.1.tmp"), [4]).unwrap();
+
+        let args: Vec<String> = vec![COMMAND_MINIFY_CORPUS.to_owned()];
+        let settings = CommandLineArguments::from_parser(&options_parser(), &args, DEFAULT_ARGUMENTS).unwrap();
+        let world = FileSystemWorld::new(ByteSerializer, settings);
+        let (inputs, nbr_files, skipped) = world.read_corpus(&folder, |_| true).unwrap();
+        assert_eq!(inputs, vec![1]);
+        assert_eq!(nbr_files, 3);
+        assert_eq!(skipped, vec![folder.join(".c.bin.1.tmp"), folder.join("b.bin")]);
+
+        assert!(world.read_corpus(&folder.join("a.bin"), |_| true).is_err());
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn test_reload_input_corpus() {
+        let folder = std::env::temp_dir().join(format!("fuzzcheck-test-reload-corpus-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+        fs::write(folder.join("a.bin"), [1]).unwrap();
+        // still being written by another fuzzer
+        fs::write(folder.join("b.bin"), [2, 2]).unwrap();
+
+        let args: Vec<String> = vec![COMMAND_FUZZ.to_owned()];
+        let mut settings = CommandLineArguments::from_parser(&options_parser(), &args, DEFAULT_ARGUMENTS).unwrap();
+        settings.corpus_in = Some(folder.clone());
+        let world = FileSystemWorld::new(ByteSerializer, settings);
+        assert_eq!(world.read_input_corpus().unwrap(), vec![1]);
+        assert!(world.read_new_inputs_of_input_corpus().unwrap().is_empty());
+
+        fs::write(folder.join("b.bin"), [2]).unwrap();
+        fs::write(folder.join("c.bin"), [3]).unwrap();
+        assert_eq!(world.read_new_inputs_of_input_corpus().unwrap(), vec![2, 3]);
+        assert!(world.read_new_inputs_of_input_corpus().unwrap().is_empty());
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn test_output_corpus_skips_saved_inputs() {
+        let folder = std::env::temp_dir().join(format!("fuzzcheck-test-corpus-out-{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        // saved by a previous run, under another name
+        fs::write(folder.join("previous.bin"), [5]).unwrap();
+
+        let args: Vec<String> = vec![COMMAND_MINIFY_CORPUS.to_owned()];
+        let mut settings = CommandLineArguments::from_parser(&options_parser(), &args, DEFAULT_ARGUMENTS).unwrap();
+        settings.corpus_out = Some(folder.clone());
+        let world = FileSystemWorld::new(ByteSerializer, settings);
+        world.add_to_output_corpus(5).unwrap();
+        world.add_to_output_corpus(6).unwrap();
+        world.add_to_output_corpus(6).unwrap();
+        assert_eq!(fs::read_dir(&folder).unwrap().count(), 2);
+        assert!(folder.join("previous.bin").is_file());
+
+        world.remove_from_output_corpus(5).unwrap();
+        world.remove_from_output_corpus(6).unwrap();
+        assert_eq!(fs::read_dir(&folder).unwrap().count(), 0);
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn test_raw_output_corpus() {
+        let folder = std::env::temp_dir().join(format!("fuzzcheck-test-raw-corpus-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        let args: Vec<String> = vec![COMMAND_MINIFY_CORPUS.to_owned(), "--".to_owned() + RAW_CORPUS_FLAG];
+        let mut settings = CommandLineArguments::from_parser(&options_parser(), &args, DEFAULT_ARGUMENTS).unwrap();
+        settings.corpus_out = Some(folder.clone());
+        let world = FileSystemWorld::new(ByteSerializer, settings);
+        world.add_to_output_corpus(5).unwrap();
+
+        let files: Vec<PathBuf> = fs::read_dir(&folder)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(files.len(), 1);
+        assert_eq!(
+            files[0].file_name().unwrap().to_string_lossy(),
+            "8dc00598417d4eb788a77ac6ccef3cb484905d8b"
+        );
+        assert!(files[0].extension().is_none());
+        assert_eq!(fs::read(&files[0]).unwrap(), vec![5]);
+        // a raw corpus can be read back as seeds
+        assert_eq!(world.read_corpus(&folder, |_| true).unwrap().0, vec![5]);
+
+        world.remove_from_output_corpus(5).unwrap();
+        assert_eq!(fs::read_dir(&folder).unwrap().count(), 0);
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn test_max_total_corpus_bytes() {
+        let folder = std::env::temp_dir().join(format!("fuzzcheck-test-corpus-bytes-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        let world = |max_total_corpus_bytes: Option<usize>| {
+            let args: Vec<String> = vec![COMMAND_FUZZ.to_owned()];
+            let mut settings = CommandLineArguments::from_parser(&options_parser(), &args, DEFAULT_ARGUMENTS).unwrap();
+            settings.corpus_out = Some(folder.clone());
+            settings.max_total_corpus_bytes = max_total_corpus_bytes;
+            FileSystemWorld::new(ByteSerializer, settings)
         };
-        if let Some(stats) = stats {
-            print!("{}\t", stats.total_number_of_runs);
-            print!("score: {:.2}\t", stats.score);
-            print!("pool: {}\t", stats.pool_size);
-            print!("exec/s: {}\t", stats.exec_per_s);
-            print!("cplx: {:.2}\t", stats.avg_cplx);
-            println!();
+        let limited_world = world(Some(2));
+        for &input in [1, 2].iter() {
+            assert_eq!(
+                limited_world
+                    .make_room_in_output_corpus(&input, &mut std::iter::empty())
+                    .unwrap(),
+                0
+            );
+            limited_world.add_to_output_corpus(input).unwrap();
         }
+        assert_eq!(limited_world.corpus_out_bytes.get(), 2);
+
+        // 2 is evicted first, after which there is enough room
+        assert_eq!(
+            limited_world
+                .make_room_in_output_corpus(&3, &mut [2, 1].iter())
+                .unwrap(),
+            1
+        );
+        limited_world.add_to_output_corpus(3).unwrap();
+        assert_eq!(limited_world.corpus_out_bytes.get(), 2);
+        assert_eq!(fs::read_dir(&folder).unwrap().count(), 2);
+        assert!(folder.join(limited_world.input_name(&1)).is_file());
+
+        // an input that is already saved takes no more room
+        assert_eq!(
+            limited_world
+                .make_room_in_output_corpus(&1, &mut [1, 3].iter())
+                .unwrap(),
+            0
+        );
+        // the files that were already evicted make no more room
+        assert_eq!(
+            limited_world.make_room_in_output_corpus(&4, &mut [2].iter()).unwrap(),
+            0
+        );
+
+        limited_world.remove_from_output_corpus(1).unwrap();
+        assert_eq!(limited_world.corpus_out_bytes.get(), 1);
+        // the evictable inputs are only taken when room must be made
+        let mut evictable = [3].iter();
+        assert_eq!(limited_world.make_room_in_output_corpus(&4, &mut evictable).unwrap(), 0);
+        assert_eq!(evictable.next(), Some(&3));
+
+        // without a limit, nothing is evicted
+        let unlimited_world = world(None);
+        assert_eq!(
+            unlimited_world.make_room_in_output_corpus(&4, &mut [3].iter()).unwrap(),
+            0
+        );
+        // and the size of the files saved by a previous run is read from the folder
+        let _ = unlimited_world.corpus_out_files(&folder);
+        assert_eq!(unlimited_world.corpus_out_bytes.get(), 1);
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn test_missing_input_corpus() {
+        let folder = std::env::temp_dir().join(format!("fuzzcheck-test-missing-corpus-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        let world = |command: &str| {
+            let args: Vec<String> = vec![command.to_owned()];
+            let mut settings = CommandLineArguments::from_parser(&options_parser(), &args, DEFAULT_ARGUMENTS).unwrap();
+            settings.corpus_in = Some(folder.join("in"));
+            settings.corpus_out = Some(folder.join("out"));
+            settings.artifacts_folder = Some(folder.join("artifacts"));
+            FileSystemWorld::new(ByteSerializer, settings)
+        };
+        // the fuzzer starts with an empty corpus
+        let fuzz_world = world(COMMAND_FUZZ);
+        assert_eq!(fuzz_world.read_input_corpus().unwrap(), vec![]);
+        assert_eq!(fuzz_world.read_new_inputs_of_input_corpus().unwrap(), vec![]);
+        // but the corpus to minify is required
+        let error = world(COMMAND_MINIFY_CORPUS).read_input_corpus().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+
+        fuzz_world.create_output_folders().unwrap();
+        assert!(folder.join("out").is_dir());
+        assert!(folder.join("artifacts").is_dir());
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn test_read_edge_weights() {
+        let path = std::env::temp_dir().join(format!("fuzzcheck-test-edge-weights-{}", std::process::id()));
+        let read = |content: &str| {
+            fs::write(&path, content).unwrap();
+            let args: Vec<String> = vec![COMMAND_FUZZ.to_owned()];
+            let mut settings = CommandLineArguments::from_parser(&options_parser(), &args, DEFAULT_ARGUMENTS).unwrap();
+            settings.edge_weights = Some(path.clone());
+            FileSystemWorld::new(ByteSerializer, settings).read_edge_weights()
+        };
+        let weights = read("# new function\n0x1a 4\n\n  2b\t0.5  \n").unwrap();
+        assert_eq!(weights.len(), 2);
+        assert_eq!(weights[&0x1a], 4.0);
+        assert_eq!(weights[&0x2b], 0.5);
+
+        assert!(read("0x1a\n").is_err());
+        assert!(read("0x1a 2 3\n").is_err());
+        assert!(read("xyz 2\n").is_err());
+        assert!(read("0x1a -1\n").is_err());
+        assert!(read("0x1a inf\n").is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_duplicate_crashes_are_not_saved() {
+        let folder = std::env::temp_dir().join(format!("fuzzcheck-test-crash-signatures-{}", std::process::id()));
+        let nbr_artifacts = |save_all_crashes: bool| {
+            let _ = fs::remove_dir_all(&folder);
+            let args: Vec<String> = vec![COMMAND_FUZZ.to_owned()];
+            let mut settings = CommandLineArguments::from_parser(&options_parser(), &args, DEFAULT_ARGUMENTS).unwrap();
+            settings.artifacts_folder = Some(folder.clone());
+            settings.save_all_crashes = save_all_crashes;
+            let world = FileSystemWorld::new(ByteSerializer, settings);
+            world.save_artifact(&1, 8.0, Some(7), None, None).unwrap();
+            world.save_artifact(&2, 8.0, Some(7), None, None).unwrap();
+            world.save_artifact(&3, 8.0, Some(8), None, None).unwrap();
+            world.save_artifact(&4, 8.0, None, None, None).unwrap();
+            fs::read_dir(&folder)
+                .unwrap()
+                .filter(|entry| entry.as_ref().unwrap().file_name() != CRASH_SIGNATURES_FILE)
+                .count()
+        };
+        assert_eq!(nbr_artifacts(false), 3);
+        assert_eq!(nbr_artifacts(true), 4);
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn test_artifact_description() {
+        let folder = std::env::temp_dir().join(format!("fuzzcheck-test-descriptions-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        let args: Vec<String> = vec![COMMAND_FUZZ.to_owned()];
+        let mut settings = CommandLineArguments::from_parser(&options_parser(), &args, DEFAULT_ARGUMENTS).unwrap();
+        settings.artifacts_folder = Some(folder.clone());
+        let world = FileSystemWorld::new(ByteSerializer, settings);
+        world
+            .save_artifact(&1, 8.0, None, Some("one".to_owned()), None)
+            .unwrap();
+        world.save_artifact(&2, 8.0, None, None, None).unwrap();
+
+        let descriptions: Vec<PathBuf> = fs::read_dir(folder.join(DESCRIPTIONS_FOLDER))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(descriptions.len(), 1);
+        assert_eq!(fs::read_to_string(&descriptions[0]).unwrap(), "one");
+        let artifact = folder.join(descriptions[0].file_name().unwrap()).with_extension("bin");
+        assert_eq!(fs::read(artifact).unwrap(), vec![1]);
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn test_do_actions_through_dyn_world() {
+        let test_world = TestWorld::new(vec![1u8, 2]);
+        let world: &dyn World<Value = u8> = &test_world;
+
+        assert_eq!(world.read_input_corpus().unwrap(), vec![1, 2]);
+
+        let actions = vec![
+            WorldAction::Add(3),
+            WorldAction::Add(4),
+            WorldAction::ReportEvent(FuzzerEvent::New),
+            WorldAction::Remove(3),
+            WorldAction::ReportEvent(FuzzerEvent::Remove),
+        ];
+        world.do_actions(actions, &FuzzerStats::new()).unwrap();
+
+        assert_eq!(*test_world.output_corpus.borrow(), vec![4]);
+        let events = test_world.events.borrow();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[0], FuzzerEvent::New));
+        assert!(matches!(events[1], FuzzerEvent::Remove));
     }
 }
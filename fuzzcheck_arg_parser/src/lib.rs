@@ -7,6 +7,8 @@ pub enum FuzzerCommand {
     Fuzz,
     Read,
     MinifyCorpus,
+    DiffCorpus,
+    SelfTest,
 }
 
 pub const MAX_NBR_RUNS_FLAG: &str = "max-iter";
@@ -19,11 +21,50 @@ pub const NO_OUT_CORPUS_FLAG: &str = "no-out-corpus";
 pub const ARTIFACTS_FLAG: &str = "artifacts";
 pub const NO_ARTIFACTS_FLAG: &str = "no-artifacts";
 pub const CORPUS_SIZE_FLAG: &str = "corpus-size";
+pub const VERBOSITY_FLAG: &str = "verbosity";
+pub const FORK_FLAG: &str = "fork";
+pub const PRINT_PCS_FLAG: &str = "print-pcs";
+pub const TRACE_ONLY_FLAG: &str = "trace-only";
+pub const NO_CMP_TRACE_FLAG: &str = "no-cmp-trace";
+pub const NO_EDGE_TRACE_FLAG: &str = "no-edge-trace";
+pub const EXPLAIN_COMPLEXITY_FLAG: &str = "explain-complexity";
+pub const ADAPTIVE_CPLX_FLAG: &str = "adaptive-cplx";
+pub const INITIAL_CPLX_FLAG: &str = "initial-cplx";
+pub const KEEP_SEEDS_FLAG: &str = "keep-seeds";
+pub const TUI_FLAG: &str = "tui";
+pub const SAVE_ALL_CRASHES_FLAG: &str = "save-all-crashes";
+pub const RAW_CORPUS_FLAG: &str = "raw-corpus";
+pub const ARBITRARY_FROM_CORPUS_FLAG: &str = "arbitrary-from-corpus";
+pub const CRASH_CAPTURE_FLAG: &str = "crash-capture";
+pub const DETERMINISTIC_FLAG: &str = "deterministic";
+pub const INPUT_MIN_LEN_FLAG: &str = "input-min-len";
+pub const INPUT_MAX_LEN_FLAG: &str = "input-max-len";
+pub const MINIMIZE_TIMEOUT_FLAG: &str = "minimize-timeout";
+pub const MINIMIZE_TO_CPLX_FLAG: &str = "minimize-to-cplx";
+pub const CURATE_FLAG: &str = "curate";
+pub const RELOAD_CORPUS_INTERVAL_FLAG: &str = "reload-corpus-interval";
+pub const ENERGY_DECAY_FLAG: &str = "energy-decay";
+pub const SEED_FLAG: &str = "seed";
+pub const OTHER_CORPUS_FLAG: &str = "other-corpus";
+pub const METADATA_OUT_FLAG: &str = "metadata-out";
+pub const TARGET_FLAG: &str = "target";
+pub const FEATURES_IN_FLAG: &str = "features-in";
+pub const FEATURES_OUT_FLAG: &str = "features-out";
+pub const COVERAGE_OUT_FLAG: &str = "coverage-out";
+pub const EDGE_WEIGHTS_FLAG: &str = "edge-weights";
+pub const MAX_TOTAL_CORPUS_BYTES_FLAG: &str = "max-total-corpus-bytes";
+pub const SAVE_ALL_INPUTS_FLAG: &str = "save-all-inputs";
+pub const REPLAY_FLAG: &str = "replay";
+pub const CRASH_EXITCODE_FLAG: &str = "crash-exitcode";
+pub const TIMEOUT_EXITCODE_FLAG: &str = "timeout-exitcode";
+pub const REPORT_SLOWEST_INPUTS_FLAG: &str = "report-slowest-inputs";
 
 pub const COMMAND_FUZZ: &str = "fuzz";
 pub const COMMAND_MINIFY_INPUT: &str = "tmin";
 pub const COMMAND_MINIFY_CORPUS: &str = "cmin";
 pub const COMMAND_READ: &str = "read";
+pub const COMMAND_DIFF_CORPUS: &str = "cdiff";
+pub const COMMAND_SELF_TEST: &str = "selftest";
 
 #[derive(Clone)]
 pub struct DefaultArguments<'a> {
@@ -31,29 +72,119 @@ pub struct DefaultArguments<'a> {
     pub out_corpus: &'a str,
     pub artifacts: &'a str,
     pub max_nbr_of_runs: usize,
+    /// The maximum number of iterations of `tmin`
+    pub tmin_max_nbr_of_runs: usize,
+    /// The maximum number of iterations of `selftest`
+    pub selftest_max_nbr_of_runs: usize,
     pub max_input_cplx: usize,
+    /// The target size of the corpus of `cmin`. The corpus of `fuzz` is not
+    /// limited unless --corpus-size is given.
     pub corpus_size: usize,
+    pub verbosity: usize,
 }
 
+/// The default value of --energy-decay
+pub const DEFAULT_ENERGY_DECAY: f64 = 0.9;
+
+/// The highest verbosity level accepted by --verbosity
+pub const MAX_VERBOSITY: usize = 3;
+
 pub const DEFAULT_ARGUMENTS: DefaultArguments<'static> = DefaultArguments {
     in_corpus: "fuzz-corpus",
     out_corpus: "fuzz-corpus",
     artifacts: "artifacts",
     max_nbr_of_runs: core::usize::MAX,
+    tmin_max_nbr_of_runs: 1_000_000,
+    selftest_max_nbr_of_runs: 10_000,
     max_input_cplx: 256,
     corpus_size: 10,
+    verbosity: 1,
 };
 
+impl<'a> DefaultArguments<'a> {
+    /// The default arguments of the given command, which are overridden by
+    /// the arguments given on the command line.
+    ///
+    /// All commands use these default arguments, except for:
+    /// * `tmin`, which stops after `tmin_max_nbr_of_runs` iterations (instead
+    ///   of running forever), so that each attempt at minifying an input is
+    ///   short-lived
+    /// * `selftest`, which checks the mutator on `selftest_max_nbr_of_runs`
+    ///   arbitrary values
+    /// * `fuzz`, which does not limit the size of its corpus
+    ///
+    /// `cmin` keeps only the `corpus_size` most important test inputs.
+    pub fn for_command(&self, command: FuzzerCommand) -> DefaultArguments<'a> {
+        match command {
+            FuzzerCommand::Read | FuzzerCommand::DiffCorpus | FuzzerCommand::MinifyCorpus => self.clone(),
+            FuzzerCommand::Fuzz => DefaultArguments {
+                corpus_size: core::usize::MAX,
+                ..self.clone()
+            },
+            FuzzerCommand::MinifyInput => DefaultArguments {
+                max_nbr_of_runs: self.tmin_max_nbr_of_runs,
+                ..self.clone()
+            },
+            FuzzerCommand::SelfTest => DefaultArguments {
+                max_nbr_of_runs: self.selftest_max_nbr_of_runs,
+                ..self.clone()
+            },
+        }
+    }
+}
+
+/// The default arguments of each command, see [DefaultArguments::for_command]
+pub fn defaults_for(command: FuzzerCommand) -> DefaultArguments<'static> {
+    DEFAULT_ARGUMENTS.for_command(command)
+}
+
 #[derive(Debug, Clone)]
 pub struct CommandLineArguments {
     pub command: FuzzerCommand,
     pub max_nbr_of_runs: usize,
     pub max_input_cplx: f64,
     pub corpus_size: usize,
+    pub verbosity: usize,
+    pub fork: bool,
+    pub print_pcs: bool,
+    pub trace_only: bool,
+    pub no_cmp_trace: bool,
+    pub no_edge_trace: bool,
+    pub explain_complexity: bool,
+    pub adaptive_cplx: bool,
+    pub keep_seeds: bool,
+    pub tui: bool,
+    pub save_all_crashes: bool,
+    pub raw_corpus: bool,
+    pub arbitrary_from_corpus: bool,
+    pub crash_capture: bool,
+    pub deterministic: bool,
+    pub input_min_len: Option<usize>,
+    pub input_max_len: Option<usize>,
+    pub minimize_timeout: Option<usize>,
+    pub minimize_to_cplx: Option<usize>,
+    pub initial_cplx: Option<usize>,
+    pub curate: Option<usize>,
+    pub reload_corpus_interval: Option<usize>,
+    pub energy_decay: Option<f64>,
+    pub seed: Option<usize>,
     pub input_file: Option<PathBuf>,
     pub corpus_in: Option<PathBuf>,
+    pub other_corpus: Option<PathBuf>,
     pub corpus_out: Option<PathBuf>,
     pub artifacts_folder: Option<PathBuf>,
+    pub metadata_out: Option<PathBuf>,
+    pub target: Option<String>,
+    pub features_in: Option<PathBuf>,
+    pub features_out: Option<PathBuf>,
+    pub coverage_out: Option<PathBuf>,
+    pub edge_weights: Option<PathBuf>,
+    pub max_total_corpus_bytes: Option<usize>,
+    pub save_all_inputs: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    pub crash_exitcode: Option<i32>,
+    pub timeout_exitcode: Option<i32>,
+    pub report_slowest_inputs: Option<usize>,
 }
 
 pub fn options_parser() -> Options {
@@ -96,12 +227,87 @@ pub fn options_parser() -> Options {
             .as_str(),
         )
         .optopt("", INPUT_FILE_FLAG, "file containing a JSON-encoded input", "PATH")
+        .optopt(
+            "",
+            METADATA_OUT_FLAG,
+            "JSON file where the configuration of the run is written when it starts",
+            "PATH",
+        )
+        .optopt(
+            "",
+            TARGET_FLAG,
+            "name of the fuzz target to run, for binaries containing several fuzz targets",
+            "NAME",
+        )
+        .optopt(
+            "",
+            FEATURES_IN_FLAG,
+            format!(
+                "with {fuzz}: file listing the features covered by previous runs, any other feature is globally new",
+                fuzz = COMMAND_FUZZ
+            )
+            .as_str(),
+            "PATH",
+        )
+        .optopt(
+            "",
+            FEATURES_OUT_FLAG,
+            format!(
+                "with {fuzz}: file where the features covered by this run and by previous runs are written on exit",
+                fuzz = COMMAND_FUZZ
+            )
+            .as_str(),
+            "PATH",
+        )
+        .optopt(
+            "",
+            COVERAGE_OUT_FLAG,
+            format!(
+                "with {fuzz}: file where the bitmap of the edges covered by this run is written on exit",
+                fuzz = COMMAND_FUZZ
+            )
+            .as_str(),
+            "PATH",
+        )
+        .optopt(
+            "",
+            EDGE_WEIGHTS_FLAG,
+            format!(
+                "with {fuzz}: file listing edges, as printed by --{print_pcs}, each followed by the factor applied to its score",
+                fuzz = COMMAND_FUZZ,
+                print_pcs = PRINT_PCS_FLAG
+            )
+            .as_str(),
+            "PATH",
+        )
+        .optopt(
+            "",
+            MAX_TOTAL_CORPUS_BYTES_FLAG,
+            format!(
+                "with {fuzz}: remove the files of the lowest-scoring inputs from the output corpus to keep its total size under N bytes",
+                fuzz = COMMAND_FUZZ
+            )
+            .as_str(),
+            "N",
+        )
+        .optopt(
+            "",
+            OTHER_CORPUS_FLAG,
+            format!(
+                "folder of the corpus to compare with the input corpus, for {cdiff}",
+                cdiff = COMMAND_DIFF_CORPUS
+            )
+            .as_str(),
+            "PATH",
+        )
         .optopt(
             "",
             CORPUS_SIZE_FLAG,
             format!(
-                "target size of the corpus (default: {default})",
-                default = DEFAULT_ARGUMENTS.corpus_size
+                "target size of the corpus (default for {cmin}: {default}), a soft limit for {fuzz} that only removes inputs whose coverage is redundant (default: unlimited)",
+                cmin = COMMAND_MINIFY_CORPUS,
+                fuzz = COMMAND_FUZZ,
+                default = defaults_for(FuzzerCommand::MinifyCorpus).corpus_size
             )
             .as_str(),
             "N",
@@ -116,13 +322,277 @@ pub fn options_parser() -> Options {
             .as_str(),
             "N",
         )
-        .optopt("", MAX_NBR_RUNS_FLAG, "maximum number of iterations", "N")
+        .optopt(
+            "",
+            MAX_NBR_RUNS_FLAG,
+            format!(
+                "maximum number of iterations (default for {tmin}: {default}, for {selftest}: {selftest_default}, unlimited otherwise)",
+                tmin = COMMAND_MINIFY_INPUT,
+                default = defaults_for(FuzzerCommand::MinifyInput).max_nbr_of_runs,
+                selftest = COMMAND_SELF_TEST,
+                selftest_default = defaults_for(FuzzerCommand::SelfTest).max_nbr_of_runs
+            )
+            .as_str(),
+            "N",
+        )
+        .optopt(
+            "",
+            VERBOSITY_FLAG,
+            format!(
+                "0: final summary only, 1: periodic stats, 2: pool updates, 3: every iteration (default: {default})",
+                default = DEFAULT_ARGUMENTS.verbosity
+            )
+            .as_str(),
+            "N",
+        )
+        .optflag(
+            "",
+            FORK_FLAG,
+            "run each test input in a forked process, so that a crash cannot corrupt the fuzzer",
+        )
+        .optflag(
+            "",
+            PRINT_PCS_FLAG,
+            "at the end of the run, print each observed edge and comparison with the number of features it produced",
+        )
+        .optflag(
+            "",
+            TRACE_ONLY_FLAG,
+            format!(
+                "with {read}: print the features covered by the input file instead of only running it",
+                read = COMMAND_READ
+            )
+            .as_str(),
+        )
+        .optflag(
+            "",
+            EXPLAIN_COMPLEXITY_FLAG,
+            format!(
+                "with {read}: print the complexity of each field of the input file, or of an arbitrary input if no file is given",
+                read = COMMAND_READ
+            )
+            .as_str(),
+        )
+        .optflag(
+            "",
+            NO_CMP_TRACE_FLAG,
+            "do not record the operands of comparisons as features, only the edges, which is faster on compute-heavy targets but gives the fuzzer less guidance",
+        )
+        .optflag(
+            "",
+            NO_EDGE_TRACE_FLAG,
+            "do not record the edges of the code coverage as features, only the operands of comparisons, for targets with little branching",
+        )
+        .optflag(
+            "",
+            ADAPTIVE_CPLX_FLAG,
+            format!(
+                "with {fuzz}: start with a low maximum complexity and double it whenever the coverage stops growing, up to --{max_cplx}",
+                fuzz = COMMAND_FUZZ,
+                max_cplx = MAX_INPUT_CPLX_FLAG
+            )
+            .as_str(),
+        )
+        .optopt(
+            "",
+            INITIAL_CPLX_FLAG,
+            format!(
+                "with {fuzz}: start with a maximum complexity of N and raise it steadily up to --{max_cplx}",
+                fuzz = COMMAND_FUZZ,
+                max_cplx = MAX_INPUT_CPLX_FLAG
+            )
+            .as_str(),
+            "N",
+        )
+        .optflag(
+            "",
+            KEEP_SEEDS_FLAG,
+            format!(
+                "with {fuzz}: never remove the inputs read from the input corpus, even when simpler inputs cover the same code",
+                fuzz = COMMAND_FUZZ
+            )
+            .as_str(),
+        )
+        .optflag(
+            "",
+            TUI_FLAG,
+            format!(
+                "with {fuzz}: show the progress of the fuzzer on a single line that is refreshed in place, when the output is a terminal",
+                fuzz = COMMAND_FUZZ
+            )
+            .as_str(),
+        )
+        .optflag(
+            "",
+            SAVE_ALL_CRASHES_FLAG,
+            format!(
+                "with {fuzz}: save a failing input as an artifact even if an artifact with the same crash signature was already saved",
+                fuzz = COMMAND_FUZZ
+            )
+            .as_str(),
+        )
+        .optflag(
+            "",
+            RAW_CORPUS_FLAG,
+            format!(
+                "with {fuzz} or {cmin}: save the output corpus as raw files named by the hash of their content, as libFuzzer and AFL do",
+                fuzz = COMMAND_FUZZ,
+                cmin = COMMAND_MINIFY_CORPUS
+            )
+            .as_str(),
+        )
+        .optflag(
+            "",
+            ARBITRARY_FROM_CORPUS_FLAG,
+            format!(
+                "with {fuzz}: build some of the new arbitrary inputs by combining pieces of inputs of the pool, if the mutator supports it",
+                fuzz = COMMAND_FUZZ
+            )
+            .as_str(),
+        )
+        .optflag(
+            "",
+            CRASH_CAPTURE_FLAG,
+            format!(
+                "with {fuzz} or {tmin}: serialize each input before testing it, so that it is saved as artifacts/crash-input even if a crash prevents saving the usual artifact",
+                fuzz = COMMAND_FUZZ,
+                tmin = COMMAND_MINIFY_INPUT
+            )
+            .as_str(),
+        )
+        .optflag(
+            "",
+            DETERMINISTIC_FLAG,
+            format!(
+                "make two runs with the same arguments and corpus identical, using --{seed} (0 by default) for every random decision",
+                seed = SEED_FLAG
+            )
+            .as_str(),
+        )
+        .optopt(
+            "",
+            INPUT_MIN_LEN_FLAG,
+            "minimum length of the test inputs, if they are collections",
+            "N",
+        )
+        .optopt(
+            "",
+            INPUT_MAX_LEN_FLAG,
+            "maximum length of the test inputs, if they are collections",
+            "N",
+        )
+        .optopt(
+            "",
+            MINIMIZE_TIMEOUT_FLAG,
+            format!(
+                "with {tmin} or {cmin}: stop minifying after N seconds, keeping the best result found so far",
+                tmin = COMMAND_MINIFY_INPUT,
+                cmin = COMMAND_MINIFY_CORPUS
+            )
+            .as_str(),
+            "N",
+        )
+        .optopt(
+            "",
+            MINIMIZE_TO_CPLX_FLAG,
+            format!(
+                "with {tmin}: stop minifying once the complexity of the input is below N",
+                tmin = COMMAND_MINIFY_INPUT
+            )
+            .as_str(),
+            "N",
+        )
+        .optopt(
+            "",
+            REPORT_SLOWEST_INPUTS_FLAG,
+            format!(
+                "with {fuzz}: save the N inputs that took the longest to test in the slowest subfolder of the artifacts folder when the fuzzer stops",
+                fuzz = COMMAND_FUZZ
+            )
+            .as_str(),
+            "N",
+        )
+        .optopt(
+            "",
+            CURATE_FLAG,
+            format!(
+                "with {cmin}: write only the N highest-scoring inputs of the input corpus to the output corpus",
+                cmin = COMMAND_MINIFY_CORPUS
+            )
+            .as_str(),
+            "N",
+        )
+        .optopt(
+            "",
+            RELOAD_CORPUS_INTERVAL_FLAG,
+            format!(
+                "with {fuzz}: every N seconds, read the inputs added to the input corpus by other fuzzers",
+                fuzz = COMMAND_FUZZ
+            )
+            .as_str(),
+            "N",
+        )
+        .optopt(
+            "",
+            ENERGY_DECAY_FLAG,
+            format!(
+                "with {fuzz}: factor between 0 and 1 applied to the weight of an input every 1000 times it is mutated (default: {default}, 1 disables it)",
+                fuzz = COMMAND_FUZZ,
+                default = DEFAULT_ENERGY_DECAY
+            )
+            .as_str(),
+            "FACTOR",
+        )
+        .optopt(
+            "",
+            SEED_FLAG,
+            "seed of the random number generators of the fuzzer, which are otherwise seeded randomly",
+            "N",
+        )
+        .optopt(
+            "",
+            SAVE_ALL_INPUTS_FLAG,
+            format!(
+                "with {fuzz}: folder where every tested input is written, for short diagnostic runs",
+                fuzz = COMMAND_FUZZ
+            )
+            .as_str(),
+            "PATH",
+        )
+        .optopt(
+            "",
+            REPLAY_FLAG,
+            format!(
+                "with {read}: test each input produced by replaying the mutator decisions recorded in the file, starting from the value of --{input_file} if given",
+                read = COMMAND_READ,
+                input_file = INPUT_FILE_FLAG
+            )
+            .as_str(),
+            "PATH",
+        )
+        .optopt(
+            "",
+            CRASH_EXITCODE_FLAG,
+            "exit code of the fuzzer when the test function fails or crashes (default: 70)",
+            "CODE",
+        )
+        .optopt(
+            "",
+            TIMEOUT_EXITCODE_FLAG,
+            "exit code of the fuzzer when the test function is stopped by SIGALRM or SIGXCPU (default: 71)",
+            "CODE",
+        )
         .optflag("", "help", "print this help menu");
 
     options
 }
 
 impl CommandLineArguments {
+    /// Parses the command line arguments.
+    ///
+    /// The values given by `defaults` are used for the arguments that are
+    /// not given on the command line, adjusted to the parsed command as
+    /// described in [DefaultArguments::for_command].
     pub fn from_parser(options: &Options, args: &[String], defaults: DefaultArguments) -> Result<Self, String> {
         let matches = options.parse(args).map_err(|e| e.to_string())?;
 
@@ -136,17 +606,23 @@ impl CommandLineArguments {
             COMMAND_READ => Ok(FuzzerCommand::Read),
             COMMAND_MINIFY_INPUT => Ok(FuzzerCommand::MinifyInput),
             COMMAND_MINIFY_CORPUS => Ok(FuzzerCommand::MinifyCorpus),
+            COMMAND_DIFF_CORPUS => Ok(FuzzerCommand::DiffCorpus),
+            COMMAND_SELF_TEST => Ok(FuzzerCommand::SelfTest),
             _ => Err(format!(
                 r#"
-The command {c} is not supported. It can either be ‘{fuzz}’, ‘{tmin}’, or ‘{cmin}’.
+The command {c} is not supported. It can either be ‘{fuzz}’, ‘{tmin}’, ‘{cmin}’, ‘{cdiff}’, or ‘{selftest}’.
                         "#,
                 c = args[0],
                 fuzz = COMMAND_FUZZ,
                 tmin = COMMAND_MINIFY_INPUT,
-                cmin = COMMAND_MINIFY_CORPUS
+                cmin = COMMAND_MINIFY_CORPUS,
+                cdiff = COMMAND_DIFF_CORPUS,
+                selftest = COMMAND_SELF_TEST
             )),
         }?;
 
+        let defaults = defaults.for_command(command);
+
         let max_input_cplx: f64 = matches
             .opt_str(MAX_INPUT_CPLX_FLAG)
             .map(|x| x.parse::<usize>().ok())
@@ -164,6 +640,152 @@ The command {c} is not supported. It can either be ‘{fuzz}’, ‘{tmin}’, o
             .flatten()
             .unwrap_or(defaults.corpus_size);
 
+        let verbosity: usize = match matches.opt_str(VERBOSITY_FLAG) {
+            Some(x) => match x.parse::<usize>() {
+                Ok(verbosity) if verbosity <= MAX_VERBOSITY => verbosity,
+                _ => {
+                    return Err(format!(
+                        "The verbosity level must be an integer between 0 and {max}, but ‘{x}’ was given",
+                        max = MAX_VERBOSITY,
+                        x = x
+                    ))
+                }
+            },
+            None => defaults.verbosity,
+        };
+
+        let fork = matches.opt_present(FORK_FLAG);
+        let print_pcs = matches.opt_present(PRINT_PCS_FLAG);
+        let trace_only = matches.opt_present(TRACE_ONLY_FLAG);
+        let no_cmp_trace = matches.opt_present(NO_CMP_TRACE_FLAG);
+        let no_edge_trace = matches.opt_present(NO_EDGE_TRACE_FLAG);
+        if no_cmp_trace && no_edge_trace {
+            return Err(format!(
+                "--{no_cmp_trace} and --{no_edge_trace} cannot be used together, since no feature would be recorded",
+                no_cmp_trace = NO_CMP_TRACE_FLAG,
+                no_edge_trace = NO_EDGE_TRACE_FLAG,
+            ));
+        }
+        let explain_complexity = matches.opt_present(EXPLAIN_COMPLEXITY_FLAG);
+        let adaptive_cplx = matches.opt_present(ADAPTIVE_CPLX_FLAG);
+        match (command, adaptive_cplx) {
+            (FuzzerCommand::Fuzz, _) | (_, false) => (),
+            _ => {
+                return Err(format!(
+                    "--{adaptive_cplx} can only be used with the {fuzz} command",
+                    adaptive_cplx = ADAPTIVE_CPLX_FLAG,
+                    fuzz = COMMAND_FUZZ
+                ))
+            }
+        }
+        let initial_cplx = parse_optional_usize(&matches, INITIAL_CPLX_FLAG)?;
+        match (command, initial_cplx) {
+            (_, None) => (),
+            (FuzzerCommand::Fuzz, Some(_)) if adaptive_cplx => {
+                return Err(format!(
+                    "--{initial_cplx} cannot be used with --{adaptive_cplx}",
+                    initial_cplx = INITIAL_CPLX_FLAG,
+                    adaptive_cplx = ADAPTIVE_CPLX_FLAG
+                ))
+            }
+            (FuzzerCommand::Fuzz, Some(initial)) if initial as f64 > max_input_cplx => {
+                return Err(format!(
+                    "--{initial_cplx} ({initial}) must not be greater than --{max_cplx} ({max})",
+                    initial_cplx = INITIAL_CPLX_FLAG,
+                    initial = initial,
+                    max_cplx = MAX_INPUT_CPLX_FLAG,
+                    max = max_input_cplx
+                ))
+            }
+            (FuzzerCommand::Fuzz, Some(_)) => (),
+            _ => {
+                return Err(format!(
+                    "--{initial_cplx} can only be used with the {fuzz} command",
+                    initial_cplx = INITIAL_CPLX_FLAG,
+                    fuzz = COMMAND_FUZZ
+                ))
+            }
+        }
+        let keep_seeds = matches.opt_present(KEEP_SEEDS_FLAG);
+        match (command, keep_seeds) {
+            (FuzzerCommand::Fuzz, _) | (_, false) => (),
+            _ => {
+                return Err(format!(
+                    "--{keep_seeds} can only be used with the {fuzz} command",
+                    keep_seeds = KEEP_SEEDS_FLAG,
+                    fuzz = COMMAND_FUZZ
+                ))
+            }
+        }
+        let tui = matches.opt_present(TUI_FLAG);
+        match (command, tui) {
+            (FuzzerCommand::Fuzz, _) | (_, false) => (),
+            _ => {
+                return Err(format!(
+                    "--{tui} can only be used with the {fuzz} command",
+                    tui = TUI_FLAG,
+                    fuzz = COMMAND_FUZZ
+                ))
+            }
+        }
+        let save_all_crashes = matches.opt_present(SAVE_ALL_CRASHES_FLAG);
+        match (command, save_all_crashes) {
+            (FuzzerCommand::Fuzz, _) | (_, false) => (),
+            _ => {
+                return Err(format!(
+                    "--{save_all_crashes} can only be used with the {fuzz} command",
+                    save_all_crashes = SAVE_ALL_CRASHES_FLAG,
+                    fuzz = COMMAND_FUZZ
+                ))
+            }
+        }
+
+        let raw_corpus = matches.opt_present(RAW_CORPUS_FLAG);
+        match (command, raw_corpus) {
+            (FuzzerCommand::Fuzz, _) | (FuzzerCommand::MinifyCorpus, _) | (_, false) => (),
+            _ => {
+                return Err(format!(
+                    "--{raw_corpus} can only be used with the {fuzz} and {cmin} commands",
+                    raw_corpus = RAW_CORPUS_FLAG,
+                    fuzz = COMMAND_FUZZ,
+                    cmin = COMMAND_MINIFY_CORPUS
+                ))
+            }
+        }
+
+        let arbitrary_from_corpus = matches.opt_present(ARBITRARY_FROM_CORPUS_FLAG);
+        match (command, arbitrary_from_corpus) {
+            (FuzzerCommand::Fuzz, _) | (_, false) => (),
+            _ => {
+                return Err(format!(
+                    "--{arbitrary_from_corpus} can only be used with the {fuzz} command",
+                    arbitrary_from_corpus = ARBITRARY_FROM_CORPUS_FLAG,
+                    fuzz = COMMAND_FUZZ
+                ))
+            }
+        }
+
+        let crash_capture = matches.opt_present(CRASH_CAPTURE_FLAG);
+        match (command, crash_capture) {
+            (FuzzerCommand::Fuzz, _) | (FuzzerCommand::MinifyInput, _) | (_, false) => (),
+            _ => {
+                return Err(format!(
+                    "--{crash_capture} can only be used with the {fuzz} and {tmin} commands",
+                    crash_capture = CRASH_CAPTURE_FLAG,
+                    fuzz = COMMAND_FUZZ,
+                    tmin = COMMAND_MINIFY_INPUT
+                ))
+            }
+        }
+        if crash_capture && fork {
+            // the parent process already saves the inputs that crash a forked process
+            return Err(format!(
+                "--{crash_capture} cannot be used with --{fork}",
+                crash_capture = CRASH_CAPTURE_FLAG,
+                fork = FORK_FLAG
+            ));
+        }
+
         let corpus_in: Option<PathBuf> = if !matches.opt_present(NO_IN_CORPUS_FLAG) {
             matches
                 .opt_str(IN_CORPUS_FLAG)
@@ -174,6 +796,11 @@ The command {c} is not supported. It can either be ‘{fuzz}’, ‘{tmin}’, o
             None
         };
 
+        let other_corpus: Option<PathBuf> = matches
+            .opt_str(OTHER_CORPUS_FLAG)
+            .map(|x| x.parse::<PathBuf>().ok())
+            .flatten();
+
         match (command, &input_file, &corpus_in) {
             (FuzzerCommand::MinifyInput, &None, _) => {
                 return Err("An input file must be given when minifying a test case".to_owned())
@@ -181,8 +808,49 @@ The command {c} is not supported. It can either be ‘{fuzz}’, ‘{tmin}’, o
             (FuzzerCommand::MinifyCorpus, _, &None) => {
                 return Err("An input corpus must be given when minifying a corpus".to_owned())
             }
+            (FuzzerCommand::DiffCorpus, _, &None) => {
+                return Err("An input corpus must be given when comparing two corpora".to_owned())
+            }
             _ => (),
         }
+        if trace_only {
+            match (command, &input_file) {
+                (FuzzerCommand::Read, &None) => {
+                    return Err("An input file must be given when tracing the features of a test case".to_owned())
+                }
+                (FuzzerCommand::Read, _) => (),
+                _ => {
+                    return Err(format!(
+                        "--{trace_only} can only be used with the {read} command",
+                        trace_only = TRACE_ONLY_FLAG,
+                        read = COMMAND_READ
+                    ))
+                }
+            }
+        }
+        match (command, explain_complexity) {
+            (FuzzerCommand::Read, _) | (_, false) => (),
+            _ => {
+                return Err(format!(
+                    "--{explain_complexity} can only be used with the {read} command",
+                    explain_complexity = EXPLAIN_COMPLEXITY_FLAG,
+                    read = COMMAND_READ
+                ))
+            }
+        }
+        if trace_only && explain_complexity {
+            return Err(format!(
+                "--{trace_only} and --{explain_complexity} cannot be used together",
+                trace_only = TRACE_ONLY_FLAG,
+                explain_complexity = EXPLAIN_COMPLEXITY_FLAG
+            ));
+        }
+        if let (FuzzerCommand::DiffCorpus, None) = (command, &other_corpus) {
+            return Err(format!(
+                "The corpus to compare with the input corpus must be given with --{other_corpus}",
+                other_corpus = OTHER_CORPUS_FLAG
+            ));
+        }
 
         let corpus_out: Option<PathBuf> = if !matches.opt_present(NO_OUT_CORPUS_FLAG) {
             matches
@@ -204,21 +872,388 @@ The command {c} is not supported. It can either be ‘{fuzz}’, ‘{tmin}’, o
             None
         };
 
+        let input_min_len = parse_optional_usize(&matches, INPUT_MIN_LEN_FLAG)?;
+        let input_max_len = parse_optional_usize(&matches, INPUT_MAX_LEN_FLAG)?;
+        if let (Some(min), Some(max)) = (input_min_len, input_max_len) {
+            if min > max {
+                return Err(format!(
+                    "--{min_len} ({min}) must not be greater than --{max_len} ({max})",
+                    min_len = INPUT_MIN_LEN_FLAG,
+                    min = min,
+                    max_len = INPUT_MAX_LEN_FLAG,
+                    max = max
+                ));
+            }
+        }
+
+        let minimize_timeout = parse_optional_usize(&matches, MINIMIZE_TIMEOUT_FLAG)?;
+        match (command, minimize_timeout) {
+            (FuzzerCommand::MinifyInput, _) | (FuzzerCommand::MinifyCorpus, _) | (_, None) => (),
+            _ => {
+                return Err(format!(
+                    "--{minimize_timeout} can only be used with the {tmin} and {cmin} commands",
+                    minimize_timeout = MINIMIZE_TIMEOUT_FLAG,
+                    tmin = COMMAND_MINIFY_INPUT,
+                    cmin = COMMAND_MINIFY_CORPUS
+                ))
+            }
+        }
+
+        let minimize_to_cplx = parse_optional_usize(&matches, MINIMIZE_TO_CPLX_FLAG)?;
+        match (command, minimize_to_cplx) {
+            (FuzzerCommand::MinifyInput, _) | (_, None) => (),
+            _ => {
+                return Err(format!(
+                    "--{minimize_to_cplx} can only be used with the {tmin} command",
+                    minimize_to_cplx = MINIMIZE_TO_CPLX_FLAG,
+                    tmin = COMMAND_MINIFY_INPUT
+                ))
+            }
+        }
+
+        let curate = parse_optional_usize(&matches, CURATE_FLAG)?;
+        match (command, curate) {
+            (FuzzerCommand::MinifyCorpus, _) | (_, None) => (),
+            _ => {
+                return Err(format!(
+                    "--{curate} can only be used with the {cmin} command",
+                    curate = CURATE_FLAG,
+                    cmin = COMMAND_MINIFY_CORPUS
+                ))
+            }
+        }
+
+        let report_slowest_inputs = parse_optional_usize(&matches, REPORT_SLOWEST_INPUTS_FLAG)?;
+        match (command, report_slowest_inputs) {
+            (_, None) => (),
+            (FuzzerCommand::Fuzz, Some(n)) if n > 0 => (),
+            (FuzzerCommand::Fuzz, Some(_)) => {
+                return Err(format!(
+                    "--{report_slowest_inputs} must be at least 1",
+                    report_slowest_inputs = REPORT_SLOWEST_INPUTS_FLAG
+                ))
+            }
+            _ => {
+                return Err(format!(
+                    "--{report_slowest_inputs} can only be used with the {fuzz} command",
+                    report_slowest_inputs = REPORT_SLOWEST_INPUTS_FLAG,
+                    fuzz = COMMAND_FUZZ
+                ))
+            }
+        }
+
+        let reload_corpus_interval = parse_optional_usize(&matches, RELOAD_CORPUS_INTERVAL_FLAG)?;
+        match (command, reload_corpus_interval, &corpus_in) {
+            (_, None, _) => (),
+            (FuzzerCommand::Fuzz, Some(_), Some(_)) => (),
+            (FuzzerCommand::Fuzz, Some(_), None) => {
+                return Err(format!(
+                    "An input corpus must be given to reload it with --{reload_corpus_interval}",
+                    reload_corpus_interval = RELOAD_CORPUS_INTERVAL_FLAG
+                ))
+            }
+            _ => {
+                return Err(format!(
+                    "--{reload_corpus_interval} can only be used with the {fuzz} command",
+                    reload_corpus_interval = RELOAD_CORPUS_INTERVAL_FLAG,
+                    fuzz = COMMAND_FUZZ
+                ))
+            }
+        }
+
+        let deterministic = matches.opt_present(DETERMINISTIC_FLAG);
+        let mut seed = parse_optional_usize(&matches, SEED_FLAG)?;
+        if deterministic {
+            // the outcome of a run must not depend on how fast it is
+            if minimize_timeout.is_some() || reload_corpus_interval.is_some() {
+                return Err(format!(
+                    "--{deterministic} cannot be used with --{minimize_timeout} or --{reload_corpus_interval}, which depend on the time",
+                    deterministic = DETERMINISTIC_FLAG,
+                    minimize_timeout = MINIMIZE_TIMEOUT_FLAG,
+                    reload_corpus_interval = RELOAD_CORPUS_INTERVAL_FLAG
+                ));
+            }
+            seed = seed.or(Some(0));
+        }
+
+        let metadata_out: Option<PathBuf> = matches
+            .opt_str(METADATA_OUT_FLAG)
+            .map(|x| x.parse::<PathBuf>().ok())
+            .flatten();
+
+        let target: Option<String> = matches.opt_str(TARGET_FLAG);
+
+        let features_in: Option<PathBuf> = matches
+            .opt_str(FEATURES_IN_FLAG)
+            .map(|x| x.parse::<PathBuf>().ok())
+            .flatten();
+        let features_out: Option<PathBuf> = matches
+            .opt_str(FEATURES_OUT_FLAG)
+            .map(|x| x.parse::<PathBuf>().ok())
+            .flatten();
+        match (command, &features_in, &features_out) {
+            (FuzzerCommand::Fuzz, _, _) | (_, None, None) => (),
+            _ => {
+                return Err(format!(
+                    "--{features_in} and --{features_out} can only be used with the {fuzz} command",
+                    features_in = FEATURES_IN_FLAG,
+                    features_out = FEATURES_OUT_FLAG,
+                    fuzz = COMMAND_FUZZ
+                ))
+            }
+        }
+
+        let coverage_out: Option<PathBuf> = matches
+            .opt_str(COVERAGE_OUT_FLAG)
+            .map(|x| x.parse::<PathBuf>().ok())
+            .flatten();
+        match (command, &coverage_out) {
+            (FuzzerCommand::Fuzz, _) | (_, None) => (),
+            _ => {
+                return Err(format!(
+                    "--{coverage_out} can only be used with the {fuzz} command",
+                    coverage_out = COVERAGE_OUT_FLAG,
+                    fuzz = COMMAND_FUZZ
+                ))
+            }
+        }
+
+        let edge_weights: Option<PathBuf> = matches
+            .opt_str(EDGE_WEIGHTS_FLAG)
+            .map(|x| x.parse::<PathBuf>().ok())
+            .flatten();
+        match (command, &edge_weights) {
+            (FuzzerCommand::Fuzz, _) | (_, None) => (),
+            _ => {
+                return Err(format!(
+                    "--{edge_weights} can only be used with the {fuzz} command",
+                    edge_weights = EDGE_WEIGHTS_FLAG,
+                    fuzz = COMMAND_FUZZ
+                ))
+            }
+        }
+
+        let max_total_corpus_bytes = parse_optional_usize(&matches, MAX_TOTAL_CORPUS_BYTES_FLAG)?;
+        match (command, max_total_corpus_bytes) {
+            (FuzzerCommand::Fuzz, _) | (_, None) => (),
+            _ => {
+                return Err(format!(
+                    "--{max_total_corpus_bytes} can only be used with the {fuzz} command",
+                    max_total_corpus_bytes = MAX_TOTAL_CORPUS_BYTES_FLAG,
+                    fuzz = COMMAND_FUZZ
+                ))
+            }
+        }
+
+        let save_all_inputs: Option<PathBuf> = matches
+            .opt_str(SAVE_ALL_INPUTS_FLAG)
+            .map(|x| x.parse::<PathBuf>().ok())
+            .flatten();
+        match (command, &save_all_inputs) {
+            (FuzzerCommand::Fuzz, _) | (_, None) => (),
+            _ => {
+                return Err(format!(
+                    "--{save_all_inputs} can only be used with the {fuzz} command",
+                    save_all_inputs = SAVE_ALL_INPUTS_FLAG,
+                    fuzz = COMMAND_FUZZ
+                ))
+            }
+        }
+
+        let replay: Option<PathBuf> = matches
+            .opt_str(REPLAY_FLAG)
+            .map(|x| x.parse::<PathBuf>().ok())
+            .flatten();
+        match (command, &replay) {
+            (_, None) => (),
+            (FuzzerCommand::Read, Some(_)) => {
+                if trace_only || explain_complexity {
+                    return Err(format!(
+                        "--{replay} cannot be used together with --{trace_only} or --{explain_complexity}",
+                        replay = REPLAY_FLAG,
+                        trace_only = TRACE_ONLY_FLAG,
+                        explain_complexity = EXPLAIN_COMPLEXITY_FLAG
+                    ));
+                }
+            }
+            _ => {
+                return Err(format!(
+                    "--{replay} can only be used with the {read} command",
+                    replay = REPLAY_FLAG,
+                    read = COMMAND_READ
+                ))
+            }
+        }
+
+        let energy_decay: Option<f64> = match matches.opt_str(ENERGY_DECAY_FLAG) {
+            Some(x) => match x.parse::<f64>() {
+                Ok(decay) if decay > 0.0 && decay <= 1.0 => Some(decay),
+                _ => {
+                    return Err(format!(
+                    "The value of --{energy_decay} must be a number greater than 0 and at most 1, but ‘{x}’ was given",
+                    energy_decay = ENERGY_DECAY_FLAG,
+                    x = x
+                ))
+                }
+            },
+            None => None,
+        };
+        match (command, energy_decay) {
+            (FuzzerCommand::Fuzz, _) | (_, None) => (),
+            _ => {
+                return Err(format!(
+                    "--{energy_decay} can only be used with the {fuzz} command",
+                    energy_decay = ENERGY_DECAY_FLAG,
+                    fuzz = COMMAND_FUZZ
+                ))
+            }
+        }
+
+        let crash_exitcode = parse_optional_exit_code(&matches, CRASH_EXITCODE_FLAG)?;
+        let timeout_exitcode = parse_optional_exit_code(&matches, TIMEOUT_EXITCODE_FLAG)?;
+
         let max_nbr_of_runs: usize = matches
             .opt_str(MAX_NBR_RUNS_FLAG)
             .map(|x| x.parse::<usize>().ok())
             .flatten()
-            .unwrap_or(core::usize::MAX);
+            .unwrap_or(defaults.max_nbr_of_runs);
 
         Ok(Self {
             command,
             max_nbr_of_runs,
             max_input_cplx,
             corpus_size,
+            verbosity,
+            fork,
+            print_pcs,
+            trace_only,
+            no_cmp_trace,
+            no_edge_trace,
+            explain_complexity,
+            adaptive_cplx,
+            keep_seeds,
+            tui,
+            save_all_crashes,
+            raw_corpus,
+            arbitrary_from_corpus,
+            crash_capture,
+            deterministic,
+            input_min_len,
+            input_max_len,
+            minimize_timeout,
+            minimize_to_cplx,
+            initial_cplx,
+            curate,
+            reload_corpus_interval,
+            energy_decay,
+            seed,
             input_file,
             corpus_in,
+            other_corpus,
             corpus_out,
             artifacts_folder,
+            metadata_out,
+            target,
+            features_in,
+            features_out,
+            coverage_out,
+            edge_weights,
+            max_total_corpus_bytes,
+            save_all_inputs,
+            replay,
+            crash_exitcode,
+            timeout_exitcode,
+            report_slowest_inputs,
         })
     }
 }
+
+/// Parses an exit code, which must fit in the 8 bits of the exit status of
+/// a process
+fn parse_optional_exit_code(matches: &getopts::Matches, flag: &str) -> Result<Option<i32>, String> {
+    match parse_optional_usize(matches, flag)? {
+        Some(code) if code > 255 => Err(format!(
+            "The value of --{flag} must be an exit code between 0 and 255, but ‘{code}’ was given",
+            flag = flag,
+            code = code
+        )),
+        code => Ok(code.map(|code| code as i32)),
+    }
+}
+
+/// Parses the value of a flag that expects an integer, if it is present
+fn parse_optional_usize(matches: &getopts::Matches, flag: &str) -> Result<Option<usize>, String> {
+    match matches.opt_str(flag) {
+        Some(x) => match x.parse::<usize>() {
+            Ok(len) => Ok(Some(len)),
+            Err(_) => Err(format!(
+                "The value of --{flag} must be a positive integer, but ‘{x}’ was given",
+                flag = flag,
+                x = x
+            )),
+        },
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str], defaults: DefaultArguments) -> CommandLineArguments {
+        let args: Vec<String> = args.iter().map(|&arg| arg.to_owned()).collect();
+        CommandLineArguments::from_parser(&options_parser(), &args, defaults).unwrap()
+    }
+
+    #[test]
+    fn test_command_defaults_are_read_from_the_given_defaults() {
+        let defaults = DefaultArguments {
+            corpus_size: 42,
+            tmin_max_nbr_of_runs: 7,
+            selftest_max_nbr_of_runs: 3,
+            max_nbr_of_runs: 100,
+            ..DEFAULT_ARGUMENTS
+        };
+        let cmin = parse(&["cmin"], defaults.clone());
+        assert_eq!(cmin.corpus_size, 42);
+        assert_eq!(cmin.max_nbr_of_runs, 100);
+
+        let tmin = parse(&["tmin", "--input-file", "input.json"], defaults.clone());
+        assert_eq!(tmin.max_nbr_of_runs, 7);
+
+        let selftest = parse(&["selftest"], defaults.clone());
+        assert_eq!(selftest.max_nbr_of_runs, 3);
+
+        let fuzz = parse(&["fuzz"], defaults.clone());
+        assert_eq!(fuzz.corpus_size, core::usize::MAX);
+        assert_eq!(fuzz.max_nbr_of_runs, 100);
+
+        // the command line overrides the defaults
+        let cmin = parse(&["cmin", "--corpus-size", "5"], defaults);
+        assert_eq!(cmin.corpus_size, 5);
+    }
+
+    #[test]
+    fn test_default_arguments_of_commands() {
+        assert_eq!(parse(&["cmin"], DEFAULT_ARGUMENTS).corpus_size, 10);
+        let tmin = parse(&["tmin", "--input-file", "input.json"], DEFAULT_ARGUMENTS);
+        assert_eq!(tmin.max_nbr_of_runs, 1_000_000);
+        assert_eq!(parse(&["fuzz"], DEFAULT_ARGUMENTS).max_nbr_of_runs, core::usize::MAX);
+    }
+
+    #[test]
+    fn test_replay_starts_from_the_input_file() {
+        let read = parse(
+            &["read", "--replay", "a.replay", "--input-file", "a.json"],
+            DEFAULT_ARGUMENTS,
+        );
+        assert_eq!(read.replay, Some(PathBuf::from("a.replay")));
+        assert_eq!(read.input_file, Some(PathBuf::from("a.json")));
+
+        let args: Vec<String> = ["read", "--replay", "a.replay", "--trace-only", "--input-file", "a.json"]
+            .iter()
+            .map(|&arg| arg.to_owned())
+            .collect();
+        assert!(CommandLineArguments::from_parser(&options_parser(), &args, DEFAULT_ARGUMENTS).is_err());
+    }
+}
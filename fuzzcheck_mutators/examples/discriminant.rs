@@ -0,0 +1,30 @@
+extern crate fuzzcheck_mutators;
+use fuzzcheck::Mutator;
+use fuzzcheck_mutators::discriminant::*;
+
+fn main() {
+    let m = DiscriminantMutator::new(3, true);
+    let mut x = 0;
+    let mut x_cache = m.cache_from_value(&x);
+    let mut x_step = m.mutation_step_from_value(&x);
+
+    let mut results: Vec<u8> = vec![];
+    for _ in 0..64 {
+        let token = if let Some(token) = m.mutate(&mut x, &mut x_cache, &mut x_step, 8.0) {
+            token
+        } else {
+            break;
+        };
+        results.push(x);
+        m.unmutate(&mut x, &mut x_cache, token);
+    }
+    println!("{:?}", results);
+
+    results.clear();
+
+    for i in 0..64 {
+        results.push(m.arbitrary(i, 8.0).0);
+    }
+
+    println!("{:?}", results);
+}
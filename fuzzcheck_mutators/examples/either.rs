@@ -45,7 +45,11 @@ fn main() {
 
     let mut results: Vec<E> = vec![];
     for _ in 0..130 {
-        let token = m.mutate(&mut x, &mut x_cache, &mut x_step, 8.0);
+        let token = if let Some(token) = m.mutate(&mut x, &mut x_cache, &mut x_step, 8.0) {
+            token
+        } else {
+            break;
+        };
         results.push(x.clone());
         m.unmutate(&mut x, &mut x_cache, token);
     }
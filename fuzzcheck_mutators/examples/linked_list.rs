@@ -0,0 +1,76 @@
+extern crate fuzzcheck_mutators;
+use fuzzcheck::Mutator;
+use fuzzcheck_mutators::boxed::*;
+use fuzzcheck_mutators::integer::*;
+use fuzzcheck_mutators::recursive::*;
+use fuzzcheck_mutators::tuples::*;
+
+#[derive(Clone, Debug)]
+struct Node {
+    value: u8,
+    next: Option<Box<Node>>,
+}
+impl TupleMap for Node {
+    type A = u8;
+    type B = Option<Box<Node>>;
+    type V = Self;
+
+    fn get_a(v: &Self) -> &u8 {
+        &v.value
+    }
+    fn get_b(v: &Self) -> &Option<Box<Node>> {
+        &v.next
+    }
+
+    fn get_a_mut(v: &mut Self) -> &mut u8 {
+        &mut v.value
+    }
+    fn get_b_mut(v: &mut Self) -> &mut Option<Box<Node>> {
+        &mut v.next
+    }
+
+    fn new(value: u8, next: Option<Box<Node>>) -> Self {
+        Self { value, next }
+    }
+
+    fn field_names() -> [&'static str; 2] {
+        ["value", "next"]
+    }
+}
+
+struct ListDef;
+impl RecursiveMutatorDef for ListDef {
+    type Value = Node;
+    type Mutator = Tuple2Mutator<Node, U8Mutator, OptionBoxMutator<RecurToMutator<Self>>>;
+}
+
+fn main() {
+    let m = RecursiveMutator::<ListDef>::new(|self_mutator| {
+        Tuple2Mutator::new(U8Mutator::default(), OptionBoxMutator::new(self_mutator))
+    });
+
+    let (mut x, mut x_cache) = m.arbitrary(1, 50.0);
+    let mut x_step = m.mutation_step_from_value(&x);
+    println!("{:?}", x);
+
+    let mut results: Vec<Node> = vec![];
+    for _ in 0..30 {
+        let token = if let Some(token) = m.mutate(&mut x, &mut x_cache, &mut x_step, 50.0) {
+            token
+        } else {
+            break;
+        };
+        results.push(x.clone());
+        m.unmutate(&mut x, &mut x_cache, token);
+    }
+    println!("{:?}", results);
+
+    results.clear();
+
+    for i in 0..30 {
+        let el = m.arbitrary(i, 50.0);
+        results.push(el.0);
+    }
+
+    println!("{:?}", results);
+}
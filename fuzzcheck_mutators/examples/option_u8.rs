@@ -13,7 +13,11 @@ fn main() {
 
     let mut results: Vec<Option<u8>> = vec![];
     for _ in 0..30 {
-        let token = m.mutate(&mut x, &mut x_cache, &mut x_step, 8.0);
+        let token = if let Some(token) = m.mutate(&mut x, &mut x_cache, &mut x_step, 8.0) {
+            token
+        } else {
+            break;
+        };
         results.push(x);
         m.unmutate(&mut x, &mut x_cache, token);
     }
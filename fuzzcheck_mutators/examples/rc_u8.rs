@@ -0,0 +1,27 @@
+extern crate fuzzcheck_mutators;
+use fuzzcheck::Mutator;
+use fuzzcheck_mutators::integer::*;
+use fuzzcheck_mutators::rc::*;
+
+use std::rc::Rc;
+
+type F = RcMutator<U8Mutator>;
+
+fn main() {
+    let m = F::default();
+    let mut x = Rc::new(10);
+    let mut x_cache = m.cache_from_value(&x);
+    let mut x_step = m.mutation_step_from_value(&x);
+
+    let mut results: Vec<Rc<u8>> = vec![];
+    for _ in 0..30 {
+        let token = if let Some(token) = m.mutate(&mut x, &mut x_cache, &mut x_step, 8.0) {
+            token
+        } else {
+            break;
+        };
+        results.push(x.clone());
+        m.unmutate(&mut x, &mut x_cache, token);
+    }
+    println!("{:?}", results);
+}
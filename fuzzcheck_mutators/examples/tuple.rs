@@ -30,6 +30,10 @@ impl TupleMap for S {
     fn new(a: u8, b: u8) -> Self {
         Self { a, b }
     }
+
+    fn field_names() -> [&'static str; 2] {
+        ["a", "b"]
+    }
 }
 
 type F = Tuple2Mutator<S, U8Mutator, U8Mutator>;
@@ -39,10 +43,15 @@ fn main() {
     let mut x = S { a: 10, b: 10 };
     let mut x_cache = m.cache_from_value(&x);
     let mut x_step = m.mutation_step_from_value(&x);
+    println!("{:?}", m.complexity_breakdown(&x, &x_cache));
 
     let mut results: Vec<S> = vec![];
     for _ in 0..30 {
-        let token = m.mutate(&mut x, &mut x_cache, &mut x_step, 8.0);
+        let token = if let Some(token) = m.mutate(&mut x, &mut x_cache, &mut x_step, 8.0) {
+            token
+        } else {
+            break;
+        };
         results.push(x.clone());
         m.unmutate(&mut x, &mut x_cache, token);
     }
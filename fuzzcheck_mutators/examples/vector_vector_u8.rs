@@ -13,7 +13,11 @@ fn main() {
 
     let mut results: Vec<Vec<Vec<u8>>> = vec![];
     for _ in 0..100 {
-        let token = m.mutate(&mut x, &mut x_cache, &mut x_step, 100.0);
+        let token = if let Some(token) = m.mutate(&mut x, &mut x_cache, &mut x_step, 100.0) {
+            token
+        } else {
+            break;
+        };
         results.push(x.clone());
 
         m.unmutate(&mut x, &mut x_cache, token);
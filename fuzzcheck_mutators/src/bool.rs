@@ -1,6 +1,8 @@
 extern crate fuzzcheck;
 use fuzzcheck::Mutator;
 
+use crate::DefaultMutator;
+
 #[derive(Clone)]
 pub struct BoolMutator {}
 
@@ -10,16 +12,37 @@ impl Default for BoolMutator {
     }
 }
 
+impl DefaultMutator for bool {
+    type Mutator = BoolMutator;
+    fn default_mutator() -> Self::Mutator {
+        BoolMutator::default()
+    }
+}
+
+/// Remembers which values were already produced, so that both `false` and
+/// `true` are tried before the mutator reports that it is exhausted
+pub struct BoolMutationStep {
+    seen: [bool; 2],
+}
+
+impl BoolMutationStep {
+    fn new(value: bool) -> Self {
+        let mut step = BoolMutationStep { seen: [false; 2] };
+        step.seen[value as usize] = true;
+        step
+    }
+}
+
 impl Mutator for BoolMutator {
     type Value = bool;
     type Cache = ();
-    type MutationStep = bool; // true if it has been mutated, false otherwise
+    type MutationStep = BoolMutationStep;
     type UnmutateToken = ();
 
     fn cache_from_value(&self, _value: &Self::Value) -> Self::Cache {}
 
-    fn mutation_step_from_value(&self, _value: &Self::Value) -> Self::MutationStep {
-        false
+    fn mutation_step_from_value(&self, value: &Self::Value) -> Self::MutationStep {
+        BoolMutationStep::new(*value)
     }
 
     fn arbitrary(&self, seed: usize, _max_cplx: f64) -> (Self::Value, Self::Cache) {
@@ -45,12 +68,47 @@ impl Mutator for BoolMutator {
         _cache: &mut Self::Cache,
         step: &mut Self::MutationStep,
         _max_cplx: f64,
-    ) -> Self::UnmutateToken {
-        *value = !*value;
-        *step = true;
+    ) -> Option<Self::UnmutateToken> {
+        let new_value = !*value;
+        if step.seen[new_value as usize] {
+            // both values were already produced
+            return None;
+        }
+        step.seen[new_value as usize] = true;
+        *value = new_value;
+        Some(())
     }
 
     fn unmutate(&self, value: &mut Self::Value, _cache: &mut Self::Cache, _t: Self::UnmutateToken) {
         *value = !*value;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bool_mutator_yields_both_values() {
+        let m = BoolMutator::default();
+        for seed in 0..2 {
+            let (mut x, mut x_cache) = m.arbitrary(seed, 1.0);
+            let mut x_step = m.mutation_step_from_value(&x);
+            let mut results = vec![x];
+            for _ in 0..2 {
+                if let Some(token) = m.mutate(&mut x, &mut x_cache, &mut x_step, 1.0) {
+                    results.push(x);
+                    m.unmutate(&mut x, &mut x_cache, token);
+                }
+            }
+            assert!(results.contains(&false) && results.contains(&true));
+            assert!(m.mutate(&mut x, &mut x_cache, &mut x_step, 1.0).is_none());
+
+            // keeping the mutation does not make the mutator produce the same values again
+            let (mut x, mut x_cache) = m.arbitrary(seed, 1.0);
+            let mut x_step = m.mutation_step_from_value(&x);
+            assert!(m.mutate(&mut x, &mut x_cache, &mut x_step, 1.0).is_some());
+            assert!(m.mutate(&mut x, &mut x_cache, &mut x_step, 1.0).is_none());
+        }
+    }
+}
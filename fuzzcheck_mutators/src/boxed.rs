@@ -0,0 +1,334 @@
+//! Mutators for boxed values, including the `Option<Box<T>>` links of
+//! recursive types such as linked lists and trees.
+
+extern crate fuzzcheck;
+use fuzzcheck::Mutator;
+
+use crate::option::OptionMutator;
+use crate::DefaultMutator;
+
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+/// A mutator for a `Box` that forwards to the mutator of its contents.
+pub struct BoxMutator<M: Mutator> {
+    m: M,
+}
+impl<M: Mutator> BoxMutator<M> {
+    pub fn new(m: M) -> Self {
+        Self { m }
+    }
+}
+impl<M: Mutator> Default for BoxMutator<M>
+where
+    M: Default,
+{
+    fn default() -> Self {
+        Self::new(M::default())
+    }
+}
+
+impl<T: DefaultMutator> DefaultMutator for Box<T> {
+    type Mutator = BoxMutator<T::Mutator>;
+    fn default_mutator() -> Self::Mutator {
+        BoxMutator::new(T::default_mutator())
+    }
+}
+
+impl<M: Mutator> Mutator for BoxMutator<M> {
+    type Value = Box<M::Value>;
+    type Cache = M::Cache;
+    type MutationStep = M::MutationStep;
+    type UnmutateToken = M::UnmutateToken;
+
+    fn cache_from_value(&self, value: &Self::Value) -> Self::Cache {
+        self.m.cache_from_value(value)
+    }
+    fn mutation_step_from_value(&self, value: &Self::Value) -> Self::MutationStep {
+        self.m.mutation_step_from_value(value)
+    }
+
+    fn seeded_mutation_step_from_value(&self, value: &Self::Value, seed: u64) -> Self::MutationStep {
+        self.m.seeded_mutation_step_from_value(value, seed)
+    }
+
+    fn max_complexity(&self) -> f64 {
+        self.m.max_complexity()
+    }
+    fn min_complexity(&self) -> f64 {
+        self.m.min_complexity()
+    }
+    fn complexity(&self, value: &Self::Value, cache: &Self::Cache) -> f64 {
+        self.m.complexity(value, cache)
+    }
+
+    fn arbitrary(&self, seed: usize, max_cplx: f64) -> (Self::Value, Self::Cache) {
+        let (value, cache) = self.m.arbitrary(seed, max_cplx);
+        (Box::new(value), cache)
+    }
+
+    fn mutate(
+        &self,
+        value: &mut Self::Value,
+        cache: &mut Self::Cache,
+        step: &mut Self::MutationStep,
+        max_cplx: f64,
+    ) -> Option<Self::UnmutateToken> {
+        self.m.mutate(value, cache, step, max_cplx)
+    }
+
+    fn unmutate(&self, value: &mut Self::Value, cache: &mut Self::Cache, t: Self::UnmutateToken) {
+        self.m.unmutate(value, cache, t)
+    }
+
+    fn crossover(
+        &self,
+        a: &Self::Value,
+        a_cache: &Self::Cache,
+        b: &Self::Value,
+        b_cache: &Self::Cache,
+        seed: usize,
+        max_cplx: f64,
+    ) -> Option<(Self::Value, Self::Cache)> {
+        let (value, cache) = self.m.crossover(a, a_cache, b, b_cache, seed, max_cplx)?;
+        Some((Box::new(value), cache))
+    }
+
+    fn record_mutation_outcome(&self, value: &Self::Value, token: &Self::UnmutateToken, nbr_new_features: usize) {
+        self.m.record_mutation_outcome(value, token, nbr_new_features)
+    }
+
+    fn describe_value(&self, value: &Self::Value) -> Option<String> {
+        self.m.describe_value(value)
+    }
+}
+
+/**
+ * A mutator for the `Option<Box<T>>` link of a recursive type, where `None`
+ * is the base case of the recursion.
+ *
+ * It behaves like `OptionMutator<BoxMutator<M>>`, except that `arbitrary`
+ * becomes more likely to return `None` as the complexity budget gets closer
+ * to the minimum complexity of the boxed value. So a recursive value
+ * generated from a large budget does not always use all of it, and the
+ * recursion stops for certain once the budget cannot fit the boxed value.
+ */
+pub struct OptionBoxMutator<M: Mutator> {
+    m: OptionMutator<BoxMutator<M>>,
+}
+impl<M: Mutator> OptionBoxMutator<M> {
+    pub fn new(m: M) -> Self {
+        Self {
+            m: OptionMutator::new(BoxMutator::new(m)),
+        }
+    }
+}
+impl<M: Mutator> Default for OptionBoxMutator<M>
+where
+    M: Default,
+{
+    fn default() -> Self {
+        Self::new(M::default())
+    }
+}
+
+impl<M: Mutator> Mutator for OptionBoxMutator<M> {
+    type Value = <OptionMutator<BoxMutator<M>> as Mutator>::Value;
+    type Cache = <OptionMutator<BoxMutator<M>> as Mutator>::Cache;
+    type MutationStep = <OptionMutator<BoxMutator<M>> as Mutator>::MutationStep;
+    type UnmutateToken = <OptionMutator<BoxMutator<M>> as Mutator>::UnmutateToken;
+
+    fn cache_from_value(&self, value: &Self::Value) -> Self::Cache {
+        self.m.cache_from_value(value)
+    }
+    fn mutation_step_from_value(&self, value: &Self::Value) -> Self::MutationStep {
+        self.m.mutation_step_from_value(value)
+    }
+
+    fn seeded_mutation_step_from_value(&self, value: &Self::Value, seed: u64) -> Self::MutationStep {
+        self.m.seeded_mutation_step_from_value(value, seed)
+    }
+
+    fn max_complexity(&self) -> f64 {
+        self.m.max_complexity()
+    }
+    fn min_complexity(&self) -> f64 {
+        self.m.min_complexity()
+    }
+    fn complexity(&self, value: &Self::Value, cache: &Self::Cache) -> f64 {
+        self.m.complexity(value, cache)
+    }
+
+    fn arbitrary(&self, seed: usize, max_cplx: f64) -> (Self::Value, Self::Cache) {
+        // 1.0 is the complexity of the option itself
+        let inner_max_cplx = max_cplx - 1.0;
+        let inner_min_cplx = self.m.inner().min_complexity();
+        if seed == 0 || inner_min_cplx > inner_max_cplx {
+            return (None, None);
+        }
+        // the probability of choosing Some goes from 1 to 0 as the budget
+        // shrinks towards the minimum complexity of the boxed value
+        let mut rng = SmallRng::seed_from_u64(seed as u64);
+        if rng.gen::<f64>() * inner_max_cplx < inner_min_cplx {
+            return (None, None);
+        }
+        self.m.arbitrary(seed, max_cplx)
+    }
+
+    fn mutate(
+        &self,
+        value: &mut Self::Value,
+        cache: &mut Self::Cache,
+        step: &mut Self::MutationStep,
+        max_cplx: f64,
+    ) -> Option<Self::UnmutateToken> {
+        self.m.mutate(value, cache, step, max_cplx)
+    }
+
+    fn unmutate(&self, value: &mut Self::Value, cache: &mut Self::Cache, t: Self::UnmutateToken) {
+        self.m.unmutate(value, cache, t)
+    }
+
+    fn crossover(
+        &self,
+        a: &Self::Value,
+        a_cache: &Self::Cache,
+        b: &Self::Value,
+        b_cache: &Self::Cache,
+        seed: usize,
+        max_cplx: f64,
+    ) -> Option<(Self::Value, Self::Cache)> {
+        self.m.crossover(a, a_cache, b, b_cache, seed, max_cplx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integer::*;
+    use crate::recursive::*;
+    use crate::tuples::*;
+
+    #[derive(Clone, Debug)]
+    struct Node {
+        value: u8,
+        next: Option<Box<Node>>,
+    }
+
+    impl Node {
+        fn len(&self) -> usize {
+            1 + self.next.as_ref().map_or(0, |next| next.len())
+        }
+    }
+
+    impl TupleMap for Node {
+        type A = u8;
+        type B = Option<Box<Node>>;
+        type V = Self;
+
+        fn get_a(v: &Self) -> &u8 {
+            &v.value
+        }
+        fn get_b(v: &Self) -> &Option<Box<Node>> {
+            &v.next
+        }
+        fn get_a_mut(v: &mut Self) -> &mut u8 {
+            &mut v.value
+        }
+        fn get_b_mut(v: &mut Self) -> &mut Option<Box<Node>> {
+            &mut v.next
+        }
+        fn new(value: u8, next: Option<Box<Node>>) -> Self {
+            Self { value, next }
+        }
+    }
+
+    struct ListDef;
+    impl RecursiveMutatorDef for ListDef {
+        type Value = Node;
+        type Mutator = Tuple2Mutator<Node, U8Mutator, OptionBoxMutator<RecurToMutator<Self>>>;
+    }
+
+    fn list_mutator() -> RecursiveMutator<ListDef> {
+        RecursiveMutator::new(|self_mutator| {
+            Tuple2Mutator::new(U8Mutator::default(), OptionBoxMutator::new(self_mutator))
+        })
+    }
+
+    #[test]
+    fn test_option_box_prefers_none_with_small_budget() {
+        let m = OptionBoxMutator::new(U8Mutator::default());
+        // the budget cannot fit the boxed value
+        for seed in 0..100 {
+            assert!(m.arbitrary(seed, 8.5).0.is_none());
+        }
+        let count_some = |max_cplx: f64| {
+            (0..1000)
+                .filter(|&seed| m.arbitrary(seed, max_cplx).0.is_some())
+                .count()
+        };
+        assert!(count_some(12.0) < count_some(100.0));
+        assert!(count_some(100.0) > 0);
+    }
+
+    #[test]
+    fn test_crossover_of_linked_lists() {
+        let m = list_mutator();
+        let list = |values: &[u8]| {
+            values
+                .iter()
+                .rev()
+                .fold(None, |next, &value| Some(Box::new(Node { value, next })))
+        };
+        let (a, b) = (*list(&[1; 4]).unwrap(), *list(&[2; 6]).unwrap());
+        let (a_cache, b_cache) = (m.cache_from_value(&a), m.cache_from_value(&b));
+        let values = |mut node: &Node| {
+            let mut values = vec![node.value];
+            while let Some(next) = &node.next {
+                node = next;
+                values.push(node.value);
+            }
+            values
+        };
+        let mut nbr_mixed = 0;
+        for seed in 0..100 {
+            let (x, x_cache) = m.crossover(&a, &a_cache, &b, &b_cache, seed, 200.0).unwrap();
+            assert_eq!(m.complexity(&x, &x_cache), m.complexity(&x, &m.cache_from_value(&x)));
+            // the nodes at the same position are combined, so the list ends
+            // like one of the two lists
+            assert!(x.len() == 4 || x.len() == 6);
+            let values = values(&x);
+            if values.contains(&1) && values.contains(&2) {
+                nbr_mixed += 1;
+            }
+        }
+        assert!(nbr_mixed > 0);
+    }
+
+    #[test]
+    fn test_linked_list_mutator() {
+        let m = list_mutator();
+
+        // a single node is 8.0 for the u8 and 1.0 for the None
+        assert_eq!(m.min_complexity(), 9.0);
+
+        for max_cplx in [0.0, 10.0, 50.0, 200.0].iter() {
+            for seed in 0..100 {
+                let (x, x_cache) = m.arbitrary(seed, *max_cplx);
+                // each node spends at least 1.0 of the complexity budget
+                assert!(x.len() as f64 <= max_cplx.max(1.0));
+                assert_eq!(m.complexity(&x, &x_cache), m.complexity(&x, &m.cache_from_value(&x)));
+            }
+        }
+
+        let (mut x, mut x_cache) = m.arbitrary(1, 100.0);
+        let mut x_step = m.mutation_step_from_value(&x);
+        let cplx = m.complexity(&x, &x_cache);
+        for _ in 0..1000 {
+            let token = m.mutate(&mut x, &mut x_cache, &mut x_step, 200.0).unwrap();
+            assert_eq!(m.complexity(&x, &x_cache), m.complexity(&x, &m.cache_from_value(&x)));
+            m.unmutate(&mut x, &mut x_cache, token);
+            assert_eq!(m.complexity(&x, &x_cache), cplx);
+        }
+    }
+}
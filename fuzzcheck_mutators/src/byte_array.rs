@@ -0,0 +1,233 @@
+//! A mutator for fixed-size byte arrays, such as keys, nonces, or hashes.
+//!
+//! Unlike a mutator built from one mutator per byte, it knows about patterns
+//! that are common in binary data, such as all-zero or all-0xFF arrays, and
+//! mutates the array bit by bit.
+
+extern crate fuzzcheck;
+use fuzzcheck::Mutator;
+
+use crate::DefaultMutator;
+
+extern crate rand;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use std::marker::PhantomData;
+
+/// The values that each byte is set to after all bits were flipped
+const INTERESTING_BYTES: &[u8] = &[0x00, 0xFF, 0x01, 0x7F, 0x80, 0xFE];
+
+/// The number of patterns that fill the whole array: all-zero, all-0xFF,
+/// and the two alternating patterns
+const NBR_FILL_PATTERNS: usize = 4;
+
+/// A mutator for byte arrays of type `A`, such as `[u8; 32]`.
+///
+/// The complexity of an array is always 8 per byte, like that of a `u8`.
+pub struct ByteArrayMutator<A> {
+    len: usize,
+    phantom: PhantomData<A>,
+}
+impl<A> ByteArrayMutator<A>
+where
+    A: AsRef<[u8]> + AsMut<[u8]> + Default + Clone,
+{
+    pub fn new() -> Self {
+        Self {
+            len: A::default().as_ref().len(),
+            phantom: PhantomData,
+        }
+    }
+}
+impl<A> Default for ByteArrayMutator<A>
+where
+    A: AsRef<[u8]> + AsMut<[u8]> + Default + Clone,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+macro_rules! impl_default_mutator_for_byte_arrays {
+    ($($len:expr)*) => {
+        $(
+            impl DefaultMutator for [u8; $len] {
+                type Mutator = ByteArrayMutator<[u8; $len]>;
+                fn default_mutator() -> Self::Mutator {
+                    ByteArrayMutator::new()
+                }
+            }
+        )*
+    };
+}
+impl_default_mutator_for_byte_arrays!(
+    1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31 32
+);
+
+pub enum UnmutateByteArrayToken<A> {
+    Replace(A),
+    Byte(usize, u8),
+}
+
+/// Fills the array with the given fill pattern, or sets a single bit if
+/// `pattern` is greater than the number of fill patterns
+fn write_pattern(bytes: &mut [u8], pattern: usize) {
+    let fill = match pattern {
+        0 => 0x00,
+        1 => 0xFF,
+        2 => 0x55,
+        3 => 0xAA,
+        _ => {
+            let bit = pattern - NBR_FILL_PATTERNS;
+            for b in bytes.iter_mut() {
+                *b = 0;
+            }
+            bytes[bit / 8] = 1 << (bit % 8);
+            return;
+        }
+    };
+    for b in bytes.iter_mut() {
+        *b = fill;
+    }
+}
+
+impl<A> ByteArrayMutator<A>
+where
+    A: AsRef<[u8]> + AsMut<[u8]> + Default + Clone,
+{
+    /// The number of patterns, including one array with a single bit set for each bit
+    fn nbr_patterns(&self) -> usize {
+        NBR_FILL_PATTERNS + 8 * self.len
+    }
+}
+
+impl<A> Mutator for ByteArrayMutator<A>
+where
+    A: AsRef<[u8]> + AsMut<[u8]> + Default + Clone,
+{
+    type Value = A;
+    type Cache = ();
+    /// The index of the next mutation: first the fill patterns, then a flip
+    /// of each bit, then each interesting byte at each position
+    type MutationStep = usize;
+    type UnmutateToken = UnmutateByteArrayToken<A>;
+
+    fn cache_from_value(&self, _value: &Self::Value) -> Self::Cache {}
+    fn mutation_step_from_value(&self, _value: &Self::Value) -> Self::MutationStep {
+        0
+    }
+
+    fn arbitrary(&self, seed: usize, _max_cplx: f64) -> (Self::Value, Self::Cache) {
+        let mut value = A::default();
+        if self.len == 0 {
+            return (value, ());
+        }
+        if seed < self.nbr_patterns() {
+            write_pattern(value.as_mut(), seed);
+        } else {
+            let mut rng = SmallRng::seed_from_u64(seed as u64);
+            for b in value.as_mut().iter_mut() {
+                *b = rng.gen();
+            }
+        }
+        (value, ())
+    }
+
+    fn max_complexity(&self) -> f64 {
+        8.0 * self.len as f64
+    }
+
+    fn min_complexity(&self) -> f64 {
+        8.0 * self.len as f64
+    }
+
+    fn complexity(&self, _value: &Self::Value, _cache: &Self::Cache) -> f64 {
+        8.0 * self.len as f64
+    }
+
+    fn mutate(
+        &self,
+        value: &mut Self::Value,
+        _cache: &mut Self::Cache,
+        step: &mut Self::MutationStep,
+        _max_cplx: f64,
+    ) -> Option<Self::UnmutateToken> {
+        let nbr_bit_flips = 8 * self.len;
+        let nbr_byte_changes = INTERESTING_BYTES.len() * self.len;
+        loop {
+            let current_step = *step;
+            *step += 1;
+            if current_step < NBR_FILL_PATTERNS {
+                let mut new_value = value.clone();
+                write_pattern(new_value.as_mut(), current_step);
+                if new_value.as_ref() == value.as_ref() {
+                    // the value already has that pattern, try the next mutation
+                    continue;
+                }
+                return Some(UnmutateByteArrayToken::Replace(std::mem::replace(value, new_value)));
+            }
+            let current_step = current_step - NBR_FILL_PATTERNS;
+            if current_step < nbr_bit_flips {
+                let idx = current_step / 8;
+                let bytes = value.as_mut();
+                let old_byte = bytes[idx];
+                bytes[idx] ^= 1 << (current_step % 8);
+                return Some(UnmutateByteArrayToken::Byte(idx, old_byte));
+            }
+            let current_step = current_step - nbr_bit_flips;
+            if current_step < nbr_byte_changes {
+                let idx = current_step / INTERESTING_BYTES.len();
+                let new_byte = INTERESTING_BYTES[current_step % INTERESTING_BYTES.len()];
+                let bytes = value.as_mut();
+                let old_byte = bytes[idx];
+                if old_byte == new_byte {
+                    continue;
+                }
+                bytes[idx] = new_byte;
+                return Some(UnmutateByteArrayToken::Byte(idx, old_byte));
+            }
+            // all mutations were tried
+            return None;
+        }
+    }
+
+    fn unmutate(&self, value: &mut Self::Value, _cache: &mut Self::Cache, t: Self::UnmutateToken) {
+        match t {
+            UnmutateByteArrayToken::Replace(old_value) => *value = old_value,
+            UnmutateByteArrayToken::Byte(idx, old_byte) => value.as_mut()[idx] = old_byte,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_byte_array_mutator() {
+        let m = ByteArrayMutator::<[u8; 4]>::default();
+
+        let patterns: Vec<[u8; 4]> = (0..m.nbr_patterns()).map(|seed| m.arbitrary(seed, 32.0).0).collect();
+        assert!(patterns.contains(&[0x00; 4]));
+        assert!(patterns.contains(&[0xFF; 4]));
+        assert!(patterns.contains(&[0x55; 4]));
+        assert!(patterns.contains(&[0x00, 0x00, 0x00, 0x80]));
+
+        for seed in 0..100 {
+            let (mut x, mut x_cache) = m.arbitrary(seed, 32.0);
+            let original = x;
+            let mut x_step = m.mutation_step_from_value(&x);
+            let mut nbr_mutations = 0;
+            while let Some(token) = m.mutate(&mut x, &mut x_cache, &mut x_step, 32.0) {
+                assert_ne!(x, original);
+                assert_eq!(m.complexity(&x, &x_cache), 32.0);
+                m.unmutate(&mut x, &mut x_cache, token);
+                assert_eq!(x, original);
+                nbr_mutations += 1;
+            }
+            assert!(nbr_mutations >= 8 * 4);
+            assert!(nbr_mutations <= NBR_FILL_PATTERNS + 8 * 4 + INTERESTING_BYTES.len() * 4);
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//! A mutator for `char`.
+//!
+//! Chars are mutated through their index among all the unicode scalar values,
+//! which skips the surrogates. Like the integer mutators, the first mutations
+//! of a char move it by 1 to 4, and the following ones go through the other
+//! chars, starting with the ones furthest from it.
+
+extern crate fuzzcheck;
+use fuzzcheck::Mutator;
+
+use crate::{arbitrary_binary, DefaultMutator};
+
+/// The number of mutations that move the char by 1 to 4, which are tried
+/// before the other chars
+const NBR_NUDGES: u64 = 8;
+
+const SURROGATES_START: u32 = 0xD800;
+const NBR_SURROGATES: u32 = 0x800;
+/// The number of unicode scalar values
+const NBR_CHARS: u32 = 0x11_0000 - NBR_SURROGATES;
+
+fn char_index(c: char) -> u32 {
+    let c = c as u32;
+    if c < SURROGATES_START {
+        c
+    } else {
+        c - NBR_SURROGATES
+    }
+}
+
+fn char_at(index: u32) -> char {
+    let c = if index < SURROGATES_START {
+        index
+    } else {
+        index + NBR_SURROGATES
+    };
+    std::char::from_u32(c).unwrap()
+}
+
+#[derive(Clone, Default)]
+pub struct CharMutator {}
+
+impl DefaultMutator for char {
+    type Mutator = CharMutator;
+    fn default_mutator() -> Self::Mutator {
+        CharMutator::default()
+    }
+}
+
+impl Mutator for CharMutator {
+    type Value = char;
+    type Cache = ();
+    type MutationStep = u64; // mutation step
+    type UnmutateToken = char; // old value
+
+    fn cache_from_value(&self, _value: &Self::Value) -> Self::Cache {}
+    fn mutation_step_from_value(&self, _value: &Self::Value) -> Self::MutationStep {
+        0
+    }
+
+    fn arbitrary(&self, seed: usize, _max_cplx: f64) -> (Self::Value, Self::Cache) {
+        // half of the arbitrary chars are ASCII, which most parsers care about the most
+        let value = if seed % 2 == 0 {
+            char_at(((seed / 2) % 128) as u32)
+        } else {
+            char_at(((seed / 2) % NBR_CHARS as usize) as u32)
+        };
+        (value, ())
+    }
+
+    fn max_complexity(&self) -> f64 {
+        32.0
+    }
+
+    fn min_complexity(&self) -> f64 {
+        32.0
+    }
+
+    fn complexity(&self, _value: &Self::Value, _cache: &Self::Cache) -> f64 {
+        32.0
+    }
+
+    fn mutate(
+        &self,
+        value: &mut Self::Value,
+        _cache: &mut Self::Cache,
+        step: &mut Self::MutationStep,
+        _max_cplx: f64,
+    ) -> Option<Self::UnmutateToken> {
+        if *step >= NBR_NUDGES + NBR_CHARS as u64 - 1 {
+            return None;
+        }
+        let token = *value;
+        let index = char_index(*value);
+        let offset = if *step < NBR_NUDGES {
+            let nudge = (*step / 2 + 1) as u32;
+            if *step % 2 == 0 {
+                nudge
+            } else {
+                NBR_CHARS - nudge
+            }
+        } else {
+            arbitrary_binary(1, NBR_CHARS as usize - 1, (*step - NBR_NUDGES) as usize) as u32
+        };
+        *value = char_at((index + offset) % NBR_CHARS);
+        *step += 1;
+
+        Some(token)
+    }
+
+    fn unmutate(&self, value: &mut Self::Value, _cache: &mut Self::Cache, t: Self::UnmutateToken) {
+        *value = t;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_char_mutator() {
+        let m = CharMutator::default();
+        assert_eq!(m.max_complexity(), 32.0);
+        let mut nbr_ascii = 0;
+        for seed in 0..20 {
+            let (mut x, mut x_cache) = m.arbitrary(seed * 1001, 32.0);
+            if x.is_ascii() {
+                nbr_ascii += 1;
+            }
+            let mut x_step = m.mutation_step_from_value(&x);
+            let mut results = HashSet::new();
+            for i in 0..1000 {
+                let token = m.mutate(&mut x, &mut x_cache, &mut x_step, 32.0).unwrap();
+                if i < NBR_NUDGES {
+                    // the first mutations move the char by 1, 2, 3, then 4
+                    let nudge = (i / 2 + 1) as u32;
+                    let expected = if i % 2 == 0 {
+                        char_index(token) + nudge
+                    } else {
+                        char_index(token) + NBR_CHARS - nudge
+                    };
+                    assert_eq!(x, char_at(expected % NBR_CHARS));
+                } else {
+                    // the following ones never produce the same char twice
+                    assert!(results.insert(x));
+                }
+                assert_ne!(x, token);
+                m.unmutate(&mut x, &mut x_cache, token);
+                assert_eq!(x, token);
+            }
+        }
+        assert!(nbr_ascii >= 10);
+    }
+
+    #[test]
+    fn test_char_index_skips_the_surrogates() {
+        assert_eq!(char_at(SURROGATES_START - 1), '\u{D7FF}');
+        assert_eq!(char_at(SURROGATES_START), '\u{E000}');
+        assert_eq!(char_at(NBR_CHARS - 1), std::char::MAX);
+        for &c in &['a', '\u{D7FF}', '\u{E000}', std::char::MAX] {
+            assert_eq!(char_at(char_index(c)), c);
+        }
+    }
+}
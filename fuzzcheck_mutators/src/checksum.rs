@@ -0,0 +1,340 @@
+//! A mutator for payloads followed by a checksum, which is kept consistent
+//! with the payload so that the tested code gets past its validation.
+
+extern crate fuzzcheck;
+use fuzzcheck::Mutator;
+
+extern crate rand;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use crate::derived::{DerivedField, DerivedFieldCache, DerivedFieldMutator, UnmutateDerivedFieldToken};
+use crate::step_rng_seed;
+
+/// The default probability that a mutation or an arbitrary value has a
+/// checksum that does not match its payload
+const DEFAULT_CORRUPT_CHECKSUM_PROBABILITY: f64 = 0.02;
+
+/// A function computing the checksum of a payload
+pub trait Checksum {
+    fn checksum(&self, payload: &[u8]) -> u32;
+}
+
+impl<F> Checksum for F
+where
+    F: Fn(&[u8]) -> u32,
+{
+    fn checksum(&self, payload: &[u8]) -> u32 {
+        self(payload)
+    }
+}
+
+/// The CRC-32 used by zlib, PNG, and Ethernet, among others
+#[derive(Clone, Copy, Default)]
+pub struct Crc32;
+
+impl Checksum for Crc32 {
+    fn checksum(&self, payload: &[u8]) -> u32 {
+        let mut crc = !0u32;
+        for &byte in payload {
+            crc ^= byte as u32;
+            for _ in 0..8 {
+                let mask = (crc & 1).wrapping_neg();
+                crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+            }
+        }
+        !crc
+    }
+}
+
+/// A payload along with its checksum.
+///
+/// The checksum usually matches the payload, but a mutator can set it to
+/// another value to test how the tested code validates its input.
+#[derive(Clone, Debug, PartialEq, Hash)]
+pub struct Checksummed {
+    pub payload: Vec<u8>,
+    pub checksum: u32,
+}
+
+impl Checksummed {
+    /// The payload followed by the checksum in big-endian
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.payload.len() + 4);
+        bytes.extend_from_slice(&self.payload);
+        bytes.extend_from_slice(&self.checksum.to_be_bytes());
+        bytes
+    }
+}
+
+/// The checksum of a [Checksummed] payload, as computed by `F`
+struct ChecksumOf<F: Checksum>(F);
+
+impl<F> DerivedField for ChecksumOf<F>
+where
+    F: Checksum,
+{
+    type Value = Checksummed;
+    type Field = u32;
+
+    /// A corrupt checksum carries as much information as a `u32`
+    const CORRUPT_CPLX: f64 = 32.0;
+
+    fn payload(value: &Checksummed) -> &Vec<u8> {
+        &value.payload
+    }
+    fn payload_mut(value: &mut Checksummed) -> &mut Vec<u8> {
+        &mut value.payload
+    }
+    fn get_field(value: &Checksummed) -> u32 {
+        value.checksum
+    }
+    fn set_field(value: &mut Checksummed, checksum: u32) {
+        value.checksum = checksum;
+    }
+    fn derive(&self, payload: &[u8]) -> u32 {
+        self.0.checksum(payload)
+    }
+    fn corrupt(valid: u32, rng: &mut SmallRng) -> u32 {
+        loop {
+            let checksum = rng.gen::<u32>();
+            if checksum != valid {
+                return checksum;
+            }
+        }
+    }
+}
+
+/**
+ * A mutator for a [Checksummed] payload, where `M` is the mutator of the
+ * payload and `F` computes its checksum.
+ *
+ * Random mutations of a payload almost never produce a valid checksum, so the
+ * tested code would reject nearly all inputs before reaching anything
+ * interesting. This mutator recomputes the checksum after each mutation of
+ * the payload instead. With the probability given to
+ * [with_corrupt_checksum_probability](ChecksummedMutator::with_corrupt_checksum_probability),
+ * a mutation instead sets the checksum to a value that does not match the
+ * payload, or restores it if it was already wrong, to test the validation
+ * itself. Arbitrary values are corrupted with the same probability.
+ *
+ * The complexity of a value is that of its payload. A corrupt checksum adds
+ * 32.0, as it holds information of its own.
+ */
+pub struct ChecksummedMutator<M, F>
+where
+    M: Mutator<Value = Vec<u8>>,
+    F: Checksum,
+{
+    m: DerivedFieldMutator<ChecksumOf<F>, M>,
+}
+
+impl<M, F> ChecksummedMutator<M, F>
+where
+    M: Mutator<Value = Vec<u8>>,
+    F: Checksum,
+{
+    pub fn new(payload: M, f: F) -> Self {
+        Self {
+            m: DerivedFieldMutator {
+                derived: ChecksumOf(f),
+                payload,
+                corrupt_probability: DEFAULT_CORRUPT_CHECKSUM_PROBABILITY,
+            },
+        }
+    }
+
+    /// Sets the probability that the checksum does not match the payload,
+    /// 0.02 by default. With 0.0, all checksums are valid.
+    pub fn with_corrupt_checksum_probability(mut self, probability: f64) -> Self {
+        assert!((0.0..=1.0).contains(&probability));
+        self.m.corrupt_probability = probability;
+        self
+    }
+
+    /// Whether the checksum of the value matches its payload
+    pub fn is_valid(&self, value: &Checksummed) -> bool {
+        self.m.is_valid(value)
+    }
+}
+
+pub type ChecksummedMutatorCache<C> = DerivedFieldCache<C>;
+
+pub struct ChecksummedMutatorStep<S> {
+    payload: S,
+    /// The number of mutations performed so far, which seeds their random decisions
+    nbr_mutations: u64,
+    /// Seeds the random decisions of the mutations, along with `nbr_mutations`
+    rng_seed: u64,
+}
+
+pub type UnmutateChecksummedToken<T> = UnmutateDerivedFieldToken<T, u32>;
+
+impl<M, F> Mutator for ChecksummedMutator<M, F>
+where
+    M: Mutator<Value = Vec<u8>>,
+    F: Checksum,
+{
+    type Value = Checksummed;
+    type Cache = ChecksummedMutatorCache<M::Cache>;
+    type MutationStep = ChecksummedMutatorStep<M::MutationStep>;
+    type UnmutateToken = UnmutateChecksummedToken<M::UnmutateToken>;
+
+    fn cache_from_value(&self, value: &Self::Value) -> Self::Cache {
+        self.m.cache_from_value(value)
+    }
+
+    fn mutation_step_from_value(&self, value: &Self::Value) -> Self::MutationStep {
+        ChecksummedMutatorStep {
+            payload: self.m.payload.mutation_step_from_value(&value.payload),
+            nbr_mutations: 0,
+            rng_seed: step_rng_seed(value),
+        }
+    }
+
+    fn seeded_mutation_step_from_value(&self, value: &Self::Value, seed: u64) -> Self::MutationStep {
+        ChecksummedMutatorStep {
+            payload: self.m.payload.seeded_mutation_step_from_value(&value.payload, seed),
+            nbr_mutations: 0,
+            rng_seed: step_rng_seed((seed, value)),
+        }
+    }
+
+    fn max_complexity(&self) -> f64 {
+        self.m.payload.max_complexity() + ChecksumOf::<F>::CORRUPT_CPLX
+    }
+
+    fn min_complexity(&self) -> f64 {
+        self.m.payload.min_complexity()
+    }
+
+    fn complexity(&self, _value: &Self::Value, cache: &Self::Cache) -> f64 {
+        DerivedFieldMutator::<ChecksumOf<F>, M>::complexity(cache)
+    }
+
+    fn arbitrary(&self, seed: usize, max_cplx: f64) -> (Self::Value, Self::Cache) {
+        let mut rng = SmallRng::seed_from_u64(seed as u64);
+        self.m.arbitrary(&mut rng, max_cplx, |payload, checksum| Checksummed {
+            payload,
+            checksum,
+        })
+    }
+
+    fn mutate(
+        &self,
+        value: &mut Self::Value,
+        cache: &mut Self::Cache,
+        step: &mut Self::MutationStep,
+        max_cplx: f64,
+    ) -> Option<Self::UnmutateToken> {
+        step.nbr_mutations += 1;
+        let mut rng = SmallRng::seed_from_u64(step.rng_seed.wrapping_add(step.nbr_mutations));
+        if rng.gen_bool(self.m.corrupt_probability) {
+            if let Some(token) = self.m.mutate_field(value, cache, &mut rng, max_cplx) {
+                return Some(token);
+            }
+        }
+        self.m.mutate_payload(value, cache, &mut step.payload, max_cplx)
+    }
+
+    fn unmutate(&self, value: &mut Self::Value, cache: &mut Self::Cache, t: Self::UnmutateToken) {
+        self.m.unmutate(value, cache, t)
+    }
+
+    fn complexity_breakdown(&self, _value: &Self::Value, cache: &Self::Cache) -> Vec<(&'static str, f64)> {
+        vec![
+            ("payload", cache.payload_cplx),
+            ("checksum", DerivedFieldMutator::<ChecksumOf<F>, M>::field_cplx(cache)),
+        ]
+    }
+
+    fn record_mutation_outcome(&self, value: &Self::Value, token: &Self::UnmutateToken, nbr_new_features: usize) {
+        self.m.record_mutation_outcome(value, token, nbr_new_features)
+    }
+
+    fn describe_value(&self, value: &Self::Value) -> Option<String> {
+        Some(format!("{:#?}", value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integer::U8Mutator;
+    use crate::keep_one_mutation_in_four;
+    use crate::vector::VecMutator;
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(Crc32.checksum(b""), 0);
+        assert_eq!(Crc32.checksum(b"123456789"), 0xCBF4_3926);
+        let value = Checksummed {
+            payload: vec![1, 2],
+            checksum: 0x0102_0304,
+        };
+        assert_eq!(value.to_bytes(), vec![1, 2, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_checksum_is_kept_valid() {
+        let m = ChecksummedMutator::new(VecMutator::new(U8Mutator::default()), Crc32)
+            .with_corrupt_checksum_probability(0.0);
+        for seed in 0..20 {
+            let (mut x, mut cache) = m.arbitrary(seed, 200.0);
+            assert!(m.is_valid(&x));
+            keep_one_mutation_in_four(&m, &mut x, &mut cache, 200.0, 200, |_, _, x, _| {
+                assert!(m.is_valid(x));
+            });
+        }
+    }
+
+    #[test]
+    fn test_corrupt_checksums() {
+        // a custom checksum: the sum of the bytes
+        let sum = |payload: &[u8]| payload.iter().map(|&b| b as u32).sum::<u32>();
+        let m =
+            ChecksummedMutator::new(VecMutator::new(U8Mutator::default()), sum).with_corrupt_checksum_probability(0.2);
+        let (mut nbr_valid, mut nbr_corrupt) = (0, 0);
+        for seed in 0..20 {
+            let (mut x, mut cache) = m.arbitrary(seed, 200.0);
+            keep_one_mutation_in_four(&m, &mut x, &mut cache, 200.0, 200, |_, _, x, cache| {
+                // the cached validity follows the mutations of the payload and of the checksum
+                assert_eq!(cache.valid, m.is_valid(x));
+                if m.is_valid(x) {
+                    nbr_valid += 1;
+                    assert_eq!(m.complexity(x, cache), cache.payload_cplx);
+                } else {
+                    nbr_corrupt += 1;
+                    assert_eq!(
+                        m.complexity(x, cache),
+                        cache.payload_cplx + ChecksumOf::<Crc32>::CORRUPT_CPLX
+                    );
+                }
+            });
+        }
+        assert!(nbr_valid > 0);
+        assert!(nbr_corrupt > 0);
+    }
+
+    #[test]
+    fn test_decisions_depend_on_the_value() {
+        let m = ChecksummedMutator::new(VecMutator::new(U8Mutator::default()), Crc32)
+            .with_corrupt_checksum_probability(0.5);
+        // whether each of the first mutations of the value corrupted its checksum
+        let decisions = |payload: Vec<u8>| {
+            let checksum = Crc32.checksum(&payload);
+            let mut x = Checksummed { payload, checksum };
+            let mut cache = m.cache_from_value(&x);
+            let mut step = m.mutation_step_from_value(&x);
+            let mut decisions = vec![];
+            for _ in 0..32 {
+                let token = m.mutate(&mut x, &mut cache, &mut step, 200.0).unwrap();
+                decisions.push(matches!(token, UnmutateChecksummedToken::Field { .. }));
+                m.unmutate(&mut x, &mut cache, token);
+            }
+            decisions
+        };
+        assert_eq!(decisions(vec![1, 2, 3]), decisions(vec![1, 2, 3]));
+        assert_ne!(decisions(vec![1, 2, 3]), decisions(vec![1, 2, 4]));
+    }
+}
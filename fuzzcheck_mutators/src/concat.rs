@@ -0,0 +1,281 @@
+//! A mutator for a buffer made of two regions, such as a header followed by
+//! a body, which are produced and mutated by two different mutators.
+
+extern crate fuzzcheck;
+use fuzzcheck::Mutator;
+
+extern crate rand;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use std::marker::PhantomData;
+
+/**
+ * A mutator for a `Vec<T>` that is the concatenation of a head, produced by
+ * the mutator `A`, and of a body, produced by the mutator `B`.
+ *
+ * This imposes a coarse structure on byte inputs without writing a grammar:
+ * for example, a header of at most 16 bytes followed by a body of any length.
+ * Each mutation edits a single region and leaves the other one untouched.
+ *
+ * The cache keeps the length of the head, which is where the buffer is split.
+ * A buffer that was not produced by this mutator, for example one read from
+ * the corpus, is split after its first `max_head_len` elements. `new` limits
+ * the length of the head to `max_head_len`, if `A` supports length ranges.
+ *
+ * The complexity of a buffer is the sum of the complexities of its regions.
+ */
+pub struct ConcatMutator<A, B, T>
+where
+    A: Mutator<Value = Vec<T>>,
+    B: Mutator<Value = Vec<T>>,
+    T: Clone,
+{
+    head: A,
+    body: B,
+    max_head_len: usize,
+    phantom: PhantomData<T>,
+}
+
+impl<A, B, T> ConcatMutator<A, B, T>
+where
+    A: Mutator<Value = Vec<T>>,
+    B: Mutator<Value = Vec<T>>,
+    T: Clone,
+{
+    pub fn new(mut head: A, body: B, max_head_len: usize) -> Self {
+        let _ = head.set_length_range(0..=max_head_len);
+        Self {
+            head,
+            body,
+            max_head_len,
+            phantom: PhantomData,
+        }
+    }
+
+    /// The two regions of a buffer that was not produced by this mutator
+    fn split(&self, value: &[T]) -> (Vec<T>, Vec<T>) {
+        let split = std::cmp::min(value.len(), self.max_head_len);
+        (value[..split].to_vec(), value[split..].to_vec())
+    }
+}
+
+#[derive(Clone)]
+pub struct ConcatMutatorCache<AC, BC> {
+    /// The length of the head
+    split: usize,
+    head: AC,
+    body: BC,
+    head_cplx: f64,
+    body_cplx: f64,
+}
+
+pub struct ConcatMutatorStep<AS, BS> {
+    head: AS,
+    body: BS,
+    pick_step: usize,
+}
+
+pub enum UnmutateConcatToken<AT, BT> {
+    Head { token: AT, split: usize, cplx: f64 },
+    Body { token: BT, cplx: f64 },
+}
+
+impl<A, B, T> ConcatMutator<A, B, T>
+where
+    A: Mutator<Value = Vec<T>>,
+    B: Mutator<Value = Vec<T>>,
+    T: Clone,
+{
+    fn mutate_head(
+        &self,
+        value: &mut Vec<T>,
+        cache: &mut ConcatMutatorCache<A::Cache, B::Cache>,
+        step: &mut A::MutationStep,
+        max_cplx: f64,
+    ) -> Option<UnmutateConcatToken<A::UnmutateToken, B::UnmutateToken>> {
+        // the head is mutated in place, and the body is appended back to it
+        let body = value.split_off(cache.split);
+        let token = self
+            .head
+            .mutate(value, &mut cache.head, step, max_cplx - cache.body_cplx);
+        let token = token.map(|token| {
+            let t = UnmutateConcatToken::Head {
+                token,
+                split: cache.split,
+                cplx: cache.head_cplx,
+            };
+            cache.split = value.len();
+            cache.head_cplx = self.head.complexity(value, &cache.head);
+            t
+        });
+        value.extend(body);
+        token
+    }
+
+    fn mutate_body(
+        &self,
+        value: &mut Vec<T>,
+        cache: &mut ConcatMutatorCache<A::Cache, B::Cache>,
+        step: &mut B::MutationStep,
+        max_cplx: f64,
+    ) -> Option<UnmutateConcatToken<A::UnmutateToken, B::UnmutateToken>> {
+        let mut body = value.split_off(cache.split);
+        let token = self
+            .body
+            .mutate(&mut body, &mut cache.body, step, max_cplx - cache.head_cplx);
+        let token = token.map(|token| {
+            let t = UnmutateConcatToken::Body {
+                token,
+                cplx: cache.body_cplx,
+            };
+            cache.body_cplx = self.body.complexity(&body, &cache.body);
+            t
+        });
+        value.extend(body);
+        token
+    }
+}
+
+impl<A, B, T> Mutator for ConcatMutator<A, B, T>
+where
+    A: Mutator<Value = Vec<T>>,
+    B: Mutator<Value = Vec<T>>,
+    T: Clone,
+{
+    type Value = Vec<T>;
+    type Cache = ConcatMutatorCache<A::Cache, B::Cache>;
+    type MutationStep = ConcatMutatorStep<A::MutationStep, B::MutationStep>;
+    type UnmutateToken = UnmutateConcatToken<A::UnmutateToken, B::UnmutateToken>;
+
+    fn cache_from_value(&self, value: &Self::Value) -> Self::Cache {
+        let (head, body) = self.split(value);
+        let head_cache = self.head.cache_from_value(&head);
+        let body_cache = self.body.cache_from_value(&body);
+        ConcatMutatorCache {
+            split: head.len(),
+            head_cplx: self.head.complexity(&head, &head_cache),
+            body_cplx: self.body.complexity(&body, &body_cache),
+            head: head_cache,
+            body: body_cache,
+        }
+    }
+
+    fn mutation_step_from_value(&self, value: &Self::Value) -> Self::MutationStep {
+        let (head, body) = self.split(value);
+        ConcatMutatorStep {
+            head: self.head.mutation_step_from_value(&head),
+            body: self.body.mutation_step_from_value(&body),
+            pick_step: 0,
+        }
+    }
+
+    fn max_complexity(&self) -> f64 {
+        self.head.max_complexity() + self.body.max_complexity()
+    }
+
+    fn min_complexity(&self) -> f64 {
+        self.head.min_complexity() + self.body.min_complexity()
+    }
+
+    fn complexity(&self, _value: &Self::Value, cache: &Self::Cache) -> f64 {
+        cache.head_cplx + cache.body_cplx
+    }
+
+    fn arbitrary(&self, seed: usize, max_cplx: f64) -> (Self::Value, Self::Cache) {
+        let mut r = SmallRng::seed_from_u64(seed as u64);
+        let (mut value, head_cache) = self.head.arbitrary(r.gen(), max_cplx * r.gen::<f64>());
+        let head_cplx = self.head.complexity(&value, &head_cache);
+        let (body, body_cache) = self.body.arbitrary(r.gen(), max_cplx - head_cplx);
+        let body_cplx = self.body.complexity(&body, &body_cache);
+        let split = value.len();
+        value.extend(body);
+        let cache = ConcatMutatorCache {
+            split,
+            head: head_cache,
+            body: body_cache,
+            head_cplx,
+            body_cplx,
+        };
+        (value, cache)
+    }
+
+    fn mutate(
+        &self,
+        value: &mut Self::Value,
+        cache: &mut Self::Cache,
+        step: &mut Self::MutationStep,
+        max_cplx: f64,
+    ) -> Option<Self::UnmutateToken> {
+        step.pick_step += 1;
+        if step.pick_step % 2 == 0 {
+            if let Some(token) = self.mutate_head(value, cache, &mut step.head, max_cplx) {
+                return Some(token);
+            }
+            // the head cannot be mutated anymore, try the body instead
+            self.mutate_body(value, cache, &mut step.body, max_cplx)
+        } else {
+            if let Some(token) = self.mutate_body(value, cache, &mut step.body, max_cplx) {
+                return Some(token);
+            }
+            // the body cannot be mutated anymore, try the head instead
+            self.mutate_head(value, cache, &mut step.head, max_cplx)
+        }
+    }
+
+    fn unmutate(&self, value: &mut Self::Value, cache: &mut Self::Cache, t: Self::UnmutateToken) {
+        match t {
+            UnmutateConcatToken::Head { token, split, cplx } => {
+                let body = value.split_off(cache.split);
+                self.head.unmutate(value, &mut cache.head, token);
+                value.extend(body);
+                cache.split = split;
+                cache.head_cplx = cplx;
+            }
+            UnmutateConcatToken::Body { token, cplx } => {
+                let mut body = value.split_off(cache.split);
+                self.body.unmutate(&mut body, &mut cache.body, token);
+                value.extend(body);
+                cache.body_cplx = cplx;
+            }
+        }
+    }
+
+    fn complexity_breakdown(&self, _value: &Self::Value, cache: &Self::Cache) -> Vec<(&'static str, f64)> {
+        vec![("head", cache.head_cplx), ("body", cache.body_cplx)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integer::U8Mutator;
+    use crate::keep_one_mutation_in_four;
+    use crate::vector::VecMutator;
+
+    #[test]
+    fn test_concat_mutator() {
+        let m = ConcatMutator::new(
+            VecMutator::new(U8Mutator::default()),
+            VecMutator::new(U8Mutator::default()),
+            4,
+        );
+        for seed in 0..100 {
+            let (mut x, mut cache) = m.arbitrary(seed, 200.0);
+            assert!(cache.split <= 4);
+            keep_one_mutation_in_four(&m, &mut x, &mut cache, 200.0, 200, |old_x, old_cache, x, cache| {
+                assert!(cache.split <= 4);
+                // only one of the two regions was changed
+                let head_changed = old_x[..old_cache.split] != x[..cache.split];
+                let body_changed = old_x[old_cache.split..] != x[cache.split..];
+                assert!(!(head_changed && body_changed));
+            });
+        }
+
+        let x = vec![1, 2, 3, 4, 5, 6];
+        let cache = m.cache_from_value(&x);
+        assert_eq!(cache.split, 4);
+        let x = vec![1, 2];
+        assert_eq!(m.cache_from_value(&x).split, 2);
+    }
+}
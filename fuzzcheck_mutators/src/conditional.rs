@@ -0,0 +1,259 @@
+//! A mutator for a value that is only meaningful when a sibling boolean is
+//! true, such as the two fields of `struct Limit { enabled: bool, max: u8 }`.
+
+extern crate fuzzcheck;
+use fuzzcheck::Mutator;
+
+use std::marker::PhantomData;
+
+/// The structure of a type made of a boolean and of a value that is only
+/// used when the boolean is true. When the boolean is false, the value is
+/// expected to be `A::default()`.
+pub trait ConditionalMap {
+    type A;
+    type V: Clone;
+
+    fn is_enabled(v: &Self::V) -> bool;
+    fn set_enabled(v: &mut Self::V, enabled: bool);
+    fn get_a(v: &Self::V) -> &Self::A;
+    fn get_a_mut(v: &mut Self::V) -> &mut Self::A;
+
+    fn new(enabled: bool, a: Self::A) -> Self::V;
+}
+
+/**
+ * A mutator for a boolean and a value that is only live when the boolean is
+ * true, where `A` is the mutator of the value.
+ *
+ * It behaves like an `OptionMutator`: the value is mutated and contributes
+ * to the complexity only when it is enabled. Otherwise, it is kept at its
+ * default and the complexity is only that of the boolean, so that no
+ * mutation is wasted on a value that the test function ignores.
+ */
+pub struct ConditionalMutator<Map, A>
+where
+    A: Mutator,
+    Map: ConditionalMap<A = A::Value>,
+{
+    a: A,
+    phantom: PhantomData<Map>,
+}
+impl<Map: ConditionalMap<A = A::Value>, A: Mutator> ConditionalMutator<Map, A> {
+    pub fn new(a: A) -> Self {
+        Self {
+            a,
+            phantom: PhantomData,
+        }
+    }
+}
+impl<Map: ConditionalMap<A = A::Value>, A: Mutator> Default for ConditionalMutator<Map, A>
+where
+    A: Default,
+{
+    fn default() -> Self {
+        Self::new(A::default())
+    }
+}
+
+pub enum UnmutateConditionalToken<Value, Cache, Token> {
+    /// The enabled value was mutated
+    Inner(Token),
+    /// The value was disabled, and was this value before
+    Enable(Value, Cache),
+    /// The value was enabled, and was disabled before
+    Disable,
+}
+
+#[derive(Clone)]
+pub struct ConditionalMutatorStep<MS> {
+    did_check_disabled: bool,
+    inner_arbitrary: usize,
+    inner: Option<MS>,
+}
+
+impl<Map: ConditionalMap<A = A::Value>, A: Mutator> Mutator for ConditionalMutator<Map, A>
+where
+    A::Value: Default,
+{
+    type Value = Map::V;
+    /// The cache of the value, if it is enabled
+    type Cache = Option<A::Cache>;
+    type MutationStep = ConditionalMutatorStep<A::MutationStep>;
+    type UnmutateToken = UnmutateConditionalToken<A::Value, A::Cache, A::UnmutateToken>;
+
+    fn cache_from_value(&self, value: &Self::Value) -> Self::Cache {
+        if Map::is_enabled(value) {
+            Some(self.a.cache_from_value(Map::get_a(value)))
+        } else {
+            None
+        }
+    }
+
+    fn mutation_step_from_value(&self, value: &Self::Value) -> Self::MutationStep {
+        let enabled = Map::is_enabled(value);
+        ConditionalMutatorStep {
+            did_check_disabled: !enabled,
+            inner_arbitrary: 0,
+            inner: if enabled {
+                Some(self.a.mutation_step_from_value(Map::get_a(value)))
+            } else {
+                None
+            },
+        }
+    }
+
+    fn arbitrary(&self, seed: usize, max_cplx: f64) -> (Self::Value, Self::Cache) {
+        if seed == 0 || self.a.min_complexity() > max_cplx - 1.0 {
+            (Map::new(false, A::Value::default()), None)
+        } else {
+            let (a, a_cache) = self.a.arbitrary(seed - 1, max_cplx - 1.0);
+            (Map::new(true, a), Some(a_cache))
+        }
+    }
+
+    fn max_complexity(&self) -> f64 {
+        1.0 + self.a.max_complexity()
+    }
+
+    fn min_complexity(&self) -> f64 {
+        // complexity of the boolean alone
+        1.0
+    }
+
+    fn complexity(&self, value: &Self::Value, cache: &Self::Cache) -> f64 {
+        match cache {
+            Some(a_cache) if Map::is_enabled(value) => 1.0 + self.a.complexity(Map::get_a(value), a_cache),
+            _ => 1.0,
+        }
+    }
+
+    fn mutate(
+        &self,
+        value: &mut Self::Value,
+        cache: &mut Self::Cache,
+        step: &mut Self::MutationStep,
+        max_cplx: f64,
+    ) -> Option<Self::UnmutateToken> {
+        let inner_max_cplx = max_cplx - 1.0;
+
+        if !step.did_check_disabled {
+            step.did_check_disabled = true;
+            let old_a = std::mem::take(Map::get_a_mut(value));
+            let old_cache = cache.take().unwrap();
+            Map::set_enabled(value, false);
+            Some(UnmutateConditionalToken::Enable(old_a, old_cache))
+        } else if let (true, Some(a_cache), Some(a_step)) =
+            (Map::is_enabled(value), cache.as_mut(), step.inner.as_mut())
+        {
+            self.a
+                .mutate(Map::get_a_mut(value), a_cache, a_step, inner_max_cplx)
+                .map(UnmutateConditionalToken::Inner)
+        } else if Map::is_enabled(value) || self.a.min_complexity() > inner_max_cplx {
+            None
+        } else {
+            let (a, a_cache) = self.a.arbitrary(step.inner_arbitrary, inner_max_cplx);
+            step.inner_arbitrary += 1;
+            // the next mutations of the enabled value start from this one
+            step.inner = Some(self.a.mutation_step_from_value(&a));
+            *Map::get_a_mut(value) = a;
+            *cache = Some(a_cache);
+            Map::set_enabled(value, true);
+            Some(UnmutateConditionalToken::Disable)
+        }
+    }
+
+    fn unmutate(&self, value: &mut Self::Value, cache: &mut Self::Cache, t: Self::UnmutateToken) {
+        match t {
+            UnmutateConditionalToken::Inner(t) => {
+                self.a.unmutate(Map::get_a_mut(value), cache.as_mut().unwrap(), t);
+            }
+            UnmutateConditionalToken::Enable(a, a_cache) => {
+                *Map::get_a_mut(value) = a;
+                *cache = Some(a_cache);
+                Map::set_enabled(value, true);
+            }
+            UnmutateConditionalToken::Disable => {
+                *Map::get_a_mut(value) = A::Value::default();
+                *cache = None;
+                Map::set_enabled(value, false);
+            }
+        }
+    }
+
+    fn record_mutation_outcome(&self, value: &Self::Value, token: &Self::UnmutateToken, nbr_new_features: usize) {
+        if let UnmutateConditionalToken::Inner(token) = token {
+            self.a
+                .record_mutation_outcome(Map::get_a(value), token, nbr_new_features)
+        }
+    }
+
+    /// Only an enabled value is described
+    fn describe_value(&self, value: &Self::Value) -> Option<String> {
+        if Map::is_enabled(value) {
+            self.a.describe_value(Map::get_a(value))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integer::U8Mutator;
+    use crate::keep_one_mutation_in_four;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Limit {
+        enabled: bool,
+        max: u8,
+    }
+
+    impl ConditionalMap for Limit {
+        type A = u8;
+        type V = Self;
+
+        fn is_enabled(v: &Self) -> bool {
+            v.enabled
+        }
+        fn set_enabled(v: &mut Self, enabled: bool) {
+            v.enabled = enabled;
+        }
+        fn get_a(v: &Self) -> &u8 {
+            &v.max
+        }
+        fn get_a_mut(v: &mut Self) -> &mut u8 {
+            &mut v.max
+        }
+        fn new(enabled: bool, max: u8) -> Self {
+            Limit { enabled, max }
+        }
+    }
+
+    #[test]
+    fn test_conditional_mutator() {
+        let m = ConditionalMutator::<Limit, U8Mutator>::default();
+        let (mut nbr_enabled, mut nbr_disabled) = (0, 0);
+        for seed in 0..10 {
+            let (mut x, mut cache) = m.arbitrary(seed, 100.0);
+            // a kept mutation may toggle the boolean
+            keep_one_mutation_in_four(&m, &mut x, &mut cache, 100.0, 100, |_, _, x, cache| {
+                if x.enabled {
+                    nbr_enabled += 1;
+                    assert_eq!(m.complexity(x, cache), 9.0);
+                } else {
+                    nbr_disabled += 1;
+                    // a disabled value is not mutated and does not count
+                    assert_eq!(x.max, 0);
+                    assert_eq!(m.complexity(x, cache), 1.0);
+                }
+            });
+        }
+        assert!(nbr_enabled > 0);
+        assert!(nbr_disabled > 0);
+
+        let x = Limit { enabled: false, max: 0 };
+        assert!(m.cache_from_value(&x).is_none());
+        assert_eq!(m.complexity(&x, &m.cache_from_value(&x)), 1.0);
+    }
+}
@@ -0,0 +1,91 @@
+extern crate fuzzcheck;
+use fuzzcheck::Mutator;
+
+use crate::DefaultMutator;
+
+use std::borrow::Cow;
+
+/// A mutator for a `Cow` that forwards to the mutator of its contents.
+///
+/// Mutating a borrowed value turns it into an owned value first.
+pub struct CowMutator<M: Mutator> {
+    m: M,
+}
+impl<M: Mutator> CowMutator<M> {
+    pub fn new(m: M) -> Self {
+        Self { m }
+    }
+}
+impl<M: Mutator> Default for CowMutator<M>
+where
+    M: Default,
+{
+    fn default() -> Self {
+        Self::new(M::default())
+    }
+}
+
+impl<T: DefaultMutator + 'static> DefaultMutator for Cow<'static, T> {
+    type Mutator = CowMutator<T::Mutator>;
+    fn default_mutator() -> Self::Mutator {
+        CowMutator::new(T::default_mutator())
+    }
+}
+
+impl<M: Mutator> Mutator for CowMutator<M>
+where
+    M::Value: 'static,
+{
+    type Value = Cow<'static, M::Value>;
+    type Cache = M::Cache;
+    type MutationStep = M::MutationStep;
+    type UnmutateToken = M::UnmutateToken;
+
+    fn cache_from_value(&self, value: &Self::Value) -> Self::Cache {
+        self.m.cache_from_value(value)
+    }
+    fn mutation_step_from_value(&self, value: &Self::Value) -> Self::MutationStep {
+        self.m.mutation_step_from_value(value)
+    }
+
+    fn seeded_mutation_step_from_value(&self, value: &Self::Value, seed: u64) -> Self::MutationStep {
+        self.m.seeded_mutation_step_from_value(value, seed)
+    }
+
+    fn max_complexity(&self) -> f64 {
+        self.m.max_complexity()
+    }
+    fn min_complexity(&self) -> f64 {
+        self.m.min_complexity()
+    }
+    fn complexity(&self, value: &Self::Value, cache: &Self::Cache) -> f64 {
+        self.m.complexity(value, cache)
+    }
+
+    fn arbitrary(&self, seed: usize, max_cplx: f64) -> (Self::Value, Self::Cache) {
+        let (value, cache) = self.m.arbitrary(seed, max_cplx);
+        (Cow::Owned(value), cache)
+    }
+
+    fn mutate(
+        &self,
+        value: &mut Self::Value,
+        cache: &mut Self::Cache,
+        step: &mut Self::MutationStep,
+        max_cplx: f64,
+    ) -> Option<Self::UnmutateToken> {
+        self.m.mutate(value.to_mut(), cache, step, max_cplx)
+    }
+
+    fn unmutate(&self, value: &mut Self::Value, cache: &mut Self::Cache, t: Self::UnmutateToken) {
+        self.m.unmutate(value.to_mut(), cache, t)
+    }
+
+    fn record_mutation_outcome(&self, value: &Self::Value, token: &Self::UnmutateToken, nbr_new_features: usize) {
+        self.m.record_mutation_outcome(value, token, nbr_new_features)
+    }
+
+    fn describe_value(&self, value: &Self::Value) -> Option<String> {
+        self.m.describe_value(value)
+    }
+}
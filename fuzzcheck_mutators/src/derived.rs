@@ -0,0 +1,218 @@
+//! The structure shared by the mutators of byte payloads that carry a field
+//! derived from them, such as a checksum or a length, which is kept
+//! consistent with the payload so that the tested code gets past its
+//! validation.
+
+extern crate fuzzcheck;
+use fuzzcheck::Mutator;
+
+extern crate rand;
+use rand::rngs::SmallRng;
+use rand::Rng;
+
+/// A field of a value that is derived from its payload, such as the checksum
+/// of a [Checksummed](crate::checksum::Checksummed) payload or the length of
+/// a [FramedMessage](crate::framed::FramedMessage).
+pub(crate) trait DerivedField {
+    /// The value made of the payload, the derived field, and possibly other
+    /// fields that do not depend on the payload
+    type Value: Clone;
+    type Field: Copy + PartialEq;
+
+    /// The complexity of a derived field that does not match the payload,
+    /// as it holds information of its own
+    const CORRUPT_CPLX: f64;
+
+    fn payload(value: &Self::Value) -> &Vec<u8>;
+    fn payload_mut(value: &mut Self::Value) -> &mut Vec<u8>;
+
+    fn get_field(value: &Self::Value) -> Self::Field;
+    fn set_field(value: &mut Self::Value, field: Self::Field);
+
+    /// The field that matches the given payload
+    fn derive(&self, payload: &[u8]) -> Self::Field;
+    /// A field that does not match the given valid field
+    fn corrupt(valid: Self::Field, rng: &mut SmallRng) -> Self::Field;
+}
+
+#[derive(Clone)]
+pub struct DerivedFieldCache<C> {
+    pub(crate) payload: C,
+    pub(crate) payload_cplx: f64,
+    /// Whether the derived field matches the payload, so that computing the
+    /// complexity does not derive the field again from the whole payload
+    pub(crate) valid: bool,
+}
+
+pub enum UnmutateDerivedFieldToken<T, F> {
+    /// The payload was mutated, and the derived field, the complexity of
+    /// the payload, and the validity of the field were these before
+    Payload { token: T, field: F, cplx: f64, valid: bool },
+    /// Only the derived field was mutated, and it and its validity were
+    /// these before
+    Field { field: F, valid: bool },
+}
+
+/// The mutations of the payload and of the derived field, shared by the
+/// mutators of the values that have one
+pub(crate) struct DerivedFieldMutator<D, M>
+where
+    D: DerivedField,
+    M: Mutator<Value = Vec<u8>>,
+{
+    pub(crate) derived: D,
+    pub(crate) payload: M,
+    /// The probability that a mutation or an arbitrary value has a derived
+    /// field that does not match the payload
+    pub(crate) corrupt_probability: f64,
+}
+
+impl<D, M> DerivedFieldMutator<D, M>
+where
+    D: DerivedField,
+    M: Mutator<Value = Vec<u8>>,
+{
+    pub(crate) fn is_valid(&self, value: &D::Value) -> bool {
+        self.derived.derive(D::payload(value)) == D::get_field(value)
+    }
+
+    pub(crate) fn field_cplx(cache: &DerivedFieldCache<M::Cache>) -> f64 {
+        if cache.valid {
+            0.0
+        } else {
+            D::CORRUPT_CPLX
+        }
+    }
+
+    /// The complexity of the payload and of the derived field
+    pub(crate) fn complexity(cache: &DerivedFieldCache<M::Cache>) -> f64 {
+        cache.payload_cplx + Self::field_cplx(cache)
+    }
+
+    pub(crate) fn cache_from_value(&self, value: &D::Value) -> DerivedFieldCache<M::Cache> {
+        let payload = self.payload.cache_from_value(D::payload(value));
+        DerivedFieldCache {
+            payload_cplx: self.payload.complexity(D::payload(value), &payload),
+            payload,
+            valid: self.is_valid(value),
+        }
+    }
+
+    /// An arbitrary payload whose complexity with its derived field is at
+    /// most `max_cplx`, made into a value by `new` along with the field
+    pub(crate) fn arbitrary(
+        &self,
+        rng: &mut SmallRng,
+        max_cplx: f64,
+        new: impl FnOnce(Vec<u8>, D::Field) -> D::Value,
+    ) -> (D::Value, DerivedFieldCache<M::Cache>) {
+        let corrupt =
+            rng.gen_bool(self.corrupt_probability) && max_cplx - D::CORRUPT_CPLX >= self.payload.min_complexity();
+        let payload_max_cplx = max_cplx - if corrupt { D::CORRUPT_CPLX } else { 0.0 };
+        let (payload, payload_cache) = self.payload.arbitrary(rng.gen(), payload_max_cplx);
+        let valid = self.derived.derive(&payload);
+        let field = if corrupt { D::corrupt(valid, rng) } else { valid };
+        let cache = DerivedFieldCache {
+            payload_cplx: self.payload.complexity(&payload, &payload_cache),
+            payload: payload_cache,
+            valid: !corrupt,
+        };
+        (new(payload, field), cache)
+    }
+
+    /// Mutates the payload, and derives the field from it again if it
+    /// matched the payload before
+    pub(crate) fn mutate_payload(
+        &self,
+        value: &mut D::Value,
+        cache: &mut DerivedFieldCache<M::Cache>,
+        step: &mut M::MutationStep,
+        max_cplx: f64,
+    ) -> Option<UnmutateDerivedFieldToken<M::UnmutateToken, D::Field>> {
+        let other_cplx = Self::field_cplx(cache);
+        let token = self
+            .payload
+            .mutate(D::payload_mut(value), &mut cache.payload, step, max_cplx - other_cplx)?;
+        let token = UnmutateDerivedFieldToken::Payload {
+            token,
+            field: D::get_field(value),
+            cplx: cache.payload_cplx,
+            valid: cache.valid,
+        };
+        let derived = self.derived.derive(D::payload(value));
+        if cache.valid {
+            D::set_field(value, derived);
+        } else {
+            // the mutated payload may happen to match the field
+            cache.valid = derived == D::get_field(value);
+        }
+        cache.payload_cplx = self.payload.complexity(D::payload(value), &cache.payload);
+        Some(token)
+    }
+
+    /// Sets the derived field to a value that does not match the payload,
+    /// or restores it half of the time if it did not match already
+    pub(crate) fn mutate_field(
+        &self,
+        value: &mut D::Value,
+        cache: &mut DerivedFieldCache<M::Cache>,
+        rng: &mut SmallRng,
+        max_cplx: f64,
+    ) -> Option<UnmutateDerivedFieldToken<M::UnmutateToken, D::Field>> {
+        let token = UnmutateDerivedFieldToken::Field {
+            field: D::get_field(value),
+            valid: cache.valid,
+        };
+        let valid = self.derived.derive(D::payload(value));
+        if cache.valid {
+            if cache.payload_cplx + D::CORRUPT_CPLX > max_cplx {
+                return None;
+            }
+            D::set_field(value, D::corrupt(valid, rng));
+            cache.valid = false;
+        } else if rng.gen_bool(0.5) {
+            D::set_field(value, valid);
+            cache.valid = true;
+        } else {
+            D::set_field(value, D::corrupt(valid, rng));
+        }
+        Some(token)
+    }
+
+    pub(crate) fn unmutate(
+        &self,
+        value: &mut D::Value,
+        cache: &mut DerivedFieldCache<M::Cache>,
+        t: UnmutateDerivedFieldToken<M::UnmutateToken, D::Field>,
+    ) {
+        match t {
+            UnmutateDerivedFieldToken::Payload {
+                token,
+                field,
+                cplx,
+                valid,
+            } => {
+                self.payload.unmutate(D::payload_mut(value), &mut cache.payload, token);
+                D::set_field(value, field);
+                cache.payload_cplx = cplx;
+                cache.valid = valid;
+            }
+            UnmutateDerivedFieldToken::Field { field, valid } => {
+                D::set_field(value, field);
+                cache.valid = valid;
+            }
+        }
+    }
+
+    pub(crate) fn record_mutation_outcome(
+        &self,
+        value: &D::Value,
+        token: &UnmutateDerivedFieldToken<M::UnmutateToken, D::Field>,
+        nbr_new_features: usize,
+    ) {
+        if let UnmutateDerivedFieldToken::Payload { token, .. } = token {
+            self.payload
+                .record_mutation_outcome(D::payload(value), token, nbr_new_features)
+        }
+    }
+}
@@ -0,0 +1,102 @@
+extern crate fuzzcheck;
+use fuzzcheck::Mutator;
+
+/**
+ * A mutator for the integer discriminant of a fieldless enum.
+ *
+ * Its values are `u8` discriminants, which the test function is expected to
+ * convert into the enum itself (e.g. with a `TryFrom<u8>` implementation or a
+ * `match` on the integer).
+ *
+ * When `fuzz_invalid_discriminant` is true, the mutator will, once in a while,
+ * produce a discriminant that is outside of `0..nbr_variants`. This is
+ * useful for testing code that transmutes or matches on integer
+ * discriminants and assumes that they are always valid, such as an
+ * `unreachable!()` in the fallback branch of a `match`. Note that it is
+ * undefined behaviour to transmute such a discriminant into the enum, so
+ * the conversion should only be done through a checked function.
+ */
+#[derive(Clone)]
+pub struct DiscriminantMutator {
+    nbr_variants: u8,
+    fuzz_invalid_discriminant: bool,
+}
+
+impl DiscriminantMutator {
+    pub fn new(nbr_variants: u8, fuzz_invalid_discriminant: bool) -> Self {
+        assert!(nbr_variants > 0);
+        Self {
+            nbr_variants,
+            fuzz_invalid_discriminant,
+        }
+    }
+
+    /// Returns the discriminant corresponding to the given step.
+    ///
+    /// One step in 16 yields an invalid discriminant, if enabled and if
+    /// there is any invalid discriminant to choose from.
+    fn discriminant(&self, step: usize) -> u8 {
+        let nbr_invalid = std::u8::MAX as usize + 1 - self.nbr_variants as usize;
+        if self.fuzz_invalid_discriminant && nbr_invalid > 0 && step % 16 == 15 {
+            (self.nbr_variants as usize + (step / 16) % nbr_invalid) as u8
+        } else {
+            (step % self.nbr_variants as usize) as u8
+        }
+    }
+}
+
+impl Mutator for DiscriminantMutator {
+    type Value = u8;
+    type Cache = ();
+    type MutationStep = usize;
+    type UnmutateToken = u8; // old value
+
+    fn cache_from_value(&self, _value: &Self::Value) -> Self::Cache {}
+    fn mutation_step_from_value(&self, value: &Self::Value) -> Self::MutationStep {
+        // start right after the current discriminant
+        *value as usize + 1
+    }
+
+    fn arbitrary(&self, seed: usize, _max_cplx: f64) -> (Self::Value, Self::Cache) {
+        (self.discriminant(seed), ())
+    }
+
+    fn max_complexity(&self) -> f64 {
+        8.0
+    }
+
+    fn min_complexity(&self) -> f64 {
+        8.0
+    }
+
+    fn complexity(&self, _value: &Self::Value, _cache: &Self::Cache) -> f64 {
+        8.0
+    }
+
+    fn mutate(
+        &self,
+        value: &mut Self::Value,
+        _cache: &mut Self::Cache,
+        step: &mut Self::MutationStep,
+        _max_cplx: f64,
+    ) -> Option<Self::UnmutateToken> {
+        if !self.fuzz_invalid_discriminant && *step >= *value as usize + self.nbr_variants as usize {
+            // the step started right after the value, so every other variant was tried
+            return None;
+        }
+        let token = *value;
+        let mut new_value = self.discriminant(*step);
+        *step = step.wrapping_add(1);
+        if new_value == token {
+            // the current value is not a mutation, try the next one
+            new_value = self.discriminant(*step);
+            *step = step.wrapping_add(1);
+        }
+        *value = new_value;
+        Some(token)
+    }
+
+    fn unmutate(&self, value: &mut Self::Value, _cache: &mut Self::Cache, t: Self::UnmutateToken) {
+        *value = t;
+    }
+}
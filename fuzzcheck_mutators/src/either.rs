@@ -1,3 +1,4 @@
+use std::cell::Cell;
 use std::marker::PhantomData;
 
 extern crate fuzzcheck;
@@ -7,6 +8,8 @@ extern crate rand;
 use rand::rngs::SmallRng;
 use rand::{Rng, SeedableRng};
 
+use crate::{complexity_fingerprint, step_rng_seed};
+
 macro_rules! match_all_eithers {
     ( $main:expr, $( $others:expr ),* ) => {
         {
@@ -66,6 +69,32 @@ pub trait EitherMap {
     fn get_either_mut(v: &mut Self::V) -> Either<&mut Self::A, &mut Self::B>;
 }
 
+/// The default probability that [EitherMutator::mutate] switches to the
+/// other variant instead of mutating the payload of the current one
+pub const DEFAULT_VARIANT_FLIP_PROBABILITY: f64 = 0.01;
+
+/// The number of tested switches of variant that an adaptive
+/// [EitherMutator] records before it starts favoring the most productive
+/// variant
+const MIN_ADAPTIVE_SWITCHES: usize = 100;
+
+/// How often the tested mutations that switched to each variant, left then
+/// right, covered new features
+#[derive(Clone, Copy, Default)]
+struct VariantStats {
+    switches: [usize; 2],
+    productive_switches: [usize; 2],
+}
+
+/**
+ * A mutator for enums with two variants, described by an [EitherMap].
+ *
+ * Most mutations refine the payload of the current variant, and only a few
+ * replace the value by an arbitrary value of the other variant, so that the
+ * progress made within a variant is not constantly thrown away. The variant
+ * is also switched when the payload of the current one cannot be mutated
+ * anymore.
+ */
 pub struct EitherMutator<Map, A, B>
 where
     A: Mutator,
@@ -74,6 +103,9 @@ where
 {
     a: A,
     b: B,
+    variant_flip_probability: f64,
+    adaptive: bool,
+    variant_stats: Cell<VariantStats>,
     phantom: PhantomData<Map>,
 }
 impl<Map: EitherMap, A: Mutator, B: Mutator> EitherMutator<Map, A, B> {
@@ -81,9 +113,51 @@ impl<Map: EitherMap, A: Mutator, B: Mutator> EitherMutator<Map, A, B> {
         Self {
             a,
             b,
+            variant_flip_probability: DEFAULT_VARIANT_FLIP_PROBABILITY,
+            adaptive: false,
+            variant_stats: Cell::new(VariantStats::default()),
             phantom: PhantomData,
         }
     }
+    /// Change the probability that a mutation switches to the other variant,
+    /// which is [DEFAULT_VARIANT_FLIP_PROBABILITY] by default.
+    ///
+    /// # Panics
+    /// Panics if the probability is not between 0 and 1.
+    pub fn with_variant_flip_probability(mut self, probability: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&probability),
+            "the variant flip probability of an EitherMutator must be between 0 and 1"
+        );
+        self.variant_flip_probability = probability;
+        self
+    }
+    /// Make the mutator learn which variant covers new features most often
+    /// during the run, and favor it both when switching variants and when
+    /// generating arbitrary values.
+    ///
+    /// This helps with enum-heavy grammars, such as the kinds of nodes of a
+    /// syntax tree, where some variants reach much more code than others.
+    /// The variants are picked uniformly until enough switches of variant
+    /// were tested.
+    pub fn with_adaptive_variants(mut self) -> Self {
+        self.adaptive = true;
+        self
+    }
+    /// The probabilities of picking the left and right variants, which sum
+    /// to 1. They are uniform unless the mutator is adaptive and learned
+    /// which variant is the most productive.
+    pub fn variant_weights(&self) -> (f64, f64) {
+        let stats = self.variant_stats.get();
+        if !self.adaptive || stats.switches[0] + stats.switches[1] < MIN_ADAPTIVE_SWITCHES {
+            return (0.5, 0.5);
+        }
+        // one productive switch in two is assumed before any is recorded, so
+        // that a variant that was rarely tried is not abandoned
+        let rate = |i: usize| (stats.productive_switches[i] as f64 + 1.0) / (stats.switches[i] as f64 + 2.0);
+        let (left, right) = (rate(0), rate(1));
+        (left / (left + right), right / (left + right))
+    }
 }
 impl<Map: EitherMap, A: Mutator, B: Mutator> Default for EitherMutator<Map, A, B>
 where
@@ -98,6 +172,12 @@ where
 pub struct EitherMutatorStep<A, B> {
     inner: Either<A, B>,
     pick_step: usize,
+    /// Seeds the random decisions of the mutations, along with `pick_step`
+    rng_seed: u64,
+    /// The seed given to
+    /// [seeded_mutation_step_from_value](Mutator::seeded_mutation_step_from_value),
+    /// if any, to compute the step of the other variant once it is switched to
+    seed: Option<u64>,
 }
 
 pub enum UnmutateEitherToken<V, C, TokenA, TokenB> {
@@ -106,6 +186,37 @@ pub enum UnmutateEitherToken<V, C, TokenA, TokenB> {
     UnmutateRight(TokenB),
 }
 
+impl<A: Mutator, B: Mutator, Map: EitherMap<A = A::Value, B = B::Value>> EitherMutator<Map, A, B> {
+    /// The mutation step of the value, for a fuzzer whose mutations are
+    /// derived from `seed`, if any
+    fn step_from_value(
+        &self,
+        value: &Map::V,
+        seed: Option<u64>,
+    ) -> EitherMutatorStep<A::MutationStep, B::MutationStep> {
+        match Map::get_either(value) {
+            Either::Left(inner_value) => EitherMutatorStep {
+                inner: Either::Left(match seed {
+                    Some(seed) => self.a.seeded_mutation_step_from_value(inner_value, seed),
+                    None => self.a.mutation_step_from_value(inner_value),
+                }),
+                pick_step: 0,
+                rng_seed: step_rng_seed((seed, 0, complexity_fingerprint(&self.a, inner_value))),
+                seed,
+            },
+            Either::Right(inner_value) => EitherMutatorStep {
+                inner: Either::Right(match seed {
+                    Some(seed) => self.b.seeded_mutation_step_from_value(inner_value, seed),
+                    None => self.b.mutation_step_from_value(inner_value),
+                }),
+                pick_step: 0,
+                rng_seed: step_rng_seed((seed, 1, complexity_fingerprint(&self.b, inner_value))),
+                seed,
+            },
+        }
+    }
+}
+
 impl<A: Mutator, B: Mutator, Map: EitherMap<A = A::Value, B = B::Value>> Mutator for EitherMutator<Map, A, B> {
     type Value = Map::V;
     type Cache = Either<A::Cache, B::Cache>;
@@ -140,32 +251,31 @@ impl<A: Mutator, B: Mutator, Map: EitherMap<A = A::Value, B = B::Value>> Mutator
         }
     }
     fn mutation_step_from_value(&self, value: &Self::Value) -> Self::MutationStep {
-        match Map::get_either(value) {
-            Either::Left(inner_value) => {
-                let inner_step = self.a.mutation_step_from_value(inner_value);
-                EitherMutatorStep {
-                    inner: Either::Left(inner_step),
-                    pick_step: 0,
-                }
-            }
-            Either::Right(inner_value) => {
-                let inner_step = self.b.mutation_step_from_value(inner_value);
-                EitherMutatorStep {
-                    inner: Either::Right(inner_step),
-                    pick_step: 0,
-                }
-            }
-        }
+        self.step_from_value(value, None)
+    }
+
+    fn seeded_mutation_step_from_value(&self, value: &Self::Value, seed: u64) -> Self::MutationStep {
+        self.step_from_value(value, Some(seed))
     }
 
     fn arbitrary(&self, seed: usize, max_cplx: f64) -> (Self::Value, Self::Cache) {
-        let pick_left = seed % 2 == 0;
+        let inner_max_cplx = max_cplx - 1.0;
+        let (min_cplx_a, min_cplx_b) = (self.a.min_complexity(), self.b.min_complexity());
+        // only pick a branch that fits within the complexity budget, so that
+        // recursive mutators eventually stop recursing
+        let pick_left = match (min_cplx_a <= inner_max_cplx, min_cplx_b <= inner_max_cplx) {
+            (true, true) if self.adaptive => SmallRng::seed_from_u64(seed as u64).gen_bool(self.variant_weights().0),
+            (true, true) => seed % 2 == 0,
+            (true, false) => true,
+            (false, true) => false,
+            (false, false) => min_cplx_a <= min_cplx_b,
+        };
         let seed = seed / 2;
         if pick_left {
-            let (inner_value, inner_cache) = self.a.arbitrary(seed, max_cplx - 1.0);
+            let (inner_value, inner_cache) = self.a.arbitrary(seed, inner_max_cplx);
             (Map::left(inner_value), Either::Left(inner_cache))
         } else {
-            let (inner_value, inner_cache) = self.b.arbitrary(seed, max_cplx - 1.0);
+            let (inner_value, inner_cache) = self.b.arbitrary(seed, inner_max_cplx);
             (Map::right(inner_value), Either::Right(inner_cache))
         }
     }
@@ -176,47 +286,67 @@ impl<A: Mutator, B: Mutator, Map: EitherMap<A = A::Value, B = B::Value>> Mutator
         cache: &mut Self::Cache,
         step: &mut Self::MutationStep,
         max_cplx: f64,
-    ) -> Self::UnmutateToken {
+    ) -> Option<Self::UnmutateToken> {
         let max_cplx = max_cplx - 1.0;
         step.pick_step += 1;
 
-        if step.pick_step % 100 == 0 {
-            // switch to a different branch once every 100 times
-            let mut r = SmallRng::from_entropy();
-            match Map::get_either(value) {
-                Either::Left(_) => {
-                    let (tmp_inner_value, tmp_inner_cache) = self.b.arbitrary(r.gen(), max_cplx);
-                    let mut tmp_value = Map::right(tmp_inner_value);
-                    let mut tmp_cache = Either::Right(tmp_inner_cache);
-
-                    std::mem::swap(&mut tmp_cache, cache);
-                    std::mem::swap(&mut tmp_value, value);
+        // the step still belongs to the previous variant if the value was
+        // switched to the other one and that switch was kept
+        let step_matches_value = matches!(
+            (Map::get_either(value), &step.inner),
+            (Either::Left(_), Either::Left(_)) | (Either::Right(_), Either::Right(_))
+        );
+        if !step_matches_value {
+            step.inner = self.step_from_value(value, step.seed).inner;
+        }
 
-                    UnmutateEitherToken::Restore(tmp_value, tmp_cache)
-                }
-                Either::Right(_) => {
-                    let (tmp_inner_value, tmp_inner_cache) = self.a.arbitrary(r.gen(), max_cplx);
-                    let mut tmp_value = Map::left(tmp_inner_value);
-                    let mut tmp_cache = Either::Left(tmp_inner_cache);
+        let mut r = SmallRng::seed_from_u64(step.rng_seed.wrapping_add(step.pick_step as u64));
+        // with uniform weights, this is the variant flip probability itself
+        let (left_weight, right_weight) = self.variant_weights();
+        let other_weight = match Map::get_either(value) {
+            Either::Left(_) => right_weight,
+            Either::Right(_) => left_weight,
+        };
+        let flip_probability = (self.variant_flip_probability * 2.0 * other_weight).min(1.0);
+        if !r.gen_bool(flip_probability) {
+            let token = match match_all_eithers!(Map::get_either_mut(value), cache.as_mut(), step.inner.as_mut()) {
+                Either::Left((inner_value, inner_cache, inner_step)) => self
+                    .a
+                    .mutate(inner_value, inner_cache, inner_step, max_cplx)
+                    .map(UnmutateEitherToken::UnmutateLeft),
+                Either::Right((inner_value, inner_cache, inner_step)) => self
+                    .b
+                    .mutate(inner_value, inner_cache, inner_step, max_cplx)
+                    .map(UnmutateEitherToken::UnmutateRight),
+            };
+            if token.is_some() {
+                return token;
+            }
+            // the current branch cannot be mutated anymore, so switch to the other one
+        }
+        // otherwise, switch to the other variant
+        Some(match Map::get_either(value) {
+            Either::Left(_) => {
+                let (tmp_inner_value, tmp_inner_cache) = self.b.arbitrary(r.gen(), max_cplx);
+                let mut tmp_value = Map::right(tmp_inner_value);
+                let mut tmp_cache = Either::Right(tmp_inner_cache);
 
-                    std::mem::swap(&mut tmp_cache, cache);
-                    std::mem::swap(&mut tmp_value, value);
+                std::mem::swap(&mut tmp_cache, cache);
+                std::mem::swap(&mut tmp_value, value);
 
-                    UnmutateEitherToken::Restore(tmp_value, tmp_cache)
-                }
+                UnmutateEitherToken::Restore(tmp_value, tmp_cache)
             }
-        } else {
-            match match_all_eithers!(Map::get_either_mut(value), cache.as_mut(), step.inner.as_mut()) {
-                Either::Left((inner_value, inner_cache, inner_step)) => {
-                    let inner_token = self.a.mutate(inner_value, inner_cache, inner_step, max_cplx);
-                    UnmutateEitherToken::UnmutateLeft(inner_token)
-                }
-                Either::Right((inner_value, inner_cache, inner_step)) => {
-                    let inner_token = self.b.mutate(inner_value, inner_cache, inner_step, max_cplx);
-                    UnmutateEitherToken::UnmutateRight(inner_token)
-                }
+            Either::Right(_) => {
+                let (tmp_inner_value, tmp_inner_cache) = self.a.arbitrary(r.gen(), max_cplx);
+                let mut tmp_value = Map::left(tmp_inner_value);
+                let mut tmp_cache = Either::Left(tmp_inner_cache);
+
+                std::mem::swap(&mut tmp_cache, cache);
+                std::mem::swap(&mut tmp_value, value);
+
+                UnmutateEitherToken::Restore(tmp_value, tmp_cache)
             }
-        }
+        })
     }
 
     fn unmutate(&self, value: &mut Self::Value, cache: &mut Self::Cache, t: Self::UnmutateToken) {
@@ -237,4 +367,270 @@ impl<A: Mutator, B: Mutator, Map: EitherMap<A = A::Value, B = B::Value>> Mutator
             }
         }
     }
+
+    /// Combines the payloads of two values of the same variant
+    fn crossover(
+        &self,
+        a: &Self::Value,
+        a_cache: &Self::Cache,
+        b: &Self::Value,
+        b_cache: &Self::Cache,
+        seed: usize,
+        max_cplx: f64,
+    ) -> Option<(Self::Value, Self::Cache)> {
+        let max_cplx = max_cplx - 1.0;
+        match (
+            match_all_eithers!(Map::get_either(a), a_cache.as_ref()),
+            match_all_eithers!(Map::get_either(b), b_cache.as_ref()),
+        ) {
+            (Either::Left((a, a_cache)), Either::Left((b, b_cache))) => {
+                let (value, cache) = self.a.crossover(a, a_cache, b, b_cache, seed, max_cplx)?;
+                Some((Map::left(value), Either::Left(cache)))
+            }
+            (Either::Right((a, a_cache)), Either::Right((b, b_cache))) => {
+                let (value, cache) = self.b.crossover(a, a_cache, b, b_cache, seed, max_cplx)?;
+                Some((Map::right(value), Either::Right(cache)))
+            }
+            _ => None,
+        }
+    }
+
+    fn record_mutation_outcome(&self, value: &Self::Value, token: &Self::UnmutateToken, nbr_new_features: usize) {
+        match token {
+            UnmutateEitherToken::Restore(..) => {
+                if !self.adaptive {
+                    return;
+                }
+                // the value was switched to its current variant
+                let variant = match Map::get_either(value) {
+                    Either::Left(_) => 0,
+                    Either::Right(_) => 1,
+                };
+                let mut stats = self.variant_stats.get();
+                stats.switches[variant] += 1;
+                if nbr_new_features > 0 {
+                    stats.productive_switches[variant] += 1;
+                }
+                self.variant_stats.set(stats);
+            }
+            UnmutateEitherToken::UnmutateLeft(left_token) => {
+                let left_value = Map::get_either(value).unwrap_left();
+                self.a.record_mutation_outcome(left_value, left_token, nbr_new_features);
+            }
+            UnmutateEitherToken::UnmutateRight(right_token) => {
+                let right_value = Map::get_either(value).unwrap_right();
+                self.b
+                    .record_mutation_outcome(right_value, right_token, nbr_new_features);
+            }
+        }
+    }
+
+    fn describe_value(&self, value: &Self::Value) -> Option<String> {
+        match Map::get_either(value) {
+            Either::Left(inner_value) => self.a.describe_value(inner_value),
+            Either::Right(inner_value) => self.b.describe_value(inner_value),
+        }
+    }
+
+    /// The learned weights of the variants, if the mutator is adaptive
+    fn complexity_breakdown(&self, _value: &Self::Value, _cache: &Self::Cache) -> Vec<(&'static str, f64)> {
+        if !self.adaptive {
+            return vec![];
+        }
+        let (left_weight, right_weight) = self.variant_weights();
+        vec![
+            ("left variant weight", left_weight),
+            ("right variant weight", right_weight),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integer::U8Mutator;
+    use crate::vector::VecMutator;
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Shape {
+        Circle(u8),
+        Square(u8),
+    }
+
+    impl EitherMap for Shape {
+        type A = u8;
+        type B = u8;
+        type V = Self;
+
+        fn left(a: u8) -> Self {
+            Shape::Circle(a)
+        }
+        fn right(b: u8) -> Self {
+            Shape::Square(b)
+        }
+        fn get_either(v: &Self) -> Either<&u8, &u8> {
+            match v {
+                Shape::Circle(a) => Either::Left(a),
+                Shape::Square(b) => Either::Right(b),
+            }
+        }
+        fn get_either_mut(v: &mut Self) -> Either<&mut u8, &mut u8> {
+            match v {
+                Shape::Circle(a) => Either::Left(a),
+                Shape::Square(b) => Either::Right(b),
+            }
+        }
+    }
+
+    type ShapeMutator = EitherMutator<Shape, U8Mutator, U8Mutator>;
+
+    /// The number of mutations, out of 200, that switch the variant of a circle
+    fn nbr_flips(m: &ShapeMutator) -> usize {
+        let mut x = Shape::Circle(0);
+        let mut cache = m.cache_from_value(&x);
+        let mut step = m.mutation_step_from_value(&x);
+        let mut nbr_flips = 0;
+        for _ in 0..200 {
+            let token = m.mutate(&mut x, &mut cache, &mut step, 9.0).unwrap();
+            if let Shape::Square(_) = x {
+                nbr_flips += 1;
+            }
+            m.unmutate(&mut x, &mut cache, token);
+            assert_eq!(x, Shape::Circle(0));
+        }
+        nbr_flips
+    }
+
+    #[test]
+    fn test_variant_flip_probability() {
+        assert_eq!(
+            nbr_flips(&ShapeMutator::default().with_variant_flip_probability(0.0)),
+            0
+        );
+        assert_eq!(
+            nbr_flips(&ShapeMutator::default().with_variant_flip_probability(1.0)),
+            200
+        );
+        let nbr_default_flips = nbr_flips(&ShapeMutator::default());
+        assert!(nbr_default_flips < 20);
+
+        // a switch of variant that is kept does not confuse the next mutations
+        let m = ShapeMutator::default().with_variant_flip_probability(0.5);
+        let mut x = Shape::Circle(0);
+        let mut cache = m.cache_from_value(&x);
+        let mut step = m.mutation_step_from_value(&x);
+        for _ in 0..1000 {
+            let _ = m.mutate(&mut x, &mut cache, &mut step, 9.0);
+        }
+    }
+
+    #[test]
+    fn test_nested_adaptive_variants() {
+        // the outcomes of the mutations of the elements of a vector reach
+        // the mutator of the elements
+        let new_mutator = || {
+            VecMutator::new(
+                ShapeMutator::default()
+                    .with_variant_flip_probability(0.5)
+                    .with_adaptive_variants(),
+            )
+        };
+        // the number of mutations, out of 400, that switch a circle to a
+        // square, where these switches cover new features if `record` is true
+        let nbr_squares = |m: &VecMutator<ShapeMutator>, record: bool| {
+            let start = vec![Shape::Circle(0), Shape::Circle(0)];
+            let mut x = start.clone();
+            let mut cache = m.cache_from_value(&x);
+            let mut step = m.mutation_step_from_value(&x);
+            let mut nbr_squares = 0;
+            for _ in 0..400 {
+                let token = m.mutate(&mut x, &mut cache, &mut step, 100.0).unwrap();
+                let is_square = x.iter().any(|shape| matches!(shape, Shape::Square(_)));
+                if is_square {
+                    nbr_squares += 1;
+                }
+                if record {
+                    m.record_mutation_outcome(&x, &token, if is_square { 1 } else { 0 });
+                }
+                m.unmutate(&mut x, &mut cache, token);
+                assert_eq!(x, start);
+            }
+            nbr_squares
+        };
+        let (learning, fixed) = (new_mutator(), new_mutator());
+        nbr_squares(&learning, true);
+        nbr_squares(&fixed, false);
+        assert!(nbr_squares(&learning, false) > nbr_squares(&fixed, false));
+    }
+
+    #[test]
+    fn test_seed_is_forwarded() {
+        // the seed reaches the steps of the elements of a vector
+        let m = VecMutator::new(ShapeMutator::default().with_variant_flip_probability(0.5));
+        let mutations = |seed: u64| {
+            let mut x = vec![Shape::Circle(0), Shape::Circle(1)];
+            let mut cache = m.cache_from_value(&x);
+            let mut step = m.seeded_mutation_step_from_value(&x, seed);
+            let mut mutations = vec![];
+            for _ in 0..32 {
+                let token = m.mutate(&mut x, &mut cache, &mut step, 100.0).unwrap();
+                mutations.push(x.clone());
+                m.unmutate(&mut x, &mut cache, token);
+            }
+            mutations
+        };
+        assert_eq!(mutations(1), mutations(1));
+        assert_ne!(mutations(1), mutations(2));
+    }
+
+    #[test]
+    fn test_adaptive_variants() {
+        let m = ShapeMutator::default()
+            .with_variant_flip_probability(0.5)
+            .with_adaptive_variants();
+        assert_eq!(m.variant_weights(), (0.5, 0.5));
+        // only the switches to a square cover new features
+        for start in [Shape::Circle(0), Shape::Square(0)].iter() {
+            let mut x = start.clone();
+            let mut cache = m.cache_from_value(&x);
+            let mut step = m.mutation_step_from_value(&x);
+            for _ in 0..200 {
+                let token = m.mutate(&mut x, &mut cache, &mut step, 9.0).unwrap();
+                let nbr_new_features = if let Shape::Square(_) = x { 1 } else { 0 };
+                m.record_mutation_outcome(&x, &token, nbr_new_features);
+                m.unmutate(&mut x, &mut cache, token);
+                assert_eq!(&x, start);
+            }
+        }
+        let (left_weight, right_weight) = m.variant_weights();
+        assert!(right_weight > 0.9);
+        assert!((left_weight + right_weight - 1.0).abs() < 1e-9);
+        let breakdown = m.complexity_breakdown(&Shape::Circle(0), &m.cache_from_value(&Shape::Circle(0)));
+        assert_eq!(
+            breakdown,
+            vec![
+                ("left variant weight", left_weight),
+                ("right variant weight", right_weight)
+            ]
+        );
+
+        // squares are now generated and switched to more often
+        let nbr_squares = (0..1000)
+            .filter(|&seed| matches!(m.arbitrary(seed, 9.0).0, Shape::Square(_)))
+            .count();
+        assert!(nbr_squares > 800);
+        assert!(nbr_flips(&m) > 150);
+
+        // a mutator that is not adaptive keeps uniform weights
+        let m = ShapeMutator::default().with_variant_flip_probability(1.0);
+        let mut x = Shape::Circle(0);
+        let mut cache = m.cache_from_value(&x);
+        let mut step = m.mutation_step_from_value(&x);
+        let token = m.mutate(&mut x, &mut cache, &mut step, 9.0).unwrap();
+        for _ in 0..MIN_ADAPTIVE_SWITCHES {
+            m.record_mutation_outcome(&x, &token, 1);
+        }
+        assert_eq!(m.variant_weights(), (0.5, 0.5));
+        assert!(m.complexity_breakdown(&x, &cache).is_empty());
+    }
 }
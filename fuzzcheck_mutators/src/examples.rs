@@ -0,0 +1,304 @@
+//! A mutator for types that have example values but no generator.
+//!
+//! Writing a mutator can be hard for complex types, such as the syntax tree
+//! of a programming language or the configuration of a server, while a few
+//! example values are easy to come by. An [ExamplesMutator] produces these
+//! examples, and can also perturb them with a mutator of the type, if there
+//! is one that is good enough at mutating but not at generating values.
+
+extern crate fuzzcheck;
+use fuzzcheck::Mutator;
+
+use std::marker::PhantomData;
+
+/**
+ * A mutator whose values are picked from a list of examples, where `M` is a
+ * mutator of the examples.
+ *
+ * Its arbitrary values are examples. A mutation either replaces the value by
+ * another example, or mutates it with `M`. The complexity of a value is the
+ * one given by `M`.
+ *
+ * [ExamplesMutator::new] uses a [FixedMutator], which never mutates the
+ * examples and gives them all the same complexity, so that only the examples
+ * themselves are tested.
+ */
+pub struct ExamplesMutator<M>
+where
+    M: Mutator,
+{
+    examples: Vec<M::Value>,
+    m: M,
+}
+
+impl<T: Clone> ExamplesMutator<FixedMutator<T>> {
+    /// A mutator that only produces the given examples, which all have the
+    /// complexity `cplx`
+    pub fn new(examples: Vec<T>, cplx: f64) -> Self {
+        Self::with_mutator(examples, FixedMutator::new(cplx))
+    }
+}
+
+impl<M: Mutator> ExamplesMutator<M> {
+    /// A mutator that produces the given examples and mutates them with `m`
+    pub fn with_mutator(examples: Vec<M::Value>, m: M) -> Self {
+        assert!(!examples.is_empty(), "an ExamplesMutator needs at least one example");
+        Self { examples, m }
+    }
+}
+
+#[derive(Clone)]
+pub struct ExamplesMutatorCache<C> {
+    inner: C,
+    /// The number of times that the value was replaced by an example since
+    /// the cache was created, which tells the mutation step whether its inner
+    /// step still belongs to the value
+    nbr_replacements: usize,
+}
+
+#[derive(Clone)]
+pub struct ExamplesMutatorStep<S> {
+    /// The index of the next example to try
+    next_example: usize,
+    inner: S,
+    pick_step: usize,
+    /// The number of replacements in the cache when `inner` was computed
+    nbr_replacements: usize,
+}
+
+pub enum UnmutateExamplesToken<V, C, T> {
+    /// The value was mutated by the inner mutator
+    Inner(T),
+    /// The value was replaced by an example, and was this value before
+    Replace(V, C),
+}
+
+impl<M: Mutator> Mutator for ExamplesMutator<M> {
+    type Value = M::Value;
+    type Cache = ExamplesMutatorCache<M::Cache>;
+    type MutationStep = ExamplesMutatorStep<M::MutationStep>;
+    type UnmutateToken = UnmutateExamplesToken<M::Value, M::Cache, M::UnmutateToken>;
+
+    fn cache_from_value(&self, value: &Self::Value) -> Self::Cache {
+        ExamplesMutatorCache {
+            inner: self.m.cache_from_value(value),
+            nbr_replacements: 0,
+        }
+    }
+
+    fn mutation_step_from_value(&self, value: &Self::Value) -> Self::MutationStep {
+        ExamplesMutatorStep {
+            next_example: 0,
+            inner: self.m.mutation_step_from_value(value),
+            pick_step: 0,
+            nbr_replacements: 0,
+        }
+    }
+
+    fn arbitrary(&self, seed: usize, max_cplx: f64) -> (Self::Value, Self::Cache) {
+        // the first example from the seed that is simple enough, if any
+        let nbr_examples = self.examples.len();
+        let mut fallback = None;
+        for i in 0..nbr_examples {
+            let example = &self.examples[(seed + i) % nbr_examples];
+            let cache = self.cache_from_value(example);
+            if self.complexity(example, &cache) <= max_cplx {
+                return (example.clone(), cache);
+            }
+            fallback.get_or_insert((example, cache));
+        }
+        let (example, cache) = fallback.unwrap();
+        (example.clone(), cache)
+    }
+
+    fn max_complexity(&self) -> f64 {
+        self.m.max_complexity()
+    }
+
+    fn min_complexity(&self) -> f64 {
+        self.m.min_complexity()
+    }
+
+    fn complexity(&self, value: &Self::Value, cache: &Self::Cache) -> f64 {
+        self.m.complexity(value, &cache.inner)
+    }
+
+    fn mutate(
+        &self,
+        value: &mut Self::Value,
+        cache: &mut Self::Cache,
+        step: &mut Self::MutationStep,
+        max_cplx: f64,
+    ) -> Option<Self::UnmutateToken> {
+        // the value was replaced by an example and that replacement was
+        // kept, so the next inner mutations start from the example
+        if step.nbr_replacements != cache.nbr_replacements {
+            step.inner = self.m.mutation_step_from_value(value);
+            step.nbr_replacements = cache.nbr_replacements;
+        }
+        step.pick_step += 1;
+        // alternate between the examples and the inner mutator, until the
+        // examples are exhausted
+        if step.pick_step % 2 == 0 {
+            if let Some(t) = self.m.mutate(value, &mut cache.inner, &mut step.inner, max_cplx) {
+                return Some(UnmutateExamplesToken::Inner(t));
+            }
+        }
+        while let Some(example) = self.examples.get(step.next_example) {
+            step.next_example += 1;
+            let example_cache = self.m.cache_from_value(example);
+            if self.m.complexity(example, &example_cache) > max_cplx {
+                continue;
+            }
+            let old_value = std::mem::replace(value, example.clone());
+            let old_cache = std::mem::replace(&mut cache.inner, example_cache);
+            cache.nbr_replacements = cache.nbr_replacements.wrapping_add(1);
+            return Some(UnmutateExamplesToken::Replace(old_value, old_cache));
+        }
+        self.m
+            .mutate(value, &mut cache.inner, &mut step.inner, max_cplx)
+            .map(UnmutateExamplesToken::Inner)
+    }
+
+    fn unmutate(&self, value: &mut Self::Value, cache: &mut Self::Cache, t: Self::UnmutateToken) {
+        match t {
+            UnmutateExamplesToken::Inner(t) => self.m.unmutate(value, &mut cache.inner, t),
+            UnmutateExamplesToken::Replace(old_value, old_cache) => {
+                *value = old_value;
+                cache.inner = old_cache;
+                cache.nbr_replacements = cache.nbr_replacements.wrapping_sub(1);
+            }
+        }
+    }
+
+    fn describe_value(&self, value: &Self::Value) -> Option<String> {
+        self.m.describe_value(value)
+    }
+}
+
+/**
+ * A mutator that never mutates its values, which all have the same
+ * complexity.
+ *
+ * It cannot generate values either, and is only meant to be the inner
+ * mutator of an [ExamplesMutator] that is given all the values to test.
+ */
+pub struct FixedMutator<T> {
+    cplx: f64,
+    _phantom: PhantomData<T>,
+}
+
+impl<T> FixedMutator<T> {
+    pub fn new(cplx: f64) -> Self {
+        Self {
+            cplx,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: Clone> Mutator for FixedMutator<T> {
+    type Value = T;
+    type Cache = ();
+    type MutationStep = ();
+    type UnmutateToken = ();
+
+    fn cache_from_value(&self, _value: &Self::Value) -> Self::Cache {}
+    fn mutation_step_from_value(&self, _value: &Self::Value) -> Self::MutationStep {}
+
+    fn arbitrary(&self, _seed: usize, _max_cplx: f64) -> (Self::Value, Self::Cache) {
+        panic!("a FixedMutator cannot generate values, use it in an ExamplesMutator instead")
+    }
+
+    fn max_complexity(&self) -> f64 {
+        self.cplx
+    }
+
+    fn min_complexity(&self) -> f64 {
+        self.cplx
+    }
+
+    fn complexity(&self, _value: &Self::Value, _cache: &Self::Cache) -> f64 {
+        self.cplx
+    }
+
+    fn mutate(
+        &self,
+        _value: &mut Self::Value,
+        _cache: &mut Self::Cache,
+        _step: &mut Self::MutationStep,
+        _max_cplx: f64,
+    ) -> Option<Self::UnmutateToken> {
+        None
+    }
+
+    fn unmutate(&self, _value: &mut Self::Value, _cache: &mut Self::Cache, _t: Self::UnmutateToken) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integer::U8Mutator;
+
+    #[test]
+    fn test_examples_mutator() {
+        let examples = vec!["GET /".to_owned(), "POST /login".to_owned(), "HEAD /".to_owned()];
+        let m = ExamplesMutator::new(examples.clone(), 16.0);
+        for seed in 0..6 {
+            let (x, cache) = m.arbitrary(seed, 100.0);
+            assert_eq!(x, examples[seed % 3]);
+            assert_eq!(m.complexity(&x, &cache), 16.0);
+        }
+        // each example is tried once, and then the mutations are exhausted
+        let (mut x, mut cache) = m.arbitrary(0, 100.0);
+        let mut step = m.mutation_step_from_value(&x);
+        let mut mutated = vec![];
+        while let Some(token) = m.mutate(&mut x, &mut cache, &mut step, 100.0) {
+            mutated.push(x.clone());
+            m.unmutate(&mut x, &mut cache, token);
+            assert_eq!(x, examples[0]);
+        }
+        assert_eq!(mutated, examples);
+        // no example is simple enough
+        let mut step = m.mutation_step_from_value(&x);
+        assert!(m.mutate(&mut x, &mut cache, &mut step, 8.0).is_none());
+
+        // with an inner mutator, the examples are perturbed too
+        let m = ExamplesMutator::with_mutator(vec![0u8, 100, 200], U8Mutator::default());
+        let (mut x, mut cache) = m.arbitrary(1, 8.0);
+        assert_eq!(x, 100);
+        let mut step = m.mutation_step_from_value(&x);
+        let mut nbr_perturbed = 0;
+        for _ in 0..100 {
+            let token = m.mutate(&mut x, &mut cache, &mut step, 8.0).unwrap();
+            if ![0, 100, 200].contains(&x) {
+                nbr_perturbed += 1;
+            }
+            m.unmutate(&mut x, &mut cache, token);
+            assert_eq!(x, 100);
+        }
+        assert!(nbr_perturbed > 90);
+    }
+
+    #[test]
+    fn test_kept_example_gets_a_new_step() {
+        let u8_mutator = U8Mutator::default();
+        let m = ExamplesMutator::with_mutator(vec![0u8, 100, 200], U8Mutator::default());
+        let (mut x, mut cache) = m.arbitrary(0, 8.0);
+        let mut step = m.mutation_step_from_value(&x);
+        // an example, then a mutation of the inner mutator, both unmutated
+        for _ in 0..2 {
+            let token = m.mutate(&mut x, &mut cache, &mut step, 8.0).unwrap();
+            m.unmutate(&mut x, &mut cache, token);
+        }
+        // the next example is kept
+        let _ = m.mutate(&mut x, &mut cache, &mut step, 8.0).unwrap();
+        assert_eq!(x, 100);
+        // it is mutated like a new value
+        let _ = m.mutate(&mut x, &mut cache, &mut step, 8.0).unwrap();
+        let mut expected = 100;
+        let mut u8_step = u8_mutator.mutation_step_from_value(&expected);
+        let _ = u8_mutator.mutate(&mut expected, &mut (), &mut u8_step, 8.0);
+        assert_eq!(x, expected);
+    }
+}
@@ -0,0 +1,220 @@
+//! A mutator for integers that are sets of bit flags.
+
+extern crate fuzzcheck;
+use fuzzcheck::Mutator;
+
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use crate::step_rng_seed;
+
+/// The probability that a random mutation sets or clears a bit that is not
+/// part of any known flag, to test how the code validates its input
+const UNKNOWN_BIT_PROBABILITY: f64 = 0.05;
+/// The number of mutation steps tried before giving up on finding a value
+/// different from the current one
+const MAX_MUTATION_ATTEMPTS: usize = 16;
+
+/**
+ * A mutator for a `u32` that is a set of bit flags.
+ *
+ * Random integers rarely form meaningful combinations of flags. So this
+ * mutator is built from the list of valid flags, and it sets, clears, and
+ * toggles these flags instead of random bits. Occasionally, it also sets a
+ * bit that is not part of any flag.
+ *
+ * The complexity of a value is 1.0 plus the number of known flags that it
+ * contains, plus 1.0 if it contains any unknown bit.
+ */
+#[derive(Clone)]
+pub struct FlagsMutator {
+    flags: Vec<u32>,
+    unknown_bits: u32,
+}
+
+impl FlagsMutator {
+    /// Creates a mutator for the sets of the given flags. Each flag is a bit
+    /// mask, which may have more than one bit set.
+    pub fn new(flags: &[u32]) -> Self {
+        let mut flags: Vec<u32> = flags.iter().copied().filter(|&f| f != 0).collect();
+        flags.sort_unstable();
+        flags.dedup();
+        let known_bits = flags.iter().fold(0, |bits, &f| bits | f);
+        Self {
+            flags,
+            unknown_bits: !known_bits,
+        }
+    }
+
+    fn nbr_flags(&self, value: u32) -> usize {
+        let nbr_known = self.flags.iter().filter(|&&f| value & f == f).count();
+        let has_unknown = value & self.unknown_bits != 0;
+        nbr_known + has_unknown as usize
+    }
+
+    /// The value after the mutation of the given step.
+    ///
+    /// The first steps toggle each flag in turn. The next ones toggle, set,
+    /// or clear a random flag, or set or clear the unknown bits.
+    fn mutated(&self, value: u32, step: u64, max_cplx: f64) -> u32 {
+        let mut rng = SmallRng::seed_from_u64(step_rng_seed((value, step)));
+        let nbr_flags = self.flags.len() as u64;
+        let mut mutated = if step < nbr_flags {
+            value ^ self.flags[step as usize]
+        } else if self.flags.is_empty() || (self.unknown_bits != 0 && rng.gen_bool(UNKNOWN_BIT_PROBABILITY)) {
+            if value & self.unknown_bits != 0 {
+                value & !self.unknown_bits
+            } else {
+                self.set_unknown_bit(value, &mut rng)
+            }
+        } else {
+            let flag = self.flags[rng.gen_range(0, self.flags.len())];
+            match rng.gen_range(0, 3) {
+                0 => value ^ flag,
+                1 => value | flag,
+                _ => value & !flag,
+            }
+        };
+        // stay within the complexity budget by removing the flags that were added
+        if self.complexity(&mutated, &()) > max_cplx {
+            mutated &= !(self.unknown_bits & !value);
+            for &flag in self.flags.iter() {
+                if mutated & flag == flag && value & flag != flag {
+                    mutated &= !flag;
+                }
+            }
+        }
+        mutated
+    }
+
+    /// Sets a random bit that is not part of any known flag
+    fn set_unknown_bit(&self, value: u32, rng: &mut SmallRng) -> u32 {
+        let nbr_unknown_bits = self.unknown_bits.count_ones();
+        let mut nth = rng.gen_range(0, nbr_unknown_bits);
+        for bit in 0..32 {
+            let mask = 1 << bit;
+            if self.unknown_bits & mask != 0 {
+                if nth == 0 {
+                    return value | mask;
+                }
+                nth -= 1;
+            }
+        }
+        unreachable!()
+    }
+}
+
+impl Mutator for FlagsMutator {
+    type Value = u32;
+    type Cache = ();
+    /// The number of mutations performed so far
+    type MutationStep = u64;
+    /// The value before the mutation
+    type UnmutateToken = u32;
+
+    fn cache_from_value(&self, _value: &Self::Value) -> Self::Cache {}
+    fn mutation_step_from_value(&self, _value: &Self::Value) -> Self::MutationStep {
+        0
+    }
+
+    fn arbitrary(&self, seed: usize, max_cplx: f64) -> (Self::Value, Self::Cache) {
+        // the empty set, then each flag on its own, then random combinations
+        if seed == 0 || self.flags.is_empty() {
+            return (0, ());
+        }
+        if let Some(&flag) = self.flags.get(seed - 1) {
+            return (flag, ());
+        }
+        let mut rng = SmallRng::seed_from_u64(seed as u64);
+        let mut value = 0;
+        for &flag in self.flags.iter() {
+            if rng.gen() && self.complexity(&(value | flag), &()) <= max_cplx {
+                value |= flag;
+            }
+        }
+        if self.unknown_bits != 0
+            && rng.gen_bool(UNKNOWN_BIT_PROBABILITY)
+            && self.complexity(&(value | self.unknown_bits), &()) <= max_cplx
+        {
+            value = self.set_unknown_bit(value, &mut rng);
+        }
+        (value, ())
+    }
+
+    fn max_complexity(&self) -> f64 {
+        1.0 + self.nbr_flags(std::u32::MAX) as f64
+    }
+
+    fn min_complexity(&self) -> f64 {
+        1.0
+    }
+
+    fn complexity(&self, value: &Self::Value, _cache: &Self::Cache) -> f64 {
+        1.0 + self.nbr_flags(*value) as f64
+    }
+
+    fn mutate(
+        &self,
+        value: &mut Self::Value,
+        _cache: &mut Self::Cache,
+        step: &mut Self::MutationStep,
+        max_cplx: f64,
+    ) -> Option<Self::UnmutateToken> {
+        let token = *value;
+        // a mutation may give back the same value, e.g. when setting a flag
+        // that is already set, so try a few steps
+        for _ in 0..MAX_MUTATION_ATTEMPTS {
+            *value = self.mutated(token, *step, max_cplx);
+            *step = step.wrapping_add(1);
+            if *value != token {
+                return Some(token);
+            }
+        }
+        *value = token;
+        None
+    }
+
+    fn unmutate(&self, value: &mut Self::Value, _cache: &mut Self::Cache, t: Self::UnmutateToken) {
+        *value = t;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const READ: u32 = 0b001;
+    const WRITE: u32 = 0b010;
+    const EXEC: u32 = 0b100;
+
+    #[test]
+    fn test_flags_mutator() {
+        let m = FlagsMutator::new(&[READ, WRITE, EXEC]);
+        assert_eq!(m.min_complexity(), 1.0);
+        assert_eq!(m.max_complexity(), 5.0);
+        assert_eq!(m.complexity(&(READ | EXEC), &()), 3.0);
+
+        let values: Vec<u32> = (0..1000).map(|seed| m.arbitrary(seed, 10.0).0).collect();
+        assert_eq!(&values[..4], &[0, READ, WRITE, EXEC]);
+        assert!(values.contains(&(READ | WRITE | EXEC)));
+        // only a few values contain unknown bits
+        let nbr_unknown = values.iter().filter(|&&v| v & !(READ | WRITE | EXEC) != 0).count();
+        assert!(nbr_unknown > 0 && nbr_unknown < 200);
+        for seed in 0..1000 {
+            assert!(m.complexity(&m.arbitrary(seed, 2.0).0, &()) <= 2.0);
+        }
+
+        let mut x = READ;
+        let mut x_step = m.mutation_step_from_value(&x);
+        for i in 0..1000 {
+            let token = m.mutate(&mut x, &mut (), &mut x_step, 3.0).unwrap();
+            if i < 3 {
+                // each flag is toggled in turn
+                assert_eq!(x, READ ^ [READ, WRITE, EXEC][i]);
+            }
+            assert!(m.complexity(&x, &()) <= 3.0);
+            m.unmutate(&mut x, &mut (), token);
+            assert_eq!(x, READ);
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//! Mutators for `f32` and `f64`.
+//!
+//! The first mutations of a value replace it by one of the special floats
+//! (zeros, ±1, infinities, NaN, the smallest and largest numbers, epsilon),
+//! and the following ones mutate its bits with the mutator of the unsigned
+//! integer of the same width.
+
+extern crate fuzzcheck;
+use fuzzcheck::Mutator;
+
+use crate::integer::{U32Mutator, U64Mutator};
+use crate::DefaultMutator;
+
+macro_rules! impl_float_mutator {
+    ($doc:literal, $name:ident, $t:ident, $bits_mutator:ident) => {
+        #[doc = $doc]
+        #[derive(Clone, Default)]
+        pub struct $name {
+            bits: $bits_mutator,
+        }
+
+        impl $name {
+            const SPECIAL_VALUES: [$t; 11] = [
+                0.0,
+                -0.0,
+                1.0,
+                -1.0,
+                $t::INFINITY,
+                $t::NEG_INFINITY,
+                $t::NAN,
+                $t::MIN_POSITIVE,
+                $t::MAX,
+                $t::MIN,
+                $t::EPSILON,
+            ];
+        }
+
+        impl Mutator for $name {
+            type Value = $t;
+            type Cache = ();
+            type MutationStep = u64; // mutation step
+            type UnmutateToken = $t; // old value
+
+            fn cache_from_value(&self, _value: &Self::Value) -> Self::Cache {}
+            fn mutation_step_from_value(&self, _value: &Self::Value) -> Self::MutationStep {
+                0
+            }
+
+            fn arbitrary(&self, seed: usize, max_cplx: f64) -> (Self::Value, Self::Cache) {
+                let nbr_special = Self::SPECIAL_VALUES.len();
+                let value = if seed < nbr_special {
+                    Self::SPECIAL_VALUES[seed]
+                } else {
+                    $t::from_bits(self.bits.arbitrary(seed - nbr_special, max_cplx).0)
+                };
+                (value, ())
+            }
+
+            fn max_complexity(&self) -> f64 {
+                self.bits.max_complexity()
+            }
+
+            fn min_complexity(&self) -> f64 {
+                self.bits.min_complexity()
+            }
+
+            fn complexity(&self, _value: &Self::Value, _cache: &Self::Cache) -> f64 {
+                self.bits.max_complexity()
+            }
+
+            fn mutate(
+                &self,
+                value: &mut Self::Value,
+                _cache: &mut Self::Cache,
+                step: &mut Self::MutationStep,
+                max_cplx: f64,
+            ) -> Option<Self::UnmutateToken> {
+                let token = *value;
+                let nbr_special = Self::SPECIAL_VALUES.len() as u64;
+                while *step < nbr_special {
+                    let special = Self::SPECIAL_VALUES[*step as usize];
+                    *step += 1;
+                    // compare the bits, so that 0.0 and -0.0 are different and NaN is equal to itself
+                    if special.to_bits() != value.to_bits() {
+                        *value = special;
+                        return Some(token);
+                    }
+                }
+                let mut bits = value.to_bits();
+                let mut bits_step = *step - nbr_special;
+                self.bits.mutate(&mut bits, &mut (), &mut bits_step, max_cplx)?;
+                *step = bits_step + nbr_special;
+                *value = $t::from_bits(bits);
+                Some(token)
+            }
+
+            fn unmutate(&self, value: &mut Self::Value, _cache: &mut Self::Cache, t: Self::UnmutateToken) {
+                *value = t;
+            }
+        }
+
+        impl DefaultMutator for $t {
+            type Mutator = $name;
+            fn default_mutator() -> Self::Mutator {
+                $name::default()
+            }
+        }
+    };
+}
+impl_float_mutator!("A mutator for `f32`", F32Mutator, f32, U32Mutator);
+impl_float_mutator!("A mutator for `f64`", F64Mutator, f64, U64Mutator);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    macro_rules! test_float_mutator {
+        ($test:ident, $name:ident, $t:ident) => {
+            #[test]
+            fn $test() {
+                let m = <$t>::default_mutator();
+                let nbr_bits = std::mem::size_of::<$t>() * 8;
+                assert_eq!(m.max_complexity(), nbr_bits as f64);
+                for seed in 0..20 {
+                    let (mut x, mut x_cache) = m.arbitrary(seed * 1000, nbr_bits as f64);
+                    let mut x_step = m.mutation_step_from_value(&x);
+                    // floats are compared by their bits, because NaN is not equal to itself
+                    let mut results = HashSet::new();
+                    for _ in 0..1000 {
+                        let token = m
+                            .mutate(&mut x, &mut x_cache, &mut x_step, nbr_bits as f64)
+                            .unwrap();
+                        assert_ne!(x.to_bits(), token.to_bits());
+                        results.insert(x.to_bits());
+                        m.unmutate(&mut x, &mut x_cache, token);
+                        assert_eq!(x.to_bits(), token.to_bits());
+                    }
+                    // all the special values other than the original one were tried
+                    for special in $name::SPECIAL_VALUES.iter() {
+                        assert!(results.contains(&special.to_bits()) || special.to_bits() == x.to_bits());
+                    }
+                    assert!(results.len() > 990);
+                }
+            }
+        };
+    }
+    test_float_mutator!(test_f32_mutator, F32Mutator, f32);
+    test_float_mutator!(test_f64_mutator, F64Mutator, f64);
+}
@@ -0,0 +1,388 @@
+//! A mutator for the length-prefixed messages of binary protocols, which are
+//! framed as `[tag: u8][len: u16][payload: len bytes]`.
+
+extern crate fuzzcheck;
+use fuzzcheck::Mutator;
+
+extern crate rand;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use crate::derived::{DerivedField, DerivedFieldCache, DerivedFieldMutator, UnmutateDerivedFieldToken};
+use crate::integer::U8Mutator;
+use crate::vector::VecMutator;
+use crate::{step_rng_seed, DefaultMutator};
+
+use std::convert::TryFrom;
+
+/// The default probability that a mutation or an arbitrary message has a
+/// length field that does not match its payload
+const DEFAULT_CORRUPT_LEN_PROBABILITY: f64 = 0.05;
+
+/// A message of a binary protocol, made of a tag, a length, and a payload.
+///
+/// The length is usually that of the payload, but a mutator can set it to
+/// another value to test how a parser validates its input. Since the framed
+/// bytes of such a message cannot be parsed back into the same message, it
+/// should be serialized field by field rather than with [to_bytes](FramedMessage::to_bytes).
+#[derive(Clone, Debug, PartialEq, Hash)]
+pub struct FramedMessage {
+    pub tag: u8,
+    pub len: u16,
+    pub payload: Vec<u8>,
+}
+
+impl FramedMessage {
+    /// A message whose length field matches its payload
+    ///
+    /// # Panics
+    /// Panics if the payload is longer than `u16::MAX` bytes.
+    pub fn new(tag: u8, payload: Vec<u8>) -> Self {
+        Self {
+            tag,
+            len: Self::consistent_len(&payload),
+            payload,
+        }
+    }
+
+    /// The length field that matches the given payload
+    fn consistent_len(payload: &[u8]) -> u16 {
+        u16::try_from(payload.len()).expect("the payload of a FramedMessage must be at most u16::MAX bytes long")
+    }
+
+    /// Whether the length field matches the payload
+    pub fn is_consistent(&self) -> bool {
+        self.len as usize == self.payload.len()
+    }
+
+    /// The framed message: the tag, the length in big-endian, and the payload
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(3 + self.payload.len());
+        bytes.push(self.tag);
+        bytes.extend_from_slice(&self.len.to_be_bytes());
+        bytes.extend_from_slice(&self.payload);
+        bytes
+    }
+}
+
+/// The concatenation of the framed messages, as sent over a connection
+pub fn frame_all(messages: &[FramedMessage]) -> Vec<u8> {
+    messages.iter().flat_map(|m| m.to_bytes()).collect()
+}
+
+/// The length field of a [FramedMessage]
+struct FramedLen;
+
+impl DerivedField for FramedLen {
+    type Value = FramedMessage;
+    type Field = u16;
+
+    /// A corrupt length field carries as much information as a `u16`
+    const CORRUPT_CPLX: f64 = 16.0;
+
+    fn payload(value: &FramedMessage) -> &Vec<u8> {
+        &value.payload
+    }
+    fn payload_mut(value: &mut FramedMessage) -> &mut Vec<u8> {
+        &mut value.payload
+    }
+    fn get_field(value: &FramedMessage) -> u16 {
+        value.len
+    }
+    fn set_field(value: &mut FramedMessage, len: u16) {
+        value.len = len;
+    }
+    fn derive(&self, payload: &[u8]) -> u16 {
+        FramedMessage::consistent_len(payload)
+    }
+    fn corrupt(valid: u16, rng: &mut SmallRng) -> u16 {
+        loop {
+            let len = rng.gen::<u16>();
+            if len != valid {
+                return len;
+            }
+        }
+    }
+}
+
+/**
+ * A mutator for a [FramedMessage], where `P` is the mutator of the payload.
+ *
+ * The tag is mutated by a `U8Mutator` and the payload by `P`, whose length
+ * is limited to `u16::MAX`. Mutations of the payload keep the length field
+ * consistent with it. With the probability given to
+ * [with_corrupt_len_probability](FramedMessageMutator::with_corrupt_len_probability),
+ * a mutation instead sets the length field to a value that does not match
+ * the payload, or restores it if it was already wrong. Arbitrary messages
+ * are corrupted with the same probability.
+ *
+ * The complexity of a message is that of its tag and of its payload. A
+ * corrupt length field adds 16.0, as it holds information of its own.
+ */
+pub struct FramedMessageMutator<P>
+where
+    P: Mutator<Value = Vec<u8>>,
+{
+    tag: U8Mutator,
+    m: DerivedFieldMutator<FramedLen, P>,
+}
+
+impl<P> FramedMessageMutator<P>
+where
+    P: Mutator<Value = Vec<u8>>,
+{
+    /// # Panics
+    /// Panics if `payload` does not support length ranges, as it could then
+    /// produce payloads longer than a length field can hold.
+    pub fn new(mut payload: P) -> Self {
+        assert!(
+            payload.set_length_range(0..=u16::MAX as usize),
+            "the payload mutator of a FramedMessageMutator must support length ranges"
+        );
+        Self {
+            tag: U8Mutator::default(),
+            m: DerivedFieldMutator {
+                derived: FramedLen,
+                payload,
+                corrupt_probability: DEFAULT_CORRUPT_LEN_PROBABILITY,
+            },
+        }
+    }
+
+    /// Sets the probability that the length field does not match the
+    /// payload, 0.05 by default. With 0.0, all messages are well-formed.
+    pub fn with_corrupt_len_probability(mut self, probability: f64) -> Self {
+        assert!((0.0..=1.0).contains(&probability));
+        self.m.corrupt_probability = probability;
+        self
+    }
+}
+
+impl<P> Default for FramedMessageMutator<P>
+where
+    P: Mutator<Value = Vec<u8>> + Default,
+{
+    fn default() -> Self {
+        Self::new(P::default())
+    }
+}
+
+impl DefaultMutator for FramedMessage {
+    type Mutator = FramedMessageMutator<VecMutator<U8Mutator>>;
+    fn default_mutator() -> Self::Mutator {
+        FramedMessageMutator::new(VecMutator::new(U8Mutator::default()))
+    }
+}
+
+pub type FramedMessageMutatorCache<PC> = DerivedFieldCache<PC>;
+
+pub struct FramedMessageMutatorStep<PS> {
+    tag: u64,
+    payload: PS,
+    /// The number of mutations performed so far, which seeds their random decisions
+    nbr_mutations: u64,
+    /// Seeds the random decisions of the mutations, along with `nbr_mutations`
+    rng_seed: u64,
+}
+
+pub enum UnmutateFramedMessageToken<PT> {
+    Tag(u8),
+    /// The payload or the length field was mutated
+    Frame(UnmutateDerivedFieldToken<PT, u16>),
+}
+
+impl<P> Mutator for FramedMessageMutator<P>
+where
+    P: Mutator<Value = Vec<u8>>,
+{
+    type Value = FramedMessage;
+    type Cache = FramedMessageMutatorCache<P::Cache>;
+    type MutationStep = FramedMessageMutatorStep<P::MutationStep>;
+    type UnmutateToken = UnmutateFramedMessageToken<P::UnmutateToken>;
+
+    fn cache_from_value(&self, value: &Self::Value) -> Self::Cache {
+        self.m.cache_from_value(value)
+    }
+
+    fn mutation_step_from_value(&self, value: &Self::Value) -> Self::MutationStep {
+        FramedMessageMutatorStep {
+            tag: self.tag.mutation_step_from_value(&value.tag),
+            payload: self.m.payload.mutation_step_from_value(&value.payload),
+            nbr_mutations: 0,
+            rng_seed: step_rng_seed(value),
+        }
+    }
+
+    fn seeded_mutation_step_from_value(&self, value: &Self::Value, seed: u64) -> Self::MutationStep {
+        FramedMessageMutatorStep {
+            tag: self.tag.seeded_mutation_step_from_value(&value.tag, seed),
+            payload: self.m.payload.seeded_mutation_step_from_value(&value.payload, seed),
+            nbr_mutations: 0,
+            rng_seed: step_rng_seed((seed, value)),
+        }
+    }
+
+    fn max_complexity(&self) -> f64 {
+        8.0 + self.m.payload.max_complexity() + FramedLen::CORRUPT_CPLX
+    }
+
+    fn min_complexity(&self) -> f64 {
+        8.0 + self.m.payload.min_complexity()
+    }
+
+    fn complexity(&self, _value: &Self::Value, cache: &Self::Cache) -> f64 {
+        8.0 + DerivedFieldMutator::<FramedLen, P>::complexity(cache)
+    }
+
+    fn arbitrary(&self, seed: usize, max_cplx: f64) -> (Self::Value, Self::Cache) {
+        let mut rng = SmallRng::seed_from_u64(seed as u64);
+        let (tag, ()) = self.tag.arbitrary(rng.gen(), 8.0);
+        self.m
+            .arbitrary(&mut rng, max_cplx - 8.0, |payload, len| FramedMessage {
+                tag,
+                len,
+                payload,
+            })
+    }
+
+    fn mutate(
+        &self,
+        value: &mut Self::Value,
+        cache: &mut Self::Cache,
+        step: &mut Self::MutationStep,
+        max_cplx: f64,
+    ) -> Option<Self::UnmutateToken> {
+        step.nbr_mutations += 1;
+        let mut rng = SmallRng::seed_from_u64(step.rng_seed.wrapping_add(step.nbr_mutations));
+        if rng.gen_bool(self.m.corrupt_probability) {
+            if let Some(token) = self.m.mutate_field(value, cache, &mut rng, max_cplx - 8.0) {
+                return Some(UnmutateFramedMessageToken::Frame(token));
+            }
+        }
+        // the tag has far fewer values than the payload, so it is mutated less often
+        if step.nbr_mutations % 4 == 0 {
+            if let Some(token) = self.tag.mutate(&mut value.tag, &mut (), &mut step.tag, 8.0) {
+                return Some(UnmutateFramedMessageToken::Tag(token));
+            }
+        }
+        if let Some(token) = self.m.mutate_payload(value, cache, &mut step.payload, max_cplx - 8.0) {
+            return Some(UnmutateFramedMessageToken::Frame(token));
+        }
+        self.tag
+            .mutate(&mut value.tag, &mut (), &mut step.tag, 8.0)
+            .map(UnmutateFramedMessageToken::Tag)
+    }
+
+    fn unmutate(&self, value: &mut Self::Value, cache: &mut Self::Cache, t: Self::UnmutateToken) {
+        match t {
+            UnmutateFramedMessageToken::Tag(tag) => {
+                value.tag = tag;
+            }
+            UnmutateFramedMessageToken::Frame(token) => {
+                self.m.unmutate(value, cache, token);
+            }
+        }
+    }
+
+    fn complexity_breakdown(&self, _value: &Self::Value, cache: &Self::Cache) -> Vec<(&'static str, f64)> {
+        vec![
+            ("tag", 8.0),
+            ("len", DerivedFieldMutator::<FramedLen, P>::field_cplx(cache)),
+            ("payload", cache.payload_cplx),
+        ]
+    }
+
+    fn record_mutation_outcome(&self, value: &Self::Value, token: &Self::UnmutateToken, nbr_new_features: usize) {
+        if let UnmutateFramedMessageToken::Frame(token) = token {
+            self.m.record_mutation_outcome(value, token, nbr_new_features)
+        }
+    }
+
+    fn describe_value(&self, value: &Self::Value) -> Option<String> {
+        Some(format!("{:#?}", value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keep_one_mutation_in_four;
+
+    #[test]
+    fn test_framing() {
+        let message = FramedMessage::new(7, vec![1, 2, 3]);
+        assert!(message.is_consistent());
+        assert_eq!(message.to_bytes(), vec![7, 0, 3, 1, 2, 3]);
+        let other = FramedMessage {
+            tag: 1,
+            len: 258,
+            payload: vec![],
+        };
+        assert!(!other.is_consistent());
+        assert_eq!(frame_all(&[message, other]), vec![7, 0, 3, 1, 2, 3, 1, 1, 2]);
+    }
+
+    #[test]
+    fn test_consistent_framing() {
+        let m = FramedMessage::default_mutator().with_corrupt_len_probability(0.0);
+        for seed in 0..20 {
+            let (mut x, mut cache) = m.arbitrary(seed, 200.0);
+            assert!(x.is_consistent());
+            keep_one_mutation_in_four(&m, &mut x, &mut cache, 200.0, 200, |_, _, x, cache| {
+                assert!(x.is_consistent());
+                assert_eq!(m.complexity(x, cache), m.complexity(x, &m.cache_from_value(x)));
+            });
+        }
+    }
+
+    #[test]
+    fn test_corrupt_framing() {
+        let m = FramedMessage::default_mutator().with_corrupt_len_probability(0.2);
+        let (mut nbr_consistent, mut nbr_corrupt) = (0, 0);
+        for seed in 0..20 {
+            let (mut x, mut cache) = m.arbitrary(seed, 200.0);
+            keep_one_mutation_in_four(&m, &mut x, &mut cache, 200.0, 200, |_, _, x, cache| {
+                if x.is_consistent() {
+                    nbr_consistent += 1;
+                } else {
+                    nbr_corrupt += 1;
+                    assert_ne!(x.len as usize, x.payload.len());
+                }
+                // the cached validity follows the mutations of the payload and of the length field
+                assert_eq!(cache.valid, x.is_consistent());
+                assert_eq!(m.complexity(x, cache), m.complexity(x, &m.cache_from_value(x)));
+            });
+        }
+        assert!(nbr_corrupt > 0);
+        assert!(nbr_consistent > 0);
+
+        // about one arbitrary message in five is corrupt
+        let nbr_corrupt = (0..1000)
+            .filter(|&seed| !m.arbitrary(seed, 200.0).0.is_consistent())
+            .count();
+        assert!(nbr_corrupt > 150 && nbr_corrupt < 250, "{}", nbr_corrupt);
+    }
+
+    #[test]
+    #[should_panic(expected = "must support length ranges")]
+    fn test_payload_mutator_without_length_range() {
+        let _ = FramedMessageMutator::new(crate::examples::ExamplesMutator::new(vec![vec![1u8]], 8.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "at most u16::MAX bytes long")]
+    fn test_payload_too_long() {
+        let _ = FramedMessage::new(0, vec![0; u16::MAX as usize + 1]);
+    }
+
+    #[test]
+    fn test_sequence_of_messages() {
+        let m = Vec::<FramedMessage>::default_mutator();
+        for seed in 0..10 {
+            let (messages, _) = m.arbitrary(seed, 500.0);
+            let bytes = frame_all(&messages);
+            let expected_len: usize = messages.iter().map(|m| 3 + m.payload.len()).sum();
+            assert_eq!(bytes.len(), expected_len);
+        }
+    }
+}
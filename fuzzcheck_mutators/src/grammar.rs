@@ -0,0 +1,866 @@
+//! A mutator for strings that conform to a grammar given at runtime, for
+//! example read from a file.
+//!
+//! ## Grammar format
+//!
+//! Each line defines the alternatives of a rule, separated by `|`. An
+//! alternative is a sequence of terminals, written between double quotes,
+//! and of references to other rules, written between angle brackets. Lines
+//! that are empty or start with `#` are ignored, and a rule that is defined
+//! on several lines has the alternatives of all of them. The first rule is
+//! the start rule.
+//!
+//! ```text
+//! # arithmetic expressions
+//! <expr> ::= <term> | <term> "+" <expr>
+//! <term> ::= <digit> | "(" <expr> ")"
+//! <digit> ::= "0" | "1" | "2" | ""
+//! ```
+//!
+//! Inside a terminal, `\"`, `\\`, `\n`, and `\t` are escape sequences.
+//!
+//! ## Invalid strings
+//!
+//! The rules whose name starts with `invalid` describe the ways in which a
+//! string may break the format, to test the code that validates it. An
+//! alternative that refers to one of them is picked with a fixed probability,
+//! 1% by default, and the other alternatives share the rest. This probability
+//! is set with [GrammarMutator::with_violation_probability], and 0 disables
+//! the invalid alternatives.
+//!
+//! ```text
+//! <digits> ::= <digit> | <digit> <digits>
+//! <digit> ::= "0" | "1" | "2" | <invalid-digit>
+//! <invalid-digit> ::= "a" | " "
+//! ```
+//!
+//! [GrammarMutator::identifier], [GrammarMutator::hostname], and
+//! [GrammarMutator::email_like] are built from such grammars, so that common
+//! formats can be fuzzed without writing one.
+//!
+//! ## Complexity
+//!
+//! The complexity of a string is its length in bytes. The complexity budget
+//! given to `arbitrary` and `mutate` bounds the length of the generated
+//! strings, and the recursion depth of their derivations is bounded as well.
+//!
+//! The mutator keeps the derivation tree of each string in its cache, and
+//! mutates a string by regenerating one node of the tree, or by replacing
+//! it with a copy of another node derived from the same rule. When a string
+//! was not generated by the mutator, for example if it was read from a
+//! corpus, the cache is computed by parsing it with the grammar. A string
+//! that cannot be parsed is replaced by a generated one the first time it is
+//! mutated. The parser gives up on strings that are very long or ambiguous,
+//! and it does not find the derivations that rely on left-recursive rules.
+
+extern crate fuzzcheck;
+use fuzzcheck::Mutator;
+
+extern crate rand;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+
+/// The maximum depth of the derivation trees built by the mutator
+const MAX_DEPTH: usize = 64;
+/// The maximum number of steps spent looking for the derivation tree of a string
+const PARSE_FUEL: usize = 10_000;
+/// The probability of picking an alternative that refers to an invalid rule
+const DEFAULT_VIOLATION_PROBABILITY: f64 = 0.01;
+
+#[derive(Clone, Debug)]
+enum Symbol {
+    Terminal(String),
+    Rule(usize),
+}
+
+/// A set of rules parsed from the format described in the
+/// [module documentation](self)
+#[derive(Clone, Debug)]
+pub struct Grammar {
+    names: Vec<String>,
+    /// The alternatives of each rule, each of which is a sequence of symbols
+    rules: Vec<Vec<Vec<Symbol>>>,
+    /// The length of the shortest string derived from each rule
+    min_len: Vec<usize>,
+    /// The alternative of each rule that starts its shortest derivation
+    shortest_alternative: Vec<usize>,
+    /// Whether each alternative of each rule refers to a rule whose name
+    /// starts with `invalid`
+    violations: Vec<Vec<bool>>,
+    violation_probability: f64,
+}
+
+impl Grammar {
+    /// Parses a grammar, or returns a message describing why it is invalid.
+    ///
+    /// Every referenced rule must be defined, and every rule must derive at
+    /// least one finite string.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut names: Vec<String> = Vec::new();
+        let mut rules: Vec<Vec<Vec<Symbol>>> = Vec::new();
+        let mut defined_rules = Vec::new();
+
+        fn rule_idx(name: String, names: &mut Vec<String>, rules: &mut Vec<Vec<Vec<Symbol>>>) -> usize {
+            if let Some(idx) = names.iter().position(|n| *n == name) {
+                idx
+            } else {
+                names.push(name);
+                rules.push(Vec::new());
+                names.len() - 1
+            }
+        }
+
+        for (line_idx, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: &str| format!("line {}: {}", line_idx + 1, message);
+
+            let mut tokens = tokenize(line).map_err(|e| error(&e))?.into_iter();
+            let name = match (tokens.next(), tokens.next()) {
+                (Some(Token::Rule(name)), Some(Token::Definition)) => name,
+                _ => return Err(error("expected a rule name followed by ::=")),
+            };
+            let rule = rule_idx(name, &mut names, &mut rules);
+            defined_rules.push(rule);
+
+            let mut alternative = Vec::new();
+            for token in tokens {
+                match token {
+                    Token::Terminal(t) => alternative.push(Symbol::Terminal(t)),
+                    Token::Rule(name) => alternative.push(Symbol::Rule(rule_idx(name, &mut names, &mut rules))),
+                    Token::Or => rules[rule].push(std::mem::take(&mut alternative)),
+                    Token::Definition => return Err(error("unexpected ::=")),
+                }
+            }
+            rules[rule].push(alternative);
+        }
+
+        if rules.is_empty() {
+            return Err("the grammar has no rules".to_owned());
+        }
+        for (idx, name) in names.iter().enumerate() {
+            if !defined_rules.contains(&idx) {
+                return Err(format!("the rule <{}> is used but never defined", name));
+            }
+        }
+
+        let violations = rules
+            .iter()
+            .map(|alternatives| {
+                alternatives
+                    .iter()
+                    .map(|symbols| {
+                        symbols.iter().any(|symbol| match symbol {
+                            Symbol::Rule(r) => names[*r].starts_with("invalid"),
+                            Symbol::Terminal(_) => false,
+                        })
+                    })
+                    .collect()
+            })
+            .collect();
+        let mut grammar = Grammar {
+            names,
+            min_len: vec![std::usize::MAX; rules.len()],
+            shortest_alternative: vec![0; rules.len()],
+            rules,
+            violations,
+            violation_probability: DEFAULT_VIOLATION_PROBABILITY,
+        };
+        grammar.compute_shortest_derivations();
+
+        if let Some(idx) = grammar.min_len.iter().position(|&len| len == std::usize::MAX) {
+            return Err(format!(
+                "the rule <{}> does not derive any finite string",
+                grammar.names[idx]
+            ));
+        }
+        Ok(grammar)
+    }
+
+    /// Reads and parses the grammar in the given file
+    pub fn from_file(path: &Path) -> std::io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        Self::parse(&text).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+
+    /// Computes `min_len` and `shortest_alternative` with a fixpoint.
+    ///
+    /// The shortest alternative of a rule only changes when its minimum length
+    /// strictly decreases, so following them always terminates.
+    fn compute_shortest_derivations(&mut self) {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for rule in 0..self.rules.len() {
+                for alternative in 0..self.rules[rule].len() {
+                    let len = self.alternative_min_len(&self.rules[rule][alternative]);
+                    if len < self.min_len[rule] {
+                        self.min_len[rule] = len;
+                        self.shortest_alternative[rule] = alternative;
+                        changed = true;
+                    }
+                }
+            }
+        }
+    }
+
+    fn alternative_min_len(&self, symbols: &[Symbol]) -> usize {
+        symbols.iter().fold(0, |len, symbol| match symbol {
+            Symbol::Terminal(t) => len.saturating_add(t.len()),
+            Symbol::Rule(r) => len.saturating_add(self.min_len[*r]),
+        })
+    }
+
+    fn shortest_derivation(&self, rule: usize) -> Derivation {
+        let alternative = self.shortest_alternative[rule];
+        let children = self
+            .child_rules(rule, alternative)
+            .map(|r| self.shortest_derivation(r))
+            .collect();
+        Derivation {
+            rule,
+            alternative,
+            children,
+        }
+    }
+
+    fn child_rules(&self, rule: usize, alternative: usize) -> impl Iterator<Item = usize> + '_ {
+        self.rules[rule][alternative].iter().filter_map(|symbol| match symbol {
+            Symbol::Rule(r) => Some(*r),
+            Symbol::Terminal(_) => None,
+        })
+    }
+
+    /// Generates a derivation of the rule whose length is at most `max_len`
+    /// if possible
+    fn arbitrary_derivation(&self, rule: usize, max_len: f64, depth: usize, rng: &mut SmallRng) -> Derivation {
+        if depth >= MAX_DEPTH {
+            return self.shortest_derivation(rule);
+        }
+        let alternatives = &self.rules[rule];
+        let fitting: Vec<usize> = (0..alternatives.len())
+            .filter(|&a| self.alternative_min_len(&alternatives[a]) as f64 <= max_len)
+            .collect();
+        let (invalid, valid): (Vec<usize>, Vec<usize>) = fitting.iter().partition(|&&a| self.violations[rule][a]);
+        let alternative = if fitting.is_empty() {
+            self.shortest_alternative[rule]
+        } else if !invalid.is_empty() && (valid.is_empty() || rng.gen_bool(self.violation_probability)) {
+            invalid[rng.gen_range(0, invalid.len())]
+        } else {
+            valid[rng.gen_range(0, valid.len())]
+        };
+
+        let child_rules: Vec<usize> = self.child_rules(rule, alternative).collect();
+        let mut slack = (max_len - self.alternative_min_len(&alternatives[alternative]) as f64).max(0.0);
+        let mut children = Vec::with_capacity(child_rules.len());
+        for (i, &r) in child_rules.iter().enumerate() {
+            let share = slack / (child_rules.len() - i) as f64;
+            let child = self.arbitrary_derivation(r, self.min_len[r] as f64 + share, depth + 1, rng);
+            slack -= (self.len(&child) - self.min_len[r]) as f64;
+            children.push(child);
+        }
+        Derivation {
+            rule,
+            alternative,
+            children,
+        }
+    }
+
+    /// The length of the string derived by the tree
+    fn len(&self, tree: &Derivation) -> usize {
+        let mut children = tree.children.iter();
+        self.rules[tree.rule][tree.alternative]
+            .iter()
+            .map(|symbol| match symbol {
+                Symbol::Terminal(t) => t.len(),
+                Symbol::Rule(_) => self.len(children.next().unwrap()),
+            })
+            .sum()
+    }
+
+    fn write(&self, tree: &Derivation, out: &mut String) {
+        let mut children = tree.children.iter();
+        for symbol in self.rules[tree.rule][tree.alternative].iter() {
+            match symbol {
+                Symbol::Terminal(t) => out.push_str(t),
+                Symbol::Rule(_) => self.write(children.next().unwrap(), out),
+            }
+        }
+    }
+
+    fn to_string(&self, tree: &Derivation) -> String {
+        let mut s = String::new();
+        self.write(tree, &mut s);
+        s
+    }
+
+    /// Finds a derivation of the whole string from the start rule, giving up
+    /// after [PARSE_FUEL] steps
+    fn parse_string(&self, value: &str) -> Option<Derivation> {
+        let mut parser = Parser {
+            grammar: self,
+            input: value,
+            memo: HashMap::new(),
+            fuel: PARSE_FUEL,
+        };
+        parser
+            .parse_rule(0, 0, 0)
+            .into_iter()
+            .find(|(_, end)| *end == value.len())
+            .map(|(tree, _)| tree)
+    }
+}
+
+/// Derivations of a rule along with the position where they end
+type Parses = Vec<(Derivation, usize)>;
+
+/// A backtracking parser that finds the derivation trees of a string.
+///
+/// The derivations of each rule at each position are memoized. A rule that
+/// is reached again at the same position while its derivations are being
+/// computed does not match anything, so the derivations that rely on left
+/// recursion are not found.
+struct Parser<'a> {
+    grammar: &'a Grammar,
+    input: &'a str,
+    /// The derivations of a rule starting at a position along with their end
+    /// position, or `None` if they are being computed
+    memo: HashMap<(usize, usize), Option<Parses>>,
+    fuel: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn parse_rule(&mut self, rule: usize, pos: usize, depth: usize) -> Parses {
+        match self.memo.get(&(rule, pos)) {
+            Some(Some(results)) => return results.clone(),
+            Some(None) => return vec![],
+            None => {}
+        }
+        if depth >= MAX_DEPTH + self.grammar.rules.len() || self.fuel == 0 {
+            return vec![];
+        }
+        self.fuel -= 1;
+        self.memo.insert((rule, pos), None);
+
+        let grammar = self.grammar;
+        let mut results = Vec::new();
+        for (alternative, symbols) in grammar.rules[rule].iter().enumerate() {
+            let mut partials: Vec<(Vec<Derivation>, usize)> = vec![(vec![], pos)];
+            for symbol in symbols.iter() {
+                let mut next = Vec::new();
+                for (children, pos) in partials {
+                    match symbol {
+                        Symbol::Terminal(t) => {
+                            if self.input[pos..].starts_with(t.as_str()) {
+                                next.push((children, pos + t.len()));
+                            }
+                        }
+                        Symbol::Rule(r) => {
+                            for (child, end) in self.parse_rule(*r, pos, depth + 1) {
+                                let mut children = children.clone();
+                                children.push(child);
+                                next.push((children, end));
+                            }
+                        }
+                    }
+                    self.fuel = self.fuel.saturating_sub(1);
+                }
+                partials = next;
+                if partials.is_empty() || self.fuel == 0 {
+                    break;
+                }
+            }
+            results.extend(partials.into_iter().map(|(children, end)| {
+                (
+                    Derivation {
+                        rule,
+                        alternative,
+                        children,
+                    },
+                    end,
+                )
+            }));
+        }
+        self.memo.insert((rule, pos), Some(results.clone()));
+        results
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Rule(String),
+    Terminal(String),
+    Definition,
+    Or,
+}
+
+fn tokenize(line: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => continue,
+            '|' => tokens.push(Token::Or),
+            ':' => {
+                if let (Some(':'), Some('=')) = (chars.next(), chars.next()) {
+                    tokens.push(Token::Definition)
+                } else {
+                    return Err("expected ::=".to_owned());
+                }
+            }
+            '<' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('>') => break,
+                        Some(c) => name.push(c),
+                        None => return Err("unterminated rule name".to_owned()),
+                    }
+                }
+                if name.is_empty() {
+                    return Err("a rule name cannot be empty".to_owned());
+                }
+                tokens.push(Token::Rule(name));
+            }
+            '"' => {
+                let mut terminal = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('"') => terminal.push('"'),
+                            Some('\\') => terminal.push('\\'),
+                            Some('n') => terminal.push('\n'),
+                            Some('t') => terminal.push('\t'),
+                            _ => return Err("invalid escape sequence in a terminal".to_owned()),
+                        },
+                        Some(c) => terminal.push(c),
+                        None => return Err("unterminated terminal".to_owned()),
+                    }
+                }
+                tokens.push(Token::Terminal(terminal));
+            }
+            c => return Err(format!("unexpected character ‘{}’", c)),
+        }
+    }
+    Ok(tokens)
+}
+
+/// A node of the derivation tree of a string. Its children are the
+/// derivations of the rules referenced by its alternative, in order.
+#[derive(Clone, Debug)]
+struct Derivation {
+    rule: usize,
+    alternative: usize,
+    children: Vec<Derivation>,
+}
+
+impl Derivation {
+    fn nbr_nodes(&self) -> usize {
+        1 + self.children.iter().map(Derivation::nbr_nodes).sum::<usize>()
+    }
+
+    /// The node at index `idx` in the pre-order traversal of the tree, along
+    /// with its depth
+    fn node_mut(&mut self, mut idx: usize) -> (&mut Derivation, usize) {
+        let mut node = self;
+        let mut depth = 0;
+        while idx != 0 {
+            idx -= 1;
+            let mut children = node.children.iter_mut();
+            node = loop {
+                let child = children.next().unwrap();
+                let nbr_child_nodes = child.nbr_nodes();
+                if idx < nbr_child_nodes {
+                    break child;
+                }
+                idx -= nbr_child_nodes;
+            };
+            depth += 1;
+        }
+        (node, depth)
+    }
+
+    fn nodes_of_rule<'a>(&'a self, rule: usize, nodes: &mut Vec<&'a Derivation>) {
+        if self.rule == rule {
+            nodes.push(self);
+        }
+        for child in self.children.iter() {
+            child.nodes_of_rule(rule, nodes);
+        }
+    }
+}
+
+/// A mutator for the strings derived from a [Grammar], see the
+/// [module documentation](self)
+#[derive(Clone)]
+pub struct GrammarMutator {
+    grammar: Grammar,
+}
+
+impl GrammarMutator {
+    pub fn new(grammar: Grammar) -> Self {
+        Self { grammar }
+    }
+
+    /// Sets the probability of picking an alternative that refers to an
+    /// invalid rule, see the [module documentation](self)
+    pub fn with_violation_probability(mut self, probability: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&probability),
+            "the violation probability must be between 0 and 1"
+        );
+        self.grammar.violation_probability = probability;
+        self
+    }
+
+    /// A mutator for identifiers, made of letters, digits, and underscores,
+    /// and that do not start with a digit
+    pub fn identifier() -> Self {
+        let text = format!(
+            r#"
+<identifier> ::= <first> | <first> <rest>
+<rest> ::= <next> | <next> <rest>
+<first> ::= <letter> | "_" | <invalid-first> | <invalid>
+<next> ::= <letter> | <digit> | "_" | <invalid>
+<invalid-first> ::= <digit>
+{letter}
+{digit}
+<invalid> ::= "-" | " " | "." | "$" | "é"
+"#,
+            letter = char_class("letter", ('a'..='z').chain('A'..='Z')),
+            digit = char_class("digit", '0'..='9'),
+        );
+        Self::new(Grammar::parse(&text).unwrap())
+    }
+
+    /// A mutator for host names, made of labels separated by dots, where a
+    /// label is made of lowercase letters, digits, and hyphens, and does not
+    /// start or end with a hyphen.
+    ///
+    /// The length of the labels is not limited to 63 characters.
+    pub fn hostname() -> Self {
+        Self::new(Grammar::parse(&hostname_rules()).unwrap())
+    }
+
+    /// A mutator for strings that look like email addresses, whose local part
+    /// is made of lowercase letters, digits, and `._+-`, and whose domain is a
+    /// [hostname](GrammarMutator::hostname) with a top-level domain of at
+    /// least two letters.
+    ///
+    /// It does not produce every address that is valid according to RFC 5322,
+    /// only the common ones.
+    pub fn email_like() -> Self {
+        let text = format!(
+            r#"
+<email> ::= <local> "@" <domain>
+<local> ::= <local-char> | <local-char> <local>
+<local-char> ::= <lower> | <digit> | "." | "_" | "+" | "-" | <invalid-local>
+<invalid-local> ::= "@" | " " | "," | "(" | "é"
+<domain> ::= <hostname> "." <tld>
+<tld> ::= <lower> <lower> | <lower> <tld>
+{hostname}
+"#,
+            hostname = hostname_rules(),
+        );
+        Self::new(Grammar::parse(&text).unwrap())
+    }
+}
+
+/// The rules of [GrammarMutator::hostname], whose start rule is `<hostname>`
+fn hostname_rules() -> String {
+    format!(
+        r#"
+<hostname> ::= <label> | <label> "." <hostname>
+<label> ::= <alnum> | <alnum> <label-tail>
+<label-tail> ::= <alnum> | <label-char> <label-tail>
+<label-char> ::= <alnum> | "-"
+<alnum> ::= <lower> | <digit> | <invalid>
+{lower}
+{digit}
+<invalid> ::= "-" | "_" | " " | "." | "@" | "é"
+"#,
+        lower = char_class("lower", 'a'..='z'),
+        digit = char_class("digit", '0'..='9'),
+    )
+}
+
+/// A rule that derives any of the given characters, which cannot be `"` or `\`
+fn char_class(name: &str, chars: impl Iterator<Item = char>) -> String {
+    let alternatives: Vec<String> = chars.map(|c| format!("\"{}\"", c)).collect();
+    format!("<{}> ::= {}", name, alternatives.join(" | "))
+}
+
+#[derive(Clone)]
+pub struct GrammarCache {
+    /// The derivation tree of the string, if it is known
+    tree: Option<Derivation>,
+}
+
+pub struct GrammarMutationStep {
+    rng: SmallRng,
+}
+
+pub struct GrammarUnmutateToken {
+    old_value: String,
+    old_tree: Option<Derivation>,
+}
+
+impl Mutator for GrammarMutator {
+    type Value = String;
+    type Cache = GrammarCache;
+    type MutationStep = GrammarMutationStep;
+    type UnmutateToken = GrammarUnmutateToken;
+
+    fn cache_from_value(&self, value: &Self::Value) -> Self::Cache {
+        GrammarCache {
+            tree: self.grammar.parse_string(value),
+        }
+    }
+    fn mutation_step_from_value(&self, value: &Self::Value) -> Self::MutationStep {
+        // seeded by the value, so that its mutations are the same in every run
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        GrammarMutationStep {
+            rng: SmallRng::seed_from_u64(hasher.finish()),
+        }
+    }
+
+    fn seeded_mutation_step_from_value(&self, value: &Self::Value, seed: u64) -> Self::MutationStep {
+        let mut hasher = DefaultHasher::new();
+        (seed, value).hash(&mut hasher);
+        GrammarMutationStep {
+            rng: SmallRng::seed_from_u64(hasher.finish()),
+        }
+    }
+
+    fn max_complexity(&self) -> f64 {
+        std::f64::INFINITY
+    }
+    fn min_complexity(&self) -> f64 {
+        self.grammar.min_len[0] as f64
+    }
+    fn complexity(&self, value: &Self::Value, _cache: &Self::Cache) -> f64 {
+        value.len() as f64
+    }
+
+    fn arbitrary(&self, seed: usize, max_cplx: f64) -> (Self::Value, Self::Cache) {
+        let tree = if seed == 0 {
+            self.grammar.shortest_derivation(0)
+        } else {
+            let mut rng = SmallRng::seed_from_u64(seed as u64);
+            self.grammar.arbitrary_derivation(0, max_cplx, 0, &mut rng)
+        };
+        let value = self.grammar.to_string(&tree);
+        (value, GrammarCache { tree: Some(tree) })
+    }
+
+    fn mutate(
+        &self,
+        value: &mut Self::Value,
+        cache: &mut Self::Cache,
+        step: &mut Self::MutationStep,
+        max_cplx: f64,
+    ) -> Option<Self::UnmutateToken> {
+        let grammar = &self.grammar;
+        let rng = &mut step.rng;
+        let token = GrammarUnmutateToken {
+            old_value: value.clone(),
+            old_tree: cache.tree.clone(),
+        };
+
+        let tree = match &mut cache.tree {
+            Some(tree) => tree,
+            None => {
+                let tree = grammar.arbitrary_derivation(0, max_cplx, 0, rng);
+                *value = grammar.to_string(&tree);
+                cache.tree = Some(tree);
+                return Some(token);
+            }
+        };
+
+        let idx = rng.gen_range(0, tree.nbr_nodes());
+        let (node, depth) = tree.node_mut(idx);
+        let max_node_len = max_cplx - (value.len() - grammar.len(node)) as f64;
+
+        // one time in four, replace the node by a copy of another node derived from the same rule
+        let mut same_rule_nodes = Vec::new();
+        if rng.gen_range(0, 4) == 0 {
+            token
+                .old_tree
+                .as_ref()
+                .unwrap()
+                .nodes_of_rule(node.rule, &mut same_rule_nodes);
+            same_rule_nodes.retain(|other| grammar.len(other) as f64 <= max_node_len);
+        }
+        *node = if !same_rule_nodes.is_empty() {
+            same_rule_nodes[rng.gen_range(0, same_rule_nodes.len())].clone()
+        } else {
+            grammar.arbitrary_derivation(node.rule, max_node_len, depth, rng)
+        };
+
+        *value = grammar.to_string(tree);
+        Some(token)
+    }
+
+    fn unmutate(&self, value: &mut Self::Value, cache: &mut Self::Cache, t: Self::UnmutateToken) {
+        *value = t.old_value;
+        cache.tree = t.old_tree;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARITHMETIC: &str = r#"
+# arithmetic expressions
+<expr> ::= <term> | <term> "+" <expr>
+<term> ::= <digit> | "(" <expr> ")"
+<digit> ::= "0" | "1" | "\"" | ""
+"#;
+
+    #[test]
+    fn test_parse_grammar() {
+        let grammar = Grammar::parse(ARITHMETIC).unwrap();
+        assert_eq!(grammar.names, vec!["expr", "term", "digit"]);
+        assert_eq!(grammar.min_len, vec![0, 0, 0]);
+
+        let grammar = Grammar::parse("<a> ::= \"xy\" <b>\n<b> ::= \"z\" | <a>").unwrap();
+        assert_eq!(grammar.min_len, vec![3, 1]);
+
+        assert!(Grammar::parse("").is_err());
+        assert!(Grammar::parse("<a> ::= <b>").is_err());
+        assert!(Grammar::parse("<a> ::= \"x\" <a>").is_err());
+        assert!(Grammar::parse("<a> = \"x\"").is_err());
+        assert!(Grammar::parse("<a> ::= \"x").is_err());
+    }
+
+    #[test]
+    fn test_grammar_mutator() {
+        let m = GrammarMutator::new(Grammar::parse(ARITHMETIC).unwrap());
+
+        for max_cplx in [0.0, 10.0, 100.0].iter() {
+            for seed in 0..100 {
+                let (x, x_cache) = m.arbitrary(seed, *max_cplx);
+                assert!(m.complexity(&x, &x_cache) <= *max_cplx);
+                // the generated strings can be parsed back
+                assert!(m.cache_from_value(&x).tree.is_some());
+            }
+        }
+        assert!(m.cache_from_value(&"1)".to_owned()).tree.is_none());
+
+        let (mut x, mut x_cache) = m.arbitrary(1, 50.0);
+        let mut x_step = m.mutation_step_from_value(&x);
+        for _ in 0..1000 {
+            let old_x = x.clone();
+            let token = m.mutate(&mut x, &mut x_cache, &mut x_step, 50.0).unwrap();
+            assert!(m.complexity(&x, &x_cache) <= 50.0);
+            assert_eq!(m.grammar.to_string(x_cache.tree.as_ref().unwrap()), x);
+            m.unmutate(&mut x, &mut x_cache, token);
+            assert_eq!(x, old_x);
+            let _ = m.mutate(&mut x, &mut x_cache, &mut x_step, 50.0).unwrap();
+        }
+
+        // a string that cannot be parsed is replaced by a generated one
+        let mut y = ")".to_owned();
+        let mut y_cache = m.cache_from_value(&y);
+        let _ = m.mutate(&mut y, &mut y_cache, &mut x_step, 50.0).unwrap();
+        assert!(y_cache.tree.is_some());
+    }
+
+    #[test]
+    fn test_seeded_mutation_steps() {
+        let m = GrammarMutator::new(Grammar::parse(ARITHMETIC).unwrap());
+        let (x, x_cache) = m.arbitrary(1, 50.0);
+        let mutations = |seed: u64| -> Vec<String> {
+            let (mut y, mut y_cache) = (x.clone(), x_cache.clone());
+            let mut step = m.seeded_mutation_step_from_value(&y, seed);
+            (0..20)
+                .map(|_| {
+                    let token = m.mutate(&mut y, &mut y_cache, &mut step, 50.0).unwrap();
+                    let mutated = y.clone();
+                    m.unmutate(&mut y, &mut y_cache, token);
+                    mutated
+                })
+                .collect()
+        };
+        // the mutations of a value only depend on the seed
+        assert_eq!(mutations(1), mutations(1));
+        assert_ne!(mutations(1), mutations(2));
+    }
+
+    fn is_identifier(s: &str) -> bool {
+        let mut chars = s.chars();
+        matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+    }
+
+    fn is_hostname(s: &str) -> bool {
+        s.split('.').all(|label| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        })
+    }
+
+    fn is_email_like(s: &str) -> bool {
+        let mut parts = s.split('@');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(local), Some(domain), None) => {
+                !local.is_empty()
+                    && local
+                        .chars()
+                        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "._+-".contains(c))
+                    && is_hostname(domain)
+                    && domain.contains('.')
+            }
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn test_string_shapes() {
+        type IsValid = fn(&str) -> bool;
+        let shapes: [(GrammarMutator, IsValid); 3] = [
+            (GrammarMutator::identifier(), is_identifier),
+            (GrammarMutator::hostname(), is_hostname),
+            (GrammarMutator::email_like(), is_email_like),
+        ];
+        for (m, is_valid) in shapes.iter() {
+            let mut nbr_invalid = 0;
+            for seed in 0..1000 {
+                let (x, _) = m.arbitrary(seed, 32.0);
+                if !is_valid(&x) {
+                    nbr_invalid += 1;
+                }
+            }
+            // mostly valid, occasionally not
+            assert!(nbr_invalid > 0 && nbr_invalid < 500, "{} invalid strings", nbr_invalid);
+
+            let m = m.clone().with_violation_probability(0.0);
+            for seed in 0..1000 {
+                let (x, _) = m.arbitrary(seed, 32.0);
+                assert!(is_valid(&x), "{:?}", x);
+            }
+            let (mut x, mut x_cache) = m.arbitrary(1, 32.0);
+            let mut x_step = m.mutation_step_from_value(&x);
+            for _ in 0..100 {
+                let _ = m.mutate(&mut x, &mut x_cache, &mut x_step, 32.0).unwrap();
+                assert!(is_valid(&x), "{:?}", x);
+            }
+            // the strings can be parsed back
+            assert!(m.cache_from_value(&x).tree.is_some());
+        }
+    }
+}
@@ -1,91 +1,196 @@
+//! Mutators for the primitive integer types, from `u8` to `i128`.
+//!
+//! They all work in the same way: the first mutations of a value add or
+//! subtract 1 to 4 to it, and the following ones go through all the other
+//! values of the type, starting with the ones furthest from it, by a binary
+//! search centered on the value. The mutators of signed integers mutate the
+//! bits of the value as those of the unsigned integer of the same width.
+
 extern crate fuzzcheck;
 use fuzzcheck::Mutator;
 
-#[derive(Clone)]
-pub struct U8Mutator {}
-impl Default for U8Mutator {
-    fn default() -> Self {
-        U8Mutator {}
-    }
+use crate::DefaultMutator;
+
+/// The number of mutations that add or subtract 1 to 4 to the value, which
+/// are tried before the other values
+const NBR_NUDGES: u64 = 8;
+
+macro_rules! impl_arbitrary_unsigned {
+    ($arbitrary:ident, $unsigned:ty) => {
+        pub fn $arbitrary(low: $unsigned, high: $unsigned, step: u64) -> $unsigned {
+            let next = low.wrapping_add(high.wrapping_sub(low) / 2);
+            if low.wrapping_add(1) == high {
+                if step % 2 == 0 {
+                    high
+                } else {
+                    low
+                }
+            } else if step == 0 {
+                next
+            } else if step % 2 == 1 {
+                $arbitrary(next.wrapping_add(1), high, step / 2)
+            } else {
+                // step % 2 == 0
+                $arbitrary(low, next.wrapping_sub(1), (step - 1) / 2)
+            }
+        }
+    };
 }
+impl_arbitrary_unsigned!(arbitrary_u8, u8);
+impl_arbitrary_unsigned!(arbitrary_u16, u16);
+impl_arbitrary_unsigned!(arbitrary_u32, u32);
+impl_arbitrary_unsigned!(arbitrary_u64, u64);
+impl_arbitrary_unsigned!(arbitrary_u128, u128);
 
-impl Mutator for U8Mutator {
-    type Value = u8;
-    type Cache = ();
-    type MutationStep = u16; // mutation step
-    type UnmutateToken = u8; // old value
+macro_rules! impl_int_mutator {
+    ($doc:literal, $name:ident, $t:ty, $unsigned:ident, $arbitrary:ident) => {
+        #[doc = $doc]
+        #[derive(Clone, Default)]
+        pub struct $name {}
 
-    fn cache_from_value(&self, _value: &Self::Value) -> Self::Cache {}
-    fn mutation_step_from_value(&self, _value: &Self::Value) -> Self::MutationStep {
-        0
-    }
+        impl Mutator for $name {
+            type Value = $t;
+            type Cache = ();
+            type MutationStep = u64; // mutation step
+            type UnmutateToken = $t; // old value
 
-    fn arbitrary(&self, seed: usize, _max_cplx: f64) -> (Self::Value, Self::Cache) {
-        let value = (seed % std::u8::MAX as usize) as u8;
-        (value, ())
-    }
+            fn cache_from_value(&self, _value: &Self::Value) -> Self::Cache {}
+            fn mutation_step_from_value(&self, _value: &Self::Value) -> Self::MutationStep {
+                0
+            }
 
-    fn max_complexity(&self) -> f64 {
-        8.0
-    }
+            fn arbitrary(&self, seed: usize, _max_cplx: f64) -> (Self::Value, Self::Cache) {
+                let value = (seed as u128 % <$unsigned>::MAX as u128) as $unsigned as $t;
+                (value, ())
+            }
 
-    fn min_complexity(&self) -> f64 {
-        8.0
-    }
+            fn max_complexity(&self) -> f64 {
+                (std::mem::size_of::<$t>() * 8) as f64
+            }
 
-    fn complexity(&self, _value: &Self::Value, _cache: &Self::Cache) -> f64 {
-        8.0
-    }
+            fn min_complexity(&self) -> f64 {
+                (std::mem::size_of::<$t>() * 8) as f64
+            }
 
-    fn mutate(
-        &self,
-        value: &mut Self::Value,
-        _cache: &mut Self::Cache,
-        step: &mut Self::MutationStep,
-        _max_cplx: f64,
-    ) -> Self::UnmutateToken {
-        let token = *value;
-        *value = {
-            let mut tmp_step = *step;
-            if tmp_step < 8 {
-                let nudge = tmp_step + 2;
-                let nudge_u8 = nudge as u8;
-                if nudge % 2 == 0 {
-                    value.wrapping_add(nudge_u8 / 2)
-                } else {
-                    value.wrapping_sub(nudge_u8 / 2)
+            fn complexity(&self, _value: &Self::Value, _cache: &Self::Cache) -> f64 {
+                (std::mem::size_of::<$t>() * 8) as f64
+            }
+
+            fn mutate(
+                &self,
+                value: &mut Self::Value,
+                _cache: &mut Self::Cache,
+                step: &mut Self::MutationStep,
+                _max_cplx: f64,
+            ) -> Option<Self::UnmutateToken> {
+                let nbr_bits = std::mem::size_of::<$t>() * 8;
+                if nbr_bits < 64 && *step >= NBR_NUDGES + (1 << nbr_bits) - 1 {
+                    // all nudges and all other values were tried
+                    return None;
                 }
-            } else {
-                tmp_step -= 7;
-                let low = value.wrapping_sub(std::u8::MAX / 2);
-                let high = value.wrapping_add(std::u8::MAX / 2 + 1);
-                arbitrary_u8(low, high, tmp_step)
+                let token = *value;
+                let unsigned = *value as $unsigned;
+                let mutated = if *step < NBR_NUDGES {
+                    let nudge = (*step + 2) as $unsigned;
+                    if nudge % 2 == 0 {
+                        unsigned.wrapping_add(nudge / 2)
+                    } else {
+                        unsigned.wrapping_sub(nudge / 2)
+                    }
+                } else {
+                    let low = unsigned.wrapping_sub(<$unsigned>::MAX / 2);
+                    let high = unsigned.wrapping_add(<$unsigned>::MAX / 2 + 1);
+                    $arbitrary(low, high, *step - (NBR_NUDGES - 1))
+                };
+                *value = mutated as $t;
+                *step = step.wrapping_add(1);
+
+                Some(token)
             }
-        };
-        *step = step.wrapping_add(1);
 
-        token
-    }
+            fn unmutate(&self, value: &mut Self::Value, _cache: &mut Self::Cache, t: Self::UnmutateToken) {
+                *value = t;
+            }
+        }
 
-    fn unmutate(&self, value: &mut Self::Value, _cache: &mut Self::Cache, t: Self::UnmutateToken) {
-        *value = t;
-    }
+        impl DefaultMutator for $t {
+            type Mutator = $name;
+            fn default_mutator() -> Self::Mutator {
+                $name::default()
+            }
+        }
+    };
 }
+impl_int_mutator!("A mutator for `u8`", U8Mutator, u8, u8, arbitrary_u8);
+impl_int_mutator!("A mutator for `u16`", U16Mutator, u16, u16, arbitrary_u16);
+impl_int_mutator!("A mutator for `u32`", U32Mutator, u32, u32, arbitrary_u32);
+impl_int_mutator!("A mutator for `u64`", U64Mutator, u64, u64, arbitrary_u64);
+impl_int_mutator!("A mutator for `u128`", U128Mutator, u128, u128, arbitrary_u128);
+impl_int_mutator!("A mutator for `i8`", I8Mutator, i8, u8, arbitrary_u8);
+impl_int_mutator!("A mutator for `i16`", I16Mutator, i16, u16, arbitrary_u16);
+impl_int_mutator!("A mutator for `i32`", I32Mutator, i32, u32, arbitrary_u32);
+impl_int_mutator!("A mutator for `i64`", I64Mutator, i64, u64, arbitrary_u64);
+impl_int_mutator!("A mutator for `i128`", I128Mutator, i128, u128, arbitrary_u128);
 
-pub fn arbitrary_u8(low: u8, high: u8, step: u16) -> u8 {
-    let next = low.wrapping_add(high.wrapping_sub(low) / 2);
-    if low.wrapping_add(1) == high {
-        if step % 2 == 0 {
-            high
-        } else {
-            low
-        }
-    } else if step == 0 {
-        next
-    } else if step % 2 == 1 {
-        arbitrary_u8(next.wrapping_add(1), high, step / 2)
-    } else {
-        // step % 2 == 0
-        arbitrary_u8(low, next.wrapping_sub(1), (step - 1) / 2)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    macro_rules! test_int_mutator {
+        ($test:ident, $t:ty) => {
+            #[test]
+            fn $test() {
+                let m = <$t>::default_mutator();
+                let nbr_bits = std::mem::size_of::<$t>() * 8;
+                assert_eq!(m.max_complexity(), nbr_bits as f64);
+                for seed in 0..10 {
+                    let (mut x, mut x_cache) = m.arbitrary(seed * 1000, nbr_bits as f64);
+                    let mut x_step = m.mutation_step_from_value(&x);
+                    let mut results = HashSet::new();
+                    let mut nbr_mutations = 0;
+                    while let Some(token) = m.mutate(&mut x, &mut x_cache, &mut x_step, nbr_bits as f64) {
+                        let old_x = token;
+                        let i = nbr_mutations;
+                        if i < NBR_NUDGES {
+                            // the first mutations add or subtract 1, 2, 3, then 4
+                            let nudge = (i / 2 + 1) as $t;
+                            let expected = if i % 2 == 0 {
+                                old_x.wrapping_add(nudge)
+                            } else {
+                                old_x.wrapping_sub(nudge)
+                            };
+                            assert_eq!(x, expected);
+                        } else {
+                            // the following ones never produce the same value twice
+                            assert_ne!(x, old_x);
+                            assert!(results.insert(x));
+                        }
+                        m.unmutate(&mut x, &mut x_cache, token);
+                        assert_eq!(x, old_x);
+                        nbr_mutations += 1;
+                        if nbr_mutations == 100_000 {
+                            break;
+                        }
+                    }
+                    if nbr_bits <= 16 {
+                        // the mutation step is exhausted once all the other values were tried
+                        assert_eq!(results.len(), (1 << nbr_bits) - 1);
+                    } else {
+                        assert_eq!(nbr_mutations, 100_000);
+                    }
+                }
+            }
+        };
     }
+    test_int_mutator!(test_u8_mutator, u8);
+    test_int_mutator!(test_u16_mutator, u16);
+    test_int_mutator!(test_u32_mutator, u32);
+    test_int_mutator!(test_u64_mutator, u64);
+    test_int_mutator!(test_u128_mutator, u128);
+    test_int_mutator!(test_i8_mutator, i8);
+    test_int_mutator!(test_i16_mutator, i16);
+    test_int_mutator!(test_i32_mutator, i32);
+    test_int_mutator!(test_i64_mutator, i64);
+    test_int_mutator!(test_i128_mutator, i128);
 }
@@ -0,0 +1,342 @@
+//! A mutator for dynamic JSON values of type `serde_json::Value`, available
+//! with the `serde_json` feature.
+//!
+//! The complexity of a JSON value is the sum of the complexities of its
+//! nodes, so the complexity budget given to `arbitrary` also bounds the
+//! depth of the generated trees.
+
+extern crate fuzzcheck;
+use fuzzcheck::Mutator;
+
+extern crate rand;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+extern crate serde_json;
+use serde_json::{Map, Number, Value};
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use crate::DefaultMutator;
+
+/// The complexity of a node, excluding its contents
+const NODE_CPLX: f64 = 1.0;
+const BOOL_CPLX: f64 = 1.0;
+const NUMBER_CPLX: f64 = 8.0;
+const CHAR_CPLX: f64 = 8.0;
+
+/// The maximum number of elements of a generated array, object, or string
+const MAX_ARBITRARY_LEN: usize = 8;
+
+/// The characters that generated strings are made of, including characters
+/// that must be escaped and characters encoded with multiple bytes
+const STRING_CHARS: &[char] = &[
+    'a', 'b', 'z', 'A', '0', '9', ' ', '_', '"', '\\', '/', '\n', '\t', '\u{0}', 'é', '€', '😀',
+];
+
+#[derive(Clone)]
+pub struct JsonValueMutator {}
+impl Default for JsonValueMutator {
+    fn default() -> Self {
+        JsonValueMutator {}
+    }
+}
+
+impl DefaultMutator for Value {
+    type Mutator = JsonValueMutator;
+    fn default_mutator() -> Self::Mutator {
+        JsonValueMutator::default()
+    }
+}
+
+pub struct JsonValueMutationStep {
+    rng: SmallRng,
+}
+
+/// Part of the path from the root of a JSON value to one of its nodes
+#[derive(Clone, Debug)]
+enum PathComponent {
+    Index(usize),
+    Key(String),
+}
+
+pub struct JsonValueUnmutateToken {
+    /// The path to the node that was mutated
+    path: Vec<PathComponent>,
+    old_node: Value,
+    old_cplx: f64,
+}
+
+fn string_complexity(s: &str) -> f64 {
+    CHAR_CPLX * s.chars().count() as f64
+}
+
+fn complexity_of(value: &Value) -> f64 {
+    NODE_CPLX
+        + match value {
+            Value::Null => 0.0,
+            Value::Bool(_) => BOOL_CPLX,
+            Value::Number(_) => NUMBER_CPLX,
+            Value::String(s) => string_complexity(s),
+            Value::Array(xs) => xs.iter().map(complexity_of).sum(),
+            Value::Object(map) => map.iter().map(|(k, v)| string_complexity(k) + complexity_of(v)).sum(),
+        }
+}
+
+fn nbr_nodes(value: &Value) -> usize {
+    1 + match value {
+        Value::Array(xs) => xs.iter().map(nbr_nodes).sum(),
+        Value::Object(map) => map.values().map(nbr_nodes).sum(),
+        _ => 0,
+    }
+}
+
+/// Appends to `path` the path to the node at index `idx` in the pre-order
+/// traversal of `value`
+fn path_to_node(value: &Value, mut idx: usize, path: &mut Vec<PathComponent>) {
+    if idx == 0 {
+        return;
+    }
+    idx -= 1;
+    let children: Box<dyn Iterator<Item = (PathComponent, &Value)> + '_> = match value {
+        Value::Array(xs) => Box::new(xs.iter().enumerate().map(|(i, x)| (PathComponent::Index(i), x))),
+        Value::Object(map) => Box::new(map.iter().map(|(k, v)| (PathComponent::Key(k.clone()), v))),
+        _ => unreachable!(),
+    };
+    for (component, child) in children {
+        let nbr_child_nodes = nbr_nodes(child);
+        if idx < nbr_child_nodes {
+            path.push(component);
+            return path_to_node(child, idx, path);
+        }
+        idx -= nbr_child_nodes;
+    }
+    unreachable!()
+}
+
+fn node_at_path_mut<'a>(value: &'a mut Value, path: &[PathComponent]) -> &'a mut Value {
+    path.iter().fold(value, |node, component| match (node, component) {
+        (Value::Array(xs), PathComponent::Index(i)) => &mut xs[*i],
+        (Value::Object(map), PathComponent::Key(k)) => map.get_mut(k).unwrap(),
+        _ => unreachable!(),
+    })
+}
+
+fn arbitrary_number(rng: &mut SmallRng) -> Number {
+    match rng.gen_range(0, 4) {
+        0 => Number::from(rng.gen_range(-10, 10) as i64),
+        1 => Number::from(rng.gen::<i64>()),
+        2 => Number::from(rng.gen::<u64>()),
+        // multiples of 1/4 are represented exactly by both floats and their decimal notation
+        _ => Number::from_f64(rng.gen_range(-1000, 1000) as f64 / 4.0).unwrap(),
+    }
+}
+
+fn arbitrary_char(rng: &mut SmallRng) -> char {
+    STRING_CHARS[rng.gen_range(0, STRING_CHARS.len())]
+}
+
+fn arbitrary_string(rng: &mut SmallRng, max_cplx: f64) -> String {
+    let max_len = std::cmp::min(MAX_ARBITRARY_LEN, (max_cplx / CHAR_CPLX) as usize);
+    let len = rng.gen_range(0, max_len + 1);
+    (0..len).map(|_| arbitrary_char(rng)).collect()
+}
+
+/// Generates a JSON value whose complexity is at most `max_cplx`, or `null`
+/// if `max_cplx` is less than the complexity of `null`
+fn arbitrary_value(rng: &mut SmallRng, max_cplx: f64) -> Value {
+    let budget = max_cplx - NODE_CPLX;
+    // the kinds of values are ordered by their minimum complexity
+    let nbr_kinds = if budget >= NUMBER_CPLX {
+        6
+    } else if budget >= BOOL_CPLX {
+        2
+    } else {
+        1
+    };
+    match rng.gen_range(0, nbr_kinds) {
+        0 => Value::Null,
+        1 => Value::Bool(rng.gen()),
+        2 => Value::Number(arbitrary_number(rng)),
+        3 => Value::String(arbitrary_string(rng, budget)),
+        4 => {
+            let max_len = std::cmp::min(MAX_ARBITRARY_LEN, (budget / NODE_CPLX) as usize);
+            let len = rng.gen_range(0, max_len + 1);
+            let mut remaining = budget;
+            let mut xs = Vec::with_capacity(len);
+            for i in 0..len {
+                let x = arbitrary_value(rng, remaining / (len - i) as f64);
+                remaining -= complexity_of(&x);
+                xs.push(x);
+            }
+            Value::Array(xs)
+        }
+        _ => {
+            let max_len = std::cmp::min(MAX_ARBITRARY_LEN, (budget / NODE_CPLX) as usize);
+            let len = rng.gen_range(0, max_len + 1);
+            let mut remaining = budget;
+            let mut map = Map::new();
+            for i in 0..len {
+                let max_entry_cplx = remaining / (len - i) as f64;
+                let k = arbitrary_string(rng, (max_entry_cplx - NODE_CPLX) / 2.0);
+                let v = arbitrary_value(rng, max_entry_cplx - string_complexity(&k));
+                remaining -= string_complexity(&k) + complexity_of(&v);
+                map.insert(k, v);
+            }
+            Value::Object(map)
+        }
+    }
+}
+
+/// Mutates a single node of a JSON value, such that its new complexity is
+/// at most `max_cplx` if possible.
+///
+/// The caller must check the complexity of the mutated node.
+fn mutate_node(rng: &mut SmallRng, node: &mut Value, max_cplx: f64) {
+    let extra_cplx = max_cplx - complexity_of(node);
+    // replace the whole node one time in four, or when there is nothing else to do
+    if rng.gen_range(0, 4) == 0 {
+        *node = arbitrary_value(rng, max_cplx);
+        return;
+    }
+    match node {
+        Value::Bool(b) => *b = !*b,
+        Value::Number(n) => {
+            *n = if let (Some(i), true) = (n.as_i64(), rng.gen()) {
+                Number::from(i.wrapping_add(if rng.gen() { 1 } else { -1 }))
+            } else {
+                arbitrary_number(rng)
+            }
+        }
+        Value::String(s) => {
+            let mut chars: Vec<char> = s.chars().collect();
+            if !chars.is_empty() && (extra_cplx < CHAR_CPLX || rng.gen()) {
+                let idx = rng.gen_range(0, chars.len());
+                if rng.gen() {
+                    chars.remove(idx);
+                } else {
+                    chars[idx] = arbitrary_char(rng);
+                }
+            } else {
+                let idx = rng.gen_range(0, chars.len() + 1);
+                chars.insert(idx, arbitrary_char(rng));
+            }
+            *s = chars.into_iter().collect();
+        }
+        Value::Array(xs) => {
+            if !xs.is_empty() && (extra_cplx < NODE_CPLX || rng.gen()) {
+                let idx = rng.gen_range(0, xs.len());
+                if rng.gen() {
+                    xs.remove(idx);
+                } else {
+                    let other_idx = rng.gen_range(0, xs.len());
+                    xs.swap(idx, other_idx);
+                }
+            } else {
+                let idx = rng.gen_range(0, xs.len() + 1);
+                xs.insert(idx, arbitrary_value(rng, extra_cplx));
+            }
+        }
+        Value::Object(map) => {
+            if !map.is_empty() && (extra_cplx < NODE_CPLX || rng.gen()) {
+                let idx = rng.gen_range(0, map.len());
+                let k = map.keys().nth(idx).unwrap().clone();
+                map.remove(&k);
+            } else {
+                let k = arbitrary_string(rng, extra_cplx / 2.0);
+                let v = arbitrary_value(rng, extra_cplx - string_complexity(&k));
+                map.insert(k, v);
+            }
+        }
+        Value::Null => *node = arbitrary_value(rng, max_cplx),
+    }
+}
+
+impl Mutator for JsonValueMutator {
+    type Value = Value;
+    type Cache = f64; // complexity
+    type MutationStep = JsonValueMutationStep;
+    type UnmutateToken = JsonValueUnmutateToken;
+
+    fn cache_from_value(&self, value: &Self::Value) -> Self::Cache {
+        complexity_of(value)
+    }
+    fn mutation_step_from_value(&self, value: &Self::Value) -> Self::MutationStep {
+        // seeded by the value, so that its mutations are the same in every run
+        let mut hasher = DefaultHasher::new();
+        value.to_string().hash(&mut hasher);
+        JsonValueMutationStep {
+            rng: SmallRng::seed_from_u64(hasher.finish()),
+        }
+    }
+
+    fn seeded_mutation_step_from_value(&self, value: &Self::Value, seed: u64) -> Self::MutationStep {
+        let mut hasher = DefaultHasher::new();
+        (seed, value.to_string()).hash(&mut hasher);
+        JsonValueMutationStep {
+            rng: SmallRng::seed_from_u64(hasher.finish()),
+        }
+    }
+
+    fn max_complexity(&self) -> f64 {
+        std::f64::INFINITY
+    }
+    fn min_complexity(&self) -> f64 {
+        // complexity of null
+        NODE_CPLX
+    }
+    fn complexity(&self, _value: &Self::Value, cache: &Self::Cache) -> f64 {
+        *cache
+    }
+
+    fn arbitrary(&self, seed: usize, max_cplx: f64) -> (Self::Value, Self::Cache) {
+        let value = if seed == 0 {
+            Value::Null
+        } else {
+            arbitrary_value(&mut SmallRng::seed_from_u64(seed as u64), max_cplx)
+        };
+        let cache = complexity_of(&value);
+        (value, cache)
+    }
+
+    fn mutate(
+        &self,
+        value: &mut Self::Value,
+        cache: &mut Self::Cache,
+        step: &mut Self::MutationStep,
+        max_cplx: f64,
+    ) -> Option<Self::UnmutateToken> {
+        let rng = &mut step.rng;
+        let mut path = Vec::new();
+        path_to_node(value, rng.gen_range(0, nbr_nodes(value)), &mut path);
+
+        let node = node_at_path_mut(value, &path);
+        let old_node = node.clone();
+        let old_node_cplx = complexity_of(node);
+
+        mutate_node(rng, node, max_cplx - (*cache - old_node_cplx));
+        if *cache <= max_cplx && *cache + complexity_of(node) - old_node_cplx > max_cplx {
+            // null is the simplest value, so replacing the node by null fits in the budget
+            *node = Value::Null;
+        }
+
+        let old_cplx = *cache;
+        *cache += complexity_of(node) - old_node_cplx;
+
+        Some(JsonValueUnmutateToken {
+            path,
+            old_node,
+            old_cplx,
+        })
+    }
+
+    fn unmutate(&self, value: &mut Self::Value, cache: &mut Self::Cache, t: Self::UnmutateToken) {
+        *node_at_path_mut(value, &t.path) = t.old_node;
+        *cache = t.old_cplx;
+    }
+
+    fn describe_value(&self, value: &Self::Value) -> Option<String> {
+        serde_json::to_string_pretty(value).ok()
+    }
+}
@@ -0,0 +1,480 @@
+//! A mutator for structures with a count field that must equal the length of
+//! another field, such as the number of records preceding the records in a
+//! binary format.
+
+extern crate fuzzcheck;
+use fuzzcheck::Mutator;
+
+extern crate rand;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use std::marker::PhantomData;
+
+use crate::{complexity_fingerprint, step_rng_seed};
+
+/// The default probability that a mutation or an arbitrary value has a
+/// length field that does not match the field it refers to
+const DEFAULT_INCONSISTENT_LEN_PROBABILITY: f64 = 0.05;
+/// The complexity of a length field that does not match the field it refers
+/// to, which carries as much information as a `u16`
+const INCONSISTENT_LEN_CPLX: f64 = 16.0;
+/// The number of times that a random choice is made again when its result
+/// does not fit in the length field, before giving up
+const MAX_ATTEMPTS: usize = 16;
+
+/// The structure of a value made of a length field and of the other fields,
+/// `A`, one of which is referenced by the length field.
+///
+/// The length field can be of any integer type, it is read and written as a
+/// `u64` by [get_len](LengthOfMap::get_len) and [set_len](LengthOfMap::set_len).
+pub trait LengthOfMap {
+    type A;
+    type V: Clone;
+
+    fn get_a(v: &Self::V) -> &Self::A;
+    fn get_a_mut(v: &mut Self::V) -> &mut Self::A;
+
+    fn get_len(v: &Self::V) -> u64;
+    fn set_len(v: &mut Self::V, len: u64);
+
+    fn new(a: Self::A, len: u64) -> Self::V;
+
+    /// The length of the field referenced by the length field
+    fn len_of(a: &Self::A) -> usize;
+
+    /// The largest length that the length field can hold, `u64::MAX` by
+    /// default. It must be given for length fields narrower than a `u64`.
+    fn max_len() -> u64 {
+        u64::MAX
+    }
+}
+
+/**
+ * A mutator for a value whose length field refers to another of its fields,
+ * where `M` is the mutator of the other fields.
+ *
+ * Like [ChecksummedMutator](crate::checksum::ChecksummedMutator) with its
+ * checksum, it sets the length field to the length of the referenced field
+ * after each mutation, so that the tested code gets past its validation. With
+ * the probability given to
+ * [with_inconsistent_len_probability](LengthOfMutator::with_inconsistent_len_probability),
+ * a mutation instead sets the length field to a value that does not match,
+ * such as the length plus or minus one, or restores it if it was already
+ * wrong. A length field that did not match before a mutation of the other
+ * fields is left as it is.
+ *
+ * The referenced field is never made longer than
+ * [max_len](LengthOfMap::max_len) by a mutation or an arbitrary value whose
+ * length field should match it.
+ *
+ * The complexity of a value is that of its other fields. A length field that
+ * does not match adds 16.0, as it holds information of its own.
+ */
+pub struct LengthOfMutator<Map, M>
+where
+    M: Mutator,
+    Map: LengthOfMap<A = M::Value>,
+{
+    m: M,
+    inconsistent_len_probability: f64,
+    phantom: PhantomData<Map>,
+}
+
+impl<Map: LengthOfMap<A = M::Value>, M: Mutator> LengthOfMutator<Map, M> {
+    pub fn new(m: M) -> Self {
+        Self {
+            m,
+            inconsistent_len_probability: DEFAULT_INCONSISTENT_LEN_PROBABILITY,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Sets the probability that the length field does not match the field
+    /// it refers to, 0.05 by default. With 0.0, all length fields are valid.
+    pub fn with_inconsistent_len_probability(mut self, probability: f64) -> Self {
+        assert!((0.0..=1.0).contains(&probability));
+        self.inconsistent_len_probability = probability;
+        self
+    }
+
+    /// Whether the length field of the value matches the field it refers to
+    pub fn is_valid(&self, value: &Map::V) -> bool {
+        Map::get_len(value) == Map::len_of(Map::get_a(value)) as u64
+    }
+
+    /// Whether the length field can hold the length of the referenced field
+    fn fits(a: &M::Value) -> bool {
+        Map::len_of(a) as u64 <= Map::max_len()
+    }
+
+    /// Sets the length field to a value that does not match the given valid
+    /// length, most often off by one, and returns whether it could find one
+    fn set_inconsistent_len(value: &mut Map::V, valid: u64, rng: &mut SmallRng) -> bool {
+        for _ in 0..MAX_ATTEMPTS {
+            let len = match rng.gen_range(0, 4) {
+                0 => valid.wrapping_add(1),
+                1 => valid.wrapping_sub(1),
+                2 => 0,
+                _ => rng.gen(),
+            };
+            Map::set_len(value, len);
+            // the length field may be narrower than a u64
+            if Map::get_len(value) != valid {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn len_cplx(&self, value: &Map::V) -> f64 {
+        if self.is_valid(value) {
+            0.0
+        } else {
+            INCONSISTENT_LEN_CPLX
+        }
+    }
+
+    fn mutate_a(
+        &self,
+        value: &mut Map::V,
+        cache: &mut LengthOfMutatorCache<M::Cache>,
+        step: &mut M::MutationStep,
+        max_cplx: f64,
+    ) -> Option<UnmutateLengthOfToken<M::UnmutateToken>> {
+        let was_valid = self.is_valid(value);
+        let other_cplx = self.len_cplx(value);
+        for _ in 0..MAX_ATTEMPTS {
+            let token = self
+                .m
+                .mutate(Map::get_a_mut(value), &mut cache.a, step, max_cplx - other_cplx)?;
+            if was_valid && !Self::fits(Map::get_a(value)) {
+                // the length field could not hold the new length, so the
+                // value would become inconsistent
+                self.m.unmutate(Map::get_a_mut(value), &mut cache.a, token);
+                continue;
+            }
+            let token = UnmutateLengthOfToken::Inner {
+                token,
+                len: Map::get_len(value),
+                cplx: cache.a_cplx,
+            };
+            if was_valid {
+                let len = Map::len_of(Map::get_a(value)) as u64;
+                Map::set_len(value, len);
+            }
+            cache.a_cplx = self.m.complexity(Map::get_a(value), &cache.a);
+            return Some(token);
+        }
+        None
+    }
+
+    fn mutate_len(
+        &self,
+        value: &mut Map::V,
+        cache: &LengthOfMutatorCache<M::Cache>,
+        rng: &mut SmallRng,
+        max_cplx: f64,
+    ) -> Option<UnmutateLengthOfToken<M::UnmutateToken>> {
+        let token = UnmutateLengthOfToken::Len(Map::get_len(value));
+        let valid = Map::len_of(Map::get_a(value)) as u64;
+        if self.is_valid(value) {
+            if cache.a_cplx + INCONSISTENT_LEN_CPLX > max_cplx || !Self::set_inconsistent_len(value, valid, rng) {
+                return None;
+            }
+        } else if rng.gen_bool(0.5) {
+            Map::set_len(value, valid);
+        } else {
+            let _ = Self::set_inconsistent_len(value, valid, rng);
+        }
+        Some(token)
+    }
+}
+
+#[derive(Clone)]
+pub struct LengthOfMutatorCache<C> {
+    a: C,
+    a_cplx: f64,
+}
+
+pub struct LengthOfMutatorStep<S> {
+    a: S,
+    /// The number of mutations performed so far, which seeds their random decisions
+    nbr_mutations: u64,
+    /// Seeds the random decisions of the mutations, along with `nbr_mutations`
+    rng_seed: u64,
+}
+
+pub enum UnmutateLengthOfToken<T> {
+    /// The other fields were mutated, and the length field and their
+    /// complexity were these before
+    Inner { token: T, len: u64, cplx: f64 },
+    /// Only the length field was mutated, and was this before
+    Len(u64),
+}
+
+impl<Map, M> Mutator for LengthOfMutator<Map, M>
+where
+    M: Mutator,
+    Map: LengthOfMap<A = M::Value>,
+{
+    type Value = Map::V;
+    type Cache = LengthOfMutatorCache<M::Cache>;
+    type MutationStep = LengthOfMutatorStep<M::MutationStep>;
+    type UnmutateToken = UnmutateLengthOfToken<M::UnmutateToken>;
+
+    fn cache_from_value(&self, value: &Self::Value) -> Self::Cache {
+        let a = self.m.cache_from_value(Map::get_a(value));
+        LengthOfMutatorCache {
+            a_cplx: self.m.complexity(Map::get_a(value), &a),
+            a,
+        }
+    }
+
+    fn mutation_step_from_value(&self, value: &Self::Value) -> Self::MutationStep {
+        LengthOfMutatorStep {
+            a: self.m.mutation_step_from_value(Map::get_a(value)),
+            nbr_mutations: 0,
+            rng_seed: step_rng_seed((Map::get_len(value), complexity_fingerprint(&self.m, Map::get_a(value)))),
+        }
+    }
+
+    fn seeded_mutation_step_from_value(&self, value: &Self::Value, seed: u64) -> Self::MutationStep {
+        LengthOfMutatorStep {
+            a: self.m.seeded_mutation_step_from_value(Map::get_a(value), seed),
+            nbr_mutations: 0,
+            rng_seed: step_rng_seed((
+                seed,
+                Map::get_len(value),
+                complexity_fingerprint(&self.m, Map::get_a(value)),
+            )),
+        }
+    }
+
+    fn max_complexity(&self) -> f64 {
+        self.m.max_complexity() + INCONSISTENT_LEN_CPLX
+    }
+
+    fn min_complexity(&self) -> f64 {
+        self.m.min_complexity()
+    }
+
+    fn complexity(&self, value: &Self::Value, cache: &Self::Cache) -> f64 {
+        cache.a_cplx + self.len_cplx(value)
+    }
+
+    fn arbitrary(&self, seed: usize, max_cplx: f64) -> (Self::Value, Self::Cache) {
+        let mut rng = SmallRng::seed_from_u64(seed as u64);
+        let inconsistent = rng.gen_bool(self.inconsistent_len_probability)
+            && max_cplx - INCONSISTENT_LEN_CPLX >= self.m.min_complexity();
+        let a_max_cplx = max_cplx - if inconsistent { INCONSISTENT_LEN_CPLX } else { 0.0 };
+        let (a, a_cache) = (0..MAX_ATTEMPTS)
+            .map(|_| self.m.arbitrary(rng.gen(), a_max_cplx))
+            .find(|(a, _)| Self::fits(a))
+            // the simplest value is the most likely to fit
+            .unwrap_or_else(|| self.m.arbitrary(0, self.m.min_complexity()));
+        let valid = Map::len_of(&a) as u64;
+        let cache = LengthOfMutatorCache {
+            a_cplx: self.m.complexity(&a, &a_cache),
+            a: a_cache,
+        };
+        let mut value = Map::new(a, valid);
+        if inconsistent {
+            let _ = Self::set_inconsistent_len(&mut value, valid, &mut rng);
+        }
+        (value, cache)
+    }
+
+    fn mutate(
+        &self,
+        value: &mut Self::Value,
+        cache: &mut Self::Cache,
+        step: &mut Self::MutationStep,
+        max_cplx: f64,
+    ) -> Option<Self::UnmutateToken> {
+        step.nbr_mutations += 1;
+        let mut rng = SmallRng::seed_from_u64(step.rng_seed.wrapping_add(step.nbr_mutations));
+        if rng.gen_bool(self.inconsistent_len_probability) {
+            if let Some(token) = self.mutate_len(value, cache, &mut rng, max_cplx) {
+                return Some(token);
+            }
+        }
+        self.mutate_a(value, cache, &mut step.a, max_cplx)
+    }
+
+    fn unmutate(&self, value: &mut Self::Value, cache: &mut Self::Cache, t: Self::UnmutateToken) {
+        match t {
+            UnmutateLengthOfToken::Inner { token, len, cplx } => {
+                self.m.unmutate(Map::get_a_mut(value), &mut cache.a, token);
+                Map::set_len(value, len);
+                cache.a_cplx = cplx;
+            }
+            UnmutateLengthOfToken::Len(len) => {
+                Map::set_len(value, len);
+            }
+        }
+    }
+
+    fn complexity_breakdown(&self, value: &Self::Value, cache: &Self::Cache) -> Vec<(&'static str, f64)> {
+        vec![("fields", cache.a_cplx), ("len", self.len_cplx(value))]
+    }
+
+    fn record_mutation_outcome(&self, value: &Self::Value, token: &Self::UnmutateToken, nbr_new_features: usize) {
+        if let UnmutateLengthOfToken::Inner { token, .. } = token {
+            self.m
+                .record_mutation_outcome(Map::get_a(value), token, nbr_new_features)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integer::*;
+    use crate::keep_one_mutation_in_four;
+    use crate::tuples::*;
+    use crate::vector::*;
+
+    /// A record of a binary format: the number of items, followed by a tag
+    /// and the items
+    #[derive(Clone, Debug, PartialEq)]
+    struct Record {
+        count: u8,
+        body: (u8, Vec<u8>),
+    }
+
+    impl LengthOfMap for Record {
+        type A = (u8, Vec<u8>);
+        type V = Self;
+
+        fn get_a(v: &Self) -> &(u8, Vec<u8>) {
+            &v.body
+        }
+        fn get_a_mut(v: &mut Self) -> &mut (u8, Vec<u8>) {
+            &mut v.body
+        }
+        fn get_len(v: &Self) -> u64 {
+            v.count as u64
+        }
+        fn set_len(v: &mut Self, len: u64) {
+            v.count = len as u8;
+        }
+        fn new(body: (u8, Vec<u8>), len: u64) -> Self {
+            Record { count: len as u8, body }
+        }
+        fn len_of(body: &(u8, Vec<u8>)) -> usize {
+            body.1.len()
+        }
+        fn max_len() -> u64 {
+            u8::MAX as u64
+        }
+    }
+
+    type RecordMutator = LengthOfMutator<Record, Tuple2Mutator<(u8, Vec<u8>), U8Mutator, VecMutator<U8Mutator>>>;
+
+    fn record_mutator() -> RecordMutator {
+        LengthOfMutator::new(Tuple2Mutator::new(
+            U8Mutator::default(),
+            VecMutator::new(U8Mutator::default()),
+        ))
+    }
+
+    #[test]
+    fn test_length_field_is_kept_valid() {
+        let m = record_mutator().with_inconsistent_len_probability(0.0);
+        for seed in 0..20 {
+            let (mut x, mut cache) = m.arbitrary(seed, 200.0);
+            assert!(m.is_valid(&x));
+            keep_one_mutation_in_four(&m, &mut x, &mut cache, 200.0, 200, |_, _, x, _| {
+                assert!(m.is_valid(x));
+            });
+        }
+    }
+
+    #[test]
+    fn test_inconsistent_length_fields() {
+        let m = record_mutator().with_inconsistent_len_probability(0.2);
+        let (mut nbr_valid, mut nbr_inconsistent) = (0, 0);
+        for seed in 0..20 {
+            let (mut x, mut cache) = m.arbitrary(seed, 200.0);
+            // restoring the items also restores their count
+            keep_one_mutation_in_four(&m, &mut x, &mut cache, 200.0, 200, |_, _, x, cache| {
+                if m.is_valid(x) {
+                    nbr_valid += 1;
+                } else {
+                    nbr_inconsistent += 1;
+                    assert_eq!(m.complexity(x, cache), cache.a_cplx + INCONSISTENT_LEN_CPLX);
+                }
+            });
+        }
+        assert!(nbr_valid > 0);
+        assert!(nbr_inconsistent > 0);
+    }
+
+    #[test]
+    fn test_length_field_does_not_overflow() {
+        // a budget of 4000 fits about 490 items, but the count holds at most 255
+        let m = record_mutator().with_inconsistent_len_probability(0.0);
+        for seed in 0..20 {
+            let (mut x, mut cache) = m.arbitrary(seed, 4000.0);
+            assert!(m.is_valid(&x));
+            assert!(m.complexity(&x, &cache) <= 4000.0);
+            let mut step = m.mutation_step_from_value(&x);
+            for _ in 0..500 {
+                if m.mutate(&mut x, &mut cache, &mut step, 4000.0).is_none() {
+                    break;
+                }
+                assert!(m.is_valid(&x));
+                assert!(m.complexity(&x, &cache) <= 4000.0);
+            }
+        }
+    }
+
+    /// A value whose length field can only hold 0
+    #[derive(Clone, Debug, PartialEq)]
+    struct Empty {
+        items: Vec<u8>,
+    }
+
+    impl LengthOfMap for Empty {
+        type A = Vec<u8>;
+        type V = Self;
+
+        fn get_a(v: &Self) -> &Vec<u8> {
+            &v.items
+        }
+        fn get_a_mut(v: &mut Self) -> &mut Vec<u8> {
+            &mut v.items
+        }
+        fn get_len(_v: &Self) -> u64 {
+            0
+        }
+        fn set_len(_v: &mut Self, _len: u64) {}
+        fn new(items: Vec<u8>, _len: u64) -> Self {
+            Empty { items }
+        }
+        fn len_of(items: &Vec<u8>) -> usize {
+            items.len()
+        }
+        fn max_len() -> u64 {
+            0
+        }
+    }
+
+    #[test]
+    fn test_length_field_without_inconsistent_values() {
+        let m = LengthOfMutator::<Empty, _>::new(VecMutator::new(U8Mutator::default()))
+            .with_inconsistent_len_probability(1.0);
+        for seed in 0..20 {
+            let (mut x, mut cache) = m.arbitrary(seed, 200.0);
+            assert_eq!(x, Empty { items: vec![] });
+            let mut step = m.mutation_step_from_value(&x);
+            for _ in 0..20 {
+                if m.mutate(&mut x, &mut cache, &mut step, 200.0).is_none() {
+                    break;
+                }
+                assert!(m.is_valid(&x));
+            }
+        }
+    }
+}
@@ -1,15 +1,183 @@
 #![feature(saturating_neg)]
 #![feature(vec_remove_item)]
+#![feature(arc_new_cyclic)]
 
 extern crate fuzzcheck;
 
 pub mod bool;
+pub mod boxed;
+pub mod byte_array;
+pub mod char;
+pub mod checksum;
+pub mod concat;
+pub mod conditional;
+pub mod cow;
+pub mod derived;
+pub mod discriminant;
 pub mod either;
+pub mod examples;
+pub mod flags;
+pub mod float;
+pub mod framed;
+pub mod grammar;
 pub mod integer;
+#[cfg(feature = "serde_json")]
+pub mod json;
+pub mod length_of;
 pub mod option;
+pub mod range;
+pub mod rc;
+pub mod recursive;
+pub mod saturating;
+pub mod sorted_vector;
+pub mod string;
+pub mod time;
+pub mod try_map;
 pub mod tuples;
+pub mod vec_deque;
 pub mod vector;
 pub mod void;
+pub mod wrapping;
+
+use fuzzcheck::{Mutator, Serializer};
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// A type that has a default mutator.
+///
+/// For example, `u8::default_mutator()` is a [U8Mutator](integer::U8Mutator)
+/// and `Vec::<Option<u8>>::default_mutator()` is a
+/// `VecMutator<OptionMutator<U8Mutator>>`.
+pub trait DefaultMutator: Clone {
+    type Mutator: Mutator<Value = Self>;
+    fn default_mutator() -> Self::Mutator;
+}
+
+/// Fuzz-test a function that takes a slice, such as `&[u8]`.
+///
+/// The fuzzer owns a `Vec<T>`, which is mutated by the default mutator of
+/// `Vec<T>`, and passes a borrow of it to the test function. The serializer
+/// encodes the vector in the corpus and artifacts.
+///
+/// ```ignore
+/// let _ = fuzz_slice(|bytes: &[u8]| parse(bytes).is_ok(), ByteSerializer::new("bin"));
+/// ```
+pub fn fuzz_slice<T, F, S>(test: F, serializer: S) -> Result<(), std::io::Error>
+where
+    T: DefaultMutator,
+    F: Fn(&[T]) -> bool,
+    S: Serializer<Value = Vec<T>> + 'static,
+{
+    fuzzcheck::launch(test, Vec::<T>::default_mutator(), serializer)
+}
+
+/// Fuzz-test a function that takes a `&str`.
+///
+/// The fuzzer owns a `String`, which is mutated by the default mutator of
+/// `String`, and passes a borrow of it to the test function. The serializer
+/// encodes the string in the corpus and artifacts.
+///
+/// ```ignore
+/// let _ = fuzz_str(|s: &str| parse(s).is_ok(), SerdeSerializer::default());
+/// ```
+pub fn fuzz_str<F, S>(test: F, serializer: S) -> Result<(), std::io::Error>
+where
+    F: Fn(&str) -> bool,
+    S: Serializer<Value = String> + 'static,
+{
+    fuzzcheck::launch(test, String::default_mutator(), serializer)
+}
+
+/// The seed of the random decisions of a mutation step, derived from a hash
+/// of the value, so that different inputs do not make the same decisions at
+/// their n-th mutation.
+///
+/// Mutators of values whose type does not implement `Hash` hash what they
+/// know of the value instead, such as its [complexity_fingerprint].
+pub(crate) fn step_rng_seed(value: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The bits of the complexity of a value, as a fingerprint of a value whose
+/// type does not implement `Hash`
+pub(crate) fn complexity_fingerprint<M: Mutator>(m: &M, value: &M::Value) -> u64 {
+    m.complexity(value, &m.cache_from_value(value)).to_bits()
+}
+
+/// The description of a composite value, made of the descriptions of its
+/// parts laid out like `{:#?}` between the `open` and `close` delimiters.
+///
+/// Each part is given with its name, if any, and `_` stands for the parts
+/// that have no description. Returns `None` if none of the parts has one.
+pub(crate) fn describe_parts<'a>(
+    open: &str,
+    parts: impl IntoIterator<Item = (Option<&'a str>, Option<String>)>,
+    close: &str,
+) -> Option<String> {
+    let mut description = format!("{}\n", open);
+    let mut is_described = false;
+    for (name, part) in parts {
+        is_described |= part.is_some();
+        let part = part.unwrap_or_else(|| "_".to_owned()).replace('\n', "\n    ");
+        match name {
+            Some(name) => description.push_str(&format!("    {}: {},\n", name, part)),
+            None => description.push_str(&format!("    {},\n", part)),
+        }
+    }
+    description.push_str(close);
+    if is_described {
+        Some(description)
+    } else {
+        None
+    }
+}
+
+/// Mutates the value `nbr_mutations` times the way the fuzzer does: one
+/// mutation in four is kept and followed by a new mutation step, and the
+/// others are undone. A new step is also started when the current one is
+/// exhausted.
+///
+/// `check` is given the value and its cache before and after each mutation.
+/// Each mutation must stay within `max_cplx`, and undoing it must restore
+/// the value, its complexity, and the breakdown of its complexity.
+#[cfg(test)]
+pub(crate) fn keep_one_mutation_in_four<M>(
+    m: &M,
+    value: &mut M::Value,
+    cache: &mut M::Cache,
+    max_cplx: f64,
+    nbr_mutations: usize,
+    mut check: impl FnMut(&M::Value, &M::Cache, &M::Value, &M::Cache),
+) where
+    M: Mutator,
+    M::Value: PartialEq + std::fmt::Debug,
+{
+    let mut step = m.mutation_step_from_value(value);
+    for i in 0..nbr_mutations {
+        let (old_value, old_cache) = (value.clone(), cache.clone());
+        let old_cplx = m.complexity(value, cache);
+        let old_breakdown = m.complexity_breakdown(value, cache);
+        let token = if let Some(token) = m.mutate(value, cache, &mut step, max_cplx) {
+            token
+        } else {
+            step = m.mutation_step_from_value(value);
+            continue;
+        };
+        assert!(m.complexity(value, cache) <= max_cplx);
+        check(&old_value, &old_cache, value, cache);
+        if i % 4 == 0 {
+            step = m.mutation_step_from_value(value);
+        } else {
+            m.unmutate(value, cache, token);
+            assert_eq!(*value, old_value);
+            assert_eq!(m.complexity(value, cache), old_cplx);
+            assert_eq!(m.complexity_breakdown(value, cache), old_breakdown);
+        }
+    }
+}
 
 pub fn arbitrary_binary(low: usize, high: usize, step: usize) -> usize {
     if high == low {
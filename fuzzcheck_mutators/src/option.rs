@@ -1,6 +1,8 @@
 extern crate fuzzcheck;
 use fuzzcheck::Mutator;
 
+use crate::DefaultMutator;
+
 macro_rules! match_all_options {
     ( $main:expr, $( $others:expr ),* ) => {
         {
@@ -20,6 +22,9 @@ impl<M: Mutator> OptionMutator<M> {
     pub fn new(m: M) -> Self {
         Self { m }
     }
+    pub(crate) fn inner(&self) -> &M {
+        &self.m
+    }
 }
 impl<M: Mutator> Default for OptionMutator<M>
 where
@@ -30,6 +35,13 @@ where
     }
 }
 
+impl<T: DefaultMutator> DefaultMutator for Option<T> {
+    type Mutator = OptionMutator<T::Mutator>;
+    fn default_mutator() -> Self::Mutator {
+        OptionMutator::new(T::default_mutator())
+    }
+}
+
 pub enum OptionMutatorUnmutateToken<Value, Token> {
     UnmutateSome(Token),
     ToSome(Value),
@@ -76,9 +88,19 @@ impl<M: Mutator> Mutator for OptionMutator<M> {
         }
     }
 
+    fn seeded_mutation_step_from_value(&self, value: &Self::Value, seed: u64) -> Self::MutationStep {
+        OptionMutatorStep {
+            did_check_none: value.is_none(),
+            inner_arbitrary: 0,
+            inner: value
+                .as_ref()
+                .map(|inner| self.m.seeded_mutation_step_from_value(inner, seed)),
+        }
+    }
+
     fn arbitrary(&self, seed: usize, max_cplx: f64) -> (Self::Value, Self::Cache) {
         let seed = OptionMutatorArbitrarySeed::new(seed);
-        if seed.check_none {
+        if seed.check_none || self.m.min_complexity() > max_cplx - 1.0 {
             (None, None)
         } else {
             let (inner_value, inner_cache) = self.m.arbitrary(seed.inner_seed, max_cplx - 1.0);
@@ -91,7 +113,8 @@ impl<M: Mutator> Mutator for OptionMutator<M> {
     }
 
     fn min_complexity(&self) -> f64 {
-        1.0 + self.m.min_complexity()
+        // complexity of None
+        1.0
     }
 
     fn complexity(&self, value: &Self::Value, cache: &Self::Cache) -> f64 {
@@ -108,19 +131,20 @@ impl<M: Mutator> Mutator for OptionMutator<M> {
         cache: &mut Self::Cache,
         step: &mut Self::MutationStep,
         max_cplx: f64,
-    ) -> Self::UnmutateToken {
+    ) -> Option<Self::UnmutateToken> {
         let inner_max_cplx = max_cplx - 1.0;
 
         if !step.did_check_none {
             let mut old_value = None;
             std::mem::swap(value, &mut old_value);
             step.did_check_none = true;
-            ToSome(old_value.unwrap())
+            Some(ToSome(old_value.unwrap()))
         } else if let Some((inner_value, inner_cache, inner_step)) =
             match_all_options!(value.as_mut(), cache.as_mut(), step.inner.as_mut())
         {
-            let inner_token = self.m.mutate(inner_value, inner_cache, inner_step, inner_max_cplx);
-            UnmutateSome(inner_token)
+            self.m
+                .mutate(inner_value, inner_cache, inner_step, inner_max_cplx)
+                .map(UnmutateSome)
         } else {
             let (inner_value, inner_cache) = self.m.arbitrary(step.inner_arbitrary, inner_max_cplx);
             *value = Some(inner_value);
@@ -128,7 +152,7 @@ impl<M: Mutator> Mutator for OptionMutator<M> {
 
             step.inner_arbitrary += 1;
 
-            ToNone
+            Some(ToNone)
         }
     }
 
@@ -147,4 +171,32 @@ impl<M: Mutator> Mutator for OptionMutator<M> {
             }
         }
     }
+
+    /// Combines the contents of two `Some` values
+    fn crossover(
+        &self,
+        a: &Self::Value,
+        a_cache: &Self::Cache,
+        b: &Self::Value,
+        b_cache: &Self::Cache,
+        seed: usize,
+        max_cplx: f64,
+    ) -> Option<(Self::Value, Self::Cache)> {
+        let (a, a_cache) = match_all_options!(a.as_ref(), a_cache.as_ref())?;
+        let (b, b_cache) = match_all_options!(b.as_ref(), b_cache.as_ref())?;
+        let (value, cache) = self.m.crossover(a, a_cache, b, b_cache, seed, max_cplx - 1.0)?;
+        Some((Some(value), Some(cache)))
+    }
+
+    fn record_mutation_outcome(&self, value: &Self::Value, token: &Self::UnmutateToken, nbr_new_features: usize) {
+        if let (Some(inner_value), UnmutateSome(inner_token)) = (value, token) {
+            self.m
+                .record_mutation_outcome(inner_value, inner_token, nbr_new_features)
+        }
+    }
+
+    fn describe_value(&self, value: &Self::Value) -> Option<String> {
+        let inner = self.m.describe_value(value.as_ref()?)?;
+        Some(format!("Some({})", inner))
+    }
 }
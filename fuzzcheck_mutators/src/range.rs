@@ -0,0 +1,380 @@
+//! A mutator for inclusive ranges, such as the bounds given to iteration or
+//! clamping code.
+
+extern crate fuzzcheck;
+use fuzzcheck::Mutator;
+
+use crate::DefaultMutator;
+
+extern crate rand;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use std::ops::RangeInclusive;
+
+/// The mutation of a range that collapses it to its start, after the first
+/// mutations of its endpoints
+const COLLAPSE_PICK_STEP: usize = 16;
+
+/**
+ * A mutator for `start..=end`, where `M` is the mutator of both endpoints.
+ *
+ * The ranges it produces are never empty: whenever a mutation moves an
+ * endpoint past the other one, the two endpoints are swapped so that
+ * `start <= end` still holds. The degenerate range `a..=a` is produced by
+ * one arbitrary value in four, and by the sixteenth mutation of a range.
+ *
+ * The complexity of a range is the sum of the complexities of its endpoints.
+ */
+pub struct RangeInclusiveMutator<M>
+where
+    M: Mutator,
+    M::Value: PartialOrd,
+{
+    m: M,
+}
+impl<M> RangeInclusiveMutator<M>
+where
+    M: Mutator,
+    M::Value: PartialOrd,
+{
+    pub fn new(m: M) -> Self {
+        Self { m }
+    }
+}
+impl<M> Default for RangeInclusiveMutator<M>
+where
+    M: Mutator + Default,
+    M::Value: PartialOrd,
+{
+    fn default() -> Self {
+        Self::new(M::default())
+    }
+}
+
+impl<T> DefaultMutator for RangeInclusive<T>
+where
+    T: DefaultMutator + PartialOrd,
+{
+    type Mutator = RangeInclusiveMutator<T::Mutator>;
+    fn default_mutator() -> Self::Mutator {
+        RangeInclusiveMutator::new(T::default_mutator())
+    }
+}
+
+#[derive(Clone)]
+pub struct RangeInclusiveMutatorCache<C> {
+    start: C,
+    end: C,
+    /// Whether the endpoints were swapped an odd number of times since the
+    /// cache was created, which tells the mutation step whether its steps
+    /// still belong to the right endpoints
+    swapped: bool,
+}
+
+pub struct RangeInclusiveMutatorStep<S> {
+    start: S,
+    end: S,
+    pick_step: usize,
+    /// The value of `swapped` in the cache when `start` and `end` last
+    /// belonged to the start and the end of the range
+    swapped: bool,
+}
+
+pub enum UnmutateRangeInclusiveToken<V, C, T> {
+    /// The start was mutated, and then swapped with the end if it went past it
+    Start { token: T, swapped: bool },
+    /// The end was mutated, and then swapped with the start if it went past it
+    End { token: T, swapped: bool },
+    /// The end was set to the start, and was this value before
+    Collapse { end: V, end_cache: C },
+}
+
+impl<M> RangeInclusiveMutator<M>
+where
+    M: Mutator,
+    M::Value: PartialOrd,
+{
+    /// Mutates one endpoint of the range, the start if `mutate_start` is
+    /// true and the end otherwise, and sorts the endpoints again
+    fn mutate_endpoint(
+        &self,
+        value: &mut RangeInclusive<M::Value>,
+        cache: &mut RangeInclusiveMutatorCache<M::Cache>,
+        step: &mut RangeInclusiveMutatorStep<M::MutationStep>,
+        mutate_start: bool,
+        max_cplx: f64,
+    ) -> Option<UnmutateRangeInclusiveToken<M::Value, M::Cache, M::UnmutateToken>> {
+        let (mut start, mut end) = value.clone().into_inner();
+        let token = if mutate_start {
+            let max_cplx = max_cplx - self.m.complexity(&end, &cache.end);
+            let token = self.m.mutate(&mut start, &mut cache.start, &mut step.start, max_cplx)?;
+            UnmutateRangeInclusiveToken::Start {
+                token,
+                swapped: start > end,
+            }
+        } else {
+            let max_cplx = max_cplx - self.m.complexity(&start, &cache.start);
+            let token = self.m.mutate(&mut end, &mut cache.end, &mut step.end, max_cplx)?;
+            UnmutateRangeInclusiveToken::End {
+                token,
+                swapped: start > end,
+            }
+        };
+        if start > end {
+            std::mem::swap(&mut start, &mut end);
+            std::mem::swap(&mut cache.start, &mut cache.end);
+            cache.swapped = !cache.swapped;
+        }
+        *value = start..=end;
+        Some(token)
+    }
+}
+
+impl<M> Mutator for RangeInclusiveMutator<M>
+where
+    M: Mutator,
+    M::Value: PartialOrd,
+{
+    type Value = RangeInclusive<M::Value>;
+    type Cache = RangeInclusiveMutatorCache<M::Cache>;
+    type MutationStep = RangeInclusiveMutatorStep<M::MutationStep>;
+    type UnmutateToken = UnmutateRangeInclusiveToken<M::Value, M::Cache, M::UnmutateToken>;
+
+    fn cache_from_value(&self, value: &Self::Value) -> Self::Cache {
+        RangeInclusiveMutatorCache {
+            start: self.m.cache_from_value(value.start()),
+            end: self.m.cache_from_value(value.end()),
+            swapped: false,
+        }
+    }
+
+    fn mutation_step_from_value(&self, value: &Self::Value) -> Self::MutationStep {
+        RangeInclusiveMutatorStep {
+            start: self.m.mutation_step_from_value(value.start()),
+            end: self.m.mutation_step_from_value(value.end()),
+            pick_step: 0,
+            swapped: false,
+        }
+    }
+
+    fn max_complexity(&self) -> f64 {
+        2.0 * self.m.max_complexity()
+    }
+
+    fn min_complexity(&self) -> f64 {
+        2.0 * self.m.min_complexity()
+    }
+
+    fn complexity(&self, value: &Self::Value, cache: &Self::Cache) -> f64 {
+        self.m.complexity(value.start(), &cache.start) + self.m.complexity(value.end(), &cache.end)
+    }
+
+    fn complexity_breakdown(&self, value: &Self::Value, cache: &Self::Cache) -> Vec<(&'static str, f64)> {
+        vec![
+            ("start", self.m.complexity(value.start(), &cache.start)),
+            ("end", self.m.complexity(value.end(), &cache.end)),
+        ]
+    }
+
+    fn arbitrary(&self, seed: usize, max_cplx: f64) -> (Self::Value, Self::Cache) {
+        let mut r = SmallRng::seed_from_u64(seed as u64);
+        let (start, start_cache) = self.m.arbitrary(r.gen(), max_cplx / 2.0);
+        if seed % 4 == 0 {
+            let cache = RangeInclusiveMutatorCache {
+                start: start_cache.clone(),
+                end: start_cache,
+                swapped: false,
+            };
+            return (start.clone()..=start, cache);
+        }
+        let (end, end_cache) = self.m.arbitrary(r.gen(), max_cplx / 2.0);
+        if start > end {
+            let cache = RangeInclusiveMutatorCache {
+                start: end_cache,
+                end: start_cache,
+                swapped: false,
+            };
+            (end..=start, cache)
+        } else {
+            let cache = RangeInclusiveMutatorCache {
+                start: start_cache,
+                end: end_cache,
+                swapped: false,
+            };
+            (start..=end, cache)
+        }
+    }
+
+    fn mutate(
+        &self,
+        value: &mut Self::Value,
+        cache: &mut Self::Cache,
+        step: &mut Self::MutationStep,
+        max_cplx: f64,
+    ) -> Option<Self::UnmutateToken> {
+        // the endpoints were swapped by a mutation that was kept, or swapped
+        // back by unmutating it, so the steps follow them
+        if step.swapped != cache.swapped {
+            std::mem::swap(&mut step.start, &mut step.end);
+            step.swapped = cache.swapped;
+        }
+        step.pick_step += 1;
+        if step.pick_step == COLLAPSE_PICK_STEP
+            && value.start() != value.end()
+            && 2.0 * self.m.complexity(value.start(), &cache.start) <= max_cplx
+        {
+            let (start, end) = value.clone().into_inner();
+            let end_cache = std::mem::replace(&mut cache.end, cache.start.clone());
+            *value = start.clone()..=start;
+            return Some(UnmutateRangeInclusiveToken::Collapse { end, end_cache });
+        }
+        // alternate between the endpoints, and fall back on the other one
+        // when the mutations of one of them are exhausted
+        let mutate_start_first = step.pick_step % 2 == 0;
+        self.mutate_endpoint(value, cache, step, mutate_start_first, max_cplx)
+            .or_else(|| self.mutate_endpoint(value, cache, step, !mutate_start_first, max_cplx))
+    }
+
+    fn unmutate(&self, value: &mut Self::Value, cache: &mut Self::Cache, t: Self::UnmutateToken) {
+        let (mut start, mut end) = value.clone().into_inner();
+        match t {
+            UnmutateRangeInclusiveToken::Start { token, swapped } => {
+                if swapped {
+                    std::mem::swap(&mut start, &mut end);
+                    std::mem::swap(&mut cache.start, &mut cache.end);
+                    cache.swapped = !cache.swapped;
+                }
+                self.m.unmutate(&mut start, &mut cache.start, token);
+            }
+            UnmutateRangeInclusiveToken::End { token, swapped } => {
+                if swapped {
+                    std::mem::swap(&mut start, &mut end);
+                    std::mem::swap(&mut cache.start, &mut cache.end);
+                    cache.swapped = !cache.swapped;
+                }
+                self.m.unmutate(&mut end, &mut cache.end, token);
+            }
+            UnmutateRangeInclusiveToken::Collapse {
+                end: old_end,
+                end_cache,
+            } => {
+                end = old_end;
+                cache.end = end_cache;
+            }
+        }
+        *value = start..=end;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keep_one_mutation_in_four;
+
+    #[test]
+    fn test_range_inclusive_mutator() {
+        let m = RangeInclusive::<u8>::default_mutator();
+        let mut nbr_degenerate = 0;
+        for seed in 0..100 {
+            let (mut x, mut cache) = m.arbitrary(seed, 16.0);
+            assert!(x.start() <= x.end());
+            if x.start() == x.end() {
+                nbr_degenerate += 1;
+            }
+            // a kept mutation may have swapped the endpoints
+            keep_one_mutation_in_four(&m, &mut x, &mut cache, 16.0, 100, |_, _, x, cache| {
+                assert!(x.start() <= x.end());
+                assert_eq!(m.complexity(x, cache), 16.0);
+                if x.start() == x.end() {
+                    nbr_degenerate += 1;
+                }
+            });
+        }
+        assert!(nbr_degenerate > 25);
+
+        // the sixteenth mutation collapses the range
+        let x = 3..=9;
+        let (mut y, mut cache) = (x.clone(), m.cache_from_value(&x));
+        let mut step = m.mutation_step_from_value(&x);
+        for _ in 1..COLLAPSE_PICK_STEP {
+            let token = m.mutate(&mut y, &mut cache, &mut step, 16.0).unwrap();
+            assert_ne!(y.start(), y.end());
+            m.unmutate(&mut y, &mut cache, token);
+        }
+        let token = m.mutate(&mut y, &mut cache, &mut step, 16.0);
+        assert_eq!(y, 3..=3);
+        m.unmutate(&mut y, &mut cache, token.unwrap());
+        assert_eq!(y, x);
+    }
+
+    /// A mutator that adds to a number the number its step was created from
+    struct AddOriginMutator;
+
+    impl Mutator for AddOriginMutator {
+        type Value = u8;
+        type Cache = ();
+        type MutationStep = u8;
+        type UnmutateToken = u8;
+
+        fn cache_from_value(&self, _value: &u8) {}
+        fn mutation_step_from_value(&self, value: &u8) -> u8 {
+            *value
+        }
+        fn max_complexity(&self) -> f64 {
+            1.0
+        }
+        fn min_complexity(&self) -> f64 {
+            1.0
+        }
+        fn complexity(&self, _value: &u8, _cache: &()) -> f64 {
+            1.0
+        }
+        fn arbitrary(&self, seed: usize, _max_cplx: f64) -> (u8, ()) {
+            (seed as u8, ())
+        }
+        fn mutate(&self, value: &mut u8, _cache: &mut (), step: &mut u8, _max_cplx: f64) -> Option<u8> {
+            let old_value = *value;
+            *value = value.wrapping_add(*step);
+            Some(old_value)
+        }
+        fn unmutate(&self, value: &mut u8, _cache: &mut (), t: u8) {
+            *value = t;
+        }
+    }
+
+    #[test]
+    fn test_steps_follow_swapped_endpoints() {
+        let m = RangeInclusiveMutator::new(AddOriginMutator);
+        let x = 2..=3;
+        let (mut y, mut cache) = (x.clone(), m.cache_from_value(&x));
+        let mut step = m.mutation_step_from_value(&x);
+        // the first mutation moves the end, 3 + 3
+        let token = m.mutate(&mut y, &mut cache, &mut step, 2.0).unwrap();
+        assert_eq!(y, 2..=6);
+        m.unmutate(&mut y, &mut cache, token);
+        // the second one moves the start past the end, 2 + 2, and is kept
+        let _ = m.mutate(&mut y, &mut cache, &mut step, 2.0).unwrap();
+        assert_eq!(y, 3..=4);
+        // the end was the start, so it moves by 2
+        let token = m.mutate(&mut y, &mut cache, &mut step, 2.0).unwrap();
+        assert_eq!(y, 3..=6);
+        m.unmutate(&mut y, &mut cache, token);
+        // the start was the end, so it moves by 3, past the end
+        let token = m.mutate(&mut y, &mut cache, &mut step, 2.0).unwrap();
+        assert_eq!(y, 4..=6);
+        m.unmutate(&mut y, &mut cache, token);
+        assert_eq!(y, 3..=4);
+        // unmutating a swap gives the steps back to their endpoints
+        let mut y = x.clone();
+        let mut cache = m.cache_from_value(&x);
+        let mut step = m.mutation_step_from_value(&x);
+        let token = m.mutate(&mut y, &mut cache, &mut step, 2.0).unwrap();
+        m.unmutate(&mut y, &mut cache, token);
+        let token = m.mutate(&mut y, &mut cache, &mut step, 2.0).unwrap();
+        assert_eq!(y, 3..=4);
+        m.unmutate(&mut y, &mut cache, token);
+        let _ = m.mutate(&mut y, &mut cache, &mut step, 2.0).unwrap();
+        assert_eq!(y, 2..=6);
+    }
+}
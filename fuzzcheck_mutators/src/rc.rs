@@ -0,0 +1,139 @@
+extern crate fuzzcheck;
+use fuzzcheck::Mutator;
+
+use crate::DefaultMutator;
+
+use std::rc::Rc;
+use std::sync::Arc;
+
+macro_rules! shared_pointer_mutator {
+    ($name:ident, $pointer:ident) => {
+        /// A mutator for a reference-counted pointer that forwards to the
+        /// mutator of its contents.
+        ///
+        /// The contents are cloned before being mutated if they are shared
+        /// with another pointer.
+        pub struct $name<M: Mutator> {
+            m: M,
+        }
+        impl<M: Mutator> $name<M> {
+            pub fn new(m: M) -> Self {
+                Self { m }
+            }
+        }
+        impl<M: Mutator> Default for $name<M>
+        where
+            M: Default,
+        {
+            fn default() -> Self {
+                Self::new(M::default())
+            }
+        }
+
+        impl<T: DefaultMutator> DefaultMutator for $pointer<T> {
+            type Mutator = $name<T::Mutator>;
+            fn default_mutator() -> Self::Mutator {
+                $name::new(T::default_mutator())
+            }
+        }
+
+        impl<M: Mutator> Mutator for $name<M> {
+            type Value = $pointer<M::Value>;
+            type Cache = M::Cache;
+            type MutationStep = M::MutationStep;
+            type UnmutateToken = M::UnmutateToken;
+
+            fn cache_from_value(&self, value: &Self::Value) -> Self::Cache {
+                self.m.cache_from_value(value)
+            }
+            fn mutation_step_from_value(&self, value: &Self::Value) -> Self::MutationStep {
+                self.m.mutation_step_from_value(value)
+            }
+
+            fn seeded_mutation_step_from_value(&self, value: &Self::Value, seed: u64) -> Self::MutationStep {
+                self.m.seeded_mutation_step_from_value(value, seed)
+            }
+
+            fn max_complexity(&self) -> f64 {
+                self.m.max_complexity()
+            }
+            fn min_complexity(&self) -> f64 {
+                self.m.min_complexity()
+            }
+            fn complexity(&self, value: &Self::Value, cache: &Self::Cache) -> f64 {
+                self.m.complexity(value, cache)
+            }
+
+            fn arbitrary(&self, seed: usize, max_cplx: f64) -> (Self::Value, Self::Cache) {
+                let (value, cache) = self.m.arbitrary(seed, max_cplx);
+                ($pointer::new(value), cache)
+            }
+
+            fn mutate(
+                &self,
+                value: &mut Self::Value,
+                cache: &mut Self::Cache,
+                step: &mut Self::MutationStep,
+                max_cplx: f64,
+            ) -> Option<Self::UnmutateToken> {
+                self.m.mutate($pointer::make_mut(value), cache, step, max_cplx)
+            }
+
+            fn unmutate(&self, value: &mut Self::Value, cache: &mut Self::Cache, t: Self::UnmutateToken) {
+                self.m.unmutate($pointer::make_mut(value), cache, t)
+            }
+
+            fn crossover(
+                &self,
+                a: &Self::Value,
+                a_cache: &Self::Cache,
+                b: &Self::Value,
+                b_cache: &Self::Cache,
+                seed: usize,
+                max_cplx: f64,
+            ) -> Option<(Self::Value, Self::Cache)> {
+                let (value, cache) = self.m.crossover(a, a_cache, b, b_cache, seed, max_cplx)?;
+                Some(($pointer::new(value), cache))
+            }
+
+            fn record_mutation_outcome(
+                &self,
+                value: &Self::Value,
+                token: &Self::UnmutateToken,
+                nbr_new_features: usize,
+            ) {
+                self.m.record_mutation_outcome(value, token, nbr_new_features)
+            }
+
+            fn describe_value(&self, value: &Self::Value) -> Option<String> {
+                self.m.describe_value(value)
+            }
+        }
+    };
+}
+
+shared_pointer_mutator!(RcMutator, Rc);
+shared_pointer_mutator!(ArcMutator, Arc);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integer::*;
+
+    #[test]
+    fn test_rc_mutator_does_not_mutate_shared_contents() {
+        let m = RcMutator::new(U8Mutator::default());
+        let (mut x, mut x_cache) = m.arbitrary(0, 8.0);
+        let mut x_step = m.mutation_step_from_value(&x);
+        let shared = x.clone();
+        let original = *x;
+
+        for _ in 0..100 {
+            let token = m.mutate(&mut x, &mut x_cache, &mut x_step, 8.0).unwrap();
+            assert_eq!(*shared, original);
+            m.unmutate(&mut x, &mut x_cache, token);
+            assert_eq!(*x, original);
+        }
+        assert_eq!(m.complexity(&x, &x_cache), 8.0);
+    }
+}
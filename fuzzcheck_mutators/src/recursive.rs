@@ -0,0 +1,379 @@
+//! Mutators for recursive types, such as trees or JSON values.
+//!
+//! The type of a recursive mutator refers to itself, which cannot be written
+//! directly in Rust. So it needs to be given a name, which is done by
+//! implementing [RecursiveMutatorDef] for a marker type. Then,
+//! [RecursiveMutator::new] ties the knot: it gives the closure building the
+//! mutator a [RecurToMutator], which is a handle to the mutator being defined.
+//!
+//! ## Example
+//!
+//! ```ignore
+//! enum Tree {
+//!     Leaf(u8),
+//!     Node(Box<(Tree, Tree)>),
+//! }
+//! // impl EitherMap for Tree { ... }
+//!
+//! struct TreeDef;
+//! impl RecursiveMutatorDef for TreeDef {
+//!     type Value = Tree;
+//!     type Mutator = EitherMutator<Tree, U8Mutator, Tuple2Mutator<(Tree, Tree), RecurToMutator<Self>, RecurToMutator<Self>>>;
+//! }
+//!
+//! let m = RecursiveMutator::<TreeDef>::new(|self_mutator| {
+//!     EitherMutator::new(U8Mutator::default(), Tuple2Mutator::new(self_mutator.clone(), self_mutator))
+//! });
+//! ```
+//!
+//! ## Termination of `arbitrary`
+//!
+//! The complexity budget given to `arbitrary` is used as recursion fuel.
+//! Each level of recursion must spend some of that budget (`EitherMutator`
+//! and `OptionMutator` spend `1.0` each), and the choice of a branch is
+//! restricted to the branches whose minimum complexity fits in the remaining
+//! budget. The minimum complexity of a [RecurToMutator] is computed by
+//! recursing into the mutator it refers to, and a recursion site that is
+//! reached again while it is being computed is considered infinitely complex.
+//!
+//! For recursive links of type `Option<Box<T>>`, prefer
+//! [OptionBoxMutator](crate::boxed::OptionBoxMutator) to
+//! `OptionMutator<BoxMutator<_>>`: it treats `None` as the base case and
+//! chooses it more often as the budget shrinks, so that generated values do
+//! not always use the whole budget.
+
+use std::cell::Cell;
+use std::rc::{Rc, Weak};
+
+extern crate fuzzcheck;
+use fuzzcheck::Mutator;
+
+/// Gives a name to the type of a recursive mutator
+pub trait RecursiveMutatorDef {
+    type Value: Clone;
+    type Mutator: Mutator<Value = Self::Value>;
+}
+
+/// A mutator for a recursive type, see the [module documentation](self)
+pub struct RecursiveMutator<D: RecursiveMutatorDef> {
+    mutator: Rc<D::Mutator>,
+}
+
+impl<D: RecursiveMutatorDef> RecursiveMutator<D> {
+    /// Create a recursive mutator from a closure that is given a handle to
+    /// the mutator being defined.
+    pub fn new<F>(data_fn: F) -> Self
+    where
+        F: FnOnce(RecurToMutator<D>) -> D::Mutator,
+    {
+        let mutator = Rc::new_cyclic(|reference| data_fn(RecurToMutator::new(reference.clone())));
+        Self { mutator }
+    }
+}
+
+impl<D: RecursiveMutatorDef> Mutator for RecursiveMutator<D> {
+    type Value = D::Value;
+    type Cache = <D::Mutator as Mutator>::Cache;
+    type MutationStep = <D::Mutator as Mutator>::MutationStep;
+    type UnmutateToken = <D::Mutator as Mutator>::UnmutateToken;
+
+    fn cache_from_value(&self, value: &Self::Value) -> Self::Cache {
+        self.mutator.cache_from_value(value)
+    }
+    fn mutation_step_from_value(&self, value: &Self::Value) -> Self::MutationStep {
+        self.mutator.mutation_step_from_value(value)
+    }
+
+    fn seeded_mutation_step_from_value(&self, value: &Self::Value, seed: u64) -> Self::MutationStep {
+        self.mutator.seeded_mutation_step_from_value(value, seed)
+    }
+
+    fn max_complexity(&self) -> f64 {
+        self.mutator.max_complexity()
+    }
+    fn min_complexity(&self) -> f64 {
+        self.mutator.min_complexity()
+    }
+    fn complexity(&self, value: &Self::Value, cache: &Self::Cache) -> f64 {
+        self.mutator.complexity(value, cache)
+    }
+
+    fn arbitrary(&self, seed: usize, max_cplx: f64) -> (Self::Value, Self::Cache) {
+        self.mutator.arbitrary(seed, max_cplx)
+    }
+
+    fn mutate(
+        &self,
+        value: &mut Self::Value,
+        cache: &mut Self::Cache,
+        step: &mut Self::MutationStep,
+        max_cplx: f64,
+    ) -> Option<Self::UnmutateToken> {
+        self.mutator.mutate(value, cache, step, max_cplx)
+    }
+
+    fn unmutate(&self, value: &mut Self::Value, cache: &mut Self::Cache, t: Self::UnmutateToken) {
+        self.mutator.unmutate(value, cache, t)
+    }
+
+    fn crossover(
+        &self,
+        a: &Self::Value,
+        a_cache: &Self::Cache,
+        b: &Self::Value,
+        b_cache: &Self::Cache,
+        seed: usize,
+        max_cplx: f64,
+    ) -> Option<(Self::Value, Self::Cache)> {
+        self.mutator.crossover(a, a_cache, b, b_cache, seed, max_cplx)
+    }
+
+    fn record_mutation_outcome(&self, value: &Self::Value, token: &Self::UnmutateToken, nbr_new_features: usize) {
+        self.mutator.record_mutation_outcome(value, token, nbr_new_features)
+    }
+}
+
+/**
+ * A handle to the recursive mutator being defined, to be used wherever the
+ * mutator refers to itself.
+ *
+ * Its cache, mutation step, and unmutate token are boxed, so that their
+ * types are not infinitely large.
+ */
+pub struct RecurToMutator<D: RecursiveMutatorDef> {
+    reference: Weak<D::Mutator>,
+    is_computing_min_complexity: Cell<bool>,
+}
+
+impl<D: RecursiveMutatorDef> RecurToMutator<D> {
+    fn new(reference: Weak<D::Mutator>) -> Self {
+        Self {
+            reference,
+            is_computing_min_complexity: Cell::new(false),
+        }
+    }
+
+    fn mutator(&self) -> Rc<D::Mutator> {
+        self.reference
+            .upgrade()
+            .expect("a RecurToMutator was used after its RecursiveMutator was dropped")
+    }
+}
+
+impl<D: RecursiveMutatorDef> Clone for RecurToMutator<D> {
+    fn clone(&self) -> Self {
+        Self::new(self.reference.clone())
+    }
+}
+
+pub struct RecurToMutatorCache<D: RecursiveMutatorDef> {
+    inner: Box<<D::Mutator as Mutator>::Cache>,
+}
+impl<D: RecursiveMutatorDef> Clone for RecurToMutatorCache<D> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct RecurToMutatorStep<D: RecursiveMutatorDef> {
+    inner: Box<<D::Mutator as Mutator>::MutationStep>,
+}
+
+pub struct RecurToMutatorUnmutateToken<D: RecursiveMutatorDef> {
+    inner: Box<<D::Mutator as Mutator>::UnmutateToken>,
+}
+
+impl<D: RecursiveMutatorDef> Mutator for RecurToMutator<D> {
+    type Value = D::Value;
+    type Cache = RecurToMutatorCache<D>;
+    type MutationStep = RecurToMutatorStep<D>;
+    type UnmutateToken = RecurToMutatorUnmutateToken<D>;
+
+    fn cache_from_value(&self, value: &Self::Value) -> Self::Cache {
+        RecurToMutatorCache {
+            inner: Box::new(self.mutator().cache_from_value(value)),
+        }
+    }
+    fn mutation_step_from_value(&self, value: &Self::Value) -> Self::MutationStep {
+        RecurToMutatorStep {
+            inner: Box::new(self.mutator().mutation_step_from_value(value)),
+        }
+    }
+
+    fn seeded_mutation_step_from_value(&self, value: &Self::Value, seed: u64) -> Self::MutationStep {
+        RecurToMutatorStep {
+            inner: Box::new(self.mutator().seeded_mutation_step_from_value(value, seed)),
+        }
+    }
+
+    fn max_complexity(&self) -> f64 {
+        // a recursive type can always be made larger
+        std::f64::INFINITY
+    }
+
+    fn min_complexity(&self) -> f64 {
+        if self.is_computing_min_complexity.get() {
+            // recursing through this handle again cannot lead to the simplest value
+            return std::f64::INFINITY;
+        }
+        self.is_computing_min_complexity.set(true);
+        let min_cplx = self.mutator().min_complexity();
+        self.is_computing_min_complexity.set(false);
+        min_cplx
+    }
+
+    fn complexity(&self, value: &Self::Value, cache: &Self::Cache) -> f64 {
+        self.mutator().complexity(value, &cache.inner)
+    }
+
+    fn arbitrary(&self, seed: usize, max_cplx: f64) -> (Self::Value, Self::Cache) {
+        let (value, cache) = self.mutator().arbitrary(seed, max_cplx);
+        (value, RecurToMutatorCache { inner: Box::new(cache) })
+    }
+
+    fn mutate(
+        &self,
+        value: &mut Self::Value,
+        cache: &mut Self::Cache,
+        step: &mut Self::MutationStep,
+        max_cplx: f64,
+    ) -> Option<Self::UnmutateToken> {
+        let token = self
+            .mutator()
+            .mutate(value, &mut cache.inner, &mut step.inner, max_cplx)?;
+        Some(RecurToMutatorUnmutateToken { inner: Box::new(token) })
+    }
+
+    fn unmutate(&self, value: &mut Self::Value, cache: &mut Self::Cache, t: Self::UnmutateToken) {
+        self.mutator().unmutate(value, &mut cache.inner, *t.inner)
+    }
+
+    fn crossover(
+        &self,
+        a: &Self::Value,
+        a_cache: &Self::Cache,
+        b: &Self::Value,
+        b_cache: &Self::Cache,
+        seed: usize,
+        max_cplx: f64,
+    ) -> Option<(Self::Value, Self::Cache)> {
+        let (value, cache) = self
+            .mutator()
+            .crossover(a, &a_cache.inner, b, &b_cache.inner, seed, max_cplx)?;
+        Some((value, RecurToMutatorCache { inner: Box::new(cache) }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::either::*;
+    use crate::integer::*;
+    use crate::tuples::*;
+
+    #[derive(Clone, Debug)]
+    enum Tree {
+        Leaf(u8),
+        Node(Box<(Tree, Tree)>),
+    }
+
+    impl Tree {
+        fn depth(&self) -> usize {
+            match self {
+                Tree::Leaf(_) => 0,
+                Tree::Node(children) => 1 + std::cmp::max(children.0.depth(), children.1.depth()),
+            }
+        }
+    }
+
+    impl EitherMap for Tree {
+        type A = u8;
+        type B = (Tree, Tree);
+        type V = Self;
+
+        fn left(a: u8) -> Self {
+            Tree::Leaf(a)
+        }
+        fn right(b: (Tree, Tree)) -> Self {
+            Tree::Node(Box::new(b))
+        }
+
+        fn get_either(v: &Self) -> Either<&u8, &(Tree, Tree)> {
+            match v {
+                Tree::Leaf(a) => Either::Left(a),
+                Tree::Node(b) => Either::Right(b),
+            }
+        }
+        fn get_either_mut(v: &mut Self) -> Either<&mut u8, &mut (Tree, Tree)> {
+            match v {
+                Tree::Leaf(a) => Either::Left(a),
+                Tree::Node(b) => Either::Right(b),
+            }
+        }
+    }
+
+    struct TreeDef;
+    impl RecursiveMutatorDef for TreeDef {
+        type Value = Tree;
+        type Mutator =
+            EitherMutator<Tree, U8Mutator, Tuple2Mutator<(Tree, Tree), RecurToMutator<Self>, RecurToMutator<Self>>>;
+    }
+
+    fn tree_mutator() -> RecursiveMutator<TreeDef> {
+        RecursiveMutator::new(|self_mutator| {
+            EitherMutator::new(
+                U8Mutator::default(),
+                Tuple2Mutator::new(self_mutator.clone(), self_mutator),
+            )
+        })
+    }
+
+    #[test]
+    fn test_binary_tree_mutator() {
+        let m = tree_mutator();
+
+        // a leaf is 1.0 for the either and 8.0 for the u8
+        assert_eq!(m.min_complexity(), 9.0);
+
+        for max_cplx in [0.0, 10.0, 50.0, 200.0].iter() {
+            for seed in 0..100 {
+                let (x, x_cache) = m.arbitrary(seed, *max_cplx);
+                // each level of the tree spends at least 1.0 of the complexity budget
+                assert!(x.depth() as f64 <= max_cplx.max(1.0));
+                assert_eq!(m.complexity(&x, &x_cache), m.complexity(&x, &m.cache_from_value(&x)));
+            }
+        }
+
+        let (mut x, mut x_cache) = m.arbitrary(1, 100.0);
+        let mut x_step = m.mutation_step_from_value(&x);
+        let cplx = m.complexity(&x, &x_cache);
+        for _ in 0..1000 {
+            let token = m.mutate(&mut x, &mut x_cache, &mut x_step, 200.0).unwrap();
+            assert_eq!(m.complexity(&x, &x_cache), m.complexity(&x, &m.cache_from_value(&x)));
+            m.unmutate(&mut x, &mut x_cache, token);
+            assert_eq!(m.complexity(&x, &x_cache), cplx);
+        }
+    }
+
+    #[test]
+    fn test_crossover_of_trees() {
+        let m = tree_mutator();
+        let (leaf_a, leaf_b) = (Tree::Leaf(1), Tree::Leaf(2));
+        let node = |a: Tree, b: Tree| Tree::Node(Box::new((a, b)));
+        // leaves cannot be combined, but the children of nodes can
+        let (a_cache, b_cache) = (m.cache_from_value(&leaf_a), m.cache_from_value(&leaf_b));
+        assert!(m.crossover(&leaf_a, &a_cache, &leaf_b, &b_cache, 0, 100.0).is_none());
+
+        let a = node(leaf_a.clone(), node(leaf_a.clone(), leaf_a));
+        let b = node(node(leaf_b.clone(), leaf_b.clone()), leaf_b);
+        let (a_cache, b_cache) = (m.cache_from_value(&a), m.cache_from_value(&b));
+        for seed in 0..100 {
+            let (x, x_cache) = m.crossover(&a, &a_cache, &b, &b_cache, seed, 200.0).unwrap();
+            assert_eq!(m.complexity(&x, &x_cache), m.complexity(&x, &m.cache_from_value(&x)));
+            assert!(m.complexity(&x, &x_cache) < 200.0);
+            assert!(matches!(x, Tree::Node(_)));
+        }
+    }
+}
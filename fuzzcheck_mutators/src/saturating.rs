@@ -0,0 +1,390 @@
+//! A mutator for saturating integers, whose arithmetic clamps its results to
+//! the bounds of the type instead of overflowing, such as a percentage that
+//! saturates at 100.
+
+extern crate fuzzcheck;
+use fuzzcheck::Mutator;
+
+use crate::tuples::NewtypeMap;
+
+use std::marker::PhantomData;
+
+/// The structure of a saturating newtype: a newtype whose wrapped value is
+/// always between [min_value](SaturatingMap::min_value) and
+/// [max_value](SaturatingMap::max_value).
+pub trait SaturatingMap: NewtypeMap {
+    fn min_value() -> Self::A;
+    fn max_value() -> Self::A;
+}
+
+/**
+ * A mutator for a saturating newtype, where `M` is the mutator of the wrapped
+ * value.
+ *
+ * Saturating arithmetic treats the bounds of the type specially, since every
+ * overflowing operation ends on them, so they are the first values it tries:
+ * the first two arbitrary values and the first two mutations of a value are
+ * the minimum and the maximum. The other values produced by `M` are saturated
+ * to the bounds when they are out of range.
+ *
+ * A saturating value has the same complexity as the wrapped value.
+ */
+pub struct SaturatingMutator<Map, M>
+where
+    M: Mutator,
+    Map: SaturatingMap<A = M::Value>,
+{
+    m: M,
+    phantom: PhantomData<Map>,
+}
+impl<Map: SaturatingMap<A = M::Value>, M: Mutator> SaturatingMutator<Map, M> {
+    pub fn new(m: M) -> Self {
+        Self {
+            m,
+            phantom: PhantomData,
+        }
+    }
+}
+impl<Map: SaturatingMap<A = M::Value>, M: Mutator> Default for SaturatingMutator<Map, M>
+where
+    M: Default,
+{
+    fn default() -> Self {
+        Self::new(M::default())
+    }
+}
+
+pub struct SaturatingMutatorStep<S> {
+    /// The number of bounds that were tried
+    bound_step: usize,
+    inner: S,
+}
+
+pub enum UnmutateSaturatingToken<A, C, T> {
+    /// The wrapped value was mutated by the inner mutator
+    Inner(T),
+    /// The wrapped value was mutated by the inner mutator to this value and
+    /// cache, and then saturated to a bound
+    Saturated(A, C, T),
+    /// The wrapped value was set to a bound, and was this value before
+    Bound(A, C),
+}
+
+impl<Map, M> SaturatingMutator<Map, M>
+where
+    M: Mutator,
+    M::Value: PartialOrd,
+    Map: SaturatingMap<A = M::Value>,
+{
+    /// The value clamped to the bounds, if it is out of range
+    fn saturate(a: &M::Value) -> Option<M::Value> {
+        let (min, max) = (Map::min_value(), Map::max_value());
+        if *a < min {
+            Some(min)
+        } else if *a > max {
+            Some(max)
+        } else {
+            None
+        }
+    }
+}
+
+impl<Map, M> Mutator for SaturatingMutator<Map, M>
+where
+    M: Mutator,
+    M::Value: PartialOrd,
+    Map: SaturatingMap<A = M::Value>,
+{
+    type Value = Map::V;
+    type Cache = M::Cache;
+    type MutationStep = SaturatingMutatorStep<M::MutationStep>;
+    type UnmutateToken = UnmutateSaturatingToken<M::Value, M::Cache, M::UnmutateToken>;
+
+    fn cache_from_value(&self, value: &Self::Value) -> Self::Cache {
+        self.m.cache_from_value(Map::get_a(value))
+    }
+
+    fn mutation_step_from_value(&self, value: &Self::Value) -> Self::MutationStep {
+        SaturatingMutatorStep {
+            bound_step: 0,
+            inner: self.m.mutation_step_from_value(Map::get_a(value)),
+        }
+    }
+
+    fn arbitrary(&self, seed: usize, max_cplx: f64) -> (Self::Value, Self::Cache) {
+        let a = match seed {
+            0 => Map::min_value(),
+            1 => Map::max_value(),
+            _ => {
+                let (a, cache) = self.m.arbitrary(seed - 2, max_cplx);
+                match Self::saturate(&a) {
+                    Some(saturated) => saturated,
+                    None => return (Map::new(a), cache),
+                }
+            }
+        };
+        let cache = self.m.cache_from_value(&a);
+        (Map::new(a), cache)
+    }
+
+    fn max_complexity(&self) -> f64 {
+        self.m.max_complexity()
+    }
+
+    fn min_complexity(&self) -> f64 {
+        self.m.min_complexity()
+    }
+
+    fn complexity(&self, value: &Self::Value, cache: &Self::Cache) -> f64 {
+        self.m.complexity(Map::get_a(value), cache)
+    }
+
+    fn mutate(
+        &self,
+        value: &mut Self::Value,
+        cache: &mut Self::Cache,
+        step: &mut Self::MutationStep,
+        max_cplx: f64,
+    ) -> Option<Self::UnmutateToken> {
+        while step.bound_step < 2 {
+            let bound = if step.bound_step == 0 {
+                Map::min_value()
+            } else {
+                Map::max_value()
+            };
+            step.bound_step += 1;
+            if *Map::get_a(value) == bound {
+                continue;
+            }
+            let bound_cache = self.m.cache_from_value(&bound);
+            let old_a = std::mem::replace(Map::get_a_mut(value), bound);
+            let old_cache = std::mem::replace(cache, bound_cache);
+            return Some(UnmutateSaturatingToken::Bound(old_a, old_cache));
+        }
+        let token = self.m.mutate(Map::get_a_mut(value), cache, &mut step.inner, max_cplx)?;
+        if let Some(saturated) = Self::saturate(Map::get_a(value)) {
+            let saturated_cache = self.m.cache_from_value(&saturated);
+            let a = std::mem::replace(Map::get_a_mut(value), saturated);
+            let a_cache = std::mem::replace(cache, saturated_cache);
+            Some(UnmutateSaturatingToken::Saturated(a, a_cache, token))
+        } else {
+            Some(UnmutateSaturatingToken::Inner(token))
+        }
+    }
+
+    fn unmutate(&self, value: &mut Self::Value, cache: &mut Self::Cache, t: Self::UnmutateToken) {
+        match t {
+            UnmutateSaturatingToken::Inner(t) => self.m.unmutate(Map::get_a_mut(value), cache, t),
+            UnmutateSaturatingToken::Saturated(a, a_cache, t) => {
+                *Map::get_a_mut(value) = a;
+                *cache = a_cache;
+                self.m.unmutate(Map::get_a_mut(value), cache, t);
+            }
+            UnmutateSaturatingToken::Bound(a, a_cache) => {
+                *Map::get_a_mut(value) = a;
+                *cache = a_cache;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integer::*;
+    use crate::tuples::*;
+    use crate::vector::*;
+    use crate::DefaultMutator;
+
+    /// A percentage, whose arithmetic saturates at 0 and 100
+    #[derive(Clone, Debug, PartialEq)]
+    struct Percent(u8);
+
+    impl NewtypeMap for Percent {
+        type A = u8;
+        type V = Self;
+
+        fn get_a(v: &Self) -> &u8 {
+            &v.0
+        }
+        fn get_a_mut(v: &mut Self) -> &mut u8 {
+            &mut v.0
+        }
+        fn new(a: u8) -> Self {
+            Percent(a)
+        }
+    }
+    impl SaturatingMap for Percent {
+        fn min_value() -> u8 {
+            0
+        }
+        fn max_value() -> u8 {
+            100
+        }
+    }
+    impl DefaultMutator for Percent {
+        type Mutator = SaturatingMutator<Percent, U8Mutator>;
+        fn default_mutator() -> Self::Mutator {
+            SaturatingMutator::default()
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Volume {
+        level: Percent,
+        data: Vec<u8>,
+    }
+
+    impl TupleMap for Volume {
+        type A = Percent;
+        type B = Vec<u8>;
+        type V = Self;
+
+        fn get_a(v: &Self) -> &Percent {
+            &v.level
+        }
+        fn get_b(v: &Self) -> &Vec<u8> {
+            &v.data
+        }
+        fn get_a_mut(v: &mut Self) -> &mut Percent {
+            &mut v.level
+        }
+        fn get_b_mut(v: &mut Self) -> &mut Vec<u8> {
+            &mut v.data
+        }
+        fn new(level: Percent, data: Vec<u8>) -> Self {
+            Volume { level, data }
+        }
+    }
+
+    #[test]
+    fn test_struct_with_saturating_field() {
+        let level_m = Percent::default_mutator();
+        assert_eq!(level_m.arbitrary(0, 8.0).0, Percent(0));
+        assert_eq!(level_m.arbitrary(1, 8.0).0, Percent(100));
+
+        // the bounds are the first mutations of a value
+        let (mut x, mut cache) = (Percent(50), ());
+        let mut step = level_m.mutation_step_from_value(&x);
+        let token = level_m.mutate(&mut x, &mut cache, &mut step, 8.0).unwrap();
+        assert_eq!(x, Percent(0));
+        level_m.unmutate(&mut x, &mut cache, token);
+        let _ = level_m.mutate(&mut x, &mut cache, &mut step, 8.0).unwrap();
+        assert_eq!(x, Percent(100));
+
+        let m = Tuple2Mutator::<Volume, SaturatingMutator<Percent, U8Mutator>, VecMutator<U8Mutator>>::new(
+            Percent::default_mutator(),
+            Vec::<u8>::default_mutator(),
+        );
+        let mut levels = std::collections::HashSet::new();
+        for seed in 0..10 {
+            let (mut x, mut x_cache) = m.arbitrary(seed, 100.0);
+            let mut x_step = m.mutation_step_from_value(&x);
+            for _ in 0..300 {
+                let old_x = x.clone();
+                let token = if let Some(token) = m.mutate(&mut x, &mut x_cache, &mut x_step, 100.0) {
+                    token
+                } else {
+                    break;
+                };
+                assert!(x.level.0 <= 100);
+                levels.insert(x.level.0);
+                m.unmutate(&mut x, &mut x_cache, token);
+                assert_eq!(x, old_x);
+            }
+        }
+        assert!(levels.contains(&0));
+        assert!(levels.contains(&100));
+        assert!(levels.len() > 50);
+    }
+
+    /// An `i32` whose arithmetic saturates at the bounds of `i32`, like
+    /// `std::num::Saturating<i32>`
+    #[derive(Clone, Debug, PartialEq)]
+    struct SaturatingI32(i32);
+
+    impl NewtypeMap for SaturatingI32 {
+        type A = i32;
+        type V = Self;
+
+        fn get_a(v: &Self) -> &i32 {
+            &v.0
+        }
+        fn get_a_mut(v: &mut Self) -> &mut i32 {
+            &mut v.0
+        }
+        fn new(a: i32) -> Self {
+            SaturatingI32(a)
+        }
+    }
+    impl SaturatingMap for SaturatingI32 {
+        fn min_value() -> i32 {
+            i32::MIN
+        }
+        fn max_value() -> i32 {
+            i32::MAX
+        }
+    }
+    impl DefaultMutator for SaturatingI32 {
+        type Mutator = SaturatingMutator<SaturatingI32, I32Mutator>;
+        fn default_mutator() -> Self::Mutator {
+            SaturatingMutator::default()
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Reading {
+        value: SaturatingI32,
+        data: Vec<u8>,
+    }
+
+    impl TupleMap for Reading {
+        type A = SaturatingI32;
+        type B = Vec<u8>;
+        type V = Self;
+
+        fn get_a(v: &Self) -> &SaturatingI32 {
+            &v.value
+        }
+        fn get_b(v: &Self) -> &Vec<u8> {
+            &v.data
+        }
+        fn get_a_mut(v: &mut Self) -> &mut SaturatingI32 {
+            &mut v.value
+        }
+        fn get_b_mut(v: &mut Self) -> &mut Vec<u8> {
+            &mut v.data
+        }
+        fn new(value: SaturatingI32, data: Vec<u8>) -> Self {
+            Reading { value, data }
+        }
+    }
+
+    #[test]
+    fn test_struct_with_saturating_i32_field() {
+        let m = Tuple2Mutator::<Reading, SaturatingMutator<SaturatingI32, I32Mutator>, VecMutator<U8Mutator>>::new(
+            SaturatingI32::default_mutator(),
+            Vec::<u8>::default_mutator(),
+        );
+        let mut values = std::collections::HashSet::new();
+        for seed in 0..10 {
+            let (mut x, mut x_cache) = m.arbitrary(seed, 100.0);
+            let mut x_step = m.mutation_step_from_value(&x);
+            for _ in 0..300 {
+                let old_x = x.clone();
+                let token = if let Some(token) = m.mutate(&mut x, &mut x_cache, &mut x_step, 100.0) {
+                    token
+                } else {
+                    break;
+                };
+                values.insert(x.value.0);
+                m.unmutate(&mut x, &mut x_cache, token);
+                assert_eq!(x, old_x);
+            }
+        }
+        // the bounds of i32 are tried even though the inner mutator rarely produces them
+        assert!(values.contains(&i32::MIN));
+        assert!(values.contains(&i32::MAX));
+        assert!(values.len() > 1000);
+    }
+}
@@ -0,0 +1,295 @@
+//! A mutator for vectors that must be sorted, such as the input of a binary
+//! search or of a merge.
+
+extern crate fuzzcheck;
+use fuzzcheck::Mutator;
+
+extern crate rand;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use crate::vector::{reorder, UnmutateVecToken, VecMutator, VecMutatorCache, VecMutatorStep};
+use crate::{complexity_fingerprint, step_rng_seed};
+
+/// The default probability that a mutation or an arbitrary value breaks the
+/// order of the vector
+const DEFAULT_UNSORTED_PROBABILITY: f64 = 0.01;
+
+/**
+ * A mutator for a `Vec<T>` whose elements are kept in ascending order, where
+ * `M` is the mutator of the elements.
+ *
+ * Code that expects a sorted slice usually rejects or mishandles anything
+ * else, so random vectors would mostly test that error path. This mutator
+ * sorts the vector after each mutation of the inner [VecMutator] instead.
+ * With the probability given to
+ * [with_unsorted_probability](SortedVecMutator::with_unsorted_probability),
+ * a mutation instead swaps two adjacent elements of a sorted vector, or sorts
+ * it again if it was not sorted, to test how the tested code handles its
+ * precondition. A vector that was not sorted before a mutation of its
+ * elements is left in its order. Arbitrary values are unsorted with the same
+ * probability.
+ *
+ * The complexity of a value is that given by the [VecMutator], as the order
+ * of the elements does not change it.
+ */
+pub struct SortedVecMutator<M>
+where
+    M: Mutator,
+    M::Value: Ord,
+{
+    m: VecMutator<M>,
+    unsorted_probability: f64,
+}
+
+impl<M> SortedVecMutator<M>
+where
+    M: Mutator,
+    M::Value: Ord,
+{
+    pub fn new(m: VecMutator<M>) -> Self {
+        Self {
+            m,
+            unsorted_probability: DEFAULT_UNSORTED_PROBABILITY,
+        }
+    }
+
+    /// Sets the probability that the vector is not sorted, 0.01 by default.
+    /// With 0.0, all vectors are sorted.
+    pub fn with_unsorted_probability(mut self, probability: f64) -> Self {
+        assert!((0.0..=1.0).contains(&probability));
+        self.unsorted_probability = probability;
+        self
+    }
+
+    /// Whether the elements of the vector are in ascending order
+    pub fn is_sorted(value: &[M::Value]) -> bool {
+        value.windows(2).all(|w| w[0] <= w[1])
+    }
+
+    /// Sorts the vector and the caches of its elements, and returns the
+    /// order that was applied, or `None` if the vector was already sorted
+    fn sort(value: &mut Vec<M::Value>, cache: &mut VecMutatorCache<M::Cache>) -> Option<Vec<usize>> {
+        if Self::is_sorted(value) {
+            return None;
+        }
+        let mut order: Vec<usize> = (0..value.len()).collect();
+        order.sort_by(|&a, &b| value[a].cmp(&value[b]));
+        reorder(value, &order);
+        cache.reorder(&order);
+        Some(order)
+    }
+
+    /// Restores the order of the vector and of the caches of its elements
+    /// before the given order was applied
+    fn undo_order(value: &mut Vec<M::Value>, cache: &mut VecMutatorCache<M::Cache>, order: &[usize]) {
+        let mut inverse = vec![0; order.len()];
+        for (i, &j) in order.iter().enumerate() {
+            inverse[j] = i;
+        }
+        reorder(value, &inverse);
+        cache.reorder(&inverse);
+    }
+
+    fn mutate_order(
+        &self,
+        value: &mut Vec<M::Value>,
+        cache: &mut VecMutatorCache<M::Cache>,
+        rng: &mut SmallRng,
+    ) -> Option<UnmutateSortedVecToken<M>> {
+        if !Self::is_sorted(value) {
+            return Self::sort(value, cache).map(UnmutateSortedVecToken::Order);
+        }
+        // swapping two equal elements would not break the order
+        let swappable: Vec<usize> = (1..value.len()).filter(|&i| value[i - 1] < value[i]).collect();
+        if swappable.is_empty() {
+            return None;
+        }
+        let i = swappable[rng.gen_range(0, swappable.len())];
+        let mut order: Vec<usize> = (0..value.len()).collect();
+        order.swap(i - 1, i);
+        reorder(value, &order);
+        cache.reorder(&order);
+        Some(UnmutateSortedVecToken::Order(order))
+    }
+}
+
+pub struct SortedVecMutatorStep<S> {
+    inner: VecMutatorStep<S>,
+    /// The number of mutations performed so far, which seeds their random decisions
+    nbr_mutations: u64,
+    /// Seeds the random decisions of the mutations, along with `nbr_mutations`
+    rng_seed: u64,
+}
+
+pub enum UnmutateSortedVecToken<M: Mutator> {
+    /// The vector was mutated by the inner mutator, and then reordered with
+    /// the given order, if any
+    Inner {
+        token: UnmutateVecToken<M>,
+        order: Option<Vec<usize>>,
+    },
+    /// Only the order of the elements was changed
+    Order(Vec<usize>),
+}
+
+impl<M> Mutator for SortedVecMutator<M>
+where
+    M: Mutator,
+    M::Value: Ord,
+{
+    type Value = Vec<M::Value>;
+    type Cache = VecMutatorCache<M::Cache>;
+    type MutationStep = SortedVecMutatorStep<M::MutationStep>;
+    type UnmutateToken = UnmutateSortedVecToken<M>;
+
+    fn cache_from_value(&self, value: &Self::Value) -> Self::Cache {
+        self.m.cache_from_value(value)
+    }
+
+    fn mutation_step_from_value(&self, value: &Self::Value) -> Self::MutationStep {
+        SortedVecMutatorStep {
+            inner: self.m.mutation_step_from_value(value),
+            nbr_mutations: 0,
+            rng_seed: step_rng_seed((value.len(), complexity_fingerprint(&self.m, value))),
+        }
+    }
+
+    fn seeded_mutation_step_from_value(&self, value: &Self::Value, seed: u64) -> Self::MutationStep {
+        SortedVecMutatorStep {
+            inner: self.m.seeded_mutation_step_from_value(value, seed),
+            nbr_mutations: 0,
+            rng_seed: step_rng_seed((seed, value.len(), complexity_fingerprint(&self.m, value))),
+        }
+    }
+
+    fn max_complexity(&self) -> f64 {
+        self.m.max_complexity()
+    }
+
+    fn min_complexity(&self) -> f64 {
+        self.m.min_complexity()
+    }
+
+    fn complexity(&self, value: &Self::Value, cache: &Self::Cache) -> f64 {
+        self.m.complexity(value, cache)
+    }
+
+    fn arbitrary(&self, seed: usize, max_cplx: f64) -> (Self::Value, Self::Cache) {
+        let (mut value, mut cache) = self.m.arbitrary(seed, max_cplx);
+        let mut rng = SmallRng::seed_from_u64(seed as u64);
+        if !rng.gen_bool(self.unsorted_probability) {
+            let _ = Self::sort(&mut value, &mut cache);
+        }
+        (value, cache)
+    }
+
+    fn mutate(
+        &self,
+        value: &mut Self::Value,
+        cache: &mut Self::Cache,
+        step: &mut Self::MutationStep,
+        max_cplx: f64,
+    ) -> Option<Self::UnmutateToken> {
+        step.nbr_mutations += 1;
+        let mut rng = SmallRng::seed_from_u64(step.rng_seed.wrapping_add(step.nbr_mutations));
+        if rng.gen_bool(self.unsorted_probability) {
+            if let Some(token) = self.mutate_order(value, cache, &mut rng) {
+                return Some(token);
+            }
+        }
+        let was_sorted = Self::is_sorted(value);
+        let token = self.m.mutate(value, cache, &mut step.inner, max_cplx)?;
+        // the steps of the elements are not reordered, since the value is
+        // either restored by unmutate or given a new step when it is kept
+        let order = if was_sorted { Self::sort(value, cache) } else { None };
+        Some(UnmutateSortedVecToken::Inner { token, order })
+    }
+
+    fn unmutate(&self, value: &mut Self::Value, cache: &mut Self::Cache, t: Self::UnmutateToken) {
+        match t {
+            UnmutateSortedVecToken::Inner { token, order } => {
+                if let Some(order) = order {
+                    Self::undo_order(value, cache, &order);
+                }
+                self.m.unmutate(value, cache, token);
+            }
+            UnmutateSortedVecToken::Order(order) => {
+                Self::undo_order(value, cache, &order);
+            }
+        }
+    }
+
+    fn set_length_range(&mut self, len_range: std::ops::RangeInclusive<usize>) -> bool {
+        self.m.set_length_range(len_range)
+    }
+
+    /// Removing elements from a vector keeps it sorted
+    fn shrink_below(
+        &self,
+        value: &Self::Value,
+        cache: &Self::Cache,
+        step: &mut usize,
+        max_cplx: f64,
+    ) -> Option<(Self::Value, Self::Cache)> {
+        self.m.shrink_below(value, cache, step, max_cplx)
+    }
+
+    /// Splices the vectors like [VecMutator], and sorts the result if both
+    /// vectors were sorted
+    fn crossover(
+        &self,
+        a: &Self::Value,
+        a_cache: &Self::Cache,
+        b: &Self::Value,
+        b_cache: &Self::Cache,
+        seed: usize,
+        max_cplx: f64,
+    ) -> Option<(Self::Value, Self::Cache)> {
+        let (mut value, mut cache) = self.m.crossover(a, a_cache, b, b_cache, seed, max_cplx)?;
+        if Self::is_sorted(a) && Self::is_sorted(b) {
+            let _ = Self::sort(&mut value, &mut cache);
+        }
+        Some((value, cache))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integer::U8Mutator;
+    use crate::keep_one_mutation_in_four;
+
+    #[test]
+    fn test_vector_is_kept_sorted() {
+        let m = SortedVecMutator::new(VecMutator::new(U8Mutator::default())).with_unsorted_probability(0.0);
+        for seed in 0..20 {
+            let (mut x, mut cache) = m.arbitrary(seed, 200.0);
+            assert!(SortedVecMutator::<U8Mutator>::is_sorted(&x));
+            keep_one_mutation_in_four(&m, &mut x, &mut cache, 200.0, 200, |_, _, x, cache| {
+                assert!(SortedVecMutator::<U8Mutator>::is_sorted(x));
+                // the cache stays consistent with the sorted vector
+                let sorted_cache = m.cache_from_value(x);
+                assert!((m.complexity(x, cache) - m.complexity(x, &sorted_cache)).abs() < 1e-9);
+            });
+        }
+    }
+
+    #[test]
+    fn test_unsorted_vectors() {
+        let m = SortedVecMutator::new(VecMutator::new(U8Mutator::default())).with_unsorted_probability(0.2);
+        let (mut nbr_sorted, mut nbr_unsorted) = (0, 0);
+        for seed in 0..20 {
+            let (mut x, mut cache) = m.arbitrary(seed, 200.0);
+            keep_one_mutation_in_four(&m, &mut x, &mut cache, 200.0, 200, |_, _, x, _| {
+                if SortedVecMutator::<U8Mutator>::is_sorted(x) {
+                    nbr_sorted += 1;
+                } else {
+                    nbr_unsorted += 1;
+                }
+            });
+        }
+        assert!(nbr_sorted > 0);
+        assert!(nbr_unsorted > 0);
+    }
+}
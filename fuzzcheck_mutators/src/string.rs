@@ -0,0 +1,66 @@
+//! A mutator for `String`.
+//!
+//! Strings are mutated as vectors of chars, so that every mutation produces
+//! valid UTF-8 without having to repair it.
+
+use crate::char::CharMutator;
+use crate::try_map::TryMapMutator;
+use crate::vector::VecMutator;
+use crate::DefaultMutator;
+
+pub type StringMutator =
+    TryMapMutator<VecMutator<CharMutator>, fn(Vec<char>) -> Option<String>, fn(&String) -> Vec<char>, String>;
+
+impl DefaultMutator for String {
+    type Mutator = StringMutator;
+    fn default_mutator() -> Self::Mutator {
+        TryMapMutator::new(
+            Vec::<char>::default_mutator(),
+            |chars| Some(chars.into_iter().collect()),
+            |string| string.chars().collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fuzzcheck::Mutator;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_string_mutator() {
+        let m = String::default_mutator();
+        let mut results = HashSet::new();
+        for seed in 0..20 {
+            let (mut x, mut x_cache) = m.arbitrary(seed, 256.0);
+            let mut x_step = m.mutation_step_from_value(&x);
+            for _ in 0..100 {
+                let old_x = x.clone();
+                let old_cplx = m.complexity(&x, &x_cache);
+                let token = m.mutate(&mut x, &mut x_cache, &mut x_step, 256.0).unwrap();
+                results.insert(x.clone());
+                m.unmutate(&mut x, &mut x_cache, token);
+                assert_eq!(x, old_x);
+                assert_eq!(m.complexity(&x, &x_cache), old_cplx);
+            }
+        }
+        assert!(results.len() > 1000);
+        assert!(results.iter().any(|s| s.chars().count() > 1));
+        assert!(results.iter().any(|s| !s.is_ascii()));
+    }
+
+    #[test]
+    fn test_string_mutator_starts_from_any_string() {
+        let m = String::default_mutator();
+        let mut x = "héllo".to_string();
+        let mut x_cache = m.cache_from_value(&x);
+        let mut x_step = m.mutation_step_from_value(&x);
+        for _ in 0..100 {
+            let token = m.mutate(&mut x, &mut x_cache, &mut x_step, 256.0).unwrap();
+            assert_ne!(x, "héllo");
+            m.unmutate(&mut x, &mut x_cache, token);
+            assert_eq!(x, "héllo");
+        }
+    }
+}
@@ -0,0 +1,370 @@
+//! Mutators for durations and points in time.
+//!
+//! Code handling timestamps often fails around a few well-known instants:
+//! the UNIX epoch, the end of 32-bit timestamps in 2038, or the largest
+//! representable time. So these mutators try such values first, before
+//! random ones.
+
+extern crate fuzzcheck;
+use fuzzcheck::Mutator;
+
+use crate::{step_rng_seed, DefaultMutator};
+
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use std::collections::HashSet;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The number of seconds from the UNIX epoch to the year 3000
+const SECS_TO_YEAR_3000: u64 = 32_503_680_000;
+/// The number of seconds from the UNIX epoch to the year 2020, as a recent
+/// time that does not depend on when the mutator is created
+const SECS_TO_YEAR_2020: u64 = 1_577_836_800;
+
+/// Durations that are likely to be edge cases of the code using them
+fn interesting_durations() -> Vec<Duration> {
+    vec![
+        Duration::from_secs(0),
+        Duration::from_nanos(1),
+        Duration::from_secs(1),
+        Duration::from_millis(999),
+        Duration::new(0, 999_999_999),
+        Duration::from_secs(60),
+        Duration::from_secs(3600),
+        Duration::from_secs(86_400),
+        // the end of signed and unsigned 32-bit timestamps
+        Duration::from_secs(std::i32::MAX as u64),
+        Duration::from_secs(std::i32::MAX as u64 + 1),
+        Duration::from_secs(std::u32::MAX as u64),
+        Duration::from_secs(std::u32::MAX as u64 + 1),
+        Duration::from_secs(SECS_TO_YEAR_3000),
+        Duration::from_secs(std::i64::MAX as u64),
+        Duration::new(std::u64::MAX, 999_999_999),
+    ]
+}
+
+/// A mutator for `Duration`.
+///
+/// Its complexity is the number of bits of the seconds (64) and nanoseconds
+/// (32) of the duration, regardless of its value.
+#[derive(Clone)]
+pub struct DurationMutator {
+    interesting: Vec<Duration>,
+}
+
+impl Default for DurationMutator {
+    fn default() -> Self {
+        DurationMutator {
+            interesting: interesting_durations(),
+        }
+    }
+}
+
+impl DefaultMutator for Duration {
+    type Mutator = DurationMutator;
+    fn default_mutator() -> Self::Mutator {
+        DurationMutator::default()
+    }
+}
+
+/// A random duration, which is more likely to be short than long
+fn random_duration(rng: &mut SmallRng) -> Duration {
+    let secs = rng.gen::<u64>() >> rng.gen_range(0, 64);
+    let nanos = rng.gen_range(0, 1_000_000_000);
+    Duration::new(secs, nanos)
+}
+
+impl Mutator for DurationMutator {
+    type Value = Duration;
+    type Cache = ();
+    /// The number of mutations performed so far
+    type MutationStep = u64;
+    /// The value before the mutation
+    type UnmutateToken = Duration;
+
+    fn cache_from_value(&self, _value: &Self::Value) -> Self::Cache {}
+    fn mutation_step_from_value(&self, _value: &Self::Value) -> Self::MutationStep {
+        0
+    }
+
+    fn arbitrary(&self, seed: usize, _max_cplx: f64) -> (Self::Value, Self::Cache) {
+        if let Some(duration) = self.interesting.get(seed) {
+            (*duration, ())
+        } else {
+            (random_duration(&mut SmallRng::seed_from_u64(seed as u64)), ())
+        }
+    }
+
+    fn max_complexity(&self) -> f64 {
+        96.0
+    }
+
+    fn min_complexity(&self) -> f64 {
+        96.0
+    }
+
+    fn complexity(&self, _value: &Self::Value, _cache: &Self::Cache) -> f64 {
+        96.0
+    }
+
+    fn mutate(
+        &self,
+        value: &mut Self::Value,
+        _cache: &mut Self::Cache,
+        step: &mut Self::MutationStep,
+        _max_cplx: f64,
+    ) -> Option<Self::UnmutateToken> {
+        let token = *value;
+        let nbr_interesting = self.interesting.len() as u64;
+        // first the interesting durations, then small nudges, then random durations
+        *value = if *step < nbr_interesting {
+            self.interesting[*step as usize]
+        } else {
+            let nudge_step = *step - nbr_interesting;
+            let nudged = match nudge_step {
+                0 => value.checked_add(Duration::from_nanos(1)),
+                1 => value.checked_sub(Duration::from_nanos(1)),
+                2 => value.checked_add(Duration::from_secs(1)),
+                3 => value.checked_sub(Duration::from_secs(1)),
+                4 => value.checked_mul(2),
+                5 => value.checked_div(2),
+                _ => None,
+            };
+            nudged.unwrap_or_else(|| random_duration(&mut SmallRng::seed_from_u64(step_rng_seed((token, *step)))))
+        };
+        *step = step.wrapping_add(1);
+
+        Some(token)
+    }
+
+    fn unmutate(&self, value: &mut Self::Value, _cache: &mut Self::Cache, t: Self::UnmutateToken) {
+        *value = t;
+    }
+}
+
+/// The distance between `time` and the UNIX epoch, and whether `time` is
+/// before the epoch
+fn offset_from_epoch(time: SystemTime) -> (bool, Duration) {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => (false, duration),
+        Err(e) => (true, e.duration()),
+    }
+}
+
+/// The instant at the given distance from the UNIX epoch, if it can be
+/// represented
+fn time_from_offset(before_epoch: bool, duration: Duration) -> Option<SystemTime> {
+    if before_epoch {
+        UNIX_EPOCH.checked_sub(duration)
+    } else {
+        UNIX_EPOCH.checked_add(duration)
+    }
+}
+
+/// The furthest representable instant from the UNIX epoch, in the given direction
+fn furthest_time(before_epoch: bool) -> SystemTime {
+    // binary search for the largest number of seconds that can be represented
+    let (mut low, mut high) = (0u64, std::u64::MAX);
+    while low < high {
+        let mid = low + (high - low) / 2 + 1;
+        if time_from_offset(before_epoch, Duration::from_secs(mid)).is_some() {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    let secs = Duration::from_secs(low);
+    time_from_offset(before_epoch, secs + Duration::new(0, 999_999_999))
+        .or_else(|| time_from_offset(before_epoch, secs))
+        .unwrap()
+}
+
+/**
+ * A mutator for `SystemTime`, built on a [DurationMutator] for the distance
+ * between the time and the UNIX epoch.
+ *
+ * It tries interesting instants first: the epoch, the start of 2020 as a
+ * recent time, the end of 32-bit timestamps in 2038, the year 3000, and the earliest and latest
+ * representable times. Then it moves the time to the other side of the epoch
+ * and mutates its distance to the epoch. Times both before and after the
+ * epoch are generated, as long as the platform can represent them.
+ *
+ * Its complexity is that of the underlying duration.
+ */
+pub struct SystemTimeMutator {
+    duration: DurationMutator,
+    interesting: Vec<SystemTime>,
+}
+
+impl Default for SystemTimeMutator {
+    fn default() -> Self {
+        let mut interesting = vec![UNIX_EPOCH];
+        for &secs in [
+            SECS_TO_YEAR_2020,
+            std::i32::MAX as u64,
+            std::i32::MAX as u64 + 1,
+            std::u32::MAX as u64 + 1,
+            SECS_TO_YEAR_3000,
+        ]
+        .iter()
+        {
+            interesting.extend(time_from_offset(false, Duration::from_secs(secs)));
+        }
+        for &secs in [1, std::i32::MAX as u64 + 1].iter() {
+            interesting.extend(time_from_offset(true, Duration::from_secs(secs)));
+        }
+        interesting.push(furthest_time(false));
+        interesting.push(furthest_time(true));
+        // dedup only removes consecutive duplicates, and the order of the instants matters
+        let mut seen = HashSet::new();
+        interesting.retain(|time| seen.insert(*time));
+
+        SystemTimeMutator {
+            duration: DurationMutator::default(),
+            interesting,
+        }
+    }
+}
+
+impl DefaultMutator for SystemTime {
+    type Mutator = SystemTimeMutator;
+    fn default_mutator() -> Self::Mutator {
+        SystemTimeMutator::default()
+    }
+}
+
+pub struct SystemTimeMutationStep {
+    interesting_idx: usize,
+    did_cross_epoch: bool,
+    duration: u64,
+}
+
+impl Mutator for SystemTimeMutator {
+    type Value = SystemTime;
+    type Cache = ();
+    type MutationStep = SystemTimeMutationStep;
+    /// The value before the mutation
+    type UnmutateToken = SystemTime;
+
+    fn cache_from_value(&self, _value: &Self::Value) -> Self::Cache {}
+    fn mutation_step_from_value(&self, value: &Self::Value) -> Self::MutationStep {
+        let (_, duration) = offset_from_epoch(*value);
+        SystemTimeMutationStep {
+            interesting_idx: 0,
+            did_cross_epoch: false,
+            duration: self.duration.mutation_step_from_value(&duration),
+        }
+    }
+
+    fn arbitrary(&self, seed: usize, max_cplx: f64) -> (Self::Value, Self::Cache) {
+        if let Some(time) = self.interesting.get(seed) {
+            return (*time, ());
+        }
+        let before_epoch = seed % 2 == 0;
+        let (duration, _) = self.duration.arbitrary(seed, max_cplx);
+        // the duration may be too large to be represented on this side of the epoch
+        let time = time_from_offset(before_epoch, duration)
+            .or_else(|| time_from_offset(!before_epoch, duration))
+            .unwrap_or_else(|| furthest_time(before_epoch));
+        (time, ())
+    }
+
+    fn max_complexity(&self) -> f64 {
+        self.duration.max_complexity()
+    }
+
+    fn min_complexity(&self) -> f64 {
+        self.duration.min_complexity()
+    }
+
+    fn complexity(&self, value: &Self::Value, _cache: &Self::Cache) -> f64 {
+        let (_, duration) = offset_from_epoch(*value);
+        self.duration.complexity(&duration, &())
+    }
+
+    fn mutate(
+        &self,
+        value: &mut Self::Value,
+        _cache: &mut Self::Cache,
+        step: &mut Self::MutationStep,
+        max_cplx: f64,
+    ) -> Option<Self::UnmutateToken> {
+        let token = *value;
+        while let Some(time) = self.interesting.get(step.interesting_idx) {
+            step.interesting_idx += 1;
+            if *time != *value {
+                *value = *time;
+                return Some(token);
+            }
+        }
+        let (before_epoch, mut duration) = offset_from_epoch(*value);
+        if !step.did_cross_epoch {
+            step.did_cross_epoch = true;
+            if let Some(time) = time_from_offset(!before_epoch, duration) {
+                if time != *value {
+                    *value = time;
+                    return Some(token);
+                }
+            }
+        }
+        // a few mutations of the duration may not be representable, but most are
+        for _ in 0..10 {
+            self.duration
+                .mutate(&mut duration, &mut (), &mut step.duration, max_cplx)?;
+            if let Some(time) = time_from_offset(before_epoch, duration) {
+                *value = time;
+                return Some(token);
+            }
+        }
+        None
+    }
+
+    fn unmutate(&self, value: &mut Self::Value, _cache: &mut Self::Cache, t: Self::UnmutateToken) {
+        *value = t;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_system_time_arbitrary_includes_boundaries() {
+        let m = SystemTimeMutator::default();
+        let values: Vec<SystemTime> = (0..100).map(|seed| m.arbitrary(seed, 96.0).0).collect();
+
+        assert!(values.contains(&UNIX_EPOCH));
+        let y2038 = UNIX_EPOCH + Duration::from_secs(std::i32::MAX as u64 + 1);
+        assert!(values.contains(&y2038));
+        assert!(values.iter().any(|t| *t < UNIX_EPOCH));
+        assert!(values.iter().any(|t| *t > y2038));
+        assert!(values.contains(&furthest_time(false)));
+        assert!(values.contains(&furthest_time(true)));
+    }
+
+    #[test]
+    fn test_system_time_interesting_instants_are_distinct() {
+        let m = SystemTimeMutator::default();
+        let distinct: HashSet<SystemTime> = m.interesting.iter().copied().collect();
+        assert_eq!(distinct.len(), m.interesting.len());
+    }
+
+    #[test]
+    fn test_system_time_unmutate() {
+        let m = SystemTimeMutator::default();
+        let mut x = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let original = x;
+        let mut x_step = m.mutation_step_from_value(&x);
+        let mut mutated = vec![];
+        for _ in 0..100 {
+            let token = m.mutate(&mut x, &mut (), &mut x_step, 96.0).unwrap();
+            assert_eq!(m.complexity(&x, &()), 96.0);
+            mutated.push(x);
+            m.unmutate(&mut x, &mut (), token);
+            assert_eq!(x, original);
+        }
+        // the mutations cross the epoch
+        assert!(mutated.iter().any(|t| *t < UNIX_EPOCH));
+    }
+}
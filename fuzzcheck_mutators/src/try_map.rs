@@ -0,0 +1,198 @@
+//! A mutator for types that can only be built through a fallible constructor.
+
+extern crate fuzzcheck;
+use fuzzcheck::Mutator;
+
+use std::marker::PhantomData;
+
+/// The number of inner values that are tried before giving up on finding one
+/// that the map function accepts
+pub const MAX_TRY_MAP_ATTEMPTS: usize = 100;
+
+/**
+ * A mutator for values built from the values of another mutator by a
+ * fallible function, such as `NonZeroU32::new` or `Url::parse`.
+ *
+ * The inner mutator produces a value, which is given to the `map` function.
+ * If `map` returns `None`, another inner value is tried, up to
+ * [MAX_TRY_MAP_ATTEMPTS] times. The inner value that was accepted is kept in
+ * the cache, so that the next mutations start from it. The `unmap` function
+ * gives back the inner value of a value that was not produced by this
+ * mutator, for example one read from the corpus.
+ *
+ * When none of the attempts succeeds:
+ * - `mutate` returns `None`, as if the mutation step was exhausted;
+ * - `arbitrary` panics, because it must return a value. The map function
+ *   should therefore accept a reasonable fraction of the inner values.
+ *
+ * The complexity of a value is the complexity of its inner value.
+ */
+pub struct TryMapMutator<M, F, G, T>
+where
+    M: Mutator,
+    F: Fn(M::Value) -> Option<T>,
+    G: Fn(&T) -> M::Value,
+{
+    m: M,
+    map: F,
+    unmap: G,
+    phantom: PhantomData<T>,
+}
+
+impl<M, F, G, T> TryMapMutator<M, F, G, T>
+where
+    M: Mutator,
+    F: Fn(M::Value) -> Option<T>,
+    G: Fn(&T) -> M::Value,
+{
+    pub fn new(m: M, map: F, unmap: G) -> Self {
+        Self {
+            m,
+            map,
+            unmap,
+            phantom: PhantomData,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct TryMapMutatorCache<V, C> {
+    inner_value: V,
+    inner_cache: C,
+}
+
+pub struct TryMapMutatorUnmutateToken<T, Token> {
+    value: T,
+    inner: Token,
+}
+
+impl<M, F, G, T> Mutator for TryMapMutator<M, F, G, T>
+where
+    M: Mutator,
+    F: Fn(M::Value) -> Option<T>,
+    G: Fn(&T) -> M::Value,
+    T: Clone,
+{
+    type Value = T;
+    type Cache = TryMapMutatorCache<M::Value, M::Cache>;
+    type MutationStep = M::MutationStep;
+    type UnmutateToken = TryMapMutatorUnmutateToken<T, M::UnmutateToken>;
+
+    fn cache_from_value(&self, value: &Self::Value) -> Self::Cache {
+        let inner_value = (self.unmap)(value);
+        let inner_cache = self.m.cache_from_value(&inner_value);
+        TryMapMutatorCache {
+            inner_value,
+            inner_cache,
+        }
+    }
+
+    fn mutation_step_from_value(&self, value: &Self::Value) -> Self::MutationStep {
+        self.m.mutation_step_from_value(&(self.unmap)(value))
+    }
+
+    fn max_complexity(&self) -> f64 {
+        self.m.max_complexity()
+    }
+
+    fn min_complexity(&self) -> f64 {
+        self.m.min_complexity()
+    }
+
+    fn complexity(&self, _value: &Self::Value, cache: &Self::Cache) -> f64 {
+        self.m.complexity(&cache.inner_value, &cache.inner_cache)
+    }
+
+    fn arbitrary(&self, seed: usize, max_cplx: f64) -> (Self::Value, Self::Cache) {
+        // spread the attempts of consecutive seeds apart so that they do not
+        // all fall back on the same inner values
+        let first_seed = seed.wrapping_mul(MAX_TRY_MAP_ATTEMPTS);
+        for attempt in 0..MAX_TRY_MAP_ATTEMPTS {
+            let (inner_value, inner_cache) = self.m.arbitrary(first_seed.wrapping_add(attempt), max_cplx);
+            if let Some(value) = (self.map)(inner_value.clone()) {
+                let cache = TryMapMutatorCache {
+                    inner_value,
+                    inner_cache,
+                };
+                return (value, cache);
+            }
+        }
+        panic!(
+            "TryMapMutator: the map function rejected {} arbitrary values in a row",
+            MAX_TRY_MAP_ATTEMPTS
+        )
+    }
+
+    fn mutate(
+        &self,
+        value: &mut Self::Value,
+        cache: &mut Self::Cache,
+        step: &mut Self::MutationStep,
+        max_cplx: f64,
+    ) -> Option<Self::UnmutateToken> {
+        for _ in 0..MAX_TRY_MAP_ATTEMPTS {
+            let inner_token = self
+                .m
+                .mutate(&mut cache.inner_value, &mut cache.inner_cache, step, max_cplx)?;
+            if let Some(new_value) = (self.map)(cache.inner_value.clone()) {
+                let old_value = std::mem::replace(value, new_value);
+                return Some(TryMapMutatorUnmutateToken {
+                    value: old_value,
+                    inner: inner_token,
+                });
+            }
+            // every attempt is a single mutation away from the current value
+            self.m
+                .unmutate(&mut cache.inner_value, &mut cache.inner_cache, inner_token);
+        }
+        None
+    }
+
+    fn unmutate(&self, value: &mut Self::Value, cache: &mut Self::Cache, t: Self::UnmutateToken) {
+        *value = t.value;
+        self.m.unmutate(&mut cache.inner_value, &mut cache.inner_cache, t.inner);
+    }
+
+    fn complexity_breakdown(&self, _value: &Self::Value, cache: &Self::Cache) -> Vec<(&'static str, f64)> {
+        self.m.complexity_breakdown(&cache.inner_value, &cache.inner_cache)
+    }
+
+    fn record_mutation_outcome(&self, value: &Self::Value, token: &Self::UnmutateToken, nbr_new_features: usize) {
+        self.m
+            .record_mutation_outcome(&(self.unmap)(value), &token.inner, nbr_new_features)
+    }
+
+    fn describe_value(&self, value: &Self::Value) -> Option<String> {
+        self.m.describe_value(&(self.unmap)(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integer::U8Mutator;
+    use std::num::NonZeroU8;
+
+    #[test]
+    fn test_try_map_mutator() {
+        let m = TryMapMutator::new(U8Mutator::default(), NonZeroU8::new, |x: &NonZeroU8| x.get());
+        for seed in 0..1000 {
+            let (x, cache) = m.arbitrary(seed, 8.0);
+            assert_eq!(m.complexity(&x, &cache), 8.0);
+        }
+
+        let mut x = NonZeroU8::new(1).unwrap();
+        let mut cache = m.cache_from_value(&x);
+        let mut step = m.mutation_step_from_value(&x);
+        let mut nbr_mutations = 0;
+        // the mutations that would produce 0 are skipped
+        while let Some(token) = m.mutate(&mut x, &mut cache, &mut step, 8.0) {
+            assert_eq!(cache.inner_value, x.get());
+            m.unmutate(&mut x, &mut cache, token);
+            assert_eq!(x.get(), 1);
+            assert_eq!(cache.inner_value, 1);
+            nbr_mutations += 1;
+        }
+        assert!(nbr_mutations > 200);
+    }
+}
@@ -3,6 +3,8 @@ use std::marker::PhantomData;
 extern crate fuzzcheck;
 use fuzzcheck::Mutator;
 
+use crate::{complexity_fingerprint, describe_parts, step_rng_seed, DefaultMutator};
+
 extern crate rand;
 use rand::rngs::SmallRng;
 use rand::{Rng, SeedableRng};
@@ -19,6 +21,12 @@ pub trait TupleMap {
     fn get_b_mut(v: &mut Self::V) -> &mut Self::B;
 
     fn new(a: Self::A, b: Self::B) -> Self::V;
+
+    /// The names of the two fields, used by
+    /// [complexity_breakdown](Mutator::complexity_breakdown)
+    fn field_names() -> [&'static str; 2] {
+        ["0", "1"]
+    }
 }
 
 impl<A, B> TupleMap for (A, B)
@@ -76,11 +84,20 @@ where
     }
 }
 
+impl<A: DefaultMutator, B: DefaultMutator> DefaultMutator for (A, B) {
+    type Mutator = Tuple2Mutator<(A, B), A::Mutator, B::Mutator>;
+    fn default_mutator() -> Self::Mutator {
+        Tuple2Mutator::new(A::default_mutator(), B::default_mutator())
+    }
+}
+
 #[derive(Clone)]
 pub struct Tuple2MutatorStep<A, B> {
     a_step: A,
     b_step: B,
     pick_step: usize,
+    /// Seeds the random decisions of the mutations, along with `pick_step`
+    rng_seed: u64,
 }
 
 pub struct UnmutateTuple2Token<A, B> {
@@ -88,6 +105,18 @@ pub struct UnmutateTuple2Token<A, B> {
     b: Option<B>,
 }
 
+impl<A: Mutator, B: Mutator, Map: TupleMap<A = A::Value, B = B::Value>> Tuple2Mutator<Map, A, B> {
+    /// The seed of the random decisions of the mutations of the value, for a
+    /// fuzzer whose mutations are derived from `seed`, if any
+    fn step_rng_seed(&self, value: &Map::V, seed: Option<u64>) -> u64 {
+        step_rng_seed((
+            seed,
+            complexity_fingerprint(&self.a, Map::get_a(value)),
+            complexity_fingerprint(&self.b, Map::get_b(value)),
+        ))
+    }
+}
+
 impl<A: Mutator, B: Mutator, Map: TupleMap<A = A::Value, B = B::Value>> Mutator for Tuple2Mutator<Map, A, B> {
     type Value = Map::V;
     type Cache = (A::Cache, B::Cache);
@@ -106,6 +135,14 @@ impl<A: Mutator, B: Mutator, Map: TupleMap<A = A::Value, B = B::Value>> Mutator
         self.a.complexity(Map::get_a(&value), &cache.0) + self.b.complexity(Map::get_b(&value), &cache.1)
     }
 
+    fn complexity_breakdown(&self, value: &Self::Value, cache: &Self::Cache) -> Vec<(&'static str, f64)> {
+        let [name_a, name_b] = Map::field_names();
+        vec![
+            (name_a, self.a.complexity(Map::get_a(value), &cache.0)),
+            (name_b, self.b.complexity(Map::get_b(value), &cache.1)),
+        ]
+    }
+
     fn cache_from_value(&self, value: &Self::Value) -> Self::Cache {
         let a_cache = self.a.cache_from_value(Map::get_a(&value));
         let b_cache = self.b.cache_from_value(Map::get_b(&value));
@@ -120,11 +157,21 @@ impl<A: Mutator, B: Mutator, Map: TupleMap<A = A::Value, B = B::Value>> Mutator
             a_step,
             b_step,
             pick_step: 0,
+            rng_seed: self.step_rng_seed(value, None),
+        }
+    }
+
+    fn seeded_mutation_step_from_value(&self, value: &Self::Value, seed: u64) -> Self::MutationStep {
+        Tuple2MutatorStep {
+            a_step: self.a.seeded_mutation_step_from_value(Map::get_a(value), seed),
+            b_step: self.b.seeded_mutation_step_from_value(Map::get_b(value), seed),
+            pick_step: 0,
+            rng_seed: self.step_rng_seed(value, Some(seed)),
         }
     }
 
     fn arbitrary(&self, seed: usize, max_cplx: f64) -> (Self::Value, Self::Cache) {
-        let mut r = SmallRng::from_entropy();
+        let mut r = SmallRng::seed_from_u64(seed as u64);
         let cplx = if seed < 10 {
             // first 10 vary in cplx from max_cplx to max_cplx / 10
             max_cplx / (10.0 - seed as f64)
@@ -145,14 +192,14 @@ impl<A: Mutator, B: Mutator, Map: TupleMap<A = A::Value, B = B::Value>> Mutator
         cache: &mut Self::Cache,
         step: &mut Self::MutationStep,
         max_cplx: f64,
-    ) -> Self::UnmutateToken {
+    ) -> Option<Self::UnmutateToken> {
         let cplx_a = self.a.complexity(Map::get_a(&value), &cache.0);
         let cplx_b = self.b.complexity(Map::get_b(&value), &cache.1);
 
         step.pick_step += 1;
         if step.pick_step % 10 == 0 {
             // mutate both once every ten times
-            let mut r = SmallRng::from_entropy();
+            let mut r = SmallRng::seed_from_u64(step.rng_seed.wrapping_add(step.pick_step as u64));
 
             let cplx = self.complexity(value, cache);
             let remaining_cplx = max_cplx - cplx;
@@ -170,30 +217,50 @@ impl<A: Mutator, B: Mutator, Map: TupleMap<A = A::Value, B = B::Value>> Mutator
             let token_b = self
                 .b
                 .mutate(Map::get_b_mut(value), &mut cache.1, &mut step.b_step, max_b_cplx);
-            UnmutateTuple2Token {
-                a: Some(token_a),
-                b: Some(token_b),
+            if token_a.is_none() && token_b.is_none() {
+                return None;
             }
+            Some(UnmutateTuple2Token { a: token_a, b: token_b })
         } else if step.pick_step % 2 == 0 {
             // TODO: frequency depending on complexity or customizable
             let max_a_cplx = max_cplx - cplx_b;
-            let token = self
+            if let Some(token) = self
                 .a
-                .mutate(Map::get_a_mut(value), &mut cache.0, &mut step.a_step, max_a_cplx);
-            UnmutateTuple2Token {
-                a: Some(token),
-                b: None,
+                .mutate(Map::get_a_mut(value), &mut cache.0, &mut step.a_step, max_a_cplx)
+            {
+                return Some(UnmutateTuple2Token {
+                    a: Some(token),
+                    b: None,
+                });
             }
+            // a cannot be mutated anymore, try b instead
+            let max_b_cplx = max_cplx - cplx_a;
+            self.b
+                .mutate(Map::get_b_mut(value), &mut cache.1, &mut step.b_step, max_b_cplx)
+                .map(|token| UnmutateTuple2Token {
+                    a: None,
+                    b: Some(token),
+                })
         } else {
             // mutate b ~half the time
             let max_b_cplx = max_cplx - cplx_a;
-            let token = self
+            if let Some(token) = self
                 .b
-                .mutate(Map::get_b_mut(value), &mut cache.1, &mut step.b_step, max_b_cplx);
-            UnmutateTuple2Token {
-                a: None,
-                b: Some(token),
+                .mutate(Map::get_b_mut(value), &mut cache.1, &mut step.b_step, max_b_cplx)
+            {
+                return Some(UnmutateTuple2Token {
+                    a: None,
+                    b: Some(token),
+                });
             }
+            // b cannot be mutated anymore, try a instead
+            let max_a_cplx = max_cplx - cplx_b;
+            self.a
+                .mutate(Map::get_a_mut(value), &mut cache.0, &mut step.a_step, max_a_cplx)
+                .map(|token| UnmutateTuple2Token {
+                    a: Some(token),
+                    b: None,
+                })
         }
     }
 
@@ -205,4 +272,366 @@ impl<A: Mutator, B: Mutator, Map: TupleMap<A = A::Value, B = B::Value>> Mutator
             self.b.unmutate(Map::get_b_mut(value), &mut cache.1, ub)
         }
     }
+
+    /// Takes the first field of one value and the second field of the other,
+    /// each of which is sometimes combined with the same field of the other
+    /// value by its own crossover
+    fn crossover(
+        &self,
+        x: &Self::Value,
+        x_cache: &Self::Cache,
+        y: &Self::Value,
+        y_cache: &Self::Cache,
+        seed: usize,
+        max_cplx: f64,
+    ) -> Option<(Self::Value, Self::Cache)> {
+        let mut r = SmallRng::seed_from_u64(seed as u64);
+        let ((x, x_cache), (y, y_cache)) = if r.gen() {
+            ((x, x_cache), (y, y_cache))
+        } else {
+            ((y, y_cache), (x, x_cache))
+        };
+        let (a_value, a_cache) = crossover_or_clone(
+            &self.a,
+            (Map::get_a(x), &x_cache.0),
+            (Map::get_a(y), &y_cache.0),
+            &mut r,
+            max_cplx - self.b.min_complexity(),
+        );
+        let a_cplx = self.a.complexity(&a_value, &a_cache);
+        let (b_value, b_cache) = crossover_or_clone(
+            &self.b,
+            (Map::get_b(y), &y_cache.1),
+            (Map::get_b(x), &x_cache.1),
+            &mut r,
+            max_cplx - a_cplx,
+        );
+        let value = Map::new(a_value, b_value);
+        let cache = (a_cache, b_cache);
+        if self.complexity(&value, &cache) >= max_cplx {
+            return None;
+        }
+        Some((value, cache))
+    }
+
+    fn record_mutation_outcome(&self, value: &Self::Value, token: &Self::UnmutateToken, nbr_new_features: usize) {
+        if let Some(ua) = &token.a {
+            self.a.record_mutation_outcome(Map::get_a(value), ua, nbr_new_features)
+        }
+        if let Some(ub) = &token.b {
+            self.b.record_mutation_outcome(Map::get_b(value), ub, nbr_new_features)
+        }
+    }
+
+    fn describe_value(&self, value: &Self::Value) -> Option<String> {
+        let [name_a, name_b] = Map::field_names();
+        describe_parts(
+            "{",
+            vec![
+                (Some(name_a), self.a.describe_value(Map::get_a(value))),
+                (Some(name_b), self.b.describe_value(Map::get_b(value))),
+            ],
+            "}",
+        )
+    }
+}
+
+/// The crossover of `x` and `y` half of the time, or a clone of `x` otherwise
+/// or if they cannot be combined
+fn crossover_or_clone<M: Mutator>(
+    m: &M,
+    x: (&M::Value, &M::Cache),
+    y: (&M::Value, &M::Cache),
+    r: &mut SmallRng,
+    max_cplx: f64,
+) -> (M::Value, M::Cache) {
+    if r.gen() {
+        if let Some(crossover) = m.crossover(x.0, x.1, y.0, y.1, r.gen(), max_cplx) {
+            return crossover;
+        }
+    }
+    (x.0.clone(), x.1.clone())
+}
+
+/// The structure of a type that wraps a single value, such as a one-field
+/// tuple struct `struct UserId(u64)`
+pub trait NewtypeMap {
+    type A;
+    type V: Clone;
+
+    fn get_a(v: &Self::V) -> &Self::A;
+    fn get_a_mut(v: &mut Self::V) -> &mut Self::A;
+
+    fn new(a: Self::A) -> Self::V;
+}
+
+impl<A> NewtypeMap for (A,)
+where
+    A: Clone,
+{
+    type A = A;
+    type V = Self;
+
+    fn get_a(v: &(A,)) -> &A {
+        &v.0
+    }
+    fn get_a_mut(v: &mut (A,)) -> &mut A {
+        &mut v.0
+    }
+    fn new(a: A) -> (A,) {
+        (a,)
+    }
+}
+
+/// A mutator for a newtype, which behaves exactly like the mutator of the
+/// wrapped value. In particular, both have the same complexity.
+pub struct NewtypeMutator<Map, A>
+where
+    A: Mutator,
+    Map: NewtypeMap,
+{
+    a: A,
+    phantom: PhantomData<Map>,
+}
+impl<Map: NewtypeMap, A: Mutator> NewtypeMutator<Map, A> {
+    pub fn new(a: A) -> Self {
+        Self {
+            a,
+            phantom: PhantomData,
+        }
+    }
+}
+impl<Map: NewtypeMap, A: Mutator> Default for NewtypeMutator<Map, A>
+where
+    A: Default,
+{
+    fn default() -> Self {
+        Self::new(A::default())
+    }
+}
+
+impl<A: DefaultMutator> DefaultMutator for (A,) {
+    type Mutator = NewtypeMutator<(A,), A::Mutator>;
+    fn default_mutator() -> Self::Mutator {
+        NewtypeMutator::new(A::default_mutator())
+    }
+}
+
+impl<A: Mutator, Map: NewtypeMap<A = A::Value>> Mutator for NewtypeMutator<Map, A> {
+    type Value = Map::V;
+    type Cache = A::Cache;
+    type MutationStep = A::MutationStep;
+    type UnmutateToken = A::UnmutateToken;
+
+    fn max_complexity(&self) -> f64 {
+        self.a.max_complexity()
+    }
+
+    fn min_complexity(&self) -> f64 {
+        self.a.min_complexity()
+    }
+
+    fn complexity(&self, value: &Self::Value, cache: &Self::Cache) -> f64 {
+        self.a.complexity(Map::get_a(value), cache)
+    }
+
+    fn complexity_breakdown(&self, value: &Self::Value, cache: &Self::Cache) -> Vec<(&'static str, f64)> {
+        self.a.complexity_breakdown(Map::get_a(value), cache)
+    }
+
+    fn cache_from_value(&self, value: &Self::Value) -> Self::Cache {
+        self.a.cache_from_value(Map::get_a(value))
+    }
+    fn mutation_step_from_value(&self, value: &Self::Value) -> Self::MutationStep {
+        self.a.mutation_step_from_value(Map::get_a(value))
+    }
+
+    fn seeded_mutation_step_from_value(&self, value: &Self::Value, seed: u64) -> Self::MutationStep {
+        self.a.seeded_mutation_step_from_value(Map::get_a(value), seed)
+    }
+
+    fn arbitrary(&self, seed: usize, max_cplx: f64) -> (Self::Value, Self::Cache) {
+        let (a, cache) = self.a.arbitrary(seed, max_cplx);
+        (Map::new(a), cache)
+    }
+
+    fn mutate(
+        &self,
+        value: &mut Self::Value,
+        cache: &mut Self::Cache,
+        step: &mut Self::MutationStep,
+        max_cplx: f64,
+    ) -> Option<Self::UnmutateToken> {
+        self.a.mutate(Map::get_a_mut(value), cache, step, max_cplx)
+    }
+
+    fn unmutate(&self, value: &mut Self::Value, cache: &mut Self::Cache, t: Self::UnmutateToken) {
+        self.a.unmutate(Map::get_a_mut(value), cache, t)
+    }
+
+    fn crossover(
+        &self,
+        a: &Self::Value,
+        a_cache: &Self::Cache,
+        b: &Self::Value,
+        b_cache: &Self::Cache,
+        seed: usize,
+        max_cplx: f64,
+    ) -> Option<(Self::Value, Self::Cache)> {
+        let (a, cache) = self
+            .a
+            .crossover(Map::get_a(a), a_cache, Map::get_a(b), b_cache, seed, max_cplx)?;
+        Some((Map::new(a), cache))
+    }
+
+    fn record_mutation_outcome(&self, value: &Self::Value, token: &Self::UnmutateToken, nbr_new_features: usize) {
+        self.a
+            .record_mutation_outcome(Map::get_a(value), token, nbr_new_features)
+    }
+
+    fn describe_value(&self, value: &Self::Value) -> Option<String> {
+        self.a.describe_value(Map::get_a(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integer::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct UserId(u8);
+
+    impl NewtypeMap for UserId {
+        type A = u8;
+        type V = Self;
+
+        fn get_a(v: &Self) -> &u8 {
+            &v.0
+        }
+        fn get_a_mut(v: &mut Self) -> &mut u8 {
+            &mut v.0
+        }
+        fn new(a: u8) -> Self {
+            UserId(a)
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Point {
+        x: u8,
+        y: Vec<u8>,
+    }
+
+    impl TupleMap for Point {
+        type A = u8;
+        type B = Vec<u8>;
+        type V = Self;
+
+        fn get_a(v: &Self) -> &u8 {
+            &v.x
+        }
+        fn get_b(v: &Self) -> &Vec<u8> {
+            &v.y
+        }
+        fn get_a_mut(v: &mut Self) -> &mut u8 {
+            &mut v.x
+        }
+        fn get_b_mut(v: &mut Self) -> &mut Vec<u8> {
+            &mut v.y
+        }
+        fn new(x: u8, y: Vec<u8>) -> Self {
+            Point { x, y }
+        }
+        fn field_names() -> [&'static str; 2] {
+            ["x", "y"]
+        }
+    }
+
+    #[test]
+    fn test_complexity_breakdown() {
+        let m = Tuple2Mutator::<Point, U8Mutator, crate::vector::VecMutator<U8Mutator>>::default();
+        let p = Point { x: 1, y: vec![1, 2, 3] };
+        let cache = m.cache_from_value(&p);
+        let breakdown = m.complexity_breakdown(&p, &cache);
+        assert_eq!(breakdown.iter().map(|x| x.0).collect::<Vec<_>>(), vec!["x", "y"]);
+        assert_eq!(breakdown[0].1, 8.0);
+        assert!((breakdown.iter().map(|x| x.1).sum::<f64>() - m.complexity(&p, &cache)).abs() < 1e-9);
+
+        let m = Tuple2Mutator::<(u8, u8), U8Mutator, U8Mutator>::default();
+        let breakdown = m.complexity_breakdown(&(1, 2), &m.cache_from_value(&(1, 2)));
+        assert_eq!(breakdown, vec![("0", 8.0), ("1", 8.0)]);
+        assert!(U8Mutator::default().complexity_breakdown(&1, &()).is_empty());
+    }
+
+    #[test]
+    fn test_crossover() {
+        let m = Tuple2Mutator::<Point, U8Mutator, crate::vector::VecMutator<U8Mutator>>::default();
+        let a = Point { x: 1, y: vec![1; 10] };
+        let b = Point { x: 2, y: vec![2; 10] };
+        let (a_cache, b_cache) = (m.cache_from_value(&a), m.cache_from_value(&b));
+        let mut nbr_mixed = 0;
+        for seed in 0..100 {
+            let (x, x_cache) = m.crossover(&a, &a_cache, &b, &b_cache, seed, 500.0).unwrap();
+            assert_eq!(m.complexity(&x, &x_cache), m.complexity(&x, &m.cache_from_value(&x)));
+            assert!(x.x == 1 || x.x == 2);
+            assert!(x.y.iter().all(|&e| e == 1 || e == 2));
+            if x.y.contains(&1) && x.y.contains(&2) {
+                nbr_mixed += 1;
+            }
+        }
+        assert!(nbr_mixed > 0);
+        assert!(m.crossover(&a, &a_cache, &b, &b_cache, 0, 10.0).is_none());
+
+        let m = NewtypeMutator::<(Vec<u8>,), crate::vector::VecMutator<U8Mutator>>::default();
+        let (a, b) = ((vec![1; 10],), (vec![2; 10],));
+        let (a_cache, b_cache) = (m.cache_from_value(&a), m.cache_from_value(&b));
+        let (x, _) = (0..100)
+            .filter_map(|seed| m.crossover(&a, &a_cache, &b, &b_cache, seed, 500.0))
+            .find(|(x, _)| x.0.contains(&1) && x.0.contains(&2))
+            .unwrap();
+        assert!(x.0.iter().all(|&e| e == 1 || e == 2));
+    }
+
+    #[test]
+    fn test_nested_descriptions() {
+        use crate::framed::FramedMessage;
+        let m = <(u8, Vec<Option<FramedMessage>>)>::default_mutator();
+        let message = FramedMessage::new(1, vec![2]);
+        let description = m.describe_value(&(3, vec![Some(message.clone()), None])).unwrap();
+        let message_description = format!("{:#?}", message).replace('\n', "\n        ");
+        assert_eq!(
+            description,
+            format!(
+                "{{\n    0: _,\n    1: [\n        Some({}),\n        _,\n    ],\n}}",
+                message_description
+            )
+        );
+        // a value without any described part has no description
+        assert!(m.describe_value(&(3, vec![None])).is_none());
+    }
+
+    #[test]
+    fn test_newtype_mutator_has_the_complexity_of_the_inner_mutator() {
+        let m = NewtypeMutator::<UserId, U8Mutator>::default();
+        let inner_m = U8Mutator::default();
+        assert_eq!(m.min_complexity(), inner_m.min_complexity());
+        assert_eq!(m.max_complexity(), inner_m.max_complexity());
+
+        for seed in 0..100 {
+            let (mut x, mut x_cache) = m.arbitrary(seed, 8.0);
+            assert_eq!(
+                m.complexity(&x, &x_cache),
+                inner_m.complexity(&x.0, &inner_m.cache_from_value(&x.0))
+            );
+            let mut x_step = m.mutation_step_from_value(&x);
+            while let Some(token) = m.mutate(&mut x, &mut x_cache, &mut x_step, 8.0) {
+                assert_eq!(
+                    m.complexity(&x, &x_cache),
+                    inner_m.complexity(&x.0, &inner_m.cache_from_value(&x.0))
+                );
+                m.unmutate(&mut x, &mut x_cache, token);
+            }
+        }
+    }
 }
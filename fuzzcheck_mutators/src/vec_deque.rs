@@ -0,0 +1,470 @@
+extern crate fuzzcheck;
+use fuzzcheck::Mutator;
+
+use crate::{describe_parts, DefaultMutator};
+
+extern crate rand;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use std::collections::VecDeque;
+use std::ops::RangeInclusive;
+
+/// A mutator for `VecDeque`, which mutates its elements and also pushes and
+/// pops elements at both of its ends, as well as in its middle.
+///
+/// Its complexity is computed in the same way as that of a
+/// [VecMutator](crate::vector::VecMutator).
+pub struct VecDequeMutator<M: Mutator> {
+    m: M,
+    len_range: RangeInclusive<usize>,
+}
+impl<M: Mutator> VecDequeMutator<M> {
+    pub fn new(m: M) -> Self {
+        Self {
+            m,
+            len_range: 0..=std::usize::MAX,
+        }
+    }
+    /// Only produce deques whose length is within `len_range`
+    pub fn with_length_range(mut self, len_range: RangeInclusive<usize>) -> Self {
+        self.len_range = len_range;
+        self
+    }
+}
+impl<M: Mutator> Default for VecDequeMutator<M>
+where
+    M: Default,
+{
+    fn default() -> Self {
+        Self::new(M::default())
+    }
+}
+
+impl<T: DefaultMutator> DefaultMutator for VecDeque<T> {
+    type Mutator = VecDequeMutator<T::Mutator>;
+    fn default_mutator() -> Self::Mutator {
+        VecDequeMutator::new(T::default_mutator())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DequeOperation {
+    PushFront,
+    PushBack,
+    PopFront,
+    PopBack,
+    Insert,
+    Remove,
+}
+
+/// The operations on the deque itself, tried in this order after the
+/// elements of the deque have been mutated
+const DEQUE_OPERATIONS: [DequeOperation; 6] = [
+    DequeOperation::PushBack,
+    DequeOperation::PopFront,
+    DequeOperation::PushFront,
+    DequeOperation::PopBack,
+    DequeOperation::Insert,
+    DequeOperation::Remove,
+];
+
+#[derive(Clone)]
+pub struct VecDequeMutatorCache<C> {
+    inner: VecDeque<C>,
+    sum_cplx: f64,
+}
+impl<C> Default for VecDequeMutatorCache<C> {
+    fn default() -> Self {
+        Self {
+            inner: VecDeque::new(),
+            sum_cplx: 0.0,
+        }
+    }
+}
+
+pub struct VecDequeMutatorStep<S> {
+    inner: Vec<S>,
+    /// The index of the next mutation, which is either the mutation of an
+    /// element or, past the last element, one of [DEQUE_OPERATIONS]
+    idx: usize,
+    /// The number of times that all mutations have been tried, used as the
+    /// seed of the inserted elements
+    cycle: usize,
+    /// The number of consecutive mutations that were skipped because they
+    /// were not possible
+    nbr_skipped: usize,
+}
+
+impl<S> VecDequeMutatorStep<S> {
+    fn nbr_mutations(&self) -> usize {
+        self.inner.len() + DEQUE_OPERATIONS.len()
+    }
+    fn increment(&mut self) {
+        self.idx += 1;
+        if self.idx == self.nbr_mutations() {
+            self.idx = 0;
+            self.cycle += 1;
+        }
+    }
+}
+
+pub enum UnmutateVecDequeToken<M: Mutator> {
+    Element(usize, M::UnmutateToken, f64),
+    PushFront(M::Value, M::Cache),
+    PushBack(M::Value, M::Cache),
+    Insert(usize, M::Value, M::Cache),
+    PopFront(f64),
+    PopBack(f64),
+    Remove(usize, f64),
+}
+
+impl<M: Mutator> VecDequeMutator<M> {
+    fn can_insert(&self, len: usize) -> bool {
+        len < *self.len_range.end()
+    }
+    fn can_remove(&self, len: usize) -> bool {
+        len > *self.len_range.start()
+    }
+
+    /// Removes elements from the back of the deque, or appends the simplest
+    /// elements to it, until its length is within the length range
+    fn fit_length_range(&self, value: &mut VecDeque<M::Value>, cache: &mut VecDequeMutatorCache<M::Cache>) {
+        while value.len() > *self.len_range.end() {
+            let el = value.pop_back().unwrap();
+            let el_cache = cache.inner.pop_back().unwrap();
+            cache.sum_cplx -= self.m.complexity(&el, &el_cache);
+        }
+        while value.len() < *self.len_range.start() {
+            let (el, el_cache) = self.m.arbitrary(0, self.m.min_complexity());
+            cache.sum_cplx += self.m.complexity(&el, &el_cache);
+            value.push_back(el);
+            cache.inner.push_back(el_cache);
+        }
+    }
+
+    /// Creates an element to insert in a deque of length `len` whose
+    /// complexity can increase by at most `spare_cplx`
+    fn new_element(&self, len: usize, seed: usize, spare_cplx: f64) -> (M::Value, M::Cache, f64) {
+        // the longer deque is also more complex
+        let len_cplx_increase = crate::size_to_cplxity(len + 2) - crate::size_to_cplxity(len + 1);
+        let (el, el_cache) = self.m.arbitrary(seed, spare_cplx - len_cplx_increase);
+        let el_cplx = self.m.complexity(&el, &el_cache);
+        (el, el_cache, el_cplx)
+    }
+
+    fn mutate_element(
+        &self,
+        value: &mut VecDeque<M::Value>,
+        cache: &mut VecDequeMutatorCache<M::Cache>,
+        el_step: &mut M::MutationStep,
+        idx: usize,
+        spare_cplx: f64,
+    ) -> Option<UnmutateVecDequeToken<M>> {
+        let el = &mut value[idx];
+        let el_cache = &mut cache.inner[idx];
+
+        let old_cplx = self.m.complexity(el, el_cache);
+        let token = self.m.mutate(el, el_cache, el_step, old_cplx + spare_cplx)?;
+        let new_cplx = self.m.complexity(el, el_cache);
+
+        cache.sum_cplx += new_cplx - old_cplx;
+
+        Some(UnmutateVecDequeToken::Element(idx, token, old_cplx - new_cplx))
+    }
+
+    fn apply_operation(
+        &self,
+        value: &mut VecDeque<M::Value>,
+        cache: &mut VecDequeMutatorCache<M::Cache>,
+        operation: DequeOperation,
+        cycle: usize,
+        spare_cplx: f64,
+    ) -> Option<UnmutateVecDequeToken<M>> {
+        let len = value.len();
+        match operation {
+            DequeOperation::PushFront | DequeOperation::PushBack | DequeOperation::Insert if !self.can_insert(len) => {
+                None
+            }
+            DequeOperation::PopFront | DequeOperation::PopBack | DequeOperation::Remove if !self.can_remove(len) => {
+                None
+            }
+            DequeOperation::PushFront => {
+                let (el, el_cache, el_cplx) = self.new_element(len, cycle, spare_cplx);
+                value.push_front(el);
+                cache.inner.push_front(el_cache);
+                cache.sum_cplx += el_cplx;
+                Some(UnmutateVecDequeToken::PopFront(el_cplx))
+            }
+            DequeOperation::PushBack => {
+                let (el, el_cache, el_cplx) = self.new_element(len, cycle, spare_cplx);
+                value.push_back(el);
+                cache.inner.push_back(el_cache);
+                cache.sum_cplx += el_cplx;
+                Some(UnmutateVecDequeToken::PopBack(el_cplx))
+            }
+            DequeOperation::Insert => {
+                let idx = cycle % (len + 1);
+                let (el, el_cache, el_cplx) = self.new_element(len, cycle, spare_cplx);
+                value.insert(idx, el);
+                cache.inner.insert(idx, el_cache);
+                cache.sum_cplx += el_cplx;
+                Some(UnmutateVecDequeToken::Remove(idx, el_cplx))
+            }
+            DequeOperation::PopFront => {
+                let el = value.pop_front().unwrap();
+                let el_cache = cache.inner.pop_front().unwrap();
+                cache.sum_cplx -= self.m.complexity(&el, &el_cache);
+                Some(UnmutateVecDequeToken::PushFront(el, el_cache))
+            }
+            DequeOperation::PopBack => {
+                let el = value.pop_back().unwrap();
+                let el_cache = cache.inner.pop_back().unwrap();
+                cache.sum_cplx -= self.m.complexity(&el, &el_cache);
+                Some(UnmutateVecDequeToken::PushBack(el, el_cache))
+            }
+            DequeOperation::Remove => {
+                let idx = cycle % len;
+                let el = value.remove(idx).unwrap();
+                let el_cache = cache.inner.remove(idx).unwrap();
+                cache.sum_cplx -= self.m.complexity(&el, &el_cache);
+                Some(UnmutateVecDequeToken::Insert(idx, el, el_cache))
+            }
+        }
+    }
+
+    /// Removes the last element of the deque, or returns `None` if it
+    /// already has the minimum length
+    fn shrink(
+        &self,
+        value: &mut VecDeque<M::Value>,
+        cache: &mut VecDequeMutatorCache<M::Cache>,
+    ) -> Option<UnmutateVecDequeToken<M>> {
+        self.apply_operation(value, cache, DequeOperation::PopBack, 0, 0.0)
+    }
+}
+
+impl<M: Mutator> Mutator for VecDequeMutator<M> {
+    type Value = VecDeque<M::Value>;
+    type Cache = VecDequeMutatorCache<M::Cache>;
+    type MutationStep = VecDequeMutatorStep<M::MutationStep>;
+    type UnmutateToken = UnmutateVecDequeToken<M>;
+
+    fn max_complexity(&self) -> f64 {
+        std::f64::INFINITY
+    }
+
+    fn min_complexity(&self) -> f64 {
+        1.0
+    }
+
+    fn complexity(&self, value: &Self::Value, cache: &Self::Cache) -> f64 {
+        1.0 + cache.sum_cplx + crate::size_to_cplxity(value.len() + 1)
+    }
+
+    fn cache_from_value(&self, value: &Self::Value) -> Self::Cache {
+        let inner: VecDeque<_> = value.iter().map(|x| self.m.cache_from_value(x)).collect();
+
+        let sum_cplx = value
+            .iter()
+            .zip(inner.iter())
+            .fold(0.0, |cplx, (v, cache)| cplx + self.m.complexity(v, cache));
+
+        VecDequeMutatorCache { inner, sum_cplx }
+    }
+
+    fn mutation_step_from_value(&self, value: &Self::Value) -> Self::MutationStep {
+        VecDequeMutatorStep {
+            inner: value.iter().map(|x| self.m.mutation_step_from_value(x)).collect(),
+            idx: 0,
+            cycle: 0,
+            nbr_skipped: 0,
+        }
+    }
+
+    fn seeded_mutation_step_from_value(&self, value: &Self::Value, seed: u64) -> Self::MutationStep {
+        VecDequeMutatorStep {
+            inner: value
+                .iter()
+                .map(|x| self.m.seeded_mutation_step_from_value(x, seed))
+                .collect(),
+            idx: 0,
+            cycle: 0,
+            nbr_skipped: 0,
+        }
+    }
+
+    fn arbitrary(&self, seed: usize, max_cplx: f64) -> (Self::Value, Self::Cache) {
+        let mut value = VecDeque::new();
+        let mut cache = VecDequeMutatorCache::default();
+
+        if seed != 0 && max_cplx > 1.0 {
+            let mut rng = SmallRng::seed_from_u64(seed as u64);
+            let target_cplx = {
+                let increments_target_cplx = (max_cplx * 100.0).round() as usize;
+                crate::arbitrary_binary(0, increments_target_cplx, seed) as f64 / 100.0
+            };
+            let min_cplx_el = self.m.min_complexity();
+            // elements may have a complexity of 0, so the length is bounded too
+            let max_len = target_cplx.trunc() as usize;
+            while value.len() < max_len && self.can_insert(value.len()) {
+                let len_cplx_increase =
+                    crate::size_to_cplxity(value.len() + 2) - crate::size_to_cplxity(value.len() + 1);
+                let max_cplx_el = target_cplx - self.complexity(&value, &cache) - len_cplx_increase;
+                if max_cplx_el < min_cplx_el {
+                    break;
+                }
+                let cplx_el = if max_cplx_el > min_cplx_el {
+                    rng.gen_range(min_cplx_el, max_cplx_el)
+                } else {
+                    min_cplx_el
+                };
+                let (el, el_cache) = self.m.arbitrary(rng.gen(), cplx_el);
+                let el_cplx = self.m.complexity(&el, &el_cache);
+                if el_cplx > max_cplx_el {
+                    break;
+                }
+                // grow the deque from both ends
+                if rng.gen() {
+                    value.push_front(el);
+                    cache.inner.push_front(el_cache);
+                } else {
+                    value.push_back(el);
+                    cache.inner.push_back(el_cache);
+                }
+                cache.sum_cplx += el_cplx;
+            }
+        }
+        self.fit_length_range(&mut value, &mut cache);
+        (value, cache)
+    }
+
+    fn mutate(
+        &self,
+        value: &mut Self::Value,
+        cache: &mut Self::Cache,
+        step: &mut Self::MutationStep,
+        max_cplx: f64,
+    ) -> Option<Self::UnmutateToken> {
+        if step.nbr_skipped >= step.nbr_mutations() {
+            // none of the mutations of a whole cycle were possible
+            return None;
+        }
+        let cplx = self.complexity(value, cache);
+        let spare_cplx = max_cplx - cplx;
+
+        let mutation_idx = step.idx;
+        let cycle = step.cycle;
+        step.increment();
+
+        let token = if mutation_idx < step.inner.len() {
+            let el_step = &mut step.inner[mutation_idx];
+            self.mutate_element(value, cache, el_step, mutation_idx, spare_cplx)
+        } else {
+            let operation = DEQUE_OPERATIONS[mutation_idx - step.inner.len()];
+            self.apply_operation(value, cache, operation, cycle, spare_cplx)
+        };
+        let token = if let Some(token) = token {
+            token
+        } else {
+            step.nbr_skipped += 1;
+            return self.mutate(value, cache, step, max_cplx);
+        };
+        step.nbr_skipped = 0;
+        if cplx <= max_cplx && self.complexity(value, cache) > max_cplx {
+            // the inner mutator or the inserted element did not fit in the budget,
+            // so make the deque simpler instead
+            self.unmutate(value, cache, token);
+            self.shrink(value, cache)
+        } else {
+            Some(token)
+        }
+    }
+
+    fn unmutate(&self, value: &mut Self::Value, cache: &mut Self::Cache, t: Self::UnmutateToken) {
+        match t {
+            UnmutateVecDequeToken::Element(idx, inner_t, diff_cplx) => {
+                self.m.unmutate(&mut value[idx], &mut cache.inner[idx], inner_t);
+                cache.sum_cplx += diff_cplx;
+            }
+            UnmutateVecDequeToken::PushFront(el, el_cache) => {
+                cache.sum_cplx += self.m.complexity(&el, &el_cache);
+                value.push_front(el);
+                cache.inner.push_front(el_cache);
+            }
+            UnmutateVecDequeToken::PushBack(el, el_cache) => {
+                cache.sum_cplx += self.m.complexity(&el, &el_cache);
+                value.push_back(el);
+                cache.inner.push_back(el_cache);
+            }
+            UnmutateVecDequeToken::Insert(idx, el, el_cache) => {
+                cache.sum_cplx += self.m.complexity(&el, &el_cache);
+                value.insert(idx, el);
+                cache.inner.insert(idx, el_cache);
+            }
+            UnmutateVecDequeToken::PopFront(el_cplx) => {
+                value.pop_front();
+                cache.inner.pop_front();
+                cache.sum_cplx -= el_cplx;
+            }
+            UnmutateVecDequeToken::PopBack(el_cplx) => {
+                value.pop_back();
+                cache.inner.pop_back();
+                cache.sum_cplx -= el_cplx;
+            }
+            UnmutateVecDequeToken::Remove(idx, el_cplx) => {
+                value.remove(idx);
+                cache.inner.remove(idx);
+                cache.sum_cplx -= el_cplx;
+            }
+        }
+    }
+
+    fn set_length_range(&mut self, len_range: RangeInclusive<usize>) -> bool {
+        self.len_range = len_range;
+        true
+    }
+
+    /// Only the mutations of an element are recorded, by the mutator of the
+    /// elements
+    fn record_mutation_outcome(&self, value: &Self::Value, token: &Self::UnmutateToken, nbr_new_features: usize) {
+        if let UnmutateVecDequeToken::Element(idx, el_token, _) = token {
+            self.m.record_mutation_outcome(&value[*idx], el_token, nbr_new_features)
+        }
+    }
+
+    fn describe_value(&self, value: &Self::Value) -> Option<String> {
+        describe_parts("[", value.iter().map(|el| (None, self.m.describe_value(el))), "]")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integer::*;
+    use crate::keep_one_mutation_in_four;
+
+    #[test]
+    fn test_unmutate_restores_deque() {
+        let m = VecDequeMutator::new(U8Mutator::default());
+        for seed in 0..20 {
+            let (mut x, mut x_cache) = m.arbitrary(seed, 100.0);
+            keep_one_mutation_in_four(&m, &mut x, &mut x_cache, 100.0, 1000, |_, _, x, x_cache| {
+                let expected_cplx = m.complexity(x, &m.cache_from_value(x));
+                assert!((m.complexity(x, x_cache) - expected_cplx).abs() < 1e-9);
+            });
+        }
+    }
+
+    #[test]
+    fn test_length_range() {
+        let m = VecDequeMutator::new(U8Mutator::default()).with_length_range(2..=4);
+        for seed in 0..100 {
+            let (mut x, mut x_cache) = m.arbitrary(seed, 100.0);
+            assert!(x.len() >= 2 && x.len() <= 4);
+            keep_one_mutation_in_four(&m, &mut x, &mut x_cache, 100.0, 1000, |_, _, x, _| {
+                assert!(x.len() >= 2 && x.len() <= 4);
+            });
+        }
+    }
+}
@@ -1,16 +1,57 @@
 extern crate fuzzcheck;
 use fuzzcheck::Mutator;
 
+use crate::{describe_parts, DefaultMutator};
+
 extern crate rand;
 use rand::rngs::SmallRng;
 use rand::{Rng, SeedableRng};
 
+use std::ops::RangeInclusive;
+
 pub struct VecMutator<M: Mutator> {
     m: M,
+    len_range: RangeInclusive<usize>,
+    length_weight: f64,
+    element_weight: f64,
 }
 impl<M: Mutator> VecMutator<M> {
     pub fn new(m: M) -> Self {
-        Self { m }
+        Self {
+            m,
+            len_range: 0..=std::usize::MAX,
+            length_weight: 1.0,
+            element_weight: 1.0,
+        }
+    }
+    /// Only produce vectors whose length is within `len_range`
+    pub fn with_length_range(mut self, len_range: RangeInclusive<usize>) -> Self {
+        self.len_range = len_range;
+        self
+    }
+    /// Change how much the length of the vector and the complexity of its
+    /// elements each contribute to its complexity.
+    ///
+    /// The complexity of a vector is computed as
+    /// `1 + length_weight * log2(len + 1) + element_weight * sum_element_cplx`.
+    /// Both weights are `1.0` by default. Lowering `length_weight` tells the
+    /// fuzzer to value diverse elements more than long vectors, which is
+    /// useful for vectors of very simple elements.
+    ///
+    /// # Panics
+    /// Panics if a weight is negative or not finite, or if `element_weight` is 0.
+    pub fn with_complexity_weights(mut self, length_weight: f64, element_weight: f64) -> Self {
+        assert!(
+            length_weight.is_finite() && length_weight >= 0.0,
+            "the length weight of a VecMutator must be a finite, non-negative number"
+        );
+        assert!(
+            element_weight.is_finite() && element_weight > 0.0,
+            "the element weight of a VecMutator must be a finite, positive number"
+        );
+        self.length_weight = length_weight;
+        self.element_weight = element_weight;
+        self
     }
 }
 impl<M: Mutator> Default for VecMutator<M>
@@ -22,31 +63,41 @@ where
     }
 }
 
+impl<T: DefaultMutator> DefaultMutator for Vec<T> {
+    type Mutator = VecMutator<T::Mutator>;
+    fn default_mutator() -> Self::Mutator {
+        VecMutator::new(T::default_mutator())
+    }
+}
+
 struct VecMutatorArbitrarySeed {
     complexity_step: usize,
+    len_class: usize,
     len_step: usize,
     rng: SmallRng,
 }
 
 impl VecMutatorArbitrarySeed {
     fn new(step: usize) -> Self {
-        let mut rng = SmallRng::from_entropy();
+        let mut rng = SmallRng::seed_from_u64(step as u64);
         if step == 0 {
             Self {
                 complexity_step: 0,
+                len_class: 0,
                 len_step: 0,
                 rng,
             }
         } else {
-            let (complexity_step, len_step) = if step < 100 {
+            let (complexity_step, len_class, len_step) = if step < 100 {
                 // deterministic phase for 100 first steps
-                (step % 10, step / 10)
+                (step % 10, step, step / 10)
             } else {
                 // default
-                (step, rng.gen())
+                (step, rng.gen(), rng.gen())
             };
             Self {
                 complexity_step,
+                len_class,
                 len_step,
                 rng,
             }
@@ -61,6 +112,9 @@ struct MutationStep {
     insert_idx: usize,
     vec_operations: Vec<VecOperation>,
     cycle: usize,
+    /// The number of consecutive mutations that were skipped because they
+    /// were not possible
+    nbr_skipped: usize,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -85,6 +139,7 @@ impl MutationStep {
             insert_idx: 0,
             vec_operations,
             cycle: 0,
+            nbr_skipped: 0,
         }
     }
 }
@@ -110,6 +165,20 @@ impl<C> Default for VecMutatorCache<C> {
         }
     }
 }
+impl<C> VecMutatorCache<C> {
+    /// Reorders the caches of the elements along with the vector, whose
+    /// element `i` becomes its former element `order[i]`
+    pub(crate) fn reorder(&mut self, order: &[usize]) {
+        reorder(&mut self.inner, order);
+    }
+}
+
+/// Reorders the vector so that its element `i` becomes its former element
+/// `order[i]`, where `order` is a permutation of its indices
+pub(crate) fn reorder<T>(v: &mut Vec<T>, order: &[usize]) {
+    let mut old: Vec<Option<T>> = v.drain(..).map(Some).collect();
+    v.extend(order.iter().map(|&i| old[i].take().unwrap()));
+}
 
 pub struct VecMutatorStep<S> {
     inner: Vec<S>,
@@ -160,6 +229,41 @@ pub enum UnmutateVecToken<M: Mutator> {
 }
 
 impl<M: Mutator> VecMutator<M> {
+    fn can_insert(&self, len: usize) -> bool {
+        len < *self.len_range.end()
+    }
+    fn can_remove(&self, len: usize) -> bool {
+        len > *self.len_range.start()
+    }
+
+    /// Removes elements from the end of the vector, or appends the simplest
+    /// elements to it, until its length is within the length range
+    fn fit_length_range(&self, value: &mut Vec<M::Value>, cache: &mut VecMutatorCache<M::Cache>) {
+        while value.len() > *self.len_range.end() {
+            let el = value.pop().unwrap();
+            let el_cache = cache.inner.pop().unwrap();
+            cache.sum_cplx -= self.m.complexity(&el, &el_cache);
+        }
+        while value.len() < *self.len_range.start() {
+            let (el, el_cache) = self.m.arbitrary(0, self.m.min_complexity());
+            cache.sum_cplx += self.m.complexity(&el, &el_cache);
+            value.push(el);
+            cache.inner.push(el_cache);
+        }
+    }
+
+    /// Moves on to the next mutation, because the current one is not possible
+    fn skip_mutation(
+        &self,
+        value: &mut Vec<M::Value>,
+        cache: &mut VecMutatorCache<M::Cache>,
+        step: &mut VecMutatorStep<M::MutationStep>,
+        max_cplx: f64,
+    ) -> Option<UnmutateVecToken<M>> {
+        step.pick_step.nbr_skipped += 1;
+        self.mutate(value, cache, step, max_cplx)
+    }
+
     fn mutate_element(
         &self,
         value: &mut Vec<M::Value>,
@@ -167,21 +271,24 @@ impl<M: Mutator> VecMutator<M> {
         step: &mut VecMutatorStep<M::MutationStep>,
         idx: usize,
         spare_cplx: f64,
-    ) -> UnmutateVecToken<M> {
+    ) -> Option<UnmutateVecToken<M>> {
         let el = &mut value[idx];
         let el_cache = &mut cache.inner[idx];
         let el_step = &mut step.inner[idx];
 
         let old_cplx = self.m.complexity(el, el_cache);
 
-        let token = self.m.mutate(el, el_cache, el_step, spare_cplx);
+        let token = self
+            .m
+            .mutate(el, el_cache, el_step, old_cplx + spare_cplx / self.element_weight);
+        step.increment_mutation_step_category();
+        let token = token?;
 
         let new_cplx = self.m.complexity(el, el_cache);
 
         cache.sum_cplx += new_cplx - old_cplx;
-        step.increment_mutation_step_category();
 
-        UnmutateVecToken::Element(idx, token, old_cplx - new_cplx)
+        Some(UnmutateVecToken::Element(idx, token, old_cplx - new_cplx))
     }
 
     fn insert_element(
@@ -191,11 +298,17 @@ impl<M: Mutator> VecMutator<M> {
         step: &mut VecMutatorStep<M::MutationStep>,
         spare_cplx: f64,
     ) -> UnmutateVecToken<M> {
-        let (idx, cycle) = (step.pick_step.insert_idx, step.pick_step.cycle);
-
-        // TODO: For now I assume that the complexity given by the length of the vector does not change
-        // Should I take it into account instead?
-        let (el, el_cache) = self.m.arbitrary(cycle, spare_cplx);
+        let (idx, cycle) = (
+            std::cmp::min(step.pick_step.insert_idx, value.len()),
+            step.pick_step.cycle,
+        );
+
+        // the longer vector is also more complex
+        let len_cplx_increase =
+            self.length_weight * (crate::size_to_cplxity(value.len() + 2) - crate::size_to_cplxity(value.len() + 1));
+        let (el, el_cache) = self
+            .m
+            .arbitrary(cycle, (spare_cplx - len_cplx_increase) / self.element_weight);
         let el_cplx = self.m.complexity(&el, &el_cache);
 
         value.insert(idx, el);
@@ -250,6 +363,136 @@ impl<M: Mutator> VecMutator<M> {
 
         token
     }
+
+    /// Chooses the length of a new vector, between 0 and `max_len`.
+    ///
+    /// `len_class` picks one of the classes of lengths: 0, 1, 2..=3, 4..=7,
+    /// and so on up to `max_len`, and `len_step` picks a length within the
+    /// class. So the lengths of arbitrary vectors are spread evenly across
+    /// these classes: they are as often empty, tiny, medium, and as large as
+    /// allowed, instead of being mostly close to `max_len`.
+    fn arbitrary_length(max_len: usize, len_class: usize, len_step: usize) -> usize {
+        let nbr_classes = (64 - (max_len as u64).leading_zeros()) as usize + 1;
+        let class = len_class % nbr_classes;
+        if class == 0 {
+            return 0;
+        }
+        let low = 1 << (class - 1);
+        let high = 1usize
+            .checked_shl(class as u32)
+            .map_or(max_len, |x| std::cmp::min(x - 1, max_len));
+        crate::arbitrary_binary(low, high, len_step)
+    }
+
+    /// The maximum complexity of the elements of a vector of length `len`
+    /// whose complexity is at most `max_cplx`
+    fn max_elements_cplx(&self, len: usize, max_cplx: f64) -> f64 {
+        (max_cplx - 1.0 - self.length_weight * crate::size_to_cplxity(len + 1)) / self.element_weight
+    }
+
+    /// Creates an arbitrary vector, ignoring the length range
+    fn arbitrary_any_length(&self, seed: usize, max_cplx: f64) -> (Vec<M::Value>, VecMutatorCache<M::Cache>) {
+        let VecMutatorArbitrarySeed {
+            complexity_step,
+            len_class,
+            len_step,
+            mut rng,
+        } = VecMutatorArbitrarySeed::new(seed);
+
+        if seed == 0 || max_cplx <= 1.0 {
+            return (Vec::new(), VecMutatorCache::default());
+        }
+
+        let min_cplx_el = self.m.min_complexity();
+        let max_cplx_el = self.m.max_complexity();
+
+        // slight underestimate of the maximum number of elements that fit in max_cplx
+        let max_len = {
+            let max_elements_cplx = self.max_elements_cplx(0, max_cplx);
+            let overestimated_max_len: f64 = max_elements_cplx / min_cplx_el;
+            let max_len = if overestimated_max_len.is_infinite() {
+                // min_cplx_el is 0, so the max length is the maximum complexity of the length component of the vector
+                crate::cplxity_to_size(max_elements_cplx)
+            } else {
+                // an underestimate of the true max_length, but not by much
+                (overestimated_max_len - overestimated_max_len.log2()).max(0.0) as usize
+            };
+            // arbitrary restriction on the length of the generated number, to avoid creating absurdly large vectors
+            // of very simple elements, that take up too much memory
+            if max_len > 10_000 {
+                /* TODO */
+                // 10_000?
+                max_elements_cplx.trunc() as usize
+            } else {
+                max_len
+            }
+        };
+        let mut len = Self::arbitrary_length(max_len, len_class, len_step);
+        while len > 0 && self.max_elements_cplx(len, max_cplx) < len as f64 * min_cplx_el {
+            len -= 1;
+        }
+
+        let mut v = Vec::new();
+        let mut cache = VecMutatorCache::default();
+        if max_cplx_el <= 0.0 {
+            // the elements are always of cplx 0, so we can only vary the length of the vector
+            for _ in 0..len {
+                // no point in adding valid step and max_cplx argument, the elements have only one possible value
+                let (el, el_cache) = self.m.arbitrary(0, 0.0);
+                v.push(el);
+                cache.inner.push(el_cache); // I don't update sum_cplx because it is 0
+            }
+            return (v, cache);
+        }
+
+        // choose the complexity of the elements, between the least and the most that `len` elements can have
+        let target_cplx = {
+            let min_target_cplx = len as f64 * min_cplx_el;
+            let max_target_cplx = f64::min(self.max_elements_cplx(len, max_cplx), len as f64 * max_cplx_el);
+            let fraction = crate::arbitrary_binary(0, 100, complexity_step) as f64 / 100.0;
+            min_target_cplx + (max_target_cplx - min_target_cplx) * fraction
+        };
+        // TODO: create a new_input_with_complexity method
+        let mut remaining_cplx = target_cplx;
+        for i in 0..len {
+            let max_cplx_element = remaining_cplx / ((len - i) as f64);
+            if max_cplx_element < min_cplx_el {
+                break;
+            }
+            let cplx_element = if max_cplx_element > min_cplx_el {
+                rng.gen_range(min_cplx_el, max_cplx_element)
+            } else {
+                min_cplx_el
+            };
+            let (x, x_cache) = self.m.arbitrary(rng.gen(), cplx_element);
+            let x_cplx = self.m.complexity(&x, &x_cache);
+            v.push(x);
+            cache.inner.push(x_cache);
+            cache.sum_cplx += x_cplx;
+            remaining_cplx -= x_cplx;
+        }
+        (v, cache)
+    }
+
+    /// Removes an element of the vector, or returns `None` if it already has
+    /// the minimum length
+    fn shrink(
+        &self,
+        value: &mut Vec<M::Value>,
+        cache: &mut VecMutatorCache<M::Cache>,
+        step: &VecMutatorStep<M::MutationStep>,
+    ) -> Option<UnmutateVecToken<M>> {
+        if !self.can_remove(value.len()) {
+            return None;
+        }
+        let idx = step.pick_step.cycle % value.len();
+        let el_cplx = self.m.complexity(&value[idx], &cache.inner[idx]);
+        let removed_el = value.remove(idx);
+        let removed_el_cache = cache.inner.remove(idx);
+        cache.sum_cplx -= el_cplx;
+
+        Some(UnmutateVecToken::Insert(idx, removed_el, removed_el_cache))
+    }
 }
 
 impl<M: Mutator> Mutator for VecMutator<M> {
@@ -267,7 +510,7 @@ impl<M: Mutator> Mutator for VecMutator<M> {
     }
 
     fn complexity(&self, value: &Self::Value, cache: &Self::Cache) -> f64 {
-        1.0 + cache.sum_cplx + crate::size_to_cplxity(value.len() + 1)
+        1.0 + self.element_weight * cache.sum_cplx + self.length_weight * crate::size_to_cplxity(value.len() + 1)
     }
 
     fn cache_from_value(&self, value: &Self::Value) -> Self::Cache {
@@ -288,90 +531,21 @@ impl<M: Mutator> Mutator for VecMutator<M> {
         }
     }
 
-    fn arbitrary(&self, seed: usize, max_cplx: f64) -> (Self::Value, Self::Cache) {
-        let VecMutatorArbitrarySeed {
-            complexity_step,
-            len_step,
-            mut rng,
-        } = VecMutatorArbitrarySeed::new(seed);
-
-        if seed == 0 || max_cplx <= 1.0 {
-            return (Self::Value::default(), Self::Cache::default());
+    fn seeded_mutation_step_from_value(&self, value: &Self::Value, seed: u64) -> Self::MutationStep {
+        let inner: Vec<_> = value
+            .iter()
+            .map(|x| self.m.seeded_mutation_step_from_value(x, seed))
+            .collect();
+        VecMutatorStep {
+            inner,
+            pick_step: MutationStep::new(value.len()),
         }
+    }
 
-        let target_cplx = {
-            let increments_target_cplx = (max_cplx * 100.0).round() as usize;
-            let multiplied_target_cplx = crate::arbitrary_binary(0, increments_target_cplx, complexity_step) as f64;
-            multiplied_target_cplx / 100.0
-        };
-        let min_cplx_el = self.m.min_complexity();
-
-        // slight underestimate of the maximum number of elements required to produce an input of max_cplx
-        let max_len_most_complex = {
-            let overestimated_max_len: f64 = target_cplx / min_cplx_el;
-            let max_len = if overestimated_max_len.is_infinite() {
-                // min_cplx_el is 0, so the max length is the maximum complexity of the length component of the vector
-                crate::cplxity_to_size(target_cplx)
-            } else {
-                // an underestimate of the true max_length, but not by much
-                (overestimated_max_len - overestimated_max_len.log2()) as usize
-            };
-            if max_len > 10_000 {
-                /* TODO */
-                // 10_000?
-                target_cplx.trunc() as usize
-            } else {
-                max_len
-            }
-        };
-        let max_cplx_el = self.m.max_complexity();
-        // slight underestimate of the minimum number of elements required to produce an input of max_cplx
-        let min_len_most_complex = target_cplx / max_cplx_el - (target_cplx / max_cplx_el).log2();
-        if !min_len_most_complex.is_finite() {
-            // in this case, the elements are always of cplx 0, so we can only vary the length of the vector
-            let len = crate::arbitrary_binary(0, max_len_most_complex, len_step);
-            let mut v = Self::Value::default();
-            let mut cache = Self::Cache::default();
-            for _ in 0..len {
-                // no point in adding valid step and max_cplx argument, the elements have only one possible value
-                let (el, el_cache) = self.m.arbitrary(0, 0.0);
-                v.push(el);
-                cache.inner.push(el_cache); // I don't update sum_cplx because it is 0
-            }
-            (v, cache)
-        } else {
-            let min_len_most_complex = min_len_most_complex.trunc() as usize;
-            // arbitrary restriction on the length of the generated number, to avoid creating absurdly large vectors
-            // of very simple elements, that take up too much memory
-            let max_len_most_complex = if max_len_most_complex > 10_000 {
-                /* TODO */
-                // 10_000?
-                target_cplx.trunc() as usize
-            } else {
-                max_len_most_complex
-            };
-
-            // choose a length between min_len_most_complex and max_len_most_complex
-            let target_len = crate::arbitrary_binary(min_len_most_complex, max_len_most_complex, len_step);
-            // TODO: create a new_input_with_complexity method
-            let mut v = Self::Value::default();
-            let mut cache = Self::Cache::default();
-            let mut remaining_cplx = target_cplx;
-            for i in 0..target_len {
-                let max_cplx_element = remaining_cplx / ((target_len - i) as f64);
-                if max_cplx_element <= min_cplx_el {
-                    break;
-                }
-                let cplx_element = rng.gen_range(min_cplx_el, max_cplx_element);
-                let (x, x_cache) = self.m.arbitrary(rng.gen(), cplx_element);
-                let x_cplx = self.m.complexity(&x, &x_cache);
-                v.push(x);
-                cache.inner.push(x_cache);
-                cache.sum_cplx += x_cplx;
-                remaining_cplx -= x_cplx;
-            }
-            (v, cache)
-        }
+    fn arbitrary(&self, seed: usize, max_cplx: f64) -> (Self::Value, Self::Cache) {
+        let (mut value, mut cache) = self.arbitrary_any_length(seed, max_cplx);
+        self.fit_length_range(&mut value, &mut cache);
+        (value, cache)
     }
 
     fn mutate(
@@ -380,12 +554,20 @@ impl<M: Mutator> Mutator for VecMutator<M> {
         cache: &mut Self::Cache,
         step: &mut Self::MutationStep,
         max_cplx: f64,
-    ) -> Self::UnmutateToken {
-        let spare_cplx = max_cplx - self.complexity(value, cache);
+    ) -> Option<Self::UnmutateToken> {
+        if step.pick_step.nbr_skipped > step.inner.len() + step.pick_step.vec_operations.len() {
+            // none of the mutations of a whole cycle were possible
+            return None;
+        }
+        let cplx = self.complexity(value, cache);
+        let spare_cplx = max_cplx - cplx;
 
-        match step.pick_step.category {
+        let token = match step.pick_step.category {
             MutationCategory::Empty => {
                 step.increment_mutation_step_category();
+                if *self.len_range.start() > 0 {
+                    return self.skip_mutation(value, cache, step, max_cplx);
+                }
 
                 let mut old_value = Self::Value::default();
                 let mut old_cache = Self::Cache::default();
@@ -395,15 +577,41 @@ impl<M: Mutator> Mutator for VecMutator<M> {
 
                 UnmutateVecToken::Replace(old_value, old_cache)
             }
-            MutationCategory::Element(idx) => self.mutate_element(value, cache, step, idx, spare_cplx),
+            MutationCategory::Element(idx) if idx >= value.len() => {
+                // kept mutations can make the vector shorter than the mutation step
+                step.increment_mutation_step_category();
+                return self.skip_mutation(value, cache, step, max_cplx);
+            }
+            MutationCategory::Element(idx) => match self.mutate_element(value, cache, step, idx, spare_cplx) {
+                Some(token) => token,
+                // the element cannot be mutated anymore, but the step has moved on to the next category
+                None => return self.skip_mutation(value, cache, step, max_cplx),
+            },
             MutationCategory::Vector(vector_step) => {
                 let operation_idx = vector_step % step.pick_step.vec_operations.len();
                 let operation = step.pick_step.vec_operations[operation_idx];
                 match operation {
-                    VecOperation::Insert => self.insert_element(value, cache, step, spare_cplx),
-                    VecOperation::Remove => self.remove_element(value, cache, step),
+                    VecOperation::Insert if self.can_insert(value.len()) => {
+                        self.insert_element(value, cache, step, spare_cplx)
+                    }
+                    VecOperation::Remove if self.can_remove(value.len()) && step.pick_step.remove_idx < value.len() => {
+                        self.remove_element(value, cache, step)
+                    }
+                    _ => {
+                        step.increment_mutation_step_category();
+                        return self.skip_mutation(value, cache, step, max_cplx);
+                    }
                 }
             }
+        };
+        step.pick_step.nbr_skipped = 0;
+        if cplx <= max_cplx && self.complexity(value, cache) > max_cplx {
+            // the inner mutator or the inserted element did not fit in the budget,
+            // so make the vector simpler instead
+            self.unmutate(value, cache, token);
+            self.shrink(value, cache, step)
+        } else {
+            Some(token)
         }
     }
 
@@ -432,4 +640,263 @@ impl<M: Mutator> Mutator for VecMutator<M> {
             }
         }
     }
+
+    fn set_length_range(&mut self, len_range: RangeInclusive<usize>) -> bool {
+        self.len_range = len_range;
+        true
+    }
+
+    /// Removes a chunk of the vector, by bisection: first the whole vector,
+    /// then each of its halves, each of its quarters, and so on until each
+    /// of its elements
+    fn shrink_below(
+        &self,
+        value: &Self::Value,
+        cache: &Self::Cache,
+        step: &mut usize,
+        max_cplx: f64,
+    ) -> Option<(Self::Value, Self::Cache)> {
+        loop {
+            let (start, chunk_len) = bisection_chunk(value.len(), *step)?;
+            *step += 1;
+            if value.len() - chunk_len < *self.len_range.start() {
+                continue;
+            }
+            let mut shrunk_value = value.clone();
+            let mut shrunk_cache = cache.clone();
+            for (el, el_cache) in shrunk_value
+                .drain(start..start + chunk_len)
+                .zip(shrunk_cache.inner.drain(start..start + chunk_len))
+            {
+                shrunk_cache.sum_cplx -= self.m.complexity(&el, &el_cache);
+            }
+            if self.complexity(&shrunk_value, &shrunk_cache) < max_cplx {
+                return Some((shrunk_value, shrunk_cache));
+            }
+        }
+    }
+
+    /// Splices the beginning of `a` with the end of `b`, at random positions
+    fn crossover(
+        &self,
+        a: &Self::Value,
+        _a_cache: &Self::Cache,
+        b: &Self::Value,
+        _b_cache: &Self::Cache,
+        seed: usize,
+        max_cplx: f64,
+    ) -> Option<(Self::Value, Self::Cache)> {
+        let mut rng = SmallRng::seed_from_u64(seed as u64);
+        let head_len = rng.gen_range(0, a.len() + 1);
+        let tail_start = rng.gen_range(0, b.len() + 1);
+        let mut value = a[..head_len].to_vec();
+        value.extend_from_slice(&b[tail_start..]);
+        if !self.len_range.contains(&value.len()) {
+            return None;
+        }
+        let cache = self.cache_from_value(&value);
+        if self.complexity(&value, &cache) >= max_cplx {
+            return None;
+        }
+        Some((value, cache))
+    }
+
+    /// Only the mutations of an element are recorded, by the mutator of the
+    /// elements
+    fn record_mutation_outcome(&self, value: &Self::Value, token: &Self::UnmutateToken, nbr_new_features: usize) {
+        if let UnmutateVecToken::Element(idx, el_token, _) = token {
+            self.m.record_mutation_outcome(&value[*idx], el_token, nbr_new_features)
+        }
+    }
+
+    fn describe_value(&self, value: &Self::Value) -> Option<String> {
+        describe_parts("[", value.iter().map(|el| (None, self.m.describe_value(el))), "]")
+    }
+}
+
+/// The start and length of the chunk of a vector of length `len` that is
+/// removed at the given step of its bisection, or `None` if the bisection
+/// is finished
+fn bisection_chunk(len: usize, mut step: usize) -> Option<(usize, usize)> {
+    let mut nbr_chunks = 1;
+    loop {
+        let chunk_len = (len + nbr_chunks - 1) / nbr_chunks;
+        if chunk_len == 0 {
+            return None;
+        }
+        // fewer chunks than nbr_chunks are needed when the last ones would be empty
+        let actual_nbr_chunks = (len + chunk_len - 1) / chunk_len;
+        if step < actual_nbr_chunks {
+            let start = step * chunk_len;
+            return Some((start, std::cmp::min(chunk_len, len - start)));
+        }
+        step -= actual_nbr_chunks;
+        if chunk_len == 1 {
+            return None;
+        }
+        nbr_chunks *= 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integer::*;
+    use crate::keep_one_mutation_in_four;
+
+    #[test]
+    fn test_mutate_stays_within_budget() {
+        let m = VecMutator::new(VecMutator::new(U8Mutator::default()));
+        for max_cplx in [1.0, 10.0, 30.0, 100.0].iter() {
+            let (mut x, mut x_cache) = m.arbitrary(1, *max_cplx);
+            assert!(m.complexity(&x, &x_cache) <= *max_cplx);
+            // keep one mutation in four, so that the vector can grow
+            keep_one_mutation_in_four(&m, &mut x, &mut x_cache, *max_cplx, 5000, |_, _, _, _| {});
+        }
+    }
+
+    #[test]
+    fn test_arbitrary_and_mutate_are_deterministic() {
+        let m = VecMutator::new(U8Mutator::default());
+        let values = || {
+            (0..200)
+                .map(|seed| {
+                    let (mut x, mut x_cache) = m.arbitrary(seed, 100.0);
+                    let mut x_step = m.mutation_step_from_value(&x);
+                    for _ in 0..20 {
+                        let _ = m.mutate(&mut x, &mut x_cache, &mut x_step, 100.0);
+                    }
+                    x
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(values(), values());
+    }
+
+    #[test]
+    fn test_arbitrary_lengths_are_diverse() {
+        let m = VecMutator::new(U8Mutator::default());
+        let lengths: Vec<usize> = (0..12)
+            .map(|i| {
+                // the seeds given by the fuzzer are random
+                let seed = 0x9E37_79B9_7F4A_7C15_u64.wrapping_mul(i + 1) as usize;
+                let (x, cache) = m.arbitrary(seed, 1000.0);
+                assert!(m.complexity(&x, &cache) <= 1000.0);
+                x.len()
+            })
+            .collect();
+        let mut distinct = lengths.clone();
+        distinct.sort_unstable();
+        distinct.dedup();
+        assert!(distinct.len() >= 8, "{:?}", lengths);
+        assert!(lengths.contains(&0));
+        assert!(lengths.iter().any(|&len| len < 4 && len > 0));
+        // about 117 u8 fit in the budget
+        assert!(lengths.iter().any(|&len| len >= 64), "{:?}", lengths);
+    }
+
+    #[test]
+    fn test_shrink_below_bisects() {
+        let m = VecMutator::new(U8Mutator::default());
+        // the test fails as long as the vector contains both 7 and 8
+        let fails = |x: &Vec<u8>| x.contains(&7) && x.contains(&8);
+        let mut x: Vec<u8> = (0..1000).map(|i| (i % 5) as u8).collect();
+        x[123] = 7;
+        x[876] = 8;
+        let mut cache = m.cache_from_value(&x);
+
+        // each reduction restarts the shrinking from the smaller vector, like
+        // successive runs of input minification
+        let mut nbr_reductions = 0;
+        let mut step = 0;
+        while let Some((shrunk, shrunk_cache)) = m.shrink_below(&x, &cache, &mut step, m.complexity(&x, &cache)) {
+            assert!(
+                (m.complexity(&shrunk, &shrunk_cache) - m.complexity(&shrunk, &m.cache_from_value(&shrunk))).abs()
+                    < 1e-9
+            );
+            if fails(&shrunk) {
+                x = shrunk;
+                cache = shrunk_cache;
+                step = 0;
+                nbr_reductions += 1;
+            }
+        }
+        assert_eq!(x, vec![7, 8]);
+        // about log2(1000) reductions for each of the two necessary elements
+        assert!(nbr_reductions <= 20, "{}", nbr_reductions);
+
+        let m = VecMutator::new(U8Mutator::default()).with_length_range(2..=10);
+        let x = vec![1, 2];
+        assert!(m.shrink_below(&x, &m.cache_from_value(&x), &mut 0, 100.0).is_none());
+    }
+
+    #[test]
+    fn test_crossover() {
+        let m = VecMutator::new(U8Mutator::default());
+        let a = vec![1, 1, 1, 1];
+        let b = vec![2, 2, 2, 2, 2];
+        let (a_cache, b_cache) = (m.cache_from_value(&a), m.cache_from_value(&b));
+        let mut children = std::collections::HashSet::new();
+        for seed in 0..100 {
+            let (x, cache) = m.crossover(&a, &a_cache, &b, &b_cache, seed, 100.0).unwrap();
+            // a head of `a` followed by a tail of `b`
+            let head_len = x.iter().take_while(|&&el| el == 1).count();
+            assert!(head_len <= a.len());
+            assert!(x[head_len..].iter().all(|&el| el == 2));
+            assert!(x.len() - head_len <= b.len());
+            assert!((m.complexity(&x, &cache) - m.complexity(&x, &m.cache_from_value(&x))).abs() < 1e-9);
+            children.insert(x);
+        }
+        assert!(children.len() > 10);
+
+        // the children must respect the length range and the complexity budget
+        let m = VecMutator::new(U8Mutator::default()).with_length_range(9..=9);
+        for seed in 0..100 {
+            if let Some((x, _)) = m.crossover(&a, &a_cache, &b, &b_cache, seed, 100.0) {
+                assert_eq!(x, vec![1, 1, 1, 1, 2, 2, 2, 2, 2]);
+            }
+        }
+        assert!(m.crossover(&a, &a_cache, &b, &b_cache, 0, 1.0).is_none());
+    }
+
+    #[test]
+    fn test_complexity_weights() {
+        let default = VecMutator::new(U8Mutator::default());
+        let weighted = VecMutator::new(U8Mutator::default()).with_complexity_weights(0.25, 2.0);
+        let x = vec![1, 2, 3, 4, 5, 6, 7];
+        let cplx = default.complexity(&x, &default.cache_from_value(&x));
+        let weighted_cplx = weighted.complexity(&x, &weighted.cache_from_value(&x));
+        assert!((cplx - (1.0 + 7.0 * 8.0 + 3.0)).abs() < 1e-9);
+        assert!((weighted_cplx - (1.0 + 2.0 * 7.0 * 8.0 + 0.25 * 3.0)).abs() < 1e-9);
+
+        let m = VecMutator::new(U8Mutator::default()).with_complexity_weights(0.1, 3.0);
+        let (mut x, mut x_cache) = m.arbitrary(1, 100.0);
+        keep_one_mutation_in_four(&m, &mut x, &mut x_cache, 100.0, 5000, |_, _, x, x_cache| {
+            assert!((m.complexity(x, x_cache) - m.complexity(x, &m.cache_from_value(x))).abs() < 1e-6);
+        });
+    }
+
+    #[test]
+    fn test_length_range() {
+        let m = VecMutator::new(U8Mutator::default()).with_length_range(2..=4);
+        for seed in 0..100 {
+            let (mut x, mut x_cache) = m.arbitrary(seed, 100.0);
+            assert!(x.len() >= 2 && x.len() <= 4);
+            keep_one_mutation_in_four(&m, &mut x, &mut x_cache, 100.0, 1000, |_, _, x, _| {
+                assert!(x.len() >= 2 && x.len() <= 4);
+            });
+        }
+    }
+
+    #[test]
+    fn test_length_range_exhaustion() {
+        // the length cannot change and the only element can only be mutated once
+        let m = VecMutator::new(crate::bool::BoolMutator::default()).with_length_range(1..=1);
+        let (mut x, mut x_cache) = m.arbitrary(0, 10.0);
+        let mut x_step = m.mutation_step_from_value(&x);
+        let token = m.mutate(&mut x, &mut x_cache, &mut x_step, 10.0).unwrap();
+        m.unmutate(&mut x, &mut x_cache, token);
+        assert!(m.mutate(&mut x, &mut x_cache, &mut x_step, 10.0).is_none());
+        assert_eq!(x.len(), 1);
+    }
 }
@@ -1,6 +1,8 @@
 extern crate fuzzcheck;
 use fuzzcheck::Mutator;
 
+use crate::DefaultMutator;
+
 #[derive(Clone)]
 pub struct VoidMutator {}
 
@@ -10,6 +12,13 @@ impl Default for VoidMutator {
     }
 }
 
+impl DefaultMutator for () {
+    type Mutator = VoidMutator;
+    fn default_mutator() -> Self::Mutator {
+        VoidMutator::default()
+    }
+}
+
 impl Mutator for VoidMutator {
     type Value = ();
     type Cache = ();
@@ -41,7 +50,8 @@ impl Mutator for VoidMutator {
         _cache: &mut Self::Cache,
         _step: &mut Self::MutationStep,
         _max_cplx: f64,
-    ) -> Self::UnmutateToken {
+    ) -> Option<Self::UnmutateToken> {
+        None
     }
 
     fn unmutate(&self, _value: &mut Self::Value, _cache: &mut Self::Cache, _t: Self::UnmutateToken) {}
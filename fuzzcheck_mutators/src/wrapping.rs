@@ -0,0 +1,122 @@
+//! A mutator for `Wrapping` integers, which is the mutator of the wrapped
+//! integer seen through the `Wrapping` newtype.
+
+extern crate fuzzcheck;
+use fuzzcheck::Mutator;
+
+use crate::tuples::{NewtypeMap, NewtypeMutator};
+use crate::DefaultMutator;
+
+use std::num::Wrapping;
+
+impl<A> NewtypeMap for Wrapping<A>
+where
+    A: Clone,
+{
+    type A = A;
+    type V = Self;
+
+    fn get_a(v: &Wrapping<A>) -> &A {
+        &v.0
+    }
+    fn get_a_mut(v: &mut Wrapping<A>) -> &mut A {
+        &mut v.0
+    }
+    fn new(a: A) -> Wrapping<A> {
+        Wrapping(a)
+    }
+}
+
+/// A mutator for a `Wrapping<T>`, where `M` is a mutator for `T`.
+/// A wrapping integer has the same complexity as the integer itself.
+pub type WrappingMutator<M> = NewtypeMutator<Wrapping<<M as Mutator>::Value>, M>;
+
+impl<A: DefaultMutator> DefaultMutator for Wrapping<A> {
+    type Mutator = WrappingMutator<A::Mutator>;
+    fn default_mutator() -> Self::Mutator {
+        WrappingMutator::new(A::default_mutator())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integer::*;
+    use crate::tuples::*;
+    use crate::vector::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Counter {
+        count: Wrapping<u64>,
+        data: Vec<u8>,
+    }
+
+    impl TupleMap for Counter {
+        type A = Wrapping<u64>;
+        type B = Vec<u8>;
+        type V = Self;
+
+        fn get_a(v: &Self) -> &Wrapping<u64> {
+            &v.count
+        }
+        fn get_b(v: &Self) -> &Vec<u8> {
+            &v.data
+        }
+        fn get_a_mut(v: &mut Self) -> &mut Wrapping<u64> {
+            &mut v.count
+        }
+        fn get_b_mut(v: &mut Self) -> &mut Vec<u8> {
+            &mut v.data
+        }
+        fn new(count: Wrapping<u64>, data: Vec<u8>) -> Self {
+            Counter { count, data }
+        }
+    }
+
+    #[test]
+    fn test_struct_with_wrapping_field() {
+        let m = Tuple2Mutator::<Counter, WrappingMutator<U64Mutator>, VecMutator<U8Mutator>>::new(
+            Wrapping::<u64>::default_mutator(),
+            Vec::<u8>::default_mutator(),
+        );
+        let count_m = Wrapping::<u64>::default_mutator();
+        assert_eq!(count_m.min_complexity(), U64Mutator::default().min_complexity());
+
+        let mut counts = std::collections::HashSet::new();
+        for seed in 0..10 {
+            let (mut x, mut x_cache) = m.arbitrary(seed, 100.0);
+            let mut x_step = m.mutation_step_from_value(&x);
+            for _ in 0..300 {
+                let old_x = x.clone();
+                let token = if let Some(token) = m.mutate(&mut x, &mut x_cache, &mut x_step, 100.0) {
+                    token
+                } else {
+                    break;
+                };
+                counts.insert(x.count.0);
+                assert_eq!(count_m.complexity(&x.count, &count_m.cache_from_value(&x.count)), 64.0);
+                m.unmutate(&mut x, &mut x_cache, token);
+                assert_eq!(x, old_x);
+            }
+        }
+        assert!(counts.len() > 1000);
+
+        // the nudges of the wrapped integer wrap around its boundaries
+        for &count in &[0, u64::MAX] {
+            let mut x = Counter {
+                count: Wrapping(count),
+                data: vec![],
+            };
+            let mut x_cache = m.cache_from_value(&x);
+            let mut x_step = m.mutation_step_from_value(&x);
+            let mut counts = std::collections::HashSet::new();
+            for _ in 0..300 {
+                let token = m.mutate(&mut x, &mut x_cache, &mut x_step, 100.0).unwrap();
+                counts.insert(x.count.0);
+                m.unmutate(&mut x, &mut x_cache, token);
+            }
+            assert!(counts.contains(&count.wrapping_add(1)));
+            assert!(counts.contains(&count.wrapping_sub(1)));
+        }
+    }
+}
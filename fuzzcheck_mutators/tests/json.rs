@@ -0,0 +1,56 @@
+extern crate fuzzcheck_mutators;
+use fuzzcheck::Mutator;
+use fuzzcheck_mutators::json::*;
+
+use serde_json::Value;
+
+fn assert_round_trip(value: &Value) {
+    let string = serde_json::to_string(value).unwrap();
+    let decoded: Value = serde_json::from_str(&string).unwrap();
+    assert_eq!(&decoded, value, "{}", string);
+}
+
+#[test]
+fn test_arbitrary_json_round_trip() {
+    let m = JsonValueMutator::default();
+    for max_cplx in [1.0, 10.0, 100.0, 1000.0].iter() {
+        for seed in 0..1000 {
+            let (x, x_cache) = m.arbitrary(seed, *max_cplx);
+            assert!(m.complexity(&x, &x_cache) <= *max_cplx);
+            assert_eq!(x_cache, m.cache_from_value(&x));
+            assert_round_trip(&x);
+        }
+    }
+}
+
+#[test]
+fn test_mutated_json_round_trip() {
+    let m = JsonValueMutator::default();
+    for seed in 1..100 {
+        let (mut x, mut x_cache) = m.arbitrary(seed, 200.0);
+        let mut x_step = m.mutation_step_from_value(&x);
+        for _ in 0..100 {
+            let original = x.clone();
+            let token = m.mutate(&mut x, &mut x_cache, &mut x_step, 400.0).unwrap();
+            assert_eq!(x_cache, m.cache_from_value(&x));
+            assert!(m.complexity(&x, &x_cache) <= 400.0);
+            assert_round_trip(&x);
+            m.unmutate(&mut x, &mut x_cache, token);
+            assert_eq!(x, original);
+            assert_eq!(x_cache, m.cache_from_value(&x));
+
+            // keep some mutations, so that the value evolves
+            let _ = m.mutate(&mut x, &mut x_cache, &mut x_step, 400.0);
+            assert!(m.complexity(&x, &x_cache) <= 400.0);
+        }
+    }
+}
+
+#[test]
+fn test_describe_value_is_pretty_printed() {
+    let m = JsonValueMutator::default();
+    let value: Value = serde_json::from_str(r#"{"a":[1,2]}"#).unwrap();
+    let description = m.describe_value(&value).unwrap();
+    assert!(description.lines().count() > 1);
+    assert_eq!(serde_json::from_str::<Value>(&description).unwrap(), value);
+}
@@ -68,4 +68,7 @@ impl fuzzcheck::Serializer for ByteSerializer {
     fn to_data(&self, value: &Self::Value) -> Vec<u8> {
         value.clone()
     }
+    fn is_raw(&self) -> bool {
+        true
+    }
 }